@@ -0,0 +1,224 @@
+//! Runs rendering code for a number of frames and reports how long it took,
+//! for comparing renderer implementations and catching regressions.
+//!
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use glume::bench::BenchConfig;
+//!
+//! let report = glume::bench::run(&BenchConfig::default(), |frame| {
+//!     // draw into the bound framebuffer, of size frame.size()
+//!     frame.record_draw_calls(1);
+//!     Ok(())
+//! })?;
+//! println!("{}", report.to_json());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::time::{Duration, Instant};
+
+use crate::gl_utils::{gpu_memory_usage, GpuMemoryUsage};
+use crate::window::HeadlessContext;
+
+type Error = Box<dyn std::error::Error>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchConfig {
+    /// Frames measured.
+    pub frames: u32,
+    /// Frames run first and not measured, so caches and drivers settle.
+    pub warmup_frames: u32,
+    pub size: (u32, u32),
+    /// Only used by [`run`], which creates its own context.
+    pub gl_version: (u8, u8),
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        Self {
+            frames: 100,
+            warmup_frames: 10,
+            size: (1280, 720),
+            gl_version: (4, 5),
+        }
+    }
+}
+
+/// Passed to the code being measured each frame.
+pub struct BenchFrame {
+    index: u32,
+    size: (u32, u32),
+    draw_calls: u64,
+    upload_bytes: u64,
+}
+
+impl BenchFrame {
+    /// Counts from 0, including warmup frames.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    /// GL calls can't be intercepted, so the code being measured reports
+    /// its own draw calls.
+    pub fn record_draw_calls(&mut self, count: u64) {
+        self.draw_calls += count;
+    }
+
+    /// Reports bytes uploaded to the GPU this frame, as for draw calls.
+    pub fn record_upload(&mut self, bytes: u64) {
+        self.upload_bytes += bytes;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TimingSummary {
+    pub mean: Duration,
+    pub min: Duration,
+    pub max: Duration,
+    pub median: Duration,
+    /// 95th percentile.
+    pub p95: Duration,
+}
+
+impl TimingSummary {
+    fn new(mut samples: Vec<Duration>) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+
+        samples.sort();
+        let percentile = |p: usize| samples[(samples.len() - 1) * p / 100];
+        Self {
+            mean: samples.iter().sum::<Duration>() / samples.len() as u32,
+            min: samples[0],
+            max: samples[samples.len() - 1],
+            median: percentile(50),
+            p95: percentile(95),
+        }
+    }
+
+    fn to_json(self) -> String {
+        let us = |d: Duration| d.as_secs_f64() * 1e6;
+        format!(
+            r#"{{"mean_us":{:.3},"min_us":{:.3},"max_us":{:.3},"median_us":{:.3},"p95_us":{:.3}}}"#,
+            us(self.mean), us(self.min), us(self.max), us(self.median), us(self.p95),
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchReport {
+    pub frames: u32,
+    /// Time spent in the measured code each frame, i.e. issuing commands.
+    pub cpu: TimingSummary,
+    /// Time the GPU spent on each frame's commands, from timer queries.
+    pub gpu: TimingSummary,
+    /// Primitives generated per frame, on average.
+    pub primitives: f64,
+    /// Per frame, on average, as reported through [`BenchFrame`].
+    pub draw_calls: f64,
+    pub upload_bytes: f64,
+    /// Memory tracked by [`gpu_memory_usage`] after the last frame.
+    pub memory: GpuMemoryUsage,
+}
+
+impl BenchReport {
+    /// The report as a single line of JSON, with times in microseconds.
+    pub fn to_json(&self) -> String {
+        format!(
+            r#"{{"frames":{},"cpu":{},"gpu":{},"primitives":{:.1},"draw_calls":{:.1},"upload_bytes":{:.1},"memory":{{"images":{},"render_targets":{}}}}}"#,
+            self.frames,
+            self.cpu.to_json(),
+            self.gpu.to_json(),
+            self.primitives,
+            self.draw_calls,
+            self.upload_bytes,
+            self.memory.images,
+            self.memory.render_targets,
+        )
+    }
+}
+
+/// Creates a [`HeadlessContext`] and measures `frame` in it; see
+/// [`run_in_current_context`].
+pub fn run<F>(config: &BenchConfig, frame: F) -> Result<BenchReport, Error>
+where
+    F: FnMut(&mut BenchFrame) -> Result<(), Error>,
+{
+    let context = HeadlessContext::new(config.gl_version, config.size)?;
+    let report = run_in_current_context(config, frame);
+    drop(context);
+    report
+}
+
+/// Measures `frame` in whatever GL context is current, drawing into
+/// whatever framebuffer is bound. Each frame is waited on before the next,
+/// so the numbers are per frame rather than pipelined throughput.
+pub fn run_in_current_context<F>(config: &BenchConfig, mut frame: F) -> Result<BenchReport, Error>
+where
+    F: FnMut(&mut BenchFrame) -> Result<(), Error>,
+{
+    let mut queries = [0; 2];
+    unsafe {
+        gl::GenQueries(2, queries.as_mut_ptr());
+    }
+    let [time_query, primitives_query] = queries;
+
+    let mut cpu = Vec::with_capacity(config.frames as usize);
+    let mut gpu = Vec::with_capacity(config.frames as usize);
+    let (mut primitives, mut draw_calls, mut upload_bytes) = (0u64, 0u64, 0u64);
+
+    let mut result = Ok(());
+    for index in 0..config.warmup_frames + config.frames {
+        let mut bench_frame = BenchFrame { index, size: config.size, draw_calls: 0, upload_bytes: 0 };
+
+        let start = Instant::now();
+        unsafe {
+            gl::BeginQuery(gl::TIME_ELAPSED, time_query);
+            gl::BeginQuery(gl::PRIMITIVES_GENERATED, primitives_query);
+        }
+        let frame_result = frame(&mut bench_frame);
+        let elapsed = start.elapsed();
+
+        let (mut gpu_nanos, mut frame_primitives) = (0u64, 0u64);
+        unsafe {
+            gl::EndQuery(gl::PRIMITIVES_GENERATED);
+            gl::EndQuery(gl::TIME_ELAPSED);
+            gl::GetQueryObjectui64v(time_query, gl::QUERY_RESULT, &mut gpu_nanos);
+            gl::GetQueryObjectui64v(primitives_query, gl::QUERY_RESULT, &mut frame_primitives);
+        }
+
+        if let Err(e) = frame_result {
+            result = Err(e);
+            break;
+        }
+
+        if index >= config.warmup_frames {
+            cpu.push(elapsed);
+            gpu.push(Duration::from_nanos(gpu_nanos));
+            primitives += frame_primitives;
+            draw_calls += bench_frame.draw_calls;
+            upload_bytes += bench_frame.upload_bytes;
+        }
+    }
+
+    unsafe {
+        gl::DeleteQueries(2, queries.as_ptr());
+    }
+    result?;
+
+    let per_frame = |total: u64| total as f64 / config.frames.max(1) as f64;
+    Ok(BenchReport {
+        frames: config.frames,
+        cpu: TimingSummary::new(cpu),
+        gpu: TimingSummary::new(gpu),
+        primitives: per_frame(primitives),
+        draw_calls: per_frame(draw_calls),
+        upload_bytes: per_frame(upload_bytes),
+        memory: gpu_memory_usage(),
+    })
+}
@@ -0,0 +1,259 @@
+//! Collects what's needed to make sense of a bug report from an end user:
+//! the GL driver, the window's setup and state, frame timing and recent GL
+//! debug messages.
+//!
+//! ```no_run
+//! # fn handle(wc: &mut glume::window::WindowController) {
+//! let report = glume::diagnostics::report(wc);
+//! eprintln!("{report}");
+//! # }
+//! ```
+//!
+//! Debug messages are only collected while
+//! [`standard_debug_callback`](crate::gl_utils::standard_debug_callback) is
+//! installed as the GL debug callback, as it is with
+//! [`WindowConfiguration::debug_context`](crate::window::WindowConfiguration::debug_context).
+
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::gl_utils::{driver_memory_info, DriverMemoryInfo};
+use crate::window::{FrameStats, WindowController};
+
+type Error = Box<dyn std::error::Error>;
+
+/// How many of the most recent debug messages are kept.
+const KEPT_MESSAGES: usize = 64;
+
+static DEBUG_MESSAGES: Mutex<VecDeque<DebugMessage>> = Mutex::new(VecDeque::new());
+
+/// A message from the GL debug callback, with its enums spelled out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DebugMessage {
+    pub source: &'static str,
+    pub kind: &'static str,
+    pub id: u32,
+    pub severity: &'static str,
+    pub message: String,
+}
+
+/// Keeps `message` among the recent ones in reports, dropping the oldest
+/// once there are too many. Called by `standard_debug_callback`; custom
+/// callbacks can call it too.
+pub fn record_debug_message(message: DebugMessage) {
+    let mut messages = DEBUG_MESSAGES.lock().unwrap_or_else(|e| e.into_inner());
+    if messages.len() == KEPT_MESSAGES {
+        messages.pop_front();
+    }
+    messages.push_back(message);
+}
+
+/// The most recent debug messages, oldest first.
+pub fn recent_debug_messages() -> Vec<DebugMessage> {
+    let messages = DEBUG_MESSAGES.lock().unwrap_or_else(|e| e.into_inner());
+    messages.iter().cloned().collect()
+}
+
+/// A snapshot of the driver, window and timing; see [`report`]. Formats as
+/// readable text with `Display`, or as JSON with [`Report::to_json`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub glume_version: &'static str,
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub gl_vendor: String,
+    pub gl_renderer: String,
+    pub gl_version: String,
+    pub glsl_version: String,
+    pub extensions: Vec<String>,
+    pub video_memory: Option<DriverMemoryInfo>,
+    /// The `WindowConfiguration` the window was created with, debug
+    /// formatted.
+    pub window_configuration: String,
+    /// The drawable size in physical pixels.
+    pub window_size: (u32, u32),
+    pub scale_factor: f64,
+    pub fullscreen: bool,
+    pub focused: bool,
+    pub minimized: bool,
+    pub frame_stats: FrameStats,
+    pub debug_messages: Vec<DebugMessage>,
+}
+
+/// Collects a report on the window's GL context, which must be current, as
+/// it is inside the event handler.
+pub fn report(wc: &WindowController) -> Report {
+    Report {
+        glume_version: env!("CARGO_PKG_VERSION"),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        gl_vendor: gl_string(gl::VENDOR),
+        gl_renderer: gl_string(gl::RENDERER),
+        gl_version: gl_string(gl::VERSION),
+        glsl_version: gl_string(gl::SHADING_LANGUAGE_VERSION),
+        extensions: extensions(),
+        video_memory: driver_memory_info(),
+        window_configuration: format!("{:?}", wc.configuration()),
+        window_size: wc.inner_size(),
+        scale_factor: wc.scale_factor(),
+        fullscreen: wc.fullscreen().is_some(),
+        focused: wc.is_focused(),
+        minimized: wc.is_minimized(),
+        frame_stats: wc.frame_stats(),
+        debug_messages: recent_debug_messages(),
+    }
+}
+
+/// Collects a report and writes it to `path`, as JSON if the file name ends
+/// in `.json` and as text otherwise.
+pub fn write_report<P: AsRef<Path>>(wc: &WindowController, path: P) -> Result<(), Error> {
+    let path = path.as_ref();
+    let report = report(wc);
+    let contents = match path.extension() {
+        Some(extension) if extension.eq_ignore_ascii_case("json") => report.to_json(),
+        _ => report.to_string(),
+    };
+    std::fs::write(path, contents)?;
+    Ok(())
+}
+
+impl Report {
+    /// The report as a single line of JSON, with frame times in
+    /// microseconds.
+    pub fn to_json(&self) -> String {
+        let us = |d: std::time::Duration| d.as_secs_f64() * 1e6;
+        let strings = |items: &mut dyn Iterator<Item = String>| items.collect::<Vec<_>>().join(",");
+
+        let video_memory = match self.video_memory {
+            Some(DriverMemoryInfo { total, available }) => format!(
+                r#"{{"total":{},"available":{}}}"#,
+                total.map_or("null".to_string(), |total| total.to_string()),
+                available,
+            ),
+            None => "null".to_string(),
+        };
+
+        let messages = strings(&mut self.debug_messages.iter().map(|m| {
+            format!(
+                r#"{{"source":{},"type":{},"id":{},"severity":{},"message":{}}}"#,
+                json_string(m.source), json_string(m.kind), m.id, json_string(m.severity), json_string(&m.message),
+            )
+        }));
+
+        let stats = &self.frame_stats;
+        format!(
+            concat!(
+                r#"{{"glume_version":{},"os":{},"arch":{},"#,
+                r#""gl":{{"vendor":{},"renderer":{},"version":{},"glsl_version":{},"extensions":[{}],"video_memory":{}}},"#,
+                r#""window":{{"configuration":{},"size":[{},{}],"scale_factor":{},"fullscreen":{},"focused":{},"minimized":{}}},"#,
+                r#""frame_stats":{{"fps":{:.1},"last_frame_us":{:.3},"average_frame_us":{:.3},"worst_frame_us":{:.3},"frame_count":{}}},"#,
+                r#""debug_messages":[{}]}}"#,
+            ),
+            json_string(self.glume_version), json_string(self.os), json_string(self.arch),
+            json_string(&self.gl_vendor), json_string(&self.gl_renderer), json_string(&self.gl_version),
+            json_string(&self.glsl_version), strings(&mut self.extensions.iter().map(|e| json_string(e))), video_memory,
+            json_string(&self.window_configuration), self.window_size.0, self.window_size.1, self.scale_factor,
+            self.fullscreen, self.focused, self.minimized,
+            stats.fps, us(stats.last_frame), us(stats.average_frame), us(stats.worst_frame), stats.frame_count,
+            messages,
+        )
+    }
+}
+
+impl std::fmt::Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "glume {} on {} {}", self.glume_version, self.os, self.arch)?;
+        writeln!(f)?;
+        writeln!(f, "OpenGL {}", self.gl_version)?;
+        writeln!(f, "Renderer: {}", self.gl_renderer)?;
+        writeln!(f, "Vendor: {}", self.gl_vendor)?;
+        writeln!(f, "GLSL: {}", self.glsl_version)?;
+        if let Some(memory) = self.video_memory {
+            let mib = |bytes: u64| bytes / (1024 * 1024);
+            match memory.total {
+                Some(total) => writeln!(f, "Video memory: {} of {} MiB available", mib(memory.available), mib(total))?,
+                None => writeln!(f, "Video memory: {} MiB available", mib(memory.available))?,
+            }
+        }
+        writeln!(f)?;
+
+        writeln!(f, "Window: {}x{} at scale {}", self.window_size.0, self.window_size.1, self.scale_factor)?;
+        let mut state = String::new();
+        for (on, name) in [(self.fullscreen, "fullscreen"), (self.focused, "focused"), (self.minimized, "minimized")] {
+            if on {
+                let _ = write!(state, " {}", name);
+            }
+        }
+        if !state.is_empty() {
+            writeln!(f, "State:{}", state)?;
+        }
+        writeln!(f, "Configuration: {}", self.window_configuration)?;
+        writeln!(f)?;
+
+        let stats = &self.frame_stats;
+        writeln!(
+            f,
+            "Frames: {} presented, {:.1} fps, last {:?}, average {:?}, worst {:?}",
+            stats.frame_count, stats.fps, stats.last_frame, stats.average_frame, stats.worst_frame,
+        )?;
+        writeln!(f)?;
+
+        writeln!(f, "Recent debug messages ({}):", self.debug_messages.len())?;
+        for m in &self.debug_messages {
+            writeln!(f, "  [{}] {} {} {}: {}", m.severity, m.source, m.kind, m.id, m.message)?;
+        }
+        writeln!(f)?;
+
+        writeln!(f, "Extensions ({}):", self.extensions.len())?;
+        for extension in &self.extensions {
+            writeln!(f, "  {}", extension)?;
+        }
+        Ok(())
+    }
+}
+
+fn gl_string(name: gl::types::GLenum) -> String {
+    unsafe {
+        let string = gl::GetString(name);
+        if string.is_null() {
+            return String::new();
+        }
+        std::ffi::CStr::from_ptr(string as *const _).to_string_lossy().into_owned()
+    }
+}
+
+fn extensions() -> Vec<String> {
+    unsafe {
+        let mut count = 0;
+        gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+        (0..count.max(0) as u32)
+            .filter_map(|i| {
+                let extension = gl::GetStringi(gl::EXTENSIONS, i);
+                (!extension.is_null())
+                    .then(|| std::ffi::CStr::from_ptr(extension as *const _).to_string_lossy().into_owned())
+            })
+            .collect()
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escaped.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
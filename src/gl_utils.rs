@@ -1,4 +1,6 @@
 
+use std::sync::atomic::{AtomicU64, Ordering};
+
 use gl::types::GLenum;
 
 type Error = Box<dyn std::error::Error>;
@@ -89,7 +91,7 @@ pub fn link_shader_program(shaders: &[u32]) -> Result<u32, Error> {
 
 pub fn create_buffer_f32(data: &[f32], usage: GLenum) -> Result<u32, Error> {
     let mut buffer = 0;
-    let data_size = (data.len() * std::mem::size_of::<f32>()) as _;
+    let data_size = std::mem::size_of_val(data) as _;
     let data_ptr = data.as_ptr() as _;
     unsafe {
         gl::GenBuffers(1, &mut buffer);
@@ -136,6 +138,55 @@ pub fn create_texture_rgba(size: (u32, u32), data: &[u8]) -> Result<u32, Error>
     create_texture(gl::RGBA, size, data)
 }
 
+/// Uploads a single-channel float volume as a `TEXTURE_3D`, with `data`
+/// ordered x fastest, then y, then z. Filtering is linear and coordinates
+/// clamp to the edge.
+pub fn create_texture_3d_r32f(size: (u32, u32, u32), data: &[f32]) -> Result<u32, Error> {
+    let expected = size.0 as usize * size.1 as usize * size.2 as usize;
+    if data.len() != expected {
+        let msg = format!(
+            "Volume of {}x{}x{} needs {} values, got {}",
+            size.0, size.1, size.2, expected, data.len(),
+        );
+        return Err(msg.into());
+    }
+
+    let mut texture = 0;
+    unsafe {
+        gl::GenTextures(1, &mut texture);
+        gl::BindTexture(gl::TEXTURE_3D, texture);
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+        gl::TexImage3D(
+            gl::TEXTURE_3D,
+            0,
+            gl::R32F as i32,
+            size.0 as i32,
+            size.1 as i32,
+            size.2 as i32,
+            0,
+            gl::RED,
+            gl::FLOAT,
+            data.as_ptr() as _,
+        );
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+
+        for wrap in [gl::TEXTURE_WRAP_S, gl::TEXTURE_WRAP_T, gl::TEXTURE_WRAP_R] {
+            gl::TexParameteri(gl::TEXTURE_3D, wrap, gl::CLAMP_TO_EDGE as i32);
+        }
+        gl::TexParameteri(gl::TEXTURE_3D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+        gl::TexParameteri(gl::TEXTURE_3D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+    }
+
+    Ok(texture)
+}
+
+/// Logs debug messages above notification severity through the `log` crate,
+/// at error, warn or info level for high, medium or low severity, and keeps
+/// the most recent for [`crate::diagnostics::report`]. Messages only show up
+/// once a logger is installed.
+// Must stay a safe fn to fit gl::types::GLDEBUGPROC; GL guarantees `message`
+// is a valid null-terminated string for the duration of the call.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "system"
 fn standard_debug_callback(
     source: u32,
@@ -173,19 +224,1166 @@ fn standard_debug_callback(
         _ => "Unknown",
     };
 
-    let severity = match severity {
-        gl::DEBUG_SEVERITY_HIGH => "High",
-        gl::DEBUG_SEVERITY_MEDIUM => "Medium",
-        gl::DEBUG_SEVERITY_LOW => "Low",
-        gl::DEBUG_SEVERITY_NOTIFICATION => "Notification",
-        _ => "Unknown",
+    let (severity, level) = match severity {
+        gl::DEBUG_SEVERITY_HIGH => ("High", log::Level::Error),
+        gl::DEBUG_SEVERITY_MEDIUM => ("Medium", log::Level::Warn),
+        gl::DEBUG_SEVERITY_LOW => ("Low", log::Level::Info),
+        _ => ("Unknown", log::Level::Warn),
     };
 
     unsafe {
-        let message = std::ffi::CStr::from_ptr(message).to_str().unwrap();
-        println!(
+        let message = std::ffi::CStr::from_ptr(message).to_string_lossy();
+        log::log!(
+            target: "glume::gl",
+            level,
             "OpenGL Debug Message: source: {}, type: {}, id: {}, severity: {}, message: {}",
             source, gltype, id, severity, message
         );
+
+        crate::diagnostics::record_debug_message(crate::diagnostics::DebugMessage {
+            source,
+            kind: gltype,
+            id,
+            severity,
+            message: message.to_string(),
+        });
+    }
+}
+
+/// Sends the current context's debug messages to [`standard_debug_callback`].
+/// Output is synchronous, so messages arrive during the call that caused
+/// them. Contexts without the debug flag may send few messages or none.
+pub fn enable_debug_output() {
+    unsafe {
+        gl::Enable(gl::DEBUG_OUTPUT);
+        gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+        gl::DebugMessageCallback(Some(standard_debug_callback), std::ptr::null());
+    }
+}
+
+/// The storage format of a [`Framebuffer`] color attachment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorFormat {
+    Rgba8,
+    Rgba16F,
+    Rgba32F,
+    /// One unsigned integer per pixel, e.g. for object IDs. Fragment shaders
+    /// write it through a `uvec4` or `uint` output, and it can only be
+    /// sampled with `NEAREST` filtering.
+    R32UI,
+}
+
+impl ColorFormat {
+    /// Internal format, pixel format and component type for `glTexImage2D`.
+    fn gl_formats(&self) -> (GLenum, GLenum, GLenum) {
+        match self {
+            ColorFormat::Rgba8 => (gl::RGBA8, gl::RGBA, gl::UNSIGNED_BYTE),
+            ColorFormat::Rgba16F => (gl::RGBA16F, gl::RGBA, gl::FLOAT),
+            ColorFormat::Rgba32F => (gl::RGBA32F, gl::RGBA, gl::FLOAT),
+            ColorFormat::R32UI => (gl::R32UI, gl::RED_INTEGER, gl::UNSIGNED_INT),
+        }
+    }
+
+    fn is_integer(&self) -> bool {
+        matches!(self, ColorFormat::R32UI)
+    }
+
+    fn bytes_per_pixel(&self) -> u64 {
+        match self {
+            ColorFormat::Rgba8 | ColorFormat::R32UI => 4,
+            ColorFormat::Rgba16F => 8,
+            ColorFormat::Rgba32F => 16,
+        }
+    }
+}
+
+/// An offscreen render target with one or more color textures.
+///
+/// Attachment `i` is written by fragment shader output location `i`. All
+/// attachments are drawn to by default; see [`Framebuffer::set_draw_buffers`].
+pub struct Framebuffer {
+    fbo: u32,
+    color_textures: Vec<u32>,
+    formats: Vec<ColorFormat>,
+    size: (u32, u32),
+    memory: TrackedMemory,
+}
+
+impl Framebuffer {
+    /// A framebuffer with a single RGBA8 color attachment.
+    pub fn new(size: (u32, u32)) -> Result<Self, Error> {
+        Self::with_attachments(size, &[ColorFormat::Rgba8])
+    }
+
+    /// A framebuffer with one color attachment per entry of `formats`.
+    pub fn with_attachments(size: (u32, u32), formats: &[ColorFormat]) -> Result<Self, Error> {
+        let mut max_attachments = 0;
+        unsafe {
+            gl::GetIntegerv(gl::MAX_COLOR_ATTACHMENTS, &mut max_attachments);
+        }
+        if formats.is_empty() || formats.len() > max_attachments as usize {
+            let msg = format!(
+                "Framebuffer needs between 1 and {} color attachments, got {}",
+                max_attachments,
+                formats.len(),
+            );
+            return Err(msg.into());
+        }
+
+        let mut fbo = 0;
+        let mut color_textures = vec![0; formats.len()];
+        unsafe {
+            gl::GenFramebuffers(1, &mut fbo);
+            gl::GenTextures(color_textures.len() as i32, color_textures.as_mut_ptr());
+        }
+
+        let mut framebuffer = Self {
+            fbo,
+            color_textures,
+            formats: formats.to_vec(),
+            size: (0, 0),
+            memory: TrackedMemory::new(MemoryCategory::RenderTarget),
+        };
+        framebuffer.resize(size)?;
+        framebuffer.set_draw_buffers(&(0..formats.len()).collect::<Vec<_>>());
+
+        Ok(framebuffer)
+    }
+
+    pub fn id(&self) -> u32 {
+        self.fbo
+    }
+
+    /// The texture of the first color attachment.
+    pub fn color_texture(&self) -> u32 {
+        self.color_textures[0]
+    }
+
+    /// The texture of color attachment `index`.
+    pub fn color_attachment(&self, index: usize) -> u32 {
+        self.color_textures[index]
+    }
+
+    pub fn color_formats(&self) -> &[ColorFormat] {
+        &self.formats
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    /// Reallocates the attachments at a new size. The contents are lost.
+    pub fn resize(&mut self, size: (u32, u32)) -> Result<(), Error> {
+        let size = (size.0.max(1), size.1.max(1));
+        unsafe {
+            let mut previous = 0;
+            gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut previous);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+
+            for (i, (&texture, format)) in self.color_textures.iter().zip(&self.formats).enumerate() {
+                let (internal_format, pixel_format, ty) = format.gl_formats();
+                let filter = if format.is_integer() { gl::NEAREST } else { gl::LINEAR };
+
+                gl::BindTexture(gl::TEXTURE_2D, texture);
+                gl::TexImage2D(
+                    gl::TEXTURE_2D,
+                    0,
+                    internal_format as i32,
+                    size.0 as i32,
+                    size.1 as i32,
+                    0,
+                    pixel_format,
+                    ty,
+                    std::ptr::null(),
+                );
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, filter as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, filter as i32);
+
+                gl::FramebufferTexture2D(
+                    gl::FRAMEBUFFER,
+                    gl::COLOR_ATTACHMENT0 + i as u32,
+                    gl::TEXTURE_2D,
+                    texture,
+                    0,
+                );
+            }
+
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, previous as u32);
+
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                return Err(format!("Framebuffer is incomplete: status 0x{:x}", status).into());
+            }
+        }
+
+        let bytes_per_pixel: u64 = self.formats.iter().map(|f| f.bytes_per_pixel()).sum();
+        self.memory.set(size.0 as u64 * size.1 as u64 * bytes_per_pixel);
+        self.size = size;
+        Ok(())
+    }
+
+    /// Sets the min and mag filter used when sampling the color textures.
+    /// Integer attachments always use `NEAREST`.
+    pub fn set_filter(&self, filter: GLenum) {
+        for (&texture, format) in self.color_textures.iter().zip(&self.formats) {
+            if format.is_integer() {
+                continue;
+            }
+
+            unsafe {
+                gl::BindTexture(gl::TEXTURE_2D, texture);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, filter as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, filter as i32);
+            }
+        }
+    }
+
+    /// Chooses which attachments fragment outputs are written to: output
+    /// location `i` goes to attachment `attachments[i]`. Outputs past the
+    /// end of the list are discarded.
+    pub fn set_draw_buffers(&self, attachments: &[usize]) {
+        let buffers: Vec<GLenum> = attachments
+            .iter()
+            .map(|&i| {
+                assert!(i < self.color_textures.len(), "No color attachment {}", i);
+                gl::COLOR_ATTACHMENT0 + i as u32
+            })
+            .collect();
+
+        unsafe {
+            let mut previous = 0;
+            gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut previous);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+            gl::DrawBuffers(buffers.len() as i32, buffers.as_ptr());
+            gl::BindFramebuffer(gl::FRAMEBUFFER, previous as u32);
+        }
+    }
+
+    /// Binds the framebuffer for drawing and sets the viewport to cover it.
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+            gl::Viewport(0, 0, self.size.0 as i32, self.size.1 as i32);
+        }
+    }
+
+    /// Drops a framebuffer whose context was lost, uncounting its memory
+    /// without deleting its GL names, which may belong to new objects by now.
+    pub(crate) fn forget_lost(mut self) {
+        self.memory.set(0);
+        self.color_textures = Vec::new();
+        self.formats = Vec::new();
+        std::mem::forget(self);
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.fbo);
+            gl::DeleteTextures(self.color_textures.len() as i32, self.color_textures.as_ptr());
+        }
+    }
+}
+
+/// An offscreen render target with only a depth texture, e.g. for shadow
+/// maps.
+///
+/// Outside the texture, lookups return the far plane, so points beyond a
+/// shadow map's coverage are lit.
+///
+/// For a directional light's shadow map, draw the scene transformed by
+/// [`directional_light_matrix`] inside [`DepthFramebuffer::render_depth_pass`],
+/// then turn on [`DepthFramebuffer::set_comparison`] and sample it in the
+/// lit pass with the `shadow_pcf` function from [`SHADOW_PCF_GLSL`].
+pub struct DepthFramebuffer {
+    fbo: u32,
+    depth_texture: u32,
+    size: (u32, u32),
+    memory: TrackedMemory,
+}
+
+impl DepthFramebuffer {
+    pub fn new(size: (u32, u32)) -> Result<Self, Error> {
+        let mut fbo = 0;
+        let mut depth_texture = 0;
+        unsafe {
+            gl::GenFramebuffers(1, &mut fbo);
+            gl::GenTextures(1, &mut depth_texture);
+        }
+
+        let mut framebuffer = Self {
+            fbo,
+            depth_texture,
+            size: (0, 0),
+            memory: TrackedMemory::new(MemoryCategory::RenderTarget),
+        };
+        framebuffer.resize(size)?;
+
+        Ok(framebuffer)
+    }
+
+    pub fn id(&self) -> u32 {
+        self.fbo
+    }
+
+    pub fn depth_texture(&self) -> u32 {
+        self.depth_texture
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    /// Reallocates the depth texture at a new size. The contents are lost,
+    /// and comparison mode is turned off.
+    pub fn resize(&mut self, size: (u32, u32)) -> Result<(), Error> {
+        let size = (size.0.max(1), size.1.max(1));
+        let border = [1.0f32; 4];
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.depth_texture);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::DEPTH_COMPONENT32F as i32,
+                size.0 as i32,
+                size.1 as i32,
+                0,
+                gl::DEPTH_COMPONENT,
+                gl::FLOAT,
+                std::ptr::null(),
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_BORDER as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_BORDER as i32);
+            gl::TexParameterfv(gl::TEXTURE_2D, gl::TEXTURE_BORDER_COLOR, border.as_ptr());
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_COMPARE_MODE, gl::NONE as i32);
+
+            let mut previous = 0;
+            gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut previous);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::DEPTH_ATTACHMENT,
+                gl::TEXTURE_2D,
+                self.depth_texture,
+                0,
+            );
+            gl::DrawBuffer(gl::NONE);
+            gl::ReadBuffer(gl::NONE);
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, previous as u32);
+
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                return Err(format!("Framebuffer is incomplete: status 0x{:x}", status).into());
+            }
+        }
+
+        self.memory.set(size.0 as u64 * size.1 as u64 * 4);
+        self.size = size;
+        Ok(())
+    }
+
+    /// Turns on depth comparison, so the texture can be sampled through a
+    /// `sampler2DShadow` that returns how lit a point is rather than a raw
+    /// depth. With `filtered`, hardware compares the four nearest texels and
+    /// blends the results (2x2 PCF); wider PCF kernels are averaged in the
+    /// shader.
+    pub fn set_comparison(&self, enabled: bool, filtered: bool) {
+        let mode = if enabled { gl::COMPARE_REF_TO_TEXTURE } else { gl::NONE };
+        let filter = if filtered { gl::LINEAR } else { gl::NEAREST };
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.depth_texture);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_COMPARE_MODE, mode as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_COMPARE_FUNC, gl::LEQUAL as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, filter as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, filter as i32);
+        }
+    }
+
+    /// Binds the framebuffer for drawing and sets the viewport to cover it.
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+            gl::Viewport(0, 0, self.size.0 as i32, self.size.1 as i32);
+        }
+    }
+
+    /// Runs `draw` as a depth-only pass into the framebuffer, e.g. the scene
+    /// from a light's point of view, then restores the previous framebuffer,
+    /// viewport and depth state.
+    ///
+    /// The depth is cleared to the far plane first, depth testing is on and
+    /// color writes are off. `slope_bias` and `constant_bias` go to
+    /// `glPolygonOffset`, pushing stored depths away from the light so lit
+    /// surfaces don't shadow themselves; `(2.0, 4.0)` is a reasonable start.
+    /// Comparison should be off while drawing, as it is after
+    /// [`DepthFramebuffer::resize`].
+    pub fn render_depth_pass<F: FnOnce()>(&self, slope_bias: f32, constant_bias: f32, draw: F) {
+        let saved = DepthPassState::save();
+
+        self.bind();
+        unsafe {
+            gl::Disable(gl::SCISSOR_TEST);
+            gl::DepthMask(gl::TRUE);
+            gl::ClearDepth(1.0);
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+
+            gl::Enable(gl::DEPTH_TEST);
+            gl::DepthFunc(gl::LESS);
+            gl::ColorMask(gl::FALSE, gl::FALSE, gl::FALSE, gl::FALSE);
+            gl::Enable(gl::POLYGON_OFFSET_FILL);
+            gl::PolygonOffset(slope_bias, constant_bias);
+        }
+
+        draw();
+
+        saved.restore();
+    }
+}
+
+impl Drop for DepthFramebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.fbo);
+            gl::DeleteTextures(1, &self.depth_texture);
+        }
+    }
+}
+
+/// What [`DepthFramebuffer::render_depth_pass`] changes.
+#[derive(Default)]
+struct DepthPassState {
+    framebuffer: i32,
+    viewport: [i32; 4],
+    scissor_test: bool,
+    depth_test: bool,
+    depth_func: i32,
+    depth_mask: u8,
+    clear_depth: f64,
+    color_mask: [u8; 4],
+    polygon_offset: bool,
+    offset_factor: f32,
+    offset_units: f32,
+}
+
+impl DepthPassState {
+    fn save() -> Self {
+        let mut state = Self::default();
+        unsafe {
+            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut state.framebuffer);
+            gl::GetIntegerv(gl::VIEWPORT, state.viewport.as_mut_ptr());
+            state.scissor_test = gl::IsEnabled(gl::SCISSOR_TEST) == gl::TRUE;
+            state.depth_test = gl::IsEnabled(gl::DEPTH_TEST) == gl::TRUE;
+            gl::GetIntegerv(gl::DEPTH_FUNC, &mut state.depth_func);
+            gl::GetBooleanv(gl::DEPTH_WRITEMASK, &mut state.depth_mask);
+            gl::GetDoublev(gl::DEPTH_CLEAR_VALUE, &mut state.clear_depth);
+            gl::GetBooleanv(gl::COLOR_WRITEMASK, state.color_mask.as_mut_ptr());
+            state.polygon_offset = gl::IsEnabled(gl::POLYGON_OFFSET_FILL) == gl::TRUE;
+            gl::GetFloatv(gl::POLYGON_OFFSET_FACTOR, &mut state.offset_factor);
+            gl::GetFloatv(gl::POLYGON_OFFSET_UNITS, &mut state.offset_units);
+        }
+        state
+    }
+
+    fn restore(&self) {
+        let set = |capability, enabled| unsafe {
+            if enabled {
+                gl::Enable(capability);
+            } else {
+                gl::Disable(capability);
+            }
+        };
+
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer as u32);
+            let [x, y, w, h] = self.viewport;
+            gl::Viewport(x, y, w, h);
+            gl::DepthFunc(self.depth_func as u32);
+            gl::DepthMask(self.depth_mask);
+            gl::ClearDepth(self.clear_depth);
+            let [r, g, b, a] = self.color_mask;
+            gl::ColorMask(r, g, b, a);
+            gl::PolygonOffset(self.offset_factor, self.offset_units);
+        }
+        set(gl::SCISSOR_TEST, self.scissor_test);
+        set(gl::DEPTH_TEST, self.depth_test);
+        set(gl::POLYGON_OFFSET_FILL, self.polygon_offset);
+    }
+}
+
+/// GLSL for sampling a shadow map with percentage-closer filtering, to paste
+/// into a fragment shader after its `#version` line. It defines:
+///
+/// ```glsl
+/// float shadow_pcf(sampler2DShadow shadow_map, vec4 light_space_position, int radius);
+/// ```
+///
+/// which returns how lit a point is, from 0 to 1, given its position
+/// transformed by the light's matrix. It averages `(2 * radius + 1)²`
+/// comparisons a texel apart, each of them 2x2 filtered by hardware with
+/// [`DepthFramebuffer::set_comparison`]'s `filtered`. Radius 0 is a single
+/// lookup.
+pub const SHADOW_PCF_GLSL: &str = include_str!("shaders/shadow_pcf.glsl");
+
+/// A column-major view-projection matrix for a directional light shining
+/// along `direction`, covering a sphere of `radius` around `center` with an
+/// orthographic projection. Draw the depth pass with it, and transform
+/// positions by it to look them up in the shadow map.
+///
+/// Nearer to the light is nearer the near plane, and everything in the
+/// sphere is between the planes.
+pub fn directional_light_matrix(direction: [f32; 3], center: [f32; 3], radius: f32) -> [f32; 16] {
+    let dot = |a: [f32; 3], b: [f32; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+    let cross = |a: [f32; 3], b: [f32; 3]| {
+        [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+    };
+    let normalize = |v: [f32; 3]| {
+        let length = dot(v, v).sqrt();
+        v.map(|c| c / length)
+    };
+
+    let forward = normalize(direction);
+    // any up works for a directional light, as long as it isn't parallel
+    let up = if forward[1].abs() > 0.99 { [0.0, 0.0, 1.0] } else { [0.0, 1.0, 0.0] };
+    let right = normalize(cross(forward, up));
+    let up = cross(right, forward);
+
+    let scale = 1.0 / radius.max(f32::EPSILON);
+    let row = |axis: [f32; 3]| {
+        [axis[0] * scale, axis[1] * scale, axis[2] * scale, -dot(axis, center) * scale]
+    };
+    let rows = [row(right), row(up), row(forward), [0.0, 0.0, 0.0, 1.0]];
+
+    std::array::from_fn(|i| rows[i % 4][i / 4])
+}
+
+/// How drawn colors are combined with what's already in the framebuffer.
+///
+/// Textures with straight alpha, as images are loaded, use `Alpha`. Scaling
+/// or filtering them bleeds the color of transparent texels into the edges,
+/// which `PremultipliedAlpha` avoids for textures uploaded with
+/// [`ImageTexture::new_premultiplied`](crate::renderers::image_renderer::ImageTexture::new_premultiplied).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Blending off; colors replace what's there.
+    Opaque,
+    Alpha,
+    PremultipliedAlpha,
+    /// Adds color, weighted by alpha, without darkening what's underneath.
+    Additive,
+}
+
+impl BlendMode {
+    /// Sets the GL blend state. Destination alpha accumulates coverage the
+    /// same way in every mode, so offscreen targets end up premultiplied.
+    pub fn apply(&self) {
+        unsafe {
+            match self {
+                BlendMode::Opaque => gl::Disable(gl::BLEND),
+                BlendMode::Alpha => {
+                    gl::Enable(gl::BLEND);
+                    gl::BlendFuncSeparate(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA, gl::ONE, gl::ONE_MINUS_SRC_ALPHA);
+                }
+                BlendMode::PremultipliedAlpha => {
+                    gl::Enable(gl::BLEND);
+                    gl::BlendFunc(gl::ONE, gl::ONE_MINUS_SRC_ALPHA);
+                }
+                BlendMode::Additive => {
+                    gl::Enable(gl::BLEND);
+                    gl::BlendFuncSeparate(gl::SRC_ALPHA, gl::ONE, gl::ONE, gl::ONE_MINUS_SRC_ALPHA);
+                }
+            }
+        }
+    }
+}
+
+/// The blend enable bit and factors, saved by renderers that change them so
+/// they can be put back afterwards.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct SavedBlendState {
+    enabled: bool,
+    src_rgb: i32,
+    dst_rgb: i32,
+    src_alpha: i32,
+    dst_alpha: i32,
+}
+
+impl SavedBlendState {
+    pub(crate) fn save() -> Self {
+        let mut state = Self::default();
+        unsafe {
+            state.enabled = gl::IsEnabled(gl::BLEND) == gl::TRUE;
+            gl::GetIntegerv(gl::BLEND_SRC_RGB, &mut state.src_rgb);
+            gl::GetIntegerv(gl::BLEND_DST_RGB, &mut state.dst_rgb);
+            gl::GetIntegerv(gl::BLEND_SRC_ALPHA, &mut state.src_alpha);
+            gl::GetIntegerv(gl::BLEND_DST_ALPHA, &mut state.dst_alpha);
+        }
+        state
+    }
+
+    pub(crate) fn restore(&self) {
+        unsafe {
+            if self.enabled {
+                gl::Enable(gl::BLEND);
+            } else {
+                gl::Disable(gl::BLEND);
+            }
+            gl::BlendFuncSeparate(
+                self.src_rgb as u32,
+                self.dst_rgb as u32,
+                self.src_alpha as u32,
+                self.dst_alpha as u32,
+            );
+        }
+    }
+}
+
+/// What memory counted by [`gpu_memory_usage`] is used for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryCategory {
+    /// Textures of [`ImageTexture`](crate::renderers::image_renderer::ImageTexture)s.
+    Image,
+    /// [`Framebuffer`] and [`DepthFramebuffer`] attachments.
+    RenderTarget,
+}
+
+static IMAGE_BYTES: AtomicU64 = AtomicU64::new(0);
+static RENDER_TARGET_BYTES: AtomicU64 = AtomicU64::new(0);
+
+impl MemoryCategory {
+    fn counter(&self) -> &'static AtomicU64 {
+        match self {
+            MemoryCategory::Image => &IMAGE_BYTES,
+            MemoryCategory::RenderTarget => &RENDER_TARGET_BYTES,
+        }
+    }
+}
+
+/// Bytes currently held by the crate's texture wrappers, by category.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GpuMemoryUsage {
+    pub images: u64,
+    pub render_targets: u64,
+}
+
+impl GpuMemoryUsage {
+    pub fn total(&self) -> u64 {
+        self.images + self.render_targets
+    }
+
+    pub fn get(&self, category: MemoryCategory) -> u64 {
+        match category {
+            MemoryCategory::Image => self.images,
+            MemoryCategory::RenderTarget => self.render_targets,
+        }
+    }
+}
+
+/// The memory held by live `ImageTexture`s and framebuffers, estimated from
+/// their sizes and formats, across all contexts.
+///
+/// A count that keeps growing in a program that should be in a steady state
+/// points at a leak. Textures and buffers created through the raw `create_*`
+/// functions aren't counted, since nothing tracks when they're deleted.
+pub fn gpu_memory_usage() -> GpuMemoryUsage {
+    GpuMemoryUsage {
+        images: IMAGE_BYTES.load(Ordering::Relaxed),
+        render_targets: RENDER_TARGET_BYTES.load(Ordering::Relaxed),
+    }
+}
+
+/// One allocation counted by [`gpu_memory_usage`], uncounted on drop.
+pub(crate) struct TrackedMemory {
+    category: MemoryCategory,
+    bytes: u64,
+}
+
+impl TrackedMemory {
+    pub(crate) fn new(category: MemoryCategory) -> Self {
+        Self { category, bytes: 0 }
+    }
+
+    /// Replaces the size of the allocation, e.g. after a resize.
+    pub(crate) fn set(&mut self, bytes: u64) {
+        let counter = self.category.counter();
+        counter.fetch_sub(self.bytes, Ordering::Relaxed);
+        counter.fetch_add(bytes, Ordering::Relaxed);
+        self.bytes = bytes;
+    }
+}
+
+impl Drop for TrackedMemory {
+    fn drop(&mut self) {
+        self.set(0);
+    }
+}
+
+/// Video memory as reported by the driver, in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DriverMemoryInfo {
+    /// Dedicated video memory, if the driver reports it.
+    pub total: Option<u64>,
+    pub available: u64,
+}
+
+// from GL_NVX_gpu_memory_info and GL_ATI_meminfo, which gl doesn't generate
+const GPU_MEMORY_INFO_DEDICATED_VIDMEM_NVX: GLenum = 0x9047;
+const GPU_MEMORY_INFO_CURRENT_AVAILABLE_VIDMEM_NVX: GLenum = 0x9049;
+const TEXTURE_FREE_MEMORY_ATI: GLenum = 0x87FC;
+
+/// Whether the current context supports the named extension.
+pub fn has_extension(name: &str) -> bool {
+    unsafe {
+        let mut count = 0;
+        gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+        (0..count.max(0) as u32).any(|i| {
+            let extension = gl::GetStringi(gl::EXTENSIONS, i);
+            !extension.is_null() && std::ffi::CStr::from_ptr(extension as *const _).to_bytes() == name.as_bytes()
+        })
+    }
+}
+
+/// Queries video memory through `GL_NVX_gpu_memory_info` or
+/// `GL_ATI_meminfo`. Returns `None` if the driver supports neither, as Mesa
+/// and most integrated GPUs don't.
+pub fn driver_memory_info() -> Option<DriverMemoryInfo> {
+    // both extensions report kilobytes
+    if has_extension("GL_NVX_gpu_memory_info") {
+        let (mut total, mut available) = (0, 0);
+        unsafe {
+            gl::GetIntegerv(GPU_MEMORY_INFO_DEDICATED_VIDMEM_NVX, &mut total);
+            gl::GetIntegerv(GPU_MEMORY_INFO_CURRENT_AVAILABLE_VIDMEM_NVX, &mut available);
+        }
+        return Some(DriverMemoryInfo {
+            total: Some(total as u64 * 1024),
+            available: available as u64 * 1024,
+        });
+    }
+
+    if has_extension("GL_ATI_meminfo") {
+        // total free, largest free block, and the same for auxiliary memory
+        let mut info = [0; 4];
+        unsafe {
+            gl::GetIntegerv(TEXTURE_FREE_MEMORY_ATI, info.as_mut_ptr());
+        }
+        return Some(DriverMemoryInfo {
+            total: None,
+            available: info[0] as u64 * 1024,
+        });
+    }
+
+    None
+}
+
+/// A value for [`Program::set_uniform_by_name`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UniformValue {
+    Float(f32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+    Int(i32),
+    IVec2([i32; 2]),
+    IVec3([i32; 3]),
+    IVec4([i32; 4]),
+    UInt(u32),
+    Bool(bool),
+    /// Column-major, like GLSL.
+    Mat3([f32; 9]),
+    /// Column-major, like GLSL.
+    Mat4([f32; 16]),
+}
+
+impl UniformValue {
+    fn type_name(&self) -> &'static str {
+        match self {
+            UniformValue::Float(_) => "float",
+            UniformValue::Vec2(_) => "vec2",
+            UniformValue::Vec3(_) => "vec3",
+            UniformValue::Vec4(_) => "vec4",
+            UniformValue::Int(_) => "int",
+            UniformValue::IVec2(_) => "ivec2",
+            UniformValue::IVec3(_) => "ivec3",
+            UniformValue::IVec4(_) => "ivec4",
+            UniformValue::UInt(_) => "uint",
+            UniformValue::Bool(_) => "bool",
+            UniformValue::Mat3(_) => "mat3",
+            UniformValue::Mat4(_) => "mat4",
+        }
+    }
+
+    /// Whether a uniform of GLSL type `ty` can be set from this value.
+    fn fits(&self, ty: GLenum) -> bool {
+        match self {
+            UniformValue::Float(_) => ty == gl::FLOAT,
+            UniformValue::Vec2(_) => ty == gl::FLOAT_VEC2,
+            UniformValue::Vec3(_) => ty == gl::FLOAT_VEC3,
+            UniformValue::Vec4(_) => ty == gl::FLOAT_VEC4,
+            // samplers are set with the texture unit index
+            UniformValue::Int(_) => ty == gl::INT || ty == gl::BOOL || is_sampler_type(ty),
+            UniformValue::IVec2(_) => ty == gl::INT_VEC2,
+            UniformValue::IVec3(_) => ty == gl::INT_VEC3,
+            UniformValue::IVec4(_) => ty == gl::INT_VEC4,
+            UniformValue::UInt(_) => ty == gl::UNSIGNED_INT,
+            UniformValue::Bool(_) => ty == gl::BOOL,
+            UniformValue::Mat3(_) => ty == gl::FLOAT_MAT3,
+            UniformValue::Mat4(_) => ty == gl::FLOAT_MAT4,
+        }
+    }
+
+    /// Uploads the value to `location` of the currently used program.
+    fn upload(&self, location: i32) {
+        unsafe {
+            match *self {
+                UniformValue::Float(v) => gl::Uniform1f(location, v),
+                UniformValue::Vec2([x, y]) => gl::Uniform2f(location, x, y),
+                UniformValue::Vec3([x, y, z]) => gl::Uniform3f(location, x, y, z),
+                UniformValue::Vec4([x, y, z, w]) => gl::Uniform4f(location, x, y, z, w),
+                UniformValue::Int(v) => gl::Uniform1i(location, v),
+                UniformValue::IVec2([x, y]) => gl::Uniform2i(location, x, y),
+                UniformValue::IVec3([x, y, z]) => gl::Uniform3i(location, x, y, z),
+                UniformValue::IVec4([x, y, z, w]) => gl::Uniform4i(location, x, y, z, w),
+                UniformValue::UInt(v) => gl::Uniform1ui(location, v),
+                UniformValue::Bool(v) => gl::Uniform1i(location, v as i32),
+                UniformValue::Mat3(m) => gl::UniformMatrix3fv(location, 1, gl::FALSE, m.as_ptr()),
+                UniformValue::Mat4(m) => gl::UniformMatrix4fv(location, 1, gl::FALSE, m.as_ptr()),
+            }
+        }
+    }
+}
+
+impl From<f32> for UniformValue {
+    fn from(v: f32) -> Self {
+        UniformValue::Float(v)
+    }
+}
+
+impl From<[f32; 2]> for UniformValue {
+    fn from(v: [f32; 2]) -> Self {
+        UniformValue::Vec2(v)
+    }
+}
+
+impl From<(f32, f32)> for UniformValue {
+    fn from(v: (f32, f32)) -> Self {
+        UniformValue::Vec2([v.0, v.1])
+    }
+}
+
+impl From<[f32; 3]> for UniformValue {
+    fn from(v: [f32; 3]) -> Self {
+        UniformValue::Vec3(v)
+    }
+}
+
+impl From<[f32; 4]> for UniformValue {
+    fn from(v: [f32; 4]) -> Self {
+        UniformValue::Vec4(v)
+    }
+}
+
+impl From<i32> for UniformValue {
+    fn from(v: i32) -> Self {
+        UniformValue::Int(v)
+    }
+}
+
+impl From<[i32; 2]> for UniformValue {
+    fn from(v: [i32; 2]) -> Self {
+        UniformValue::IVec2(v)
+    }
+}
+
+impl From<[i32; 3]> for UniformValue {
+    fn from(v: [i32; 3]) -> Self {
+        UniformValue::IVec3(v)
+    }
+}
+
+impl From<[i32; 4]> for UniformValue {
+    fn from(v: [i32; 4]) -> Self {
+        UniformValue::IVec4(v)
+    }
+}
+
+impl From<u32> for UniformValue {
+    fn from(v: u32) -> Self {
+        UniformValue::UInt(v)
+    }
+}
+
+impl From<bool> for UniformValue {
+    fn from(v: bool) -> Self {
+        UniformValue::Bool(v)
+    }
+}
+
+impl From<[f32; 9]> for UniformValue {
+    fn from(v: [f32; 9]) -> Self {
+        UniformValue::Mat3(v)
+    }
+}
+
+impl From<[f32; 16]> for UniformValue {
+    fn from(v: [f32; 16]) -> Self {
+        UniformValue::Mat4(v)
+    }
+}
+
+fn is_sampler_type(ty: GLenum) -> bool {
+    matches!(
+        ty,
+        gl::SAMPLER_1D
+            | gl::SAMPLER_2D
+            | gl::SAMPLER_3D
+            | gl::SAMPLER_CUBE
+            | gl::SAMPLER_2D_ARRAY
+            | gl::SAMPLER_2D_SHADOW
+            | gl::SAMPLER_2D_RECT
+            | gl::SAMPLER_BUFFER
+            | gl::SAMPLER_2D_MULTISAMPLE
+            | gl::INT_SAMPLER_2D
+            | gl::INT_SAMPLER_3D
+            | gl::UNSIGNED_INT_SAMPLER_2D
+            | gl::UNSIGNED_INT_SAMPLER_3D
+    )
+}
+
+/// The GLSL name of a uniform type as reported by `glGetActiveUniform`.
+pub fn uniform_type_name(ty: GLenum) -> &'static str {
+    match ty {
+        gl::FLOAT => "float",
+        gl::FLOAT_VEC2 => "vec2",
+        gl::FLOAT_VEC3 => "vec3",
+        gl::FLOAT_VEC4 => "vec4",
+        gl::INT => "int",
+        gl::INT_VEC2 => "ivec2",
+        gl::INT_VEC3 => "ivec3",
+        gl::INT_VEC4 => "ivec4",
+        gl::UNSIGNED_INT => "uint",
+        gl::BOOL => "bool",
+        gl::FLOAT_MAT2 => "mat2",
+        gl::FLOAT_MAT3 => "mat3",
+        gl::FLOAT_MAT4 => "mat4",
+        gl::SAMPLER_1D => "sampler1D",
+        gl::SAMPLER_2D => "sampler2D",
+        gl::SAMPLER_3D => "sampler3D",
+        gl::SAMPLER_CUBE => "samplerCube",
+        gl::SAMPLER_2D_ARRAY => "sampler2DArray",
+        gl::SAMPLER_2D_SHADOW => "sampler2DShadow",
+        gl::SAMPLER_2D_RECT => "sampler2DRect",
+        gl::SAMPLER_BUFFER => "samplerBuffer",
+        gl::SAMPLER_2D_MULTISAMPLE => "sampler2DMS",
+        gl::INT_SAMPLER_2D => "isampler2D",
+        gl::INT_SAMPLER_3D => "isampler3D",
+        gl::UNSIGNED_INT_SAMPLER_2D => "usampler2D",
+        gl::UNSIGNED_INT_SAMPLER_3D => "usampler3D",
+        _ => "unknown",
+    }
+}
+
+/// An active uniform of a [`Program`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UniformInfo {
+    pub location: i32,
+    /// The GLSL type, e.g. `gl::FLOAT_VEC4`. See [`uniform_type_name`].
+    pub ty: GLenum,
+    /// The number of elements, for arrays.
+    pub size: i32,
+}
+
+/// A linked shader program that knows its active uniforms, so they can be set
+/// by name with type checking instead of through raw locations.
+pub struct Program {
+    id: u32,
+    uniforms: std::collections::HashMap<String, UniformInfo>,
+}
+
+impl Program {
+    /// Compiles and links a vertex and fragment shader.
+    pub fn new(vertex_source: &str, fragment_source: &str) -> Result<Self, Error> {
+        let vshader = compile_shader(vertex_source, gl::VERTEX_SHADER)?;
+        let fshader = match compile_shader(fragment_source, gl::FRAGMENT_SHADER) {
+            Ok(fshader) => fshader,
+            Err(e) => {
+                unsafe { gl::DeleteShader(vshader) };
+                return Err(e);
+            }
+        };
+
+        let program = link_shader_program(&[vshader, fshader]);
+
+        unsafe {
+            gl::DeleteShader(vshader);
+            gl::DeleteShader(fshader);
+        }
+
+        Ok(Self::from_raw(program?))
+    }
+
+    /// Takes ownership of an already linked program object.
+    pub fn from_raw(id: u32) -> Self {
+        let mut uniforms = std::collections::HashMap::new();
+
+        unsafe {
+            let mut count = 0;
+            let mut max_name_length = 0;
+            gl::GetProgramiv(id, gl::ACTIVE_UNIFORMS, &mut count);
+            gl::GetProgramiv(id, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut max_name_length);
+
+            let mut buffer = vec![0u8; max_name_length.max(1) as usize];
+            for index in 0..count as u32 {
+                let mut length = 0;
+                let mut size = 0;
+                let mut ty = 0;
+                gl::GetActiveUniform(
+                    id,
+                    index,
+                    buffer.len() as i32,
+                    &mut length,
+                    &mut size,
+                    &mut ty,
+                    buffer.as_mut_ptr() as _,
+                );
+
+                let name = String::from_utf8_lossy(&buffer[..length as usize]).into_owned();
+                let c_name = std::ffi::CString::new(name.as_str()).unwrap();
+                let location = gl::GetUniformLocation(id, c_name.as_ptr());
+
+                // uniforms in blocks have no location and can't be set this way
+                if location < 0 {
+                    continue;
+                }
+
+                let info = UniformInfo { location, ty, size };
+
+                // arrays are reported as "name[0]"; accept the bare name too
+                if let Some(base) = name.strip_suffix("[0]") {
+                    uniforms.insert(base.to_string(), info);
+                }
+                uniforms.insert(name, info);
+            }
+        }
+
+        Self { id, uniforms }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Every active uniform by name. Uniforms the compiler optimized out
+    /// don't appear.
+    pub fn uniforms(&self) -> &std::collections::HashMap<String, UniformInfo> {
+        &self.uniforms
+    }
+
+    pub fn uniform(&self, name: &str) -> Option<&UniformInfo> {
+        self.uniforms.get(name)
+    }
+
+    /// Makes this the current program.
+    pub fn use_program(&self) {
+        unsafe {
+            gl::UseProgram(self.id);
+        }
+    }
+
+    /// Makes this the current program and sets a uniform, checking that it
+    /// exists and that its type matches the value.
+    pub fn set_uniform_by_name<V: Into<UniformValue>>(&self, name: &str, value: V) -> Result<(), Error> {
+        let value = value.into();
+
+        let info = match self.uniforms.get(name) {
+            Some(info) => info,
+            None => {
+                let mut names: Vec<&str> = self.uniforms.keys().map(|n| n.as_str()).collect();
+                names.sort_unstable();
+                let msg = format!(
+                    "No active uniform named '{}' (it may be misspelled or optimized out). Active uniforms: [{}]",
+                    name,
+                    names.join(", "),
+                );
+                return Err(msg.into());
+            }
+        };
+
+        if !value.fits(info.ty) {
+            let msg = format!(
+                "Uniform '{}' has type {} but was given a {}",
+                name,
+                uniform_type_name(info.ty),
+                value.type_name(),
+            );
+            return Err(msg.into());
+        }
+
+        self.use_program();
+        value.upload(info.location);
+        Ok(())
+    }
+}
+
+impl Drop for Program {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteProgram(self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transform(m: [f32; 16], p: [f32; 3]) -> [f32; 4] {
+        std::array::from_fn(|row| m[row] * p[0] + m[4 + row] * p[1] + m[8 + row] * p[2] + m[12 + row])
+    }
+
+    fn assert_close(actual: [f32; 4], expected: [f32; 4]) {
+        let close = actual.iter().zip(expected).all(|(a, e)| (a - e).abs() < 1e-5);
+        assert!(close, "expected {expected:?}, got {actual:?}");
+    }
+
+    #[test]
+    fn light_matrix_centers_the_sphere() {
+        let m = directional_light_matrix([1.0, -2.0, 0.5], [3.0, 4.0, 5.0], 10.0);
+        assert_close(transform(m, [3.0, 4.0, 5.0]), [0.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn light_matrix_depth_increases_away_from_the_light() {
+        let m = directional_light_matrix([0.0, 0.0, -1.0], [0.0, 0.0, 0.0], 2.0);
+        assert_close(transform(m, [0.0, 0.0, 2.0]), [0.0, 0.0, -1.0, 1.0]);
+        assert_close(transform(m, [0.0, 0.0, -2.0]), [0.0, 0.0, 1.0, 1.0]);
+        assert_close(transform(m, [2.0, 0.0, 0.0]), [1.0, 0.0, 0.0, 1.0]);
+        assert_close(transform(m, [0.0, 2.0, 0.0]), [0.0, 1.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn light_matrix_keeps_the_sphere_in_clip_space() {
+        let center = [1.0, 2.0, 3.0];
+        let m = directional_light_matrix([0.3, -0.8, 0.5], center, 4.0);
+        for axis in 0..3 {
+            for sign in [-1.0, 1.0] {
+                let mut p = center;
+                p[axis] += sign * 4.0;
+                let clip = transform(m, p);
+                let length = (clip[0] * clip[0] + clip[1] * clip[1] + clip[2] * clip[2]).sqrt();
+                assert!((length - 1.0).abs() < 1e-5, "{p:?} went to {clip:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn light_matrix_handles_vertical_light() {
+        let m = directional_light_matrix([0.0, -1.0, 0.0], [0.0, 0.0, 0.0], 1.0);
+        assert!(m.iter().all(|c| c.is_finite()));
+        assert_close(transform(m, [0.0, 1.0, 0.0]), [0.0, 0.0, -1.0, 1.0]);
+        assert_close(transform(m, [0.0, -1.0, 0.0]), [0.0, 0.0, 1.0, 1.0]);
     }
 }
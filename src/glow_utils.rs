@@ -0,0 +1,93 @@
+//! Versions of the [`gl_utils`](crate::gl_utils) helpers written against
+//! glow's `HasContext`, so they work on any glow backend, including GLES
+//! and WebGL. Objects are glow's handle types rather than raw GL names.
+//!
+//! On desktop, get a context from `WindowController::glow_context`.
+
+use glow::HasContext;
+
+type Error = Box<dyn std::error::Error>;
+
+pub fn compile_shader<G: HasContext>(gl: &G, src: &str, ty: u32) -> Result<G::Shader, Error> {
+    let ty_str = match ty {
+        glow::VERTEX_SHADER => "vertex",
+        glow::FRAGMENT_SHADER => "fragment",
+        glow::GEOMETRY_SHADER => "geometry",
+        glow::COMPUTE_SHADER => "compute",
+        _ => return Err("Invalid shader type".into()),
+    };
+
+    unsafe {
+        let shader = gl.create_shader(ty)?;
+        gl.shader_source(shader, src);
+        gl.compile_shader(shader);
+
+        if gl.get_shader_compile_status(shader) {
+            Ok(shader)
+        } else {
+            let msg = format!("Failed to compile {} shader: {}", ty_str, gl.get_shader_info_log(shader));
+            gl.delete_shader(shader);
+            Err(msg.into())
+        }
+    }
+}
+
+pub fn link_shader_program<G: HasContext>(gl: &G, shaders: &[G::Shader]) -> Result<G::Program, Error> {
+    unsafe {
+        let program = gl.create_program()?;
+        for &shader in shaders {
+            gl.attach_shader(program, shader);
+        }
+
+        gl.link_program(program);
+
+        for &shader in shaders {
+            gl.detach_shader(program, shader);
+        }
+
+        if gl.get_program_link_status(program) {
+            Ok(program)
+        } else {
+            let msg = format!("Failed to link shader program: {}", gl.get_program_info_log(program));
+            gl.delete_program(program);
+            Err(msg.into())
+        }
+    }
+}
+
+pub fn create_buffer_f32<G: HasContext>(gl: &G, data: &[f32], usage: u32) -> Result<G::Buffer, Error> {
+    let bytes: Vec<u8> = data.iter().flat_map(|f| f.to_ne_bytes()).collect();
+    unsafe {
+        let buffer = gl.create_buffer()?;
+        gl.bind_buffer(glow::ARRAY_BUFFER, Some(buffer));
+        gl.buffer_data_u8_slice(glow::ARRAY_BUFFER, &bytes, usage);
+        Ok(buffer)
+    }
+}
+
+pub fn create_texture<G: HasContext>(gl: &G, format: u32, size: (u32, u32), data: &[u8]) -> Result<G::Texture, Error> {
+    unsafe {
+        let texture = gl.create_texture()?;
+        gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+        gl.tex_image_2d(
+            glow::TEXTURE_2D,
+            0,
+            format as i32,
+            size.0 as i32,
+            size.1 as i32,
+            0,
+            format,
+            glow::UNSIGNED_BYTE,
+            glow::PixelUnpackData::Slice(Some(data)),
+        );
+        Ok(texture)
+    }
+}
+
+pub fn create_texture_rgb<G: HasContext>(gl: &G, size: (u32, u32), data: &[u8]) -> Result<G::Texture, Error> {
+    create_texture(gl, glow::RGB, size, data)
+}
+
+pub fn create_texture_rgba<G: HasContext>(gl: &G, size: (u32, u32), data: &[u8]) -> Result<G::Texture, Error> {
+    create_texture(gl, glow::RGBA, size, data)
+}
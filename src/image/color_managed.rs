@@ -0,0 +1,23 @@
+use super::{Image, PixelArray};
+
+type Error = Box<dyn std::error::Error>;
+
+impl Image {
+    /// Converts the pixels from the color space of an ICC profile to sRGB,
+    /// with relative colorimetric intent. Alpha is left alone.
+    pub fn convert_to_srgb(&mut self, icc_profile: &[u8]) -> Result<(), Error> {
+        let input = qcms::Profile::new_from_slice(icc_profile, false).ok_or("Couldn't parse the ICC profile")?;
+        let output = qcms::Profile::new_sRGB();
+
+        let (data, ty) = match &mut self.pixel_array {
+            PixelArray::RGB(data) => (data.as_mut_slice(), qcms::DataType::RGB8),
+            PixelArray::RGBA(data) => (data.as_mut_slice(), qcms::DataType::RGBA8),
+            PixelArray::Indexed { palette, .. } => (palette.as_flattened_mut(), qcms::DataType::RGBA8),
+        };
+
+        let transform = qcms::Transform::new(&input, &output, ty, qcms::Intent::RelativeColorimetric)
+            .ok_or("Couldn't convert from the ICC profile's color space")?;
+        transform.apply(data);
+        Ok(())
+    }
+}
@@ -0,0 +1,407 @@
+use super::{Image, PixelArray};
+
+/// An RGBA color with straight (not premultiplied) alpha.
+pub type Rgba8 = [u8; 4];
+
+/// How [`Image::draw_text`] draws text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextStyle {
+    /// The size of each character cell in pixels. Lines are `char_size.1`
+    /// apart.
+    pub char_size: (f32, f32),
+    pub color: Rgba8,
+}
+
+impl Image {
+    /// Draws `color` over the pixel at `(x, y)` with alpha blending. Pixels
+    /// outside the image are ignored, as are the alpha of RGB images.
+    /// Indexed images get the palette entry closest to the blended color.
+    pub fn blend_pixel(&mut self, x: i32, y: i32, color: Rgba8) {
+        let (w, h) = self.size;
+        if x < 0 || y < 0 || x as u32 >= w || y as u32 >= h || color[3] == 0 {
+            return;
+        }
+
+        let index = y as usize * w as usize + x as usize;
+        let a = color[3] as u32;
+        match &mut self.pixel_array {
+            PixelArray::RGB(data) => {
+                let dst = &mut data[index * 3..index * 3 + 3];
+                for c in 0..3 {
+                    dst[c] = ((color[c] as u32 * a + dst[c] as u32 * (255 - a) + 127) / 255) as u8;
+                }
+            }
+
+            PixelArray::RGBA(data) => {
+                let dst: &mut [u8; 4] = (&mut data[index * 4..index * 4 + 4]).try_into().unwrap();
+                *dst = blend(*dst, color);
+            }
+
+            PixelArray::Indexed { palette, indices } => {
+                let dst = palette.get(indices[index] as usize).copied().unwrap_or_default();
+                if let Some(nearest) = nearest_color(palette, blend(dst, color)) {
+                    indices[index] = nearest;
+                }
+            }
+        }
+    }
+
+    /// A one pixel wide line from `a` to `b`, both ends included. Only the
+    /// part over the image is walked, so far away endpoints cost nothing.
+    pub fn draw_line(&mut self, a: (i32, i32), b: (i32, i32), color: Rgba8) {
+        let Some((a, b)) = clip_line(a, b, self.size) else {
+            return;
+        };
+        let (dx, dy) = ((b.0 - a.0).abs(), -(b.1 - a.1).abs());
+        let (sx, sy) = ((b.0 - a.0).signum(), (b.1 - a.1).signum());
+
+        let (mut x, mut y) = a;
+        let mut error = dx + dy;
+        loop {
+            self.blend_pixel(x as i32, y as i32, color);
+            if (x, y) == b {
+                break;
+            }
+
+            let e2 = 2 * error;
+            if e2 >= dy {
+                error += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                error += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// A one pixel wide outline just inside the rect.
+    pub fn draw_rect(&mut self, origin: (i32, i32), size: (u32, u32), color: Rgba8) {
+        if size.0 == 0 || size.1 == 0 {
+            return;
+        }
+
+        let (x0, y0) = origin;
+        let (x1, y1) = (x0 + size.0 as i32 - 1, y0 + size.1 as i32 - 1);
+        self.fill_rect((x0, y0), (size.0, 1), color);
+        if y1 > y0 {
+            self.fill_rect((x0, y1), (size.0, 1), color);
+        }
+        if size.1 > 2 {
+            self.fill_rect((x0, y0 + 1), (1, size.1 - 2), color);
+            if x1 > x0 {
+                self.fill_rect((x1, y0 + 1), (1, size.1 - 2), color);
+            }
+        }
+    }
+
+    pub fn fill_rect(&mut self, origin: (i32, i32), size: (u32, u32), color: Rgba8) {
+        let (w, h) = (self.size.0 as i64, self.size.1 as i64);
+        let x0 = (origin.0 as i64).clamp(0, w) as i32;
+        let y0 = (origin.1 as i64).clamp(0, h) as i32;
+        let x1 = (origin.0 as i64 + size.0 as i64).clamp(0, w) as i32;
+        let y1 = (origin.1 as i64 + size.1 as i64).clamp(0, h) as i32;
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                self.blend_pixel(x, y, color);
+            }
+        }
+    }
+
+    /// A one pixel wide circle outline.
+    pub fn draw_circle(&mut self, center: (i32, i32), radius: u32, color: Rgba8) {
+        let (cx, cy) = center;
+        let (mut x, mut y) = (radius as i32, 0);
+        let mut error = 1 - x;
+
+        while x >= y {
+            // each octant's point once, so blending doesn't double up where
+            // they meet
+            let mut points = [
+                (x, y), (y, x), (-y, x), (-x, y),
+                (-x, -y), (-y, -x), (y, -x), (x, -y),
+            ];
+            points.sort_unstable();
+            let mut last = None;
+            for (px, py) in points {
+                if last != Some((px, py)) {
+                    self.blend_pixel(cx + px, cy + py, color);
+                    last = Some((px, py));
+                }
+            }
+
+            y += 1;
+            if error < 0 {
+                error += 2 * y + 1;
+            } else {
+                x -= 1;
+                error += 2 * (y - x) + 1;
+            }
+        }
+    }
+
+    /// Draws `other` over this image with its top left at `pos`, alpha
+    /// blending if it has alpha.
+    pub fn blit(&mut self, other: &Image, pos: (i32, i32)) {
+        let (w, h) = other.size;
+        for y in 0..h as usize {
+            for x in 0..w as usize {
+                let index = y * w as usize + x;
+                let color = match &other.pixel_array {
+                    PixelArray::RGB(data) => [data[index * 3], data[index * 3 + 1], data[index * 3 + 2], 255],
+                    PixelArray::RGBA(data) => data[index * 4..index * 4 + 4].try_into().unwrap(),
+                    PixelArray::Indexed { palette, indices } => {
+                        palette.get(indices[index] as usize).copied().unwrap_or_default()
+                    }
+                };
+                self.blend_pixel(pos.0 + x as i32, pos.1 + y as i32, color);
+            }
+        }
+    }
+
+    /// Draws `text` in the stroke font used by
+    /// [`SystemTextRenderer`](crate::renderers::system_text::SystemTextRenderer),
+    /// with one pixel wide strokes, starting at `pos` as the top left of the
+    /// first character. `\n` starts a new line.
+    pub fn draw_text(&mut self, pos: (i32, i32), text: &str, style: TextStyle) {
+        use crate::renderers::system_text::glyph;
+
+        let (cw, ch) = style.char_size;
+        let char_scale = (0.8 * cw, 0.7 * ch);
+        let mut char_start = (pos.0 as f32, pos.1 as f32);
+        for c in text.chars() {
+            if c == '\n' {
+                char_start = (pos.0 as f32, char_start.1 + ch);
+                continue;
+            }
+
+            for segment in glyph(c).unwrap_or(&[]).chunks_exact(4) {
+                let point = |x: f32, y: f32| {
+                    let x = char_start.0 + x * char_scale.0;
+                    let y = char_start.1 + y * char_scale.1;
+                    (x.round() as i32, y.round() as i32)
+                };
+                self.draw_line(point(segment[0], segment[1]), point(segment[2], segment[3]), style.color);
+            }
+            char_start.0 += cw;
+        }
+    }
+}
+
+/// The part of the segment from `a` to `b` that crosses a `size` image,
+/// clipped to its pixels' edges. Ends inside the image are kept exactly.
+/// `None` if the segment misses the image.
+fn clip_line(a: (i32, i32), b: (i32, i32), size: (u32, u32)) -> Option<((i64, i64), (i64, i64))> {
+    let (x0, y0) = (a.0 as f64, a.1 as f64);
+    let (dx, dy) = (b.0 as f64 - x0, b.1 as f64 - y0);
+    let (min, max_x, max_y) = (-0.5, size.0 as f64 - 0.5, size.1 as f64 - 0.5);
+
+    // Liang-Barsky: the point at t is inside every edge where p * t <= q
+    let (mut t0, mut t1) = (0.0f64, 1.0f64);
+    for (p, q) in [(-dx, x0 - min), (dx, max_x - x0), (-dy, y0 - min), (dy, max_y - y0)] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else if p < 0.0 {
+            t0 = t0.max(q / p);
+        } else {
+            t1 = t1.min(q / p);
+        }
+    }
+    if t0 > t1 {
+        return None;
+    }
+
+    let point = |t: f64| ((x0 + t * dx).round() as i64, (y0 + t * dy).round() as i64);
+    Some((point(t0), point(t1)))
+}
+
+/// `src` over `dst`, in 0..=255 * 255 fixed point.
+fn blend(dst: Rgba8, src: Rgba8) -> Rgba8 {
+    let a = src[3] as u32;
+    let dst_a = dst[3] as u32 * (255 - a);
+    let out_a = a * 255 + dst_a;
+    if out_a == 0 {
+        return [0; 4];
+    }
+
+    let mut out = [0; 4];
+    for c in 0..3 {
+        let value = src[c] as u32 * a * 255 + dst[c] as u32 * dst_a;
+        out[c] = ((value + out_a / 2) / out_a) as u8;
+    }
+    out[3] = ((out_a + 127) / 255) as u8;
+    out
+}
+
+/// The index of the palette entry closest to `color`.
+fn nearest_color(palette: &[Rgba8], color: Rgba8) -> Option<u8> {
+    let distance = |p: &Rgba8| -> i32 { (0..4).map(|c| (p[c] as i32 - color[c] as i32).pow(2)).sum() };
+    palette
+        .iter()
+        .take(256)
+        .enumerate()
+        .min_by_key(|(_, p)| distance(p))
+        .map(|(i, _)| i as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Rgba8 = [255, 0, 0, 255];
+
+    fn rgba(size: (u32, u32)) -> Image {
+        Image::new(size, PixelArray::RGBA(vec![0; (size.0 * size.1 * 4) as usize]))
+    }
+
+    /// The positions of pixels that aren't fully transparent.
+    fn drawn(image: &Image) -> Vec<(i32, i32)> {
+        let PixelArray::RGBA(data) = &image.pixel_array else { panic!("not RGBA") };
+        let w = image.size.0 as usize;
+        let pixels = data.chunks_exact(4).enumerate().filter(|(_, p)| p[3] != 0);
+        pixels.map(|(i, _)| ((i % w) as i32, (i / w) as i32)).collect()
+    }
+
+    fn pixel(image: &Image, x: u32, y: u32) -> Rgba8 {
+        let PixelArray::RGBA(data) = &image.pixel_array else { panic!("not RGBA") };
+        let index = (y * image.size.0 + x) as usize * 4;
+        data[index..index + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn line_covers_both_ends_in_every_octant() {
+        for (dx, dy) in [(4, 2), (2, 4), (-2, 4), (-4, 2), (-4, -2), (-2, -4), (2, -4), (4, -2)] {
+            let mut image = rgba((9, 9));
+            let (a, b) = ((4, 4), (4 + dx, 4 + dy));
+            image.draw_line(a, b, RED);
+
+            let points = drawn(&image);
+            assert!(points.contains(&a) && points.contains(&b), "{a:?} to {b:?}: {points:?}");
+            // one pixel per step along the longer axis
+            let steps = dx.abs().max(dy.abs());
+            assert_eq!(points.len() as i32, steps + 1, "{a:?} to {b:?}: {points:?}");
+            for &(x, y) in &points {
+                // within half a pixel of the ideal line
+                let cross = ((x - a.0) * dy - (y - a.1) * dx).abs() as f32;
+                assert!(cross / steps as f32 <= 0.5 + 1e-6, "{a:?} to {b:?}: {points:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn line_of_one_point() {
+        let mut image = rgba((3, 3));
+        image.draw_line((1, 2), (1, 2), RED);
+        assert_eq!(drawn(&image), [(1, 2)]);
+    }
+
+    #[test]
+    fn line_is_clipped_to_the_image() {
+        let mut image = rgba((8, 4));
+        image.draw_line((-1000, 2), (1000, 2), RED);
+        assert_eq!(drawn(&image), (0..8).map(|x| (x, 2)).collect::<Vec<_>>());
+
+        let mut image = rgba((8, 4));
+        image.draw_line((-10, -10), (-1, 5), RED);
+        image.draw_line((8, 0), (20, 3), RED);
+        assert!(drawn(&image).is_empty());
+    }
+
+    #[test]
+    fn clipping_keeps_ends_inside_the_image() {
+        let mut clipped = rgba((8, 8));
+        clipped.draw_line((2, 3), (-37, 200), RED);
+        let mut reference = rgba((8, 8));
+        reference.draw_line((2, 3), (1, 8), RED);
+
+        assert!(drawn(&clipped).contains(&(2, 3)));
+        assert_eq!(drawn(&clipped), drawn(&reference));
+    }
+
+    #[test]
+    fn line_between_extreme_points_does_not_overflow() {
+        let mut image = rgba((5, 5));
+        image.draw_line((i32::MIN, i32::MIN), (i32::MAX, i32::MAX), RED);
+        assert_eq!(drawn(&image), (0..5).map(|i| (i, i)).collect::<Vec<_>>());
+
+        let mut image = rgba((5, 5));
+        image.draw_line((i32::MAX, 2), (i32::MIN, 2), RED);
+        assert_eq!(drawn(&image).len(), 5);
+    }
+
+    #[test]
+    fn circle_draws_each_point_once() {
+        let half = [255, 0, 0, 128];
+        for radius in 0..12 {
+            let mut image = rgba((32, 32));
+            image.draw_circle((16, 16), radius, half);
+
+            let points = drawn(&image);
+            assert!(!points.is_empty());
+            for (x, y) in points {
+                // a second blend would raise the alpha
+                assert_eq!(pixel(&image, x as u32, y as u32), half, "radius {radius} at {x}, {y}");
+                let distance = (((x - 16).pow(2) + (y - 16).pow(2)) as f32).sqrt();
+                assert!((distance - radius as f32).abs() < 1.0, "radius {radius} at {x}, {y}");
+            }
+        }
+    }
+
+    #[test]
+    fn circle_is_clipped_to_the_image() {
+        let mut image = rgba((4, 4));
+        image.draw_circle((0, 0), 3, RED);
+        assert_eq!(drawn(&image), [(3, 0), (3, 1), (2, 2), (0, 3), (1, 3)]);
+    }
+
+    #[test]
+    fn blending_into_rgb_ignores_destination_alpha() {
+        let mut image = Image::new((2, 1), PixelArray::RGB(vec![255, 255, 255, 0, 0, 0]));
+        image.blend_pixel(0, 0, [0, 0, 0, 128]);
+        image.blend_pixel(1, 0, [255, 0, 0, 255]);
+        image.blend_pixel(1, 0, [0, 255, 0, 0]);
+        let PixelArray::RGB(data) = &image.pixel_array else { panic!("not RGB") };
+        assert_eq!(data, &[127, 127, 127, 255, 0, 0]);
+    }
+
+    #[test]
+    fn blending_into_rgba_composites_alpha() {
+        let data = vec![255, 255, 255, 255, 0, 0, 0, 0, 10, 20, 30, 40];
+        let mut image = Image::new((3, 1), PixelArray::RGBA(data));
+        image.blend_pixel(0, 0, [0, 0, 0, 128]);
+        image.blend_pixel(1, 0, [255, 0, 0, 128]);
+        image.blend_pixel(2, 0, [1, 2, 3, 255]);
+        assert_eq!(pixel(&image, 0, 0), [127, 127, 127, 255]);
+        // over nothing, the color is kept as is
+        assert_eq!(pixel(&image, 1, 0), [255, 0, 0, 128]);
+        assert_eq!(pixel(&image, 2, 0), [1, 2, 3, 255]);
+
+        // half over half covers three quarters
+        image.blend_pixel(1, 0, [0, 0, 255, 128]);
+        assert_eq!(pixel(&image, 1, 0), [85, 0, 170, 192]);
+    }
+
+    #[test]
+    fn blending_into_indexed_picks_the_nearest_entry() {
+        let palette = vec![[0, 0, 0, 255], [255, 255, 255, 255], [128, 128, 128, 255]];
+        let mut image = Image::new((3, 1), PixelArray::Indexed { palette, indices: vec![1, 1, 0] });
+        image.blend_pixel(0, 0, [0, 0, 0, 128]);
+        image.blend_pixel(1, 0, [0, 0, 0, 20]);
+        image.blend_pixel(2, 0, [255, 255, 255, 255]);
+
+        let PixelArray::Indexed { indices, .. } = &image.pixel_array else { panic!("not indexed") };
+        assert_eq!(indices, &[2, 1, 1]);
+    }
+
+    #[test]
+    fn pixels_outside_the_image_are_ignored() {
+        let mut image = rgba((2, 2));
+        for (x, y) in [(-1, 0), (0, -1), (2, 0), (0, 2), (i32::MIN, i32::MAX)] {
+            image.blend_pixel(x, y, RED);
+        }
+        assert!(drawn(&image).is_empty());
+    }
+}
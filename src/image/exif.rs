@@ -0,0 +1,514 @@
+use super::Image;
+
+/// Information about how an image was taken and how its colors are meant,
+/// read from its file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    /// The EXIF orientation, 1 to 8, that the pixels were stored in. 1 means
+    /// upright. [`Image::load`] has already turned the image upright.
+    pub orientation: Option<u16>,
+    /// When the photo was taken, as `YYYY:MM:DD HH:MM:SS`.
+    pub date_time: Option<String>,
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    /// The embedded ICC color profile, from JPEGs, and from PNGs with the
+    /// `png` feature. With `color-managed`, [`Image::load`] has already
+    /// converted the pixels to sRGB.
+    pub icc_profile: Option<Vec<u8>>,
+}
+
+const TAG_MAKE: u16 = 0x010F;
+const TAG_MODEL: u16 = 0x0110;
+const TAG_ORIENTATION: u16 = 0x0112;
+const TAG_DATE_TIME: u16 = 0x0132;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+
+impl Metadata {
+    /// Reads EXIF metadata and any ICC profile from the bytes of a JPEG
+    /// file, which need only extend past its metadata segments. Returns
+    /// `None` for other files.
+    pub fn from_jpeg(bytes: &[u8]) -> Option<Metadata> {
+        if !bytes.starts_with(&[0xFF, 0xD8]) {
+            return None;
+        }
+
+        let mut metadata = Metadata::default();
+        let mut icc_chunks = Vec::new();
+        for (marker, segment) in jpeg_segments(bytes) {
+            match marker {
+                0xE1 if segment.starts_with(b"Exif\0\0") && metadata.orientation.is_none() => {
+                    metadata.read_exif(&segment[6..]);
+                }
+                // profiles too big for one segment are split, each piece
+                // numbered from 1
+                0xE2 if segment.starts_with(b"ICC_PROFILE\0") && segment.len() >= 14 => {
+                    icc_chunks.push((segment[12], &segment[14..]));
+                }
+                _ => (),
+            }
+        }
+
+        if !icc_chunks.is_empty() {
+            icc_chunks.sort_by_key(|(sequence, _)| *sequence);
+            metadata.icc_profile = Some(icc_chunks.iter().flat_map(|(_, data)| data.iter().copied()).collect());
+        }
+
+        Some(metadata)
+    }
+
+    fn read_exif(&mut self, tiff: &[u8]) -> Option<()> {
+        let tiff = Tiff::new(tiff)?;
+
+        let mut date_time = None;
+        let mut exif_ifd = None;
+
+        for entry in tiff.ifd(tiff.u32(4)?)? {
+            match entry.tag {
+                TAG_MAKE => self.camera_make = tiff.ascii(&entry),
+                TAG_MODEL => self.camera_model = tiff.ascii(&entry),
+                TAG_ORIENTATION => self.orientation = tiff.short(&entry),
+                TAG_DATE_TIME => date_time = tiff.ascii(&entry),
+                TAG_EXIF_IFD => exif_ifd = Some(entry.value_offset),
+                _ => (),
+            }
+        }
+
+        // prefer when the photo was taken over when the file was last edited
+        if let Some(entries) = exif_ifd.and_then(|offset| tiff.ifd(offset)) {
+            for entry in entries {
+                if entry.tag == TAG_DATE_TIME_ORIGINAL {
+                    self.date_time = tiff.ascii(&entry);
+                }
+            }
+        }
+        self.date_time = self.date_time.take().or(date_time);
+
+        Some(())
+    }
+}
+
+/// The marker and contents of each JPEG segment before the image data.
+fn jpeg_segments(bytes: &[u8]) -> impl Iterator<Item = (u8, &[u8])> {
+    let mut pos = 2;
+    std::iter::from_fn(move || {
+        if pos + 4 > bytes.len() || bytes[pos] != 0xFF {
+            return None;
+        }
+
+        let marker = bytes[pos + 1];
+        // start of scan: the metadata segments are all before it
+        if marker == 0xDA {
+            return None;
+        }
+
+        let length = u16::from_be_bytes([bytes[pos + 2], bytes[pos + 3]]) as usize;
+        let segment = bytes.get(pos + 4..pos + 2 + length.max(2))?;
+        pos += 2 + length.max(2);
+        Some((marker, segment))
+    })
+}
+
+struct IfdEntry {
+    tag: u16,
+    ty: u16,
+    count: u32,
+    value_offset: u32,
+    /// Where the entry's 4-byte value field is, for values stored inline.
+    position: usize,
+}
+
+struct Tiff<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl<'a> Tiff<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        let little_endian = match data.get(..2)? {
+            b"II" => true,
+            b"MM" => false,
+            _ => return None,
+        };
+
+        let tiff = Self { data, little_endian };
+        (tiff.u16(2)? == 42).then_some(tiff)
+    }
+
+    fn u16(&self, offset: usize) -> Option<u16> {
+        let bytes: [u8; 2] = self.data.get(offset..offset.checked_add(2)?)?.try_into().ok()?;
+        Some(if self.little_endian { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) })
+    }
+
+    fn u32(&self, offset: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.data.get(offset..offset.checked_add(4)?)?.try_into().ok()?;
+        Some(if self.little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) })
+    }
+
+    /// The entries of the IFD at `offset`, or `None` if any of it is
+    /// outside the data.
+    fn ifd(&self, offset: u32) -> Option<Vec<IfdEntry>> {
+        let offset = offset as usize;
+        let count = self.u16(offset)? as usize;
+
+        (0..count)
+            .map(|i| {
+                let position = offset + 2 + i * 12;
+                Some(IfdEntry {
+                    tag: self.u16(position)?,
+                    ty: self.u16(position + 2)?,
+                    count: self.u32(position + 4)?,
+                    value_offset: self.u32(position + 8)?,
+                    position: position + 8,
+                })
+            })
+            .collect()
+    }
+
+    fn short(&self, entry: &IfdEntry) -> Option<u16> {
+        const SHORT: u16 = 3;
+        if entry.ty != SHORT || entry.count == 0 {
+            return None;
+        }
+        self.u16(entry.position)
+    }
+
+    fn ascii(&self, entry: &IfdEntry) -> Option<String> {
+        const ASCII: u16 = 2;
+        if entry.ty != ASCII {
+            return None;
+        }
+
+        let count = entry.count as usize;
+        let start = if count <= 4 { entry.position } else { entry.value_offset as usize };
+        let bytes = self.data.get(start..start.checked_add(count)?)?;
+        let text = String::from_utf8_lossy(bytes);
+        let text = text.trim_end_matches('\0').trim();
+        (!text.is_empty()).then(|| text.to_string())
+    }
+}
+
+impl Image {
+    /// Returns a copy turned upright from an EXIF `orientation`, rotating
+    /// and mirroring as needed. Unknown values leave the image as it is.
+    pub fn oriented(&self, orientation: u16) -> Image {
+        let (w, h) = self.size;
+        if !(2..=8).contains(&orientation) || w == 0 || h == 0 {
+            return self.clone();
+        }
+
+        let pixels = self.pixel_array.as_ref();
+        let channels = pixels.bytes_per_pixel();
+        let src = pixels.bytes();
+
+        // 5 through 8 swap the axes
+        let size = if orientation >= 5 { (h, w) } else { (w, h) };
+        let mut data = Vec::with_capacity(src.len());
+        for y in 0..size.1 {
+            for x in 0..size.0 {
+                let (sx, sy) = match orientation {
+                    2 => (w - 1 - x, y),
+                    3 => (w - 1 - x, h - 1 - y),
+                    4 => (x, h - 1 - y),
+                    5 => (y, x),
+                    6 => (y, h - 1 - x),
+                    7 => (w - 1 - y, h - 1 - x),
+                    _ => (w - 1 - y, x),
+                };
+                let index = (sy * w + sx) as usize * channels;
+                data.extend_from_slice(&src[index..index + channels]);
+            }
+        }
+
+        let mut image = Image::new(size, self.pixel_array.with_data(data));
+        image.metadata = self.metadata.clone();
+        image
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::PixelArray;
+
+    enum Value {
+        Short(u16),
+        Ascii(&'static str),
+        Long(u32),
+        Ifd(Vec<(u16, Value)>),
+    }
+
+    fn u16_bytes(little_endian: bool, v: u16) -> [u8; 2] {
+        if little_endian { v.to_le_bytes() } else { v.to_be_bytes() }
+    }
+
+    fn u32_bytes(little_endian: bool, v: u32) -> [u8; 4] {
+        if little_endian { v.to_le_bytes() } else { v.to_be_bytes() }
+    }
+
+    /// Writes an IFD at the end of `out`, with values that don't fit inline
+    /// and sub-IFDs after it, and returns its offset.
+    fn write_ifd(out: &mut Vec<u8>, little_endian: bool, entries: &[(u16, Value)]) -> u32 {
+        let start = out.len();
+        out.extend(u16_bytes(little_endian, entries.len() as u16));
+        out.resize(start + 2 + entries.len() * 12 + 4, 0);
+
+        for (i, (tag, value)) in entries.iter().enumerate() {
+            let mut field = [0; 4];
+            let (ty, count) = match value {
+                Value::Short(v) => {
+                    field[..2].copy_from_slice(&u16_bytes(little_endian, *v));
+                    (3, 1)
+                }
+                Value::Ascii(text) => {
+                    let mut bytes = text.as_bytes().to_vec();
+                    bytes.push(0);
+                    if bytes.len() <= 4 {
+                        field[..bytes.len()].copy_from_slice(&bytes);
+                    } else {
+                        field = u32_bytes(little_endian, out.len() as u32);
+                        out.extend(&bytes);
+                    }
+                    (2, bytes.len() as u32)
+                }
+                Value::Long(v) => {
+                    field = u32_bytes(little_endian, *v);
+                    (4, 1)
+                }
+                Value::Ifd(sub) => {
+                    field = u32_bytes(little_endian, write_ifd(out, little_endian, sub));
+                    (4, 1)
+                }
+            };
+
+            let p = start + 2 + i * 12;
+            out[p..p + 2].copy_from_slice(&u16_bytes(little_endian, *tag));
+            out[p + 2..p + 4].copy_from_slice(&u16_bytes(little_endian, ty));
+            out[p + 4..p + 8].copy_from_slice(&u32_bytes(little_endian, count));
+            out[p + 8..p + 12].copy_from_slice(&field);
+        }
+
+        start as u32
+    }
+
+    fn tiff(little_endian: bool, entries: &[(u16, Value)]) -> Vec<u8> {
+        let mut out = if little_endian { b"II".to_vec() } else { b"MM".to_vec() };
+        out.extend(u16_bytes(little_endian, 42));
+        out.extend(u32_bytes(little_endian, 8));
+        write_ifd(&mut out, little_endian, entries);
+        out
+    }
+
+    /// A JPEG holding `segments` and the start of an empty scan.
+    fn jpeg(segments: &[(u8, Vec<u8>)]) -> Vec<u8> {
+        let mut out = vec![0xFF, 0xD8];
+        for (marker, payload) in segments {
+            out.extend([0xFF, *marker]);
+            out.extend((payload.len() as u16 + 2).to_be_bytes());
+            out.extend(payload);
+        }
+        out.extend([0xFF, 0xDA, 0x00, 0x02]);
+        out
+    }
+
+    fn exif_segment(tiff: &[u8]) -> (u8, Vec<u8>) {
+        let mut payload = b"Exif\0\0".to_vec();
+        payload.extend(tiff);
+        (0xE1, payload)
+    }
+
+    fn camera_tiff(little_endian: bool) -> Vec<u8> {
+        tiff(
+            little_endian,
+            &[
+                (TAG_MAKE, Value::Ascii("Glume")),
+                (TAG_MODEL, Value::Ascii("X1")),
+                (TAG_ORIENTATION, Value::Short(6)),
+                (TAG_DATE_TIME, Value::Ascii("2021:05:06 07:08:09")),
+                (
+                    TAG_EXIF_IFD,
+                    Value::Ifd(vec![(TAG_DATE_TIME_ORIGINAL, Value::Ascii("2020:01:02 03:04:05"))]),
+                ),
+            ],
+        )
+    }
+
+    #[test]
+    fn reads_both_byte_orders() {
+        for little_endian in [true, false] {
+            let metadata = Metadata::from_jpeg(&jpeg(&[exif_segment(&camera_tiff(little_endian))])).unwrap();
+            assert_eq!(
+                metadata,
+                Metadata {
+                    orientation: Some(6),
+                    date_time: Some("2020:01:02 03:04:05".to_string()),
+                    camera_make: Some("Glume".to_string()),
+                    camera_model: Some("X1".to_string()),
+                    icc_profile: None,
+                },
+                "little endian: {little_endian}",
+            );
+        }
+    }
+
+    #[test]
+    fn date_falls_back_to_last_modified() {
+        let tiff = tiff(true, &[(TAG_DATE_TIME, Value::Ascii("2021:05:06 07:08:09"))]);
+        let metadata = Metadata::from_jpeg(&jpeg(&[exif_segment(&tiff)])).unwrap();
+        assert_eq!(metadata.date_time.as_deref(), Some("2021:05:06 07:08:09"));
+    }
+
+    #[test]
+    fn ignores_values_of_the_wrong_type() {
+        let tiff = tiff(false, &[(TAG_ORIENTATION, Value::Long(6)), (TAG_MAKE, Value::Short(1))]);
+        let metadata = Metadata::from_jpeg(&jpeg(&[exif_segment(&tiff)])).unwrap();
+        assert_eq!(metadata, Metadata::default());
+    }
+
+    #[test]
+    fn other_files_have_no_metadata() {
+        assert_eq!(Metadata::from_jpeg(b""), None);
+        assert_eq!(Metadata::from_jpeg(b"\x89PNG\r\n\x1a\n"), None);
+    }
+
+    #[test]
+    fn jpeg_without_app1_has_empty_metadata() {
+        let app0 = (0xE0, b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0".to_vec());
+        assert_eq!(Metadata::from_jpeg(&jpeg(&[app0])), Some(Metadata::default()));
+        assert_eq!(Metadata::from_jpeg(&jpeg(&[])), Some(Metadata::default()));
+    }
+
+    #[test]
+    fn app1_without_exif_header_is_skipped() {
+        let xmp = (0xE1, b"http://ns.adobe.com/xap/1.0/\0<x/>".to_vec());
+        assert_eq!(Metadata::from_jpeg(&jpeg(&[xmp])), Some(Metadata::default()));
+    }
+
+    #[test]
+    fn truncated_files_do_not_panic() {
+        let file = jpeg(&[exif_segment(&camera_tiff(true))]);
+        for end in 0..file.len() {
+            let metadata = Metadata::from_jpeg(&file[..end]);
+            // the segment is only read once all of it is there
+            if let Some(metadata) = metadata {
+                assert!(metadata.orientation.is_none() || end >= file.len() - 4, "{end}");
+            }
+        }
+    }
+
+    #[test]
+    fn truncated_exif_segment_does_not_panic() {
+        let tiff = camera_tiff(false);
+        // the header and first IFD's five entries
+        let first_ifd_end = 8 + 2 + 5 * 12;
+        for end in 0..tiff.len() {
+            let metadata = Metadata::from_jpeg(&jpeg(&[exif_segment(&tiff[..end])])).unwrap();
+            if end < first_ifd_end {
+                assert_eq!(metadata, Metadata::default(), "{end}");
+            }
+        }
+    }
+
+    #[test]
+    fn segment_longer_than_the_file_ends_the_scan() {
+        let mut file = vec![0xFF, 0xD8, 0xFF, 0xE1, 0xFF, 0xFF];
+        file.extend(b"Exif\0\0II");
+        assert_eq!(Metadata::from_jpeg(&file), Some(Metadata::default()));
+    }
+
+    #[test]
+    fn out_of_range_offsets_return_none() {
+        let data = tiff(true, &[(TAG_ORIENTATION, Value::Short(3))]);
+        let tiff = Tiff::new(&data).unwrap();
+        assert!(tiff.ifd(8).is_some());
+        assert!(tiff.ifd(data.len() as u32).is_none());
+        assert!(tiff.ifd(u32::MAX).is_none());
+        assert!(tiff.ifd(u32::MAX - 1).is_none());
+        assert_eq!(tiff.u32(usize::MAX - 1), None);
+
+        let entry = IfdEntry { tag: TAG_MAKE, ty: 2, count: 16, value_offset: u32::MAX, position: 0 };
+        assert_eq!(tiff.ascii(&entry), None);
+        let entry = IfdEntry { tag: TAG_MAKE, ty: 2, count: u32::MAX, value_offset: 8, position: 0 };
+        assert_eq!(tiff.ascii(&entry), None);
+    }
+
+    #[test]
+    fn out_of_range_ifd_pointers_are_ignored() {
+        // the first IFD past the end
+        let mut data = b"MM\0\x2a".to_vec();
+        data.extend(0xFFFF_FFF0u32.to_be_bytes());
+        let metadata = Metadata::from_jpeg(&jpeg(&[exif_segment(&data)])).unwrap();
+        assert_eq!(metadata, Metadata::default());
+
+        // an EXIF IFD pointer past the end still leaves the first IFD's tags
+        let data = tiff(
+            true,
+            &[(TAG_ORIENTATION, Value::Short(8)), (TAG_EXIF_IFD, Value::Long(u32::MAX))],
+        );
+        let metadata = Metadata::from_jpeg(&jpeg(&[exif_segment(&data)])).unwrap();
+        assert_eq!(metadata.orientation, Some(8));
+    }
+
+    #[test]
+    fn rejects_bad_tiff_headers() {
+        assert!(Tiff::new(b"").is_none());
+        assert!(Tiff::new(b"II").is_none());
+        assert!(Tiff::new(b"IM\x2a\0\x08\0\0\0").is_none());
+        assert!(Tiff::new(b"II\0\x2a\x08\0\0\0").is_none());
+        assert!(Tiff::new(b"MM\0\x2a\0\0\0\x08").is_some());
+    }
+
+    /// A 3x2 image whose pixels are numbered 0 to 5 in reading order.
+    fn numbered() -> Image {
+        let data = (0..6u8).flat_map(|i| [i, i * 10, 0]).collect();
+        Image::new((3, 2), PixelArray::RGB(data))
+    }
+
+    fn numbers(image: &Image) -> ((u32, u32), Vec<u8>) {
+        let numbers = image.pixel_array().bytes().chunks(3).map(|p| p[0]).collect();
+        (image.size(), numbers)
+    }
+
+    #[test]
+    fn orientations_turn_the_image_upright() {
+        // each stored image shows as 0 1 2 / 3 4 5 once turned upright
+        let expected: [((u32, u32), [u8; 6]); 8] = [
+            ((3, 2), [0, 1, 2, 3, 4, 5]),
+            ((3, 2), [2, 1, 0, 5, 4, 3]),
+            ((3, 2), [5, 4, 3, 2, 1, 0]),
+            ((3, 2), [3, 4, 5, 0, 1, 2]),
+            ((2, 3), [0, 3, 1, 4, 2, 5]),
+            ((2, 3), [3, 0, 4, 1, 5, 2]),
+            ((2, 3), [5, 2, 4, 1, 3, 0]),
+            ((2, 3), [2, 5, 1, 4, 0, 3]),
+        ];
+        for (orientation, (size, pixels)) in (1..=8).zip(expected) {
+            assert_eq!(numbers(&numbered().oriented(orientation)), (size, pixels.to_vec()), "{orientation}");
+        }
+    }
+
+    #[test]
+    fn orientation_keeps_every_channel() {
+        let turned = numbered().oriented(6);
+        assert_eq!(&turned.pixel_array().bytes()[..6], [3, 30, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn rotations_undo_each_other() {
+        let image = numbered();
+        assert_eq!(numbers(&image.oriented(6).oriented(8)), numbers(&image));
+        assert_eq!(numbers(&image.oriented(3).oriented(3)), numbers(&image));
+        for mirror in [2, 4, 5, 7] {
+            assert_eq!(numbers(&image.oriented(mirror).oriented(mirror)), numbers(&image), "{mirror}");
+        }
+    }
+
+    #[test]
+    fn unknown_orientations_leave_the_image() {
+        for orientation in [0, 9, u16::MAX] {
+            assert_eq!(numbers(&numbered().oriented(orientation)), numbers(&numbered()));
+        }
+        let empty = Image::new((0, 4), PixelArray::RGB(Vec::new()));
+        assert_eq!(empty.oriented(6).size(), (0, 4));
+    }
+}
@@ -1,39 +1,105 @@
 use std::path::Path;
 
+#[cfg(feature = "color-managed")]
+mod color_managed;
+mod draw;
+mod exif;
+#[cfg(feature = "png")]
+mod png_encode;
+#[cfg(feature = "png")]
+mod png_region;
+mod texture_cache;
+mod thumbnail_cache;
+pub use draw::{Rgba8, TextStyle};
+pub use exif::Metadata;
+#[cfg(feature = "png")]
+pub use png_region::png_size;
+pub use texture_cache::TextureCache;
+pub use thumbnail_cache::ThumbnailCache;
+
 type Error = Box<dyn std::error::Error>;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PixelFormat {
-    RGB, RGBA,
+    RGB, RGBA, Indexed,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub enum PixelArrayRef<'a> {
     RGB(&'a [u8]),
     RGBA(&'a [u8]),
+    Indexed { palette: &'a [Rgba8], indices: &'a [u8] },
 }
 
-impl PixelArrayRef<'_> {
+impl<'a> PixelArrayRef<'a> {
 
     /// Returns the number of pixels in the pixel array.
     pub fn len(&self) -> usize {
         match self {
             PixelArrayRef::RGB(data) => data.len() / 3,
             PixelArrayRef::RGBA(data) => data.len() / 4,
+            PixelArrayRef::Indexed { indices, .. } => indices.len(),
         }
     }
 
-    pub fn as_ptr(&self) -> *const u8 {
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        match self {
+            PixelArrayRef::RGB(_) => PixelFormat::RGB,
+            PixelArrayRef::RGBA(_) => PixelFormat::RGBA,
+            PixelArrayRef::Indexed { .. } => PixelFormat::Indexed,
+        }
+    }
+
+    /// Returns the raw pixel bytes, regardless of format. For indexed
+    /// arrays these are the palette indices.
+    pub fn bytes(&self) -> &'a [u8] {
         match self {
-            PixelArrayRef::RGB(data) => data.as_ptr(),
-            PixelArrayRef::RGBA(data) => data.as_ptr(),
+            PixelArrayRef::RGB(data) => data,
+            PixelArrayRef::RGBA(data) => data,
+            PixelArrayRef::Indexed { indices, .. } => indices,
         }
     }
 
+    /// How many of [`PixelArrayRef::bytes`] make up each pixel.
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            PixelArrayRef::RGB(_) => 3,
+            PixelArrayRef::RGBA(_) => 4,
+            PixelArrayRef::Indexed { .. } => 1,
+        }
+    }
+
+    pub fn as_ptr(&self) -> *const u8 {
+        self.bytes().as_ptr()
+    }
+
     pub fn to_owned(&self) -> PixelArray {
         match self {
             PixelArrayRef::RGB(data) => PixelArray::RGB(data.to_vec()),
             PixelArrayRef::RGBA(data) => PixelArray::RGBA(data.to_vec()),
+            PixelArrayRef::Indexed { palette, indices } => PixelArray::Indexed {
+                palette: palette.to_vec(),
+                indices: indices.to_vec(),
+            },
+        }
+    }
+
+    /// Looks indexed pixels up in their palette, giving RGBA. Indices past
+    /// the end of the palette are transparent black. Other formats are
+    /// copied as they are.
+    pub fn expand_palette(&self) -> PixelArray {
+        match self {
+            PixelArrayRef::Indexed { palette, indices } => PixelArray::RGBA(
+                indices
+                    .iter()
+                    .flat_map(|&i| palette.get(i as usize).copied().unwrap_or_default())
+                    .collect(),
+            ),
+            _ => self.to_owned(),
         }
     }
 }
@@ -42,20 +108,74 @@ impl PixelArrayRef<'_> {
 pub enum PixelArray {
     RGB(Vec<u8>),
     RGBA(Vec<u8>),
+    /// One byte per pixel indexing into a palette of up to 256 colors, as
+    /// used by retro-style sprites. Swapping or cycling the palette recolors
+    /// the image without touching its pixels.
+    Indexed { palette: Vec<Rgba8>, indices: Vec<u8> },
 }
 
 impl PixelArray {
     pub fn len(&self) -> usize {
-        match self {
-            PixelArray::RGB(data) => data.len() / 3,
-            PixelArray::RGBA(data) => data.len() / 4,
-        }
+        self.as_ref().len()
     }
 
-    pub fn as_ref(&self) -> PixelArrayRef {
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.as_ref().format()
+    }
+
+    pub fn as_ref(&self) -> PixelArrayRef<'_> {
         match self {
             PixelArray::RGB(data) => PixelArrayRef::RGB(data),
             PixelArray::RGBA(data) => PixelArrayRef::RGBA(data),
+            PixelArray::Indexed { palette, indices } => PixelArrayRef::Indexed { palette, indices },
+        }
+    }
+
+    /// Multiplies the color of each RGBA pixel by its alpha, for drawing with
+    /// [`BlendMode::PremultipliedAlpha`](crate::gl_utils::BlendMode::PremultipliedAlpha).
+    /// RGB arrays are left alone, and indexed arrays have their palette
+    /// premultiplied.
+    pub fn premultiply(&mut self) {
+        for pixel in self.rgba_bytes_mut().chunks_exact_mut(4) {
+            let a = pixel[3] as u32;
+            for c in &mut pixel[..3] {
+                *c = ((*c as u32 * a + 127) / 255) as u8;
+            }
+        }
+    }
+
+    /// Undoes [`PixelArray::premultiply`]. Fully transparent pixels become
+    /// black, and precision lost to low alpha doesn't come back.
+    pub fn unpremultiply(&mut self) {
+        for pixel in self.rgba_bytes_mut().chunks_exact_mut(4) {
+            let a = pixel[3] as u32;
+            for c in &mut pixel[..3] {
+                *c = (*c as u32 * 255 + a / 2).checked_div(a).map_or(0, |v| v.min(255) as u8);
+            }
+        }
+    }
+
+    /// The bytes of RGBA pixels, or of an indexed array's palette. Empty for
+    /// RGB.
+    fn rgba_bytes_mut(&mut self) -> &mut [u8] {
+        match self {
+            PixelArray::RGB(_) => &mut [],
+            PixelArray::RGBA(data) => data,
+            PixelArray::Indexed { palette, .. } => palette.as_flattened_mut(),
+        }
+    }
+
+    /// Creates a pixel array of the same format holding `data`, keeping the
+    /// palette of an indexed array.
+    fn with_data(&self, data: Vec<u8>) -> PixelArray {
+        match self {
+            PixelArray::RGB(_) => PixelArray::RGB(data),
+            PixelArray::RGBA(_) => PixelArray::RGBA(data),
+            PixelArray::Indexed { palette, .. } => PixelArray::Indexed { palette: palette.clone(), indices: data },
         }
     }
 }
@@ -81,6 +201,13 @@ impl<'a> ImageRef<'a> {
         self.size
     }
 
+    pub fn pixel_array(&self) -> PixelArrayRef<'a> {
+        self.pixel_array
+    }
+
+    /// Indexed images are uploaded with their palette applied, as RGBA; see
+    /// [`IndexedTexture`](crate::renderers::image_renderer::IndexedTexture)
+    /// for keeping the indices on the GPU.
     pub fn create_texture(&self) -> Result<u32, Error> {
         use crate::gl_utils::{create_texture_rgb, create_texture_rgba};
 
@@ -89,6 +216,17 @@ impl<'a> ImageRef<'a> {
                 => create_texture_rgb(self.size, data),
             PixelArrayRef::RGBA(data)
                 => create_texture_rgba(self.size, data),
+            PixelArrayRef::Indexed { .. }
+                => create_texture_rgba(self.size, self.pixel_array.expand_palette().as_ref().bytes()),
+        }
+    }
+
+    /// The size of the texture `create_texture` makes, in bytes. Indexed
+    /// images are expanded to RGBA on upload.
+    pub fn texture_bytes(&self) -> u64 {
+        match self.pixel_array {
+            PixelArrayRef::Indexed { .. } => self.size.0 as u64 * self.size.1 as u64 * 4,
+            _ => self.pixel_array.bytes().len() as u64,
         }
     }
 }
@@ -97,6 +235,7 @@ impl<'a> ImageRef<'a> {
 pub struct Image {
     size: (u32, u32),
     pixel_array: PixelArray,
+    metadata: Metadata,
 }
 
 impl Image {
@@ -104,6 +243,7 @@ impl Image {
         Image {
             size,
             pixel_array,
+            metadata: Metadata::default(),
         }
     }
 
@@ -111,11 +251,156 @@ impl Image {
         self.size
     }
 
-    pub fn as_ref(&self) -> ImageRef {
+    /// EXIF metadata, for JPEGs loaded with [`Image::load`]. Copies such as
+    /// resized images keep it.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    pub fn set_metadata(&mut self, metadata: Metadata) {
+        self.metadata = metadata;
+    }
+
+    pub fn as_ref(&self) -> ImageRef<'_> {
         ImageRef::new(self.size, self.pixel_array.as_ref())
     }
 
+    pub fn pixel_array(&self) -> PixelArrayRef<'_> {
+        self.pixel_array.as_ref()
+    }
+
+    /// The palette of an indexed image.
+    pub fn palette(&self) -> Option<&[Rgba8]> {
+        match &self.pixel_array {
+            PixelArray::Indexed { palette, .. } => Some(palette),
+            _ => None,
+        }
+    }
+
+    /// The palette of an indexed image, for changing colors in place, e.g.
+    /// rotating a range of it for color cycling.
+    pub fn palette_mut(&mut self) -> Option<&mut Vec<Rgba8>> {
+        match &mut self.pixel_array {
+            PixelArray::Indexed { palette, .. } => Some(palette),
+            _ => None,
+        }
+    }
+
+    /// Replaces the palette of an indexed image, recoloring it.
+    pub fn set_palette(&mut self, new_palette: Vec<Rgba8>) -> Result<(), Error> {
+        if new_palette.len() > 256 {
+            return Err("A palette can't have more than 256 colors".into());
+        }
+
+        let palette = self.palette_mut().ok_or("Only indexed images have a palette")?;
+        *palette = new_palette;
+        Ok(())
+    }
+
+    /// Returns an RGBA copy of an indexed image with its palette applied, or
+    /// a plain copy of any other image.
+    pub fn palette_expanded(&self) -> Image {
+        let mut image = Image::new(self.size, self.pixel_array().expand_palette());
+        image.metadata = self.metadata.clone();
+        image
+    }
+
+    /// Returns a copy of the image resampled to `size`. Each destination pixel
+    /// is the average of the source pixels it covers, so this is suitable for
+    /// generating thumbnails. Indexed images come out as RGBA.
+    pub fn resized(&self, size: (u32, u32)) -> Image {
+        if let PixelArray::Indexed { .. } = self.pixel_array {
+            return self.palette_expanded().resized(size);
+        }
+
+        let (sw, sh) = (self.size.0 as usize, self.size.1 as usize);
+        let (dw, dh) = (size.0 as usize, size.1 as usize);
+        let src = self.pixel_array.as_ref();
+        let channels = src.bytes().len() / (sw * sh).max(1);
+        let src = src.bytes();
+
+        let mut data = vec![0u8; dw * dh * channels];
+        if sw == 0 || sh == 0 {
+            let mut image = Image::new(size, self.pixel_array.with_data(data));
+            image.metadata = self.metadata.clone();
+            return image;
+        }
+
+        let span = |d: usize, dst_len: usize, src_len: usize| {
+            let start = d * src_len / dst_len;
+            let end = ((d + 1) * src_len / dst_len).max(start + 1).min(src_len);
+            (start.min(src_len - 1), end)
+        };
+
+        let mut sum = vec![0u32; channels];
+        for dy in 0..dh {
+            let (y0, y1) = span(dy, dh, sh);
+            for dx in 0..dw {
+                let (x0, x1) = span(dx, dw, sw);
+                sum.iter_mut().for_each(|s| *s = 0);
+                for y in y0..y1 {
+                    for x in x0..x1 {
+                        let index = (y * sw + x) * channels;
+                        for c in 0..channels {
+                            sum[c] += src[index + c] as u32;
+                        }
+                    }
+                }
+
+                let count = ((y1 - y0) * (x1 - x0)) as u32;
+                let index = (dy * dw + dx) * channels;
+                for c in 0..channels {
+                    data[index + c] = (sum[c] / count) as u8;
+                }
+            }
+        }
+
+        let mut image = Image::new(size, self.pixel_array.with_data(data));
+        image.metadata = self.metadata.clone();
+        image
+    }
+
+    /// Returns a copy of the image scaled down to fit within `max_size`,
+    /// preserving the aspect ratio. Images that already fit are copied as-is.
+    pub fn downscaled_to_fit(&self, max_size: (u32, u32)) -> Image {
+        let (w, h) = self.size;
+        if w <= max_size.0 && h <= max_size.1 {
+            return self.clone();
+        }
+
+        let scale = f64::min(max_size.0 as f64 / w as f64, max_size.1 as f64 / h as f64);
+        let size = (
+            ((w as f64 * scale).round() as u32).max(1),
+            ((h as f64 * scale).round() as u32).max(1),
+        );
+
+        self.resized(size)
+    }
+
+    /// Loads an image file. JPEGs are turned upright according to their
+    /// EXIF orientation, and their metadata is kept; see [`Image::metadata`].
+    /// With the `color-managed` feature, images with an embedded ICC profile
+    /// are converted to sRGB.
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Image, Error> {
+        let path = path.as_ref();
+        let mut image = Self::decode(path)?;
+
+        if let Some(metadata) = read_metadata(path) {
+            image = image.oriented(metadata.orientation.unwrap_or(1));
+
+            #[cfg(feature = "color-managed")]
+            if let Some(profile) = &metadata.icc_profile {
+                // an unusable profile leaves the colors as they are
+                let _ = image.convert_to_srgb(profile);
+            }
+
+            image.metadata = metadata;
+        }
+
+        Ok(image)
+    }
+
+    fn decode(path: &Path) -> Result<Image, Error> {
         use stb_image::image::{load, LoadResult};
         match load(path) {
             LoadResult::Error(e) => Err(e.into()),
@@ -151,6 +436,65 @@ impl Image {
     pub fn create_texture(&self) -> Result<u32, Error> {
         self.as_ref().create_texture()
     }
+
+    /// Reads pixels back from the currently bound read framebuffer, with
+    /// `origin` at its bottom left as in OpenGL. Rows are flipped so the
+    /// image is top to bottom like every other `Image`.
+    ///
+    /// # Panics
+    ///
+    /// If `format` is `Indexed`, since framebuffers hold colors rather than
+    /// palette indices.
+    pub fn read_from_framebuffer(origin: (i32, i32), size: (u32, u32), format: PixelFormat) -> Image {
+        let (gl_format, channels) = match format {
+            PixelFormat::RGB => (gl::RGB, 3),
+            PixelFormat::RGBA => (gl::RGBA, 4),
+            PixelFormat::Indexed => panic!("Framebuffers can't be read as indexed images"),
+        };
+
+        let row_len = size.0 as usize * channels;
+        let mut data = vec![0u8; row_len * size.1 as usize];
+        unsafe {
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::ReadPixels(
+                origin.0,
+                origin.1,
+                size.0 as i32,
+                size.1 as i32,
+                gl_format,
+                gl::UNSIGNED_BYTE,
+                data.as_mut_ptr() as _,
+            );
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 4);
+        }
+
+        let mut flipped = Vec::with_capacity(data.len());
+        for row in data.chunks(row_len.max(1)).rev() {
+            flipped.extend_from_slice(row);
+        }
+
+        let pixel_array = match format {
+            PixelFormat::RGB => PixelArray::RGB(flipped),
+            _ => PixelArray::RGBA(flipped),
+        };
+        Image::new(size, pixel_array)
+    }
+}
+
+/// Reads metadata from the start of a file, where JPEGs keep it.
+fn read_metadata(path: &Path) -> Option<Metadata> {
+    use std::io::Read;
+
+    // EXIF and ICC segments come first; a large profile can span several
+    let mut header = Vec::new();
+    std::fs::File::open(path).ok()?.take(1024 * 1024).read_to_end(&mut header).ok()?;
+
+    #[cfg(feature = "png")]
+    if header.starts_with(b"\x89PNG") {
+        return png_region::read_png_metadata(path);
+    }
+
+    Metadata::from_jpeg(&header)
 }
 
 
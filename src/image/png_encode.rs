@@ -0,0 +1,28 @@
+use super::{Image, PixelArrayRef};
+
+use std::path::Path;
+
+type Error = Box<dyn std::error::Error>;
+
+impl Image {
+    /// Writes the image to a PNG file. Metadata isn't saved.
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        let mut encoder = png::Encoder::new(file, self.size.0, self.size.1);
+        match self.pixel_array() {
+            PixelArrayRef::RGB(_) => encoder.set_color(png::ColorType::Rgb),
+            PixelArrayRef::RGBA(_) => encoder.set_color(png::ColorType::Rgba),
+            PixelArrayRef::Indexed { palette, .. } => {
+                encoder.set_color(png::ColorType::Indexed);
+                encoder.set_palette(palette.iter().flat_map(|c| [c[0], c[1], c[2]]).collect::<Vec<_>>());
+                encoder.set_trns(palette.iter().map(|c| c[3]).collect::<Vec<_>>());
+            }
+        }
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(self.pixel_array().bytes())?;
+        writer.finish()?;
+        Ok(())
+    }
+}
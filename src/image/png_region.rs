@@ -0,0 +1,97 @@
+use super::{Image, Metadata, PixelArray};
+
+use std::path::Path;
+
+type Error = Box<dyn std::error::Error>;
+
+/// The size of a PNG file's image, read from its header without decoding.
+pub fn png_size<P: AsRef<Path>>(path: P) -> Result<(u32, u32), Error> {
+    let decoder = png::Decoder::new(std::fs::File::open(path)?);
+    let reader = decoder.read_info()?;
+    let info = reader.info();
+    Ok((info.width, info.height))
+}
+
+/// Reads the ICC profile of a PNG file, if it has one.
+pub(crate) fn read_png_metadata(path: &Path) -> Option<Metadata> {
+    let decoder = png::Decoder::new(std::fs::File::open(path).ok()?);
+    let reader = decoder.read_info().ok()?;
+    Some(Metadata {
+        icc_profile: reader.info().icc_profile.as_ref().map(|profile| profile.to_vec()),
+        ..Metadata::default()
+    })
+}
+
+impl Image {
+    /// Decodes only part of a PNG file: `size` pixels from `origin`, keeping
+    /// every `step`-th pixel in each direction for a lower resolution level.
+    /// The region is clipped to the image.
+    ///
+    /// Rows are decoded one at a time and only the region is kept, so peak
+    /// memory is about one source row plus the result, though the file is
+    /// still read up to the region's last row. Interlaced files are decoded
+    /// whole and then cropped.
+    pub fn load_png_region<P: AsRef<Path>>(
+        path: P,
+        origin: (u32, u32),
+        size: (u32, u32),
+        step: u32,
+    ) -> Result<Image, Error> {
+        let step = step.max(1);
+
+        let mut decoder = png::Decoder::new(std::fs::File::open(path)?);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+
+        let (width, height) = (reader.info().width, reader.info().height);
+        let x0 = origin.0.min(width);
+        let y0 = origin.1.min(height);
+        let x1 = origin.0.saturating_add(size.0).min(width);
+        let y1 = origin.1.saturating_add(size.1).min(height);
+        let out_size = ((x1 - x0).div_ceil(step), (y1 - y0).div_ceil(step));
+
+        let (color_type, _) = reader.output_color_type();
+        let (channels, alpha) = match color_type {
+            png::ColorType::Grayscale => (1, false),
+            png::ColorType::GrayscaleAlpha => (2, true),
+            png::ColorType::Rgb => (3, false),
+            png::ColorType::Rgba => (4, true),
+            png::ColorType::Indexed => return Err("Unexpected indexed PNG output".into()),
+        };
+        let out_channels = if alpha { 4 } else { 3 };
+
+        let mut data = Vec::with_capacity(out_size.0 as usize * out_size.1 as usize * out_channels);
+        let mut keep_row = |y: u32, row: &[u8]| {
+            if y < y0 || y >= y1 || !(y - y0).is_multiple_of(step) {
+                return;
+            }
+
+            for x in (x0..x1).step_by(step as usize) {
+                let pixel = &row[x as usize * channels..(x as usize + 1) * channels];
+                match channels {
+                    1 => data.extend_from_slice(&[pixel[0]; 3]),
+                    2 => data.extend_from_slice(&[pixel[0], pixel[0], pixel[0], pixel[1]]),
+                    _ => data.extend_from_slice(pixel),
+                }
+            }
+        };
+
+        if reader.info().interlaced {
+            let mut buffer = vec![0; reader.output_buffer_size()];
+            let frame = reader.next_frame(&mut buffer)?;
+            for (y, row) in buffer.chunks(frame.line_size).take(y1 as usize).enumerate() {
+                keep_row(y as u32, row);
+            }
+        } else {
+            let mut y = 0;
+            while y < y1 {
+                let row = reader.next_row()?.ok_or("PNG file ended early")?;
+                keep_row(y, row.data());
+                y += 1;
+            }
+        }
+
+        let pixel_array = if alpha { PixelArray::RGBA(data) } else { PixelArray::RGB(data) };
+        Ok(Image::new(out_size, pixel_array))
+    }
+}
@@ -0,0 +1,286 @@
+use super::Image;
+use crate::renderers::image_renderer::ImageTexture;
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
+
+type Error = Box<dyn std::error::Error>;
+
+type Loader<K> = Box<dyn FnMut(&K) -> Result<Image, Error>>;
+
+/// The least recently used bookkeeping behind [`TextureCache`], kept apart
+/// from loading and GL so the eviction order can be checked on its own.
+struct LruBudget<K, V> {
+    entries: HashMap<K, Entry<V>>,
+    budget: u64,
+    used: u64,
+    clock: u64,
+}
+
+struct Entry<V> {
+    value: V,
+    bytes: u64,
+    last_used: u64,
+}
+
+impl<K: Hash + Eq + Clone, V> LruBudget<K, V> {
+    fn new(budget: u64) -> Self {
+        Self {
+            entries: HashMap::new(),
+            budget,
+            used: 0,
+            clock: 0,
+        }
+    }
+
+    /// Returns the value for `key`, marking it as most recently used.
+    fn touch(&mut self, key: &K) -> Option<&V> {
+        self.clock += 1;
+        let entry = self.entries.get_mut(key)?;
+        entry.last_used = self.clock;
+        Some(&entry.value)
+    }
+
+    /// Adds `value` as most recently used, evicting others until its `bytes`
+    /// fit. It's added even if it's larger than the whole budget.
+    fn insert(&mut self, key: K, value: V, bytes: u64) {
+        self.remove(&key);
+        self.evict_to_fit(bytes);
+        self.clock += 1;
+        self.entries.insert(key, Entry { value, bytes, last_used: self.clock });
+        self.used += bytes;
+    }
+
+    /// Evicts least recently used values until `incoming` more bytes fit.
+    fn evict_to_fit(&mut self, incoming: u64) {
+        while self.used + incoming > self.budget {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+
+            match oldest {
+                Some(key) => self.remove(&key),
+                None => break,
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &K) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.used -= entry.bytes;
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.used = 0;
+    }
+
+    fn set_budget(&mut self, budget: u64) {
+        self.budget = budget;
+        self.evict_to_fit(0);
+    }
+}
+
+/// Keeps textures resident within a memory budget, evicting the least
+/// recently used ones when it's exceeded.
+///
+/// Textures are created on demand by a loader, so an evicted texture is just
+/// loaded again the next time it's asked for.
+pub struct TextureCache<K = PathBuf> {
+    loader: Loader<K>,
+    lru: LruBudget<K, ImageTexture>,
+}
+
+impl TextureCache<PathBuf> {
+    /// A cache of image files, loaded with [`Image::load`].
+    pub fn new(budget: u64) -> Self {
+        Self::with_loader(budget, |path: &PathBuf| Image::load(path))
+    }
+
+    /// Like [`TextureCache::get`], taking any path type.
+    pub fn get_path<P: AsRef<Path>>(&mut self, path: P) -> Result<&ImageTexture, Error> {
+        self.get(&path.as_ref().to_path_buf())
+    }
+}
+
+impl<K: Hash + Eq + Clone> TextureCache<K> {
+    /// A cache that creates the texture for a key from the image `loader`
+    /// returns for it. `budget` is in bytes of pixel data.
+    pub fn with_loader<F>(budget: u64, loader: F) -> Self
+    where
+        F: FnMut(&K) -> Result<Image, Error> + 'static,
+    {
+        Self {
+            loader: Box::new(loader),
+            lru: LruBudget::new(budget),
+        }
+    }
+
+    /// Returns the texture for `key`, loading and uploading it if it isn't
+    /// resident, and marks it as most recently used. Must be called with the
+    /// GL context current.
+    ///
+    /// A texture larger than the whole budget is still returned, after
+    /// everything else has been evicted.
+    pub fn get(&mut self, key: &K) -> Result<&ImageTexture, Error> {
+        if !self.lru.entries.contains_key(key) {
+            let image = (self.loader)(key)?;
+            let bytes = image.as_ref().texture_bytes();
+            // free the evicted textures before uploading the new one
+            self.lru.evict_to_fit(bytes);
+
+            let texture = ImageTexture::new(image.as_ref());
+            self.lru.insert(key.clone(), texture, bytes);
+        }
+
+        Ok(self.lru.touch(key).unwrap())
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.lru.entries.contains_key(key)
+    }
+
+    /// Frees the texture for `key`, if it's resident.
+    pub fn remove(&mut self, key: &K) {
+        self.lru.remove(key);
+    }
+
+    pub fn clear(&mut self) {
+        self.lru.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.lru.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lru.entries.is_empty()
+    }
+
+    /// Bytes of pixel data currently resident.
+    pub fn used(&self) -> u64 {
+        self.lru.used
+    }
+
+    pub fn budget(&self) -> u64 {
+        self.lru.budget
+    }
+
+    /// Changes the budget, evicting right away if it shrank.
+    pub fn set_budget(&mut self, budget: u64) {
+        self.lru.set_budget(budget);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resident(lru: &LruBudget<&'static str, ()>) -> Vec<&'static str> {
+        let mut keys: Vec<_> = lru.entries.keys().copied().collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    #[test]
+    fn evicts_least_recently_inserted_first() {
+        let mut lru = LruBudget::new(300);
+        lru.insert("a", (), 100);
+        lru.insert("b", (), 100);
+        lru.insert("c", (), 100);
+        assert_eq!(lru.used, 300);
+
+        lru.insert("d", (), 100);
+        assert_eq!(resident(&lru), ["b", "c", "d"]);
+        lru.insert("e", (), 100);
+        assert_eq!(resident(&lru), ["c", "d", "e"]);
+        assert_eq!(lru.used, 300);
+    }
+
+    #[test]
+    fn touching_an_entry_keeps_it_resident() {
+        let mut lru = LruBudget::new(300);
+        lru.insert("a", (), 100);
+        lru.insert("b", (), 100);
+        lru.insert("c", (), 100);
+        assert!(lru.touch(&"a").is_some());
+
+        lru.insert("d", (), 100);
+        assert_eq!(resident(&lru), ["a", "c", "d"]);
+        lru.insert("e", (), 100);
+        assert_eq!(resident(&lru), ["a", "d", "e"]);
+    }
+
+    #[test]
+    fn touching_a_missing_entry_returns_none() {
+        let mut lru = LruBudget::<&str, ()>::new(100);
+        assert!(lru.touch(&"a").is_none());
+    }
+
+    #[test]
+    fn evicts_as_many_as_needed_to_fit() {
+        let mut lru = LruBudget::new(300);
+        lru.insert("a", (), 100);
+        lru.insert("b", (), 100);
+        lru.insert("c", (), 100);
+
+        lru.insert("d", (), 250);
+        assert_eq!(resident(&lru), ["d"]);
+        assert_eq!(lru.used, 250);
+    }
+
+    #[test]
+    fn entry_larger_than_budget_is_kept_alone() {
+        let mut lru = LruBudget::new(100);
+        lru.insert("a", (), 50);
+        lru.insert("huge", (), 500);
+        assert_eq!(resident(&lru), ["huge"]);
+        assert_eq!(lru.used, 500);
+
+        lru.insert("b", (), 50);
+        assert_eq!(resident(&lru), ["b"]);
+        assert_eq!(lru.used, 50);
+    }
+
+    #[test]
+    fn reinserting_replaces_the_byte_count() {
+        let mut lru = LruBudget::new(300);
+        lru.insert("a", (), 100);
+        lru.insert("a", (), 200);
+        assert_eq!(resident(&lru), ["a"]);
+        assert_eq!(lru.used, 200);
+    }
+
+    #[test]
+    fn shrinking_the_budget_evicts_oldest() {
+        let mut lru = LruBudget::new(300);
+        lru.insert("a", (), 100);
+        lru.insert("b", (), 100);
+        lru.insert("c", (), 100);
+        lru.touch(&"a");
+
+        lru.set_budget(200);
+        assert_eq!(resident(&lru), ["a", "c"]);
+        assert_eq!(lru.used, 200);
+    }
+
+    #[test]
+    fn remove_and_clear_release_bytes() {
+        let mut lru = LruBudget::new(300);
+        lru.insert("a", (), 100);
+        lru.insert("b", (), 100);
+
+        lru.remove(&"a");
+        assert_eq!(lru.used, 100);
+        lru.remove(&"a");
+        assert_eq!(lru.used, 100);
+
+        lru.clear();
+        assert!(lru.entries.is_empty());
+        assert_eq!(lru.used, 0);
+    }
+}
@@ -0,0 +1,267 @@
+use super::{Image, PixelArray};
+use crate::renderers::image_renderer::ImageTexture;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+enum Entry {
+    Pending,
+    Loaded(Image),
+    Uploaded(ImageTexture),
+    Failed(String),
+}
+
+struct Job {
+    path: PathBuf,
+}
+
+struct JobResult {
+    path: PathBuf,
+    result: Result<Image, String>,
+}
+
+/// Generates downscaled thumbnails for image files on worker threads.
+///
+/// Thumbnails are optionally cached on disk, keyed by the source path,
+/// modification time, file size and thumbnail size, so a second run doesn't
+/// have to decode the full images again. Textures are only created when
+/// [`ThumbnailCache::texture`] is called, so callers should only ask for the
+/// thumbnails that are currently visible.
+pub struct ThumbnailCache {
+    entries: HashMap<PathBuf, Entry>,
+    job_sender: Option<Sender<Job>>,
+    result_receiver: Receiver<JobResult>,
+    /// Tells workers to skip the jobs still queued when the cache is dropped.
+    cancelled: Arc<AtomicBool>,
+    workers: Vec<std::thread::JoinHandle<()>>,
+}
+
+impl ThumbnailCache {
+    /// Creates a cache that produces thumbnails fitting within `max_size`,
+    /// using `num_workers` background threads. If `disk_cache_dir` is given,
+    /// generated thumbnails are stored there and reused on later runs.
+    pub fn new(max_size: (u32, u32), num_workers: usize, disk_cache_dir: Option<PathBuf>) -> Self {
+        let (job_sender, job_receiver) = channel::<Job>();
+        let (result_sender, result_receiver) = channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        if let Some(dir) = &disk_cache_dir {
+            // failing to create the directory just means nothing gets cached
+            let _ = std::fs::create_dir_all(dir);
+        }
+
+        let workers = (0..num_workers.max(1))
+            .map(|_| {
+                let job_receiver = job_receiver.clone();
+                let result_sender = result_sender.clone();
+                let disk_cache_dir = disk_cache_dir.clone();
+                let cancelled = cancelled.clone();
+                std::thread::spawn(move || loop {
+                    let job = match job_receiver.lock().unwrap().recv() {
+                        Ok(job) => job,
+                        Err(_) => break,
+                    };
+                    if cancelled.load(Ordering::Relaxed) {
+                        break;
+                    }
+
+                    let result = generate_thumbnail(&job.path, max_size, disk_cache_dir.as_deref());
+                    let result = JobResult { path: job.path, result };
+                    if result_sender.send(result).is_err() {
+                        break;
+                    }
+                })
+            })
+            .collect();
+
+        Self {
+            entries: HashMap::new(),
+            job_sender: Some(job_sender),
+            result_receiver,
+            cancelled,
+            workers,
+        }
+    }
+
+    /// Queues a thumbnail for generation without waiting for it. Paths that
+    /// were already requested are ignored.
+    pub fn request<P: AsRef<Path>>(&mut self, path: P) {
+        let path = path.as_ref();
+        if self.entries.contains_key(path) {
+            return;
+        }
+
+        self.entries.insert(path.to_path_buf(), Entry::Pending);
+        if let Some(sender) = &self.job_sender {
+            let _ = sender.send(Job { path: path.to_path_buf() });
+        }
+    }
+
+    /// Collects thumbnails finished by the workers. Returns true if any new
+    /// thumbnails became available, which usually warrants a redraw.
+    pub fn poll(&mut self) -> bool {
+        let mut any = false;
+        while let Ok(JobResult { path, result }) = self.result_receiver.try_recv() {
+            let entry = match result {
+                Ok(image) => Entry::Loaded(image),
+                Err(e) => Entry::Failed(e),
+            };
+            self.entries.insert(path, entry);
+            any = true;
+        }
+
+        any
+    }
+
+    /// Returns the thumbnail texture for `path`, uploading it on first use.
+    /// If the thumbnail hasn't been generated yet, it is requested and `None`
+    /// is returned. Must be called with the GL context current.
+    pub fn texture<P: AsRef<Path>>(&mut self, path: P) -> Option<&ImageTexture> {
+        let path = path.as_ref();
+        self.request(path);
+        self.poll();
+
+        let entry = self.entries.get_mut(path)?;
+        if let Entry::Loaded(image) = entry {
+            *entry = Entry::Uploaded(ImageTexture::new(image.as_ref()));
+        }
+
+        match entry {
+            Entry::Uploaded(texture) => Some(texture),
+            _ => None,
+        }
+    }
+
+    /// Returns the reason thumbnail generation failed for `path`, if it did.
+    pub fn error<P: AsRef<Path>>(&self, path: P) -> Option<&str> {
+        match self.entries.get(path.as_ref()) {
+            Some(Entry::Failed(e)) => Some(e),
+            _ => None,
+        }
+    }
+
+    /// Returns true if a thumbnail for `path` has been requested but is not
+    /// ready yet.
+    pub fn is_pending<P: AsRef<Path>>(&self, path: P) -> bool {
+        matches!(self.entries.get(path.as_ref()), Some(Entry::Pending))
+    }
+
+    /// Forgets the thumbnail for `path`, freeing its texture. The next
+    /// request generates it again.
+    pub fn remove<P: AsRef<Path>>(&mut self, path: P) {
+        self.entries.remove(path.as_ref());
+    }
+}
+
+impl Drop for ThumbnailCache {
+    fn drop(&mut self) {
+        // the channel still hands out every queued job after it's closed, so
+        // the flag is what makes workers exit once their current job is done
+        self.cancelled.store(true, Ordering::Relaxed);
+        self.job_sender = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn generate_thumbnail(path: &Path, max_size: (u32, u32), disk_cache_dir: Option<&Path>)
+    -> Result<Image, String>
+{
+    let cache_file = disk_cache_dir.and_then(|dir| {
+        let key = cache_key(path, max_size)?;
+        Some(dir.join(format!("{:016x}.thumb", key)))
+    });
+
+    if let Some(image) = cache_file.as_deref().and_then(read_cached_thumbnail) {
+        return Ok(image);
+    }
+
+    let image = Image::load(path).map_err(|e| e.to_string())?;
+    let thumbnail = image.downscaled_to_fit(max_size);
+
+    if let Some(cache_file) = cache_file {
+        // the disk cache is best-effort
+        let _ = write_cached_thumbnail(&cache_file, &thumbnail);
+    }
+
+    Ok(thumbnail)
+}
+
+/// A hash of everything that makes a thumbnail stale. FNV-1a rather than
+/// std's hasher, whose output may change between Rust releases and would
+/// invalidate the disk cache with them.
+fn cache_key(path: &Path, max_size: (u32, u32)) -> Option<u64> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(std::time::UNIX_EPOCH).ok()?;
+
+    let mut hash = Fnv1a::new();
+    hash.write(path.canonicalize().ok()?.as_os_str().as_encoded_bytes());
+    hash.write(&modified.as_nanos().to_le_bytes());
+    hash.write(&metadata.len().to_le_bytes());
+    hash.write(&max_size.0.to_le_bytes());
+    hash.write(&max_size.1.to_le_bytes());
+    Some(hash.0)
+}
+
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+const CACHE_MAGIC: &[u8; 4] = b"GLT1";
+
+fn write_cached_thumbnail(cache_file: &Path, image: &Image) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let pixels = image.pixel_array();
+    let channels = match pixels.format() {
+        super::PixelFormat::RGB => 3u8,
+        super::PixelFormat::RGBA => 4u8,
+        super::PixelFormat::Indexed => return write_cached_thumbnail(cache_file, &image.palette_expanded()),
+    };
+
+    let mut file = std::io::BufWriter::new(std::fs::File::create(cache_file)?);
+    file.write_all(CACHE_MAGIC)?;
+    file.write_all(&image.size().0.to_le_bytes())?;
+    file.write_all(&image.size().1.to_le_bytes())?;
+    file.write_all(&[channels])?;
+    file.write_all(pixels.bytes())?;
+    file.flush()
+}
+
+fn read_cached_thumbnail(cache_file: &Path) -> Option<Image> {
+    let data = std::fs::read(cache_file).ok()?;
+    if data.len() < 13 || &data[0..4] != CACHE_MAGIC {
+        return None;
+    }
+
+    let width = u32::from_le_bytes(data[4..8].try_into().ok()?);
+    let height = u32::from_le_bytes(data[8..12].try_into().ok()?);
+    let pixels = data[13..].to_vec();
+    if pixels.len() != width as usize * height as usize * data[12] as usize {
+        return None;
+    }
+
+    let pixel_array = match data[12] {
+        3 => PixelArray::RGB(pixels),
+        4 => PixelArray::RGBA(pixels),
+        _ => return None,
+    };
+
+    Some(Image::new((width, height), pixel_array))
+}
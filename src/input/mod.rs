@@ -0,0 +1,384 @@
+//! Named actions bound to keys and mouse buttons.
+//!
+//! Install an [`ActionMap`] with `WindowController::set_action_map`, and the
+//! window sends `Event::Action` whenever a bound action starts or stops, on
+//! top of the raw input events. Current state can also be queried with
+//! `WindowController::is_action_active`.
+//!
+//! For typing, [`TextInputAccumulator`] turns key and character events into
+//! text edits.
+
+use std::collections::HashMap;
+
+use crate::window::{ModifierState, MouseButton, VirtualKeyCode};
+
+mod text;
+pub use text::{TextEdit, TextInputAccumulator};
+
+/// The name of an action, like `"jump"` or `"pan"`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ActionId(String);
+
+impl ActionId {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        ActionId(name.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for ActionId {
+    fn from(name: &str) -> Self {
+        ActionId::new(name)
+    }
+}
+
+impl From<String> for ActionId {
+    fn from(name: String) -> Self {
+        ActionId(name)
+    }
+}
+
+impl PartialEq<str> for ActionId {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for ActionId {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl std::fmt::Display for ActionId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ActionState {
+    Pressed,
+    Released,
+}
+
+/// A physical input an action can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Input {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+}
+
+/// An input plus the modifiers that must be held with it. Extra modifiers
+/// don't prevent a match, so a plain `Space` binding still fires while
+/// Shift is held.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Binding {
+    pub input: Input,
+    pub modifiers: ModifierState,
+}
+
+impl Binding {
+    pub fn key(key: VirtualKeyCode) -> Self {
+        Self { input: Input::Key(key), modifiers: ModifierState::NONE }
+    }
+
+    pub fn mouse(button: MouseButton) -> Self {
+        Self { input: Input::Mouse(button), modifiers: ModifierState::NONE }
+    }
+
+    /// Requires `modifiers` to be held as well.
+    pub fn with_modifiers(self, modifiers: ModifierState) -> Self {
+        Self { modifiers, ..self }
+    }
+
+    fn modifiers_held(&self, held: ModifierState) -> bool {
+        let required = self.modifiers;
+        (!required.shift || held.shift)
+            && (!required.ctrl || held.ctrl)
+            && (!required.alt || held.alt)
+            && (!required.super_ || held.super_)
+    }
+}
+
+/// Bindings from inputs to named actions. An action can have any number of
+/// bindings, and is active while any of them is held.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ActionMap {
+    bindings: Vec<(ActionId, Binding)>,
+}
+
+impl ActionMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a binding for `action`. Binding the same thing twice has no
+    /// effect.
+    pub fn bind<A: Into<ActionId>>(&mut self, action: A, binding: Binding) {
+        let entry = (action.into(), binding);
+        if !self.bindings.contains(&entry) {
+            self.bindings.push(entry);
+        }
+    }
+
+    /// Removes every binding of `action`.
+    pub fn unbind_all(&mut self, action: &str) {
+        self.bindings.retain(|(a, _)| a != action);
+    }
+
+    /// Removes one binding of `action`.
+    pub fn unbind(&mut self, action: &str, binding: Binding) {
+        self.bindings.retain(|(a, b)| !(a == action && *b == binding));
+    }
+
+    /// Replaces every binding of `action` with a single one, as a controls
+    /// menu would.
+    pub fn rebind<A: Into<ActionId>>(&mut self, action: A, binding: Binding) {
+        let action = action.into();
+        self.unbind_all(action.as_str());
+        self.bind(action, binding);
+    }
+
+    pub fn bindings<'a>(&'a self, action: &'a str) -> impl Iterator<Item = Binding> + 'a {
+        self.bindings.iter().filter(move |(a, _)| a == action).map(|(_, b)| *b)
+    }
+
+    /// Every action with at least one binding, without duplicates.
+    pub fn actions(&self) -> Vec<&ActionId> {
+        let mut actions: Vec<&ActionId> = self.bindings.iter().map(|(a, _)| a).collect();
+        actions.sort();
+        actions.dedup();
+        actions
+    }
+}
+
+/// Which bindings of an [`ActionMap`] are currently held.
+#[derive(Debug, Default)]
+pub(crate) struct ActionTracker {
+    /// Indices into the map's bindings that are held, grouped by action.
+    active: HashMap<ActionId, Vec<usize>>,
+}
+
+impl ActionTracker {
+    /// Updates state for an input change and returns the actions that
+    /// started or stopped.
+    pub(crate) fn process(
+        &mut self,
+        map: &ActionMap,
+        input: Input,
+        state: ActionState,
+        modifiers: ModifierState,
+    ) -> Vec<(ActionId, ActionState)> {
+        let mut changes = Vec::new();
+
+        for (index, (action, binding)) in map.bindings.iter().enumerate() {
+            if binding.input != input {
+                continue;
+            }
+
+            let held = self.active.entry(action.clone()).or_default();
+            let was_active = !held.is_empty();
+
+            match state {
+                // key repeat presses land here again and change nothing
+                ActionState::Pressed if binding.modifiers_held(modifiers) => {
+                    if !held.contains(&index) {
+                        held.push(index);
+                    }
+                }
+                ActionState::Pressed => continue,
+                ActionState::Released => held.retain(|&i| i != index),
+            }
+
+            let is_active = !held.is_empty();
+            if was_active != is_active {
+                changes.push((action.clone(), state));
+            }
+        }
+
+        changes
+    }
+
+    pub(crate) fn is_active(&self, action: &str) -> bool {
+        self.active
+            .iter()
+            .any(|(a, held)| a == action && !held.is_empty())
+    }
+
+    /// Forgets all held bindings, e.g. when the map changes or focus is lost.
+    pub(crate) fn clear(&mut self) -> Vec<ActionId> {
+        self.active
+            .drain()
+            .filter(|(_, held)| !held.is_empty())
+            .map(|(action, _)| action)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CTRL: ModifierState = ModifierState { ctrl: true, ..ModifierState::NONE };
+    const CTRL_SHIFT: ModifierState = ModifierState { ctrl: true, shift: true, ..ModifierState::NONE };
+
+    fn pressed(action: &str) -> (ActionId, ActionState) {
+        (ActionId::new(action), ActionState::Pressed)
+    }
+
+    fn released(action: &str) -> (ActionId, ActionState) {
+        (ActionId::new(action), ActionState::Released)
+    }
+
+    fn press(
+        tracker: &mut ActionTracker,
+        map: &ActionMap,
+        key: VirtualKeyCode,
+        modifiers: ModifierState,
+    ) -> Vec<(ActionId, ActionState)> {
+        tracker.process(map, Input::Key(key), ActionState::Pressed, modifiers)
+    }
+
+    fn release(
+        tracker: &mut ActionTracker,
+        map: &ActionMap,
+        key: VirtualKeyCode,
+        modifiers: ModifierState,
+    ) -> Vec<(ActionId, ActionState)> {
+        tracker.process(map, Input::Key(key), ActionState::Released, modifiers)
+    }
+
+    #[test]
+    fn binding_twice_has_no_effect() {
+        let mut map = ActionMap::new();
+        map.bind("jump", Binding::key(VirtualKeyCode::Space));
+        map.bind("jump", Binding::key(VirtualKeyCode::Space));
+        map.bind("jump", Binding::key(VirtualKeyCode::W));
+        assert_eq!(map.bindings("jump").count(), 2);
+        assert_eq!(map.actions(), [&ActionId::new("jump")]);
+    }
+
+    #[test]
+    fn unbind_removes_one_binding() {
+        let mut map = ActionMap::new();
+        map.bind("jump", Binding::key(VirtualKeyCode::Space));
+        map.bind("jump", Binding::key(VirtualKeyCode::W));
+        map.bind("fire", Binding::mouse(MouseButton::Left));
+
+        map.unbind("jump", Binding::key(VirtualKeyCode::Space));
+        assert_eq!(map.bindings("jump").collect::<Vec<_>>(), [Binding::key(VirtualKeyCode::W)]);
+
+        map.unbind_all("jump");
+        assert_eq!(map.bindings("jump").count(), 0);
+        assert_eq!(map.actions(), [&ActionId::new("fire")]);
+    }
+
+    #[test]
+    fn rebind_replaces_every_binding() {
+        let mut map = ActionMap::new();
+        map.bind("jump", Binding::key(VirtualKeyCode::Space));
+        map.bind("jump", Binding::key(VirtualKeyCode::W));
+        map.bind("fire", Binding::key(VirtualKeyCode::F));
+
+        map.rebind("jump", Binding::key(VirtualKeyCode::J));
+        assert_eq!(map.bindings("jump").collect::<Vec<_>>(), [Binding::key(VirtualKeyCode::J)]);
+        assert_eq!(map.bindings("fire").collect::<Vec<_>>(), [Binding::key(VirtualKeyCode::F)]);
+
+        let mut tracker = ActionTracker::default();
+        assert!(press(&mut tracker, &map, VirtualKeyCode::Space, ModifierState::NONE).is_empty());
+        assert_eq!(press(&mut tracker, &map, VirtualKeyCode::J, ModifierState::NONE), [pressed("jump")]);
+    }
+
+    #[test]
+    fn action_is_active_while_any_binding_is_held() {
+        let mut map = ActionMap::new();
+        map.bind("jump", Binding::key(VirtualKeyCode::Space));
+        map.bind("jump", Binding::key(VirtualKeyCode::W));
+        let mut tracker = ActionTracker::default();
+        let none = ModifierState::NONE;
+
+        assert_eq!(press(&mut tracker, &map, VirtualKeyCode::Space, none), [pressed("jump")]);
+        assert!(press(&mut tracker, &map, VirtualKeyCode::W, none).is_empty());
+        assert!(release(&mut tracker, &map, VirtualKeyCode::Space, none).is_empty());
+        assert!(tracker.is_active("jump"));
+        assert_eq!(release(&mut tracker, &map, VirtualKeyCode::W, none), [released("jump")]);
+        assert!(!tracker.is_active("jump"));
+    }
+
+    #[test]
+    fn key_repeat_changes_nothing() {
+        let mut map = ActionMap::new();
+        map.bind("jump", Binding::key(VirtualKeyCode::Space));
+        let mut tracker = ActionTracker::default();
+
+        assert_eq!(press(&mut tracker, &map, VirtualKeyCode::Space, ModifierState::NONE).len(), 1);
+        assert!(press(&mut tracker, &map, VirtualKeyCode::Space, ModifierState::NONE).is_empty());
+        assert_eq!(release(&mut tracker, &map, VirtualKeyCode::Space, ModifierState::NONE).len(), 1);
+    }
+
+    #[test]
+    fn chord_needs_its_modifiers_held() {
+        let mut map = ActionMap::new();
+        map.bind("save", Binding::key(VirtualKeyCode::S).with_modifiers(CTRL));
+        let mut tracker = ActionTracker::default();
+
+        assert!(press(&mut tracker, &map, VirtualKeyCode::S, ModifierState::NONE).is_empty());
+        assert!(!tracker.is_active("save"));
+        release(&mut tracker, &map, VirtualKeyCode::S, ModifierState::NONE);
+
+        assert_eq!(press(&mut tracker, &map, VirtualKeyCode::S, CTRL), [pressed("save")]);
+        // releasing Ctrl first still ends the action when S comes up
+        assert_eq!(release(&mut tracker, &map, VirtualKeyCode::S, ModifierState::NONE), [released("save")]);
+    }
+
+    #[test]
+    fn extra_modifiers_still_match() {
+        let mut map = ActionMap::new();
+        map.bind("jump", Binding::key(VirtualKeyCode::Space));
+        map.bind("save", Binding::key(VirtualKeyCode::S).with_modifiers(CTRL));
+        let mut tracker = ActionTracker::default();
+
+        assert_eq!(press(&mut tracker, &map, VirtualKeyCode::Space, CTRL_SHIFT).len(), 1);
+        assert_eq!(press(&mut tracker, &map, VirtualKeyCode::S, CTRL_SHIFT), [pressed("save")]);
+    }
+
+    #[test]
+    fn chord_and_plain_binding_on_the_same_key() {
+        let mut map = ActionMap::new();
+        map.bind("save", Binding::key(VirtualKeyCode::S).with_modifiers(CTRL));
+        map.bind("back", Binding::key(VirtualKeyCode::S));
+        let mut tracker = ActionTracker::default();
+
+        let mut changes = press(&mut tracker, &map, VirtualKeyCode::S, CTRL);
+        changes.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(changes, [pressed("back"), pressed("save")]);
+
+        release(&mut tracker, &map, VirtualKeyCode::S, CTRL);
+        assert_eq!(press(&mut tracker, &map, VirtualKeyCode::S, ModifierState::NONE), [pressed("back")]);
+    }
+
+    #[test]
+    fn clear_reports_held_actions() {
+        let mut map = ActionMap::new();
+        map.bind("jump", Binding::key(VirtualKeyCode::Space));
+        map.bind("fire", Binding::mouse(MouseButton::Left));
+        let mut tracker = ActionTracker::default();
+
+        press(&mut tracker, &map, VirtualKeyCode::Space, ModifierState::NONE);
+        tracker.process(&map, Input::Mouse(MouseButton::Left), ActionState::Pressed, ModifierState::NONE);
+        tracker.process(&map, Input::Mouse(MouseButton::Left), ActionState::Released, ModifierState::NONE);
+
+        assert_eq!(tracker.clear(), [ActionId::new("jump")]);
+        assert!(!tracker.is_active("jump"));
+        assert!(tracker.clear().is_empty());
+    }
+}
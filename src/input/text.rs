@@ -0,0 +1,228 @@
+use crate::window::{Event, ModifierState, VirtualKeyCode};
+
+/// One editing operation for a text field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEdit {
+    Insert(char),
+    /// Deletes the character before the cursor.
+    Backspace,
+    /// Deletes the character after the cursor.
+    Delete,
+    DeleteWordBackward,
+    DeleteWordForward,
+    Newline,
+}
+
+/// Turns the window's key and character events into a stream of
+/// [`TextEdit`]s, so text fields don't each have to sort out which events
+/// produce text.
+///
+/// Pass every event to [`TextInputAccumulator::process`], then take the
+/// edits with [`TextInputAccumulator::drain`]. Characters come from
+/// `Event::ReceivedCharacter`, so they follow the keyboard layout and lock
+/// keys; editing keys come from `Event::KeyPressed` and repeat while held.
+/// Ctrl with Backspace or Delete deletes a word.
+#[derive(Debug, Clone, Default)]
+pub struct TextInputAccumulator {
+    edits: Vec<TextEdit>,
+    modifiers: ModifierState,
+}
+
+impl TextInputAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn process(&mut self, event: &Event) {
+        use VirtualKeyCode as Vk;
+
+        match event {
+            Event::ModifiersChanged(modifiers) => self.modifiers = *modifiers,
+
+            Event::KeyPressed(key_event) => {
+                self.modifiers = key_event.modifiers;
+                let word = key_event.modifiers.ctrl;
+                let edit = match key_event.key {
+                    Vk::Back if word => TextEdit::DeleteWordBackward,
+                    Vk::Back => TextEdit::Backspace,
+                    Vk::Delete if word => TextEdit::DeleteWordForward,
+                    Vk::Delete => TextEdit::Delete,
+                    Vk::Return | Vk::NumpadEnter => TextEdit::Newline,
+                    _ => return,
+                };
+                self.edits.push(edit);
+            }
+
+            Event::KeyReleased(key_event) => self.modifiers = key_event.modifiers,
+
+            // editing keys also arrive as control characters, and Ctrl
+            // shortcuts aren't text; Ctrl+Alt is AltGr on Windows
+            Event::ReceivedCharacter(c) => {
+                let shortcut = self.modifiers.ctrl && !self.modifiers.alt;
+                if !c.is_control() && !shortcut {
+                    self.edits.push(TextEdit::Insert(*c));
+                }
+            }
+
+            Event::Focused(false) => self.modifiers = ModifierState::NONE,
+
+            _ => (),
+        }
+    }
+
+    /// Takes the edits accumulated so far, oldest first.
+    pub fn drain(&mut self) -> Vec<TextEdit> {
+        std::mem::take(&mut self.edits)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window::KeyEvent;
+
+    const CTRL: ModifierState = ModifierState { ctrl: true, ..ModifierState::NONE };
+    const ALT_GR: ModifierState = ModifierState { ctrl: true, alt: true, ..ModifierState::NONE };
+
+    fn key(key: VirtualKeyCode, modifiers: ModifierState) -> KeyEvent {
+        KeyEvent { key, scancode: 0, is_repeat: false, modifiers }
+    }
+
+    fn edits(events: &[Event]) -> Vec<TextEdit> {
+        let mut accumulator = TextInputAccumulator::new();
+        for event in events {
+            accumulator.process(event);
+        }
+        accumulator.drain()
+    }
+
+    #[test]
+    fn characters_become_inserts() {
+        let events = [Event::ReceivedCharacter('h'), Event::ReceivedCharacter('i')];
+        assert_eq!(edits(&events), [TextEdit::Insert('h'), TextEdit::Insert('i')]);
+    }
+
+    #[test]
+    fn composed_text_arrives_as_committed_characters() {
+        // an input method commits its composition as a run of characters,
+        // with no key presses of its own in between
+        let events = [
+            Event::ReceivedCharacter('日'),
+            Event::ReceivedCharacter('本'),
+            Event::ReceivedCharacter('🎉'),
+        ];
+        assert_eq!(
+            edits(&events),
+            [TextEdit::Insert('日'), TextEdit::Insert('本'), TextEdit::Insert('🎉')],
+        );
+    }
+
+    #[test]
+    fn dead_key_press_inserts_only_the_composed_character() {
+        let events = [
+            Event::KeyPressed(key(VirtualKeyCode::Apostrophe, ModifierState::NONE)),
+            Event::KeyReleased(key(VirtualKeyCode::Apostrophe, ModifierState::NONE)),
+            Event::KeyPressed(key(VirtualKeyCode::E, ModifierState::NONE)),
+            Event::ReceivedCharacter('é'),
+        ];
+        assert_eq!(edits(&events), [TextEdit::Insert('é')]);
+    }
+
+    #[test]
+    fn editing_keys_ignore_their_control_characters() {
+        let events = [
+            Event::KeyPressed(key(VirtualKeyCode::Back, ModifierState::NONE)),
+            Event::ReceivedCharacter('\u{8}'),
+            Event::KeyPressed(key(VirtualKeyCode::Delete, ModifierState::NONE)),
+            Event::ReceivedCharacter('\u{7f}'),
+            Event::KeyPressed(key(VirtualKeyCode::Return, ModifierState::NONE)),
+            Event::ReceivedCharacter('\r'),
+            Event::KeyPressed(key(VirtualKeyCode::NumpadEnter, ModifierState::NONE)),
+            Event::ReceivedCharacter('\r'),
+        ];
+        assert_eq!(
+            edits(&events),
+            [TextEdit::Backspace, TextEdit::Delete, TextEdit::Newline, TextEdit::Newline],
+        );
+    }
+
+    #[test]
+    fn held_editing_keys_repeat() {
+        let repeat = KeyEvent { is_repeat: true, ..key(VirtualKeyCode::Back, ModifierState::NONE) };
+        let events = [
+            Event::KeyPressed(key(VirtualKeyCode::Back, ModifierState::NONE)),
+            Event::KeyPressed(repeat),
+            Event::KeyPressed(repeat),
+        ];
+        assert_eq!(edits(&events), [TextEdit::Backspace; 3]);
+    }
+
+    #[test]
+    fn ctrl_deletes_words() {
+        let events = [
+            Event::KeyPressed(key(VirtualKeyCode::Back, CTRL)),
+            Event::KeyPressed(key(VirtualKeyCode::Delete, CTRL)),
+        ];
+        assert_eq!(edits(&events), [TextEdit::DeleteWordBackward, TextEdit::DeleteWordForward]);
+    }
+
+    #[test]
+    fn ctrl_shortcuts_are_not_text() {
+        let events = [
+            Event::ModifiersChanged(CTRL),
+            Event::KeyPressed(key(VirtualKeyCode::A, CTRL)),
+            Event::ReceivedCharacter('\u{1}'),
+            Event::KeyPressed(key(VirtualKeyCode::C, CTRL)),
+            Event::ReceivedCharacter('c'),
+        ];
+        assert!(edits(&events).is_empty());
+    }
+
+    #[test]
+    fn alt_gr_characters_are_text() {
+        let events = [
+            Event::ModifiersChanged(ALT_GR),
+            Event::KeyPressed(key(VirtualKeyCode::Q, ALT_GR)),
+            Event::ReceivedCharacter('@'),
+        ];
+        assert_eq!(edits(&events), [TextEdit::Insert('@')]);
+    }
+
+    #[test]
+    fn releasing_ctrl_allows_text_again() {
+        let events = [
+            Event::KeyPressed(key(VirtualKeyCode::LControl, CTRL)),
+            Event::ReceivedCharacter('x'),
+            Event::KeyReleased(key(VirtualKeyCode::LControl, ModifierState::NONE)),
+            Event::ReceivedCharacter('y'),
+        ];
+        assert_eq!(edits(&events), [TextEdit::Insert('y')]);
+    }
+
+    #[test]
+    fn losing_focus_forgets_held_modifiers() {
+        // the release of Ctrl happens in another window and never arrives
+        let events = [
+            Event::ModifiersChanged(CTRL),
+            Event::Focused(false),
+            Event::Focused(true),
+            Event::ReceivedCharacter('z'),
+        ];
+        assert_eq!(edits(&events), [TextEdit::Insert('z')]);
+    }
+
+    #[test]
+    fn drain_takes_the_edits() {
+        let mut accumulator = TextInputAccumulator::new();
+        assert!(accumulator.is_empty());
+        accumulator.process(&Event::ReceivedCharacter('a'));
+        assert!(!accumulator.is_empty());
+        assert_eq!(accumulator.drain(), [TextEdit::Insert('a')]);
+        assert!(accumulator.is_empty());
+        assert!(accumulator.drain().is_empty());
+    }
+}
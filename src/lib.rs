@@ -1,9 +1,20 @@
 pub mod window;
 pub mod gl_utils;
+#[cfg(feature = "glow")]
+pub mod glow_utils;
 pub mod renderers;
 pub mod image;
+pub mod input;
+pub mod diagnostics;
+#[cfg(feature = "bench")]
+pub mod bench;
 
 pub use gl;
+pub use raw_window_handle;
+#[cfg(feature = "raw-access")]
+pub use glutin;
+#[cfg(feature = "glow")]
+pub use glow;
 
 #[doc = include_str!("../README.md")]
 #[cfg(doctest)]
@@ -0,0 +1,5 @@
+mod spectrogram;
+mod waveform;
+
+pub use spectrogram::SpectrogramRenderer;
+pub use waveform::WaveformRenderer;
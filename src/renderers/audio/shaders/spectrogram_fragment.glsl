@@ -0,0 +1,30 @@
+#version 330 core
+
+in vec2 v_tex_coords;
+out vec4 f_color;
+
+// one column per frame, low frequencies in row 0
+uniform sampler2D u_history;
+// texture x of the oldest column, so the newest lands on the right
+uniform float u_offset;
+uniform float u_min_db;
+uniform float u_max_db;
+
+vec3 colormap(float t) {
+    const vec3 c0 = vec3(0.0, 0.0, 0.02);
+    const vec3 c1 = vec3(0.25, 0.05, 0.45);
+    const vec3 c2 = vec3(0.8, 0.2, 0.35);
+    const vec3 c3 = vec3(0.98, 0.6, 0.1);
+    const vec3 c4 = vec3(1.0, 1.0, 0.75);
+
+    if (t < 0.25) return mix(c0, c1, t * 4.0);
+    if (t < 0.5) return mix(c1, c2, (t - 0.25) * 4.0);
+    if (t < 0.75) return mix(c2, c3, (t - 0.5) * 4.0);
+    return mix(c3, c4, (t - 0.75) * 4.0);
+}
+
+void main() {
+    float db = texture(u_history, vec2(fract(v_tex_coords.x + u_offset), v_tex_coords.y)).r;
+    float t = clamp((db - u_min_db) / (u_max_db - u_min_db), 0.0, 1.0);
+    f_color = vec4(colormap(t), 1.0);
+}
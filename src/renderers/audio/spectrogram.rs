@@ -0,0 +1,420 @@
+use crate::gl_utils::{create_buffer_f32, Program};
+use crate::renderers::{Renderer, WindowRect};
+
+type Error = Box<dyn std::error::Error>;
+
+/// A scrolling time-frequency view of an audio stream.
+///
+/// Appended samples are cut into overlapping Hann-windowed frames, each
+/// becoming one column of decibel magnitudes. The newest column is on the
+/// right and low frequencies are at the bottom.
+pub struct SpectrogramRenderer {
+    program: Program,
+    vao: u32,
+    vbo: u32,
+    texture: u32,
+    fft_size: usize,
+    history: usize,
+    write_column: usize,
+    frames: Frames,
+    window: Vec<f32>,
+    viewport: WindowRect,
+    window_size: (u32, u32),
+    /// Magnitudes at or below this show as black.
+    pub min_db: f32,
+    /// Magnitudes at or above this show at full brightness.
+    pub max_db: f32,
+}
+
+impl SpectrogramRenderer {
+    /// `fft_size` is the frame length in samples and must be a power of two;
+    /// a new frame starts every `hop` samples, which can be more than
+    /// `fft_size` to skip samples between frames. `history` is how many
+    /// frames stay visible.
+    pub fn new(fft_size: usize, hop: usize, history: usize) -> Result<Self, Error> {
+        if !fft_size.is_power_of_two() || fft_size < 2 {
+            return Err(format!("FFT size must be a power of two, got {}", fft_size).into());
+        }
+        if hop == 0 || history == 0 {
+            return Err("Spectrogram hop and history must be nonzero".into());
+        }
+
+        let program = Program::new(
+            include_str!("shaders/spectrogram_vertex.glsl"),
+            include_str!("shaders/spectrogram_fragment.glsl"),
+        )?;
+
+        let vertices: &[f32] = &[
+            -1.0, -1.0,
+            1.0, -1.0,
+            1.0, 1.0,
+            -1.0, 1.0,
+        ];
+
+        let vbo = create_buffer_f32(vertices, gl::STATIC_DRAW)?;
+
+        let mut vao = 0;
+        let mut texture = 0;
+        let bins = fft_size / 2;
+        let silence = vec![-200.0f32; history * bins];
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, 0, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+
+            gl::GenTextures(1, &mut texture);
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::R32F as i32,
+                history as i32,
+                bins as i32,
+                0,
+                gl::RED,
+                gl::FLOAT,
+                silence.as_ptr() as _,
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            // linear filtering would blend the newest column into the oldest
+            // where the ring wraps
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+        }
+
+        let window = hann_window(fft_size);
+
+        Ok(Self {
+            program,
+            vao,
+            vbo,
+            texture,
+            fft_size,
+            history,
+            write_column: 0,
+            frames: Frames::new(fft_size, hop),
+            window,
+            viewport: WindowRect::default(),
+            window_size: (1, 1),
+            min_db: -90.0,
+            max_db: 0.0,
+        })
+    }
+
+    pub fn fft_size(&self) -> usize {
+        self.fft_size
+    }
+
+    /// Feeds samples in, analysing every complete frame.
+    pub fn append(&mut self, samples: &[f32]) {
+        self.frames.push(samples);
+
+        let mut column = vec![0.0f32; self.fft_size / 2];
+        while let Some(frame) = self.frames.next_frame() {
+            analyse(frame, &self.window, &mut column);
+            self.write(&column);
+        }
+    }
+
+    fn write(&mut self, column: &[f32]) {
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            gl::TexSubImage2D(
+                gl::TEXTURE_2D,
+                0,
+                self.write_column as i32,
+                0,
+                1,
+                column.len() as i32,
+                gl::RED,
+                gl::FLOAT,
+                column.as_ptr() as _,
+            );
+        }
+
+        self.write_column = (self.write_column + 1) % self.history;
+    }
+
+    /// Sets the area of the window the spectrogram occupies.
+    pub fn set_viewport(&mut self, viewport: WindowRect, window_size: (u32, u32)) {
+        self.viewport = viewport;
+        self.window_size = window_size;
+    }
+
+    pub fn render(&self) {
+        let [x, y, w, h] = self.viewport.to_f32();
+        let gl_y = self.window_size.1 as f32 - y - h;
+
+        let mut previous_viewport = [0i32; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, previous_viewport.as_mut_ptr());
+            gl::Viewport(x as i32, gl_y as i32, w as i32, h as i32);
+        }
+
+        // the next column to be written holds the oldest frame
+        let offset = self.write_column as f32 / self.history as f32;
+
+        let program = &self.program;
+        program.set_uniform_by_name("u_history", 0).unwrap();
+        program.set_uniform_by_name("u_offset", offset).unwrap();
+        program.set_uniform_by_name("u_min_db", self.min_db).unwrap();
+        program.set_uniform_by_name("u_max_db", self.max_db.max(self.min_db + 1e-3)).unwrap();
+
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+            gl::BindVertexArray(self.vao);
+            gl::DrawArrays(gl::TRIANGLE_FAN, 0, 4);
+
+            let [x, y, w, h] = previous_viewport;
+            gl::Viewport(x, y, w, h);
+        }
+    }
+}
+
+impl Drop for SpectrogramRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteTextures(1, &self.texture);
+        }
+    }
+}
+
+/// Cuts a stream of samples into frames of `size` samples, starting every
+/// `hop` samples.
+struct Frames {
+    size: usize,
+    hop: usize,
+    pending: Vec<f32>,
+    /// Where the next frame starts in `pending`. Past its end while samples
+    /// between frames are still to be skipped.
+    start: usize,
+}
+
+impl Frames {
+    fn new(size: usize, hop: usize) -> Self {
+        Self { size, hop, pending: Vec::new(), start: 0 }
+    }
+
+    fn push(&mut self, samples: &[f32]) {
+        // drop what's before the next frame, keeping count of any gap left
+        let done = self.start.min(self.pending.len());
+        self.pending.drain(..done);
+        self.start -= done;
+
+        let skip = self.start.min(samples.len());
+        self.start -= skip;
+        self.pending.extend_from_slice(&samples[skip..]);
+    }
+
+    fn next_frame(&mut self) -> Option<&[f32]> {
+        let frame = self.pending.get(self.start..self.start + self.size)?;
+        self.start += self.hop;
+        Some(frame)
+    }
+}
+
+fn hann_window(size: usize) -> Vec<f32> {
+    (0..size)
+        .map(|i| 0.5 - 0.5 * (std::f32::consts::TAU * i as f32 / size as f32).cos())
+        .collect()
+}
+
+/// Computes decibel magnitudes of one frame through `window`, relative to a
+/// full-scale sine.
+fn analyse(frame: &[f32], window: &[f32], out: &mut [f32]) {
+    let mut re: Vec<f32> = frame.iter().zip(window).map(|(s, w)| s * w).collect();
+    let mut im = vec![0.0f32; frame.len()];
+    fft(&mut re, &mut im);
+
+    // a full-scale sine through a Hann window peaks at n / 4
+    let reference = frame.len() as f32 / 4.0;
+    for (bin, out) in out.iter_mut().enumerate() {
+        let magnitude = (re[bin] * re[bin] + im[bin] * im[bin]).sqrt() / reference;
+        *out = 20.0 * magnitude.max(1e-10).log10();
+    }
+}
+
+/// In-place iterative radix-2 FFT. The length must be a power of two.
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+
+    let bits = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -std::f32::consts::TAU / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (s, c) = (angle * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let tr = re[b] * c - im[b] * s;
+                let ti = re[b] * s + im[b] * c;
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+            }
+        }
+        len *= 2;
+    }
+}
+
+impl Renderer for SpectrogramRenderer {
+    fn set_viewport(&mut self, viewport: WindowRect, window_size: (u32, u32)) -> Result<(), Error> {
+        SpectrogramRenderer::set_viewport(self, viewport, window_size);
+        Ok(())
+    }
+
+    fn render(&mut self) {
+        SpectrogramRenderer::render(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::TAU;
+
+    fn magnitudes(samples: &[f32]) -> Vec<f32> {
+        let mut re = samples.to_vec();
+        let mut im = vec![0.0; samples.len()];
+        fft(&mut re, &mut im);
+        re.iter().zip(&im).map(|(re, im)| re.hypot(*im)).collect()
+    }
+
+    #[test]
+    fn fft_of_an_impulse_is_flat() {
+        let mut impulse = vec![0.0; 64];
+        impulse[0] = 1.0;
+        for (bin, magnitude) in magnitudes(&impulse).into_iter().enumerate() {
+            assert!((magnitude - 1.0).abs() < 1e-5, "bin {bin}: {magnitude}");
+        }
+    }
+
+    #[test]
+    fn fft_of_a_sine_has_one_peak() {
+        let n = 64;
+        let sine: Vec<f32> = (0..n).map(|i| (TAU * 5.0 * i as f32 / n as f32).sin()).collect();
+        let magnitudes = magnitudes(&sine);
+
+        // a unit sine splits into bins k and n - k, each n / 2
+        for (bin, magnitude) in magnitudes.into_iter().enumerate() {
+            let expected = if bin == 5 || bin == n - 5 { n as f32 / 2.0 } else { 0.0 };
+            assert!((magnitude - expected).abs() < 1e-3, "bin {bin}: {magnitude}");
+        }
+    }
+
+    #[test]
+    fn fft_of_a_constant_is_all_in_bin_zero() {
+        let magnitudes = magnitudes(&[0.5; 16]);
+        assert!((magnitudes[0] - 8.0).abs() < 1e-5);
+        assert!(magnitudes[1..].iter().all(|m| m.abs() < 1e-5));
+    }
+
+    #[test]
+    fn full_scale_sine_analyses_to_zero_db() {
+        let n = 256;
+        let bin = 32;
+        let sine: Vec<f32> = (0..n).map(|i| (TAU * bin as f32 * i as f32 / n as f32).sin()).collect();
+        let mut out = vec![0.0; n / 2];
+        analyse(&sine, &hann_window(n), &mut out);
+
+        let peak = (0..out.len()).max_by(|&a, &b| out[a].total_cmp(&out[b])).unwrap();
+        assert_eq!(peak, bin);
+        assert!(out[bin].abs() < 0.01, "{} dB", out[bin]);
+        // the Hann window leaks into the neighbours at half amplitude only
+        assert!((out[bin - 1] + 6.02).abs() < 0.01 && (out[bin + 1] + 6.02).abs() < 0.01);
+        assert!(out[bin + 4] < -90.0);
+    }
+
+    #[test]
+    fn half_scale_sine_is_six_db_down() {
+        let n = 128;
+        let sine: Vec<f32> = (0..n).map(|i| 0.5 * (TAU * 10.0 * i as f32 / n as f32).sin()).collect();
+        let mut out = vec![0.0; n / 2];
+        analyse(&sine, &hann_window(n), &mut out);
+        assert!((out[10] + 6.02).abs() < 0.01, "{} dB", out[10]);
+    }
+
+    fn collect(frames: &mut Frames) -> Vec<Vec<f32>> {
+        let mut collected = Vec::new();
+        while let Some(frame) = frames.next_frame() {
+            collected.push(frame.to_vec());
+        }
+        collected
+    }
+
+    fn ramp(range: std::ops::Range<u32>) -> Vec<f32> {
+        range.map(|i| i as f32).collect()
+    }
+
+    #[test]
+    fn frames_overlap_when_hop_is_smaller() {
+        let mut frames = Frames::new(4, 2);
+        frames.push(&ramp(0..9));
+        assert_eq!(collect(&mut frames), [ramp(0..4), ramp(2..6), ramp(4..8)]);
+        frames.push(&ramp(9..11));
+        assert_eq!(collect(&mut frames), [ramp(6..10)]);
+    }
+
+    #[test]
+    fn frames_skip_samples_when_hop_is_larger() {
+        let mut frames = Frames::new(4, 6);
+        frames.push(&ramp(0..20));
+        assert_eq!(collect(&mut frames), [ramp(0..4), ramp(6..10), ramp(12..16)]);
+
+        // the gap before the next frame at 18 spans two pushes
+        frames.push(&ramp(20..21));
+        assert!(collect(&mut frames).is_empty());
+        frames.push(&ramp(21..30));
+        assert_eq!(collect(&mut frames), [ramp(18..22), ramp(24..28)]);
+    }
+
+    #[test]
+    fn frames_skip_across_pushes_smaller_than_the_gap() {
+        let mut frames = Frames::new(2, 10);
+        frames.push(&ramp(0..2));
+        assert_eq!(collect(&mut frames), [ramp(0..2)]);
+        for i in 2..12 {
+            frames.push(&[i as f32]);
+            let collected = collect(&mut frames);
+            if i == 11 {
+                assert_eq!(collected, [ramp(10..12)]);
+            } else {
+                assert!(collected.is_empty(), "{i}");
+            }
+        }
+    }
+
+    #[test]
+    fn frames_do_not_depend_on_how_samples_are_split() {
+        for (size, hop) in [(8, 3), (8, 8), (4, 11)] {
+            let samples = ramp(0..100);
+            let mut whole = Frames::new(size, hop);
+            whole.push(&samples);
+            let expected = collect(&mut whole);
+
+            let mut pieces = Frames::new(size, hop);
+            let mut collected = Vec::new();
+            for chunk in samples.chunks(7) {
+                pieces.push(chunk);
+                collected.extend(collect(&mut pieces));
+            }
+            assert_eq!(collected, expected, "size {size}, hop {hop}");
+        }
+    }
+}
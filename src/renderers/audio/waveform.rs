@@ -0,0 +1,230 @@
+use crate::renderers::shapes::ShapeRenderer;
+use crate::renderers::{Renderer, WindowRect};
+use crate::window::{Event, MouseButton, MouseScrollDelta};
+
+type Error = Box<dyn std::error::Error>;
+
+/// Draws audio samples as a min/max envelope, one vertical bar per pixel
+/// column, so any zoom level shows every peak.
+///
+/// The mouse wheel zooms around the cursor and dragging with the left button
+/// scrolls. While following, the view keeps the newest samples at its right
+/// edge as they are appended.
+pub struct WaveformRenderer {
+    shapes: ShapeRenderer,
+    samples: Vec<f32>,
+    max_samples: Option<usize>,
+    /// Index of the sample at the left edge of the view.
+    view_start: f64,
+    samples_per_pixel: f64,
+    viewport: WindowRect,
+    window_size: (u32, u32),
+    cursor: Option<(f32, f32)>,
+    dragging: bool,
+    pub follow: bool,
+    pub color: [f32; 4],
+    pub background: [f32; 4],
+}
+
+impl WaveformRenderer {
+    pub fn new() -> Result<Self, Error> {
+        Ok(Self {
+            shapes: ShapeRenderer::new()?,
+            samples: Vec::new(),
+            max_samples: None,
+            view_start: 0.0,
+            samples_per_pixel: 1.0,
+            viewport: WindowRect::default(),
+            window_size: (1, 1),
+            cursor: None,
+            dragging: false,
+            follow: true,
+            color: [0.4, 0.8, 0.5, 1.0],
+            background: [0.08, 0.08, 0.08, 1.0],
+        })
+    }
+
+    pub fn samples(&self) -> &[f32] {
+        &self.samples
+    }
+
+    /// Replaces the samples and zooms out to show all of them.
+    pub fn set_samples(&mut self, samples: &[f32]) {
+        self.samples = samples.to_vec();
+        self.trim();
+        self.zoom_to_fit();
+    }
+
+    /// Adds samples to the end, dropping the oldest ones past the limit set
+    /// by [`WaveformRenderer::set_max_samples`].
+    pub fn append(&mut self, samples: &[f32]) {
+        self.samples.extend_from_slice(samples);
+        let dropped = self.trim();
+        self.view_start -= dropped as f64;
+
+        if self.follow {
+            self.view_start = self.samples.len() as f64 - self.visible_samples();
+        }
+        self.clamp_view();
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.view_start = 0.0;
+    }
+
+    /// Keeps at most `max_samples`, for scrolling displays of live input.
+    pub fn set_max_samples(&mut self, max_samples: Option<usize>) {
+        self.max_samples = max_samples;
+        let dropped = self.trim();
+        self.view_start -= dropped as f64;
+        self.clamp_view();
+    }
+
+    fn trim(&mut self) -> usize {
+        match self.max_samples {
+            Some(max) if self.samples.len() > max => {
+                let excess = self.samples.len() - max;
+                self.samples.drain(..excess);
+                excess
+            }
+            _ => 0,
+        }
+    }
+
+    /// The visible range as `(first sample, samples per pixel)`.
+    pub fn view(&self) -> (f64, f64) {
+        (self.view_start, self.samples_per_pixel)
+    }
+
+    pub fn set_view(&mut self, start: f64, samples_per_pixel: f64) {
+        self.view_start = start;
+        self.samples_per_pixel = samples_per_pixel.max(1.0 / 64.0);
+        self.clamp_view();
+    }
+
+    pub fn zoom_to_fit(&mut self) {
+        let width = self.viewport.width.max(1) as f64;
+        self.set_view(0.0, (self.samples.len() as f64 / width).max(1.0 / 64.0));
+    }
+
+    fn visible_samples(&self) -> f64 {
+        self.viewport.width as f64 * self.samples_per_pixel
+    }
+
+    fn clamp_view(&mut self) {
+        let max_start = (self.samples.len() as f64 - self.visible_samples()).max(0.0);
+        self.view_start = self.view_start.clamp(0.0, max_start);
+    }
+
+    /// Sets the area of the window the waveform occupies.
+    pub fn set_viewport(&mut self, viewport: WindowRect, window_size: (u32, u32)) {
+        self.viewport = viewport;
+        self.window_size = window_size;
+        self.clamp_view();
+    }
+
+    /// Converts a window x position to a sample index.
+    pub fn sample_at(&self, x: f32) -> f64 {
+        self.view_start + (x as f64 - self.viewport.x as f64) * self.samples_per_pixel
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        match *event {
+            Event::CursorMoved(x, y) => {
+                let previous = self.cursor.replace((x, y));
+                if let (true, Some(previous)) = (self.dragging, previous) {
+                    self.follow = false;
+                    self.view_start -= (x - previous.0) as f64 * self.samples_per_pixel;
+                    self.clamp_view();
+                }
+            }
+
+            Event::CursorLeft => {
+                self.cursor = None;
+            }
+
+            Event::MouseButtonPressed(MouseButton::Left) => {
+                self.dragging = self.cursor.is_some_and(|p| self.viewport.contains(p.0, p.1));
+            }
+
+            Event::MouseButtonReleased(MouseButton::Left) => {
+                self.dragging = false;
+            }
+
+            Event::MouseWheel(delta) => {
+                let p = match self.cursor {
+                    Some(p) if self.viewport.contains(p.0, p.1) => p,
+                    _ => return,
+                };
+
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y,
+                    MouseScrollDelta::PixelDelta(_, y) => y / 20.0,
+                };
+
+                // keep the sample under the cursor in place
+                let anchor = self.sample_at(p.0);
+                let factor = 0.8f64.powf(lines as f64);
+                self.samples_per_pixel = (self.samples_per_pixel * factor).max(1.0 / 64.0);
+                self.view_start = anchor - (p.0 as f64 - self.viewport.x as f64) * self.samples_per_pixel;
+                self.clamp_view();
+            }
+
+            _ => (),
+        }
+    }
+
+    pub fn render(&mut self) {
+        let [x, y, w, h] = self.viewport.to_f32();
+        let mid = y + h * 0.5;
+        let half = h * 0.5;
+
+        self.shapes.clear();
+        self.shapes.rect([x, y, w, h], self.background);
+        self.shapes.rect([x, mid - 0.5, w, 1.0], [0.3, 0.3, 0.3, 1.0]);
+
+        let len = self.samples.len();
+        let mut previous = None;
+        for column in 0..self.viewport.width {
+            let start = self.view_start + column as f64 * self.samples_per_pixel;
+            let end = start + self.samples_per_pixel;
+
+            let (first, last) = (start.floor().max(0.0) as usize, (end.ceil() as usize).min(len));
+            if first >= len {
+                break;
+            }
+
+            let (mut min, mut max) = self.samples[first..last.max(first + 1)]
+                .iter()
+                .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &s| (min.min(s), max.max(s)));
+
+            // when zoomed in past one sample per pixel, connect neighbouring
+            // columns so the waveform reads as a line
+            if let Some((prev_min, prev_max)) = previous {
+                if self.samples_per_pixel < 1.0 {
+                    min = min.min(prev_max);
+                    max = max.max(prev_min);
+                }
+            }
+            previous = Some((min, max));
+
+            let top = mid - max.clamp(-1.0, 1.0) * half;
+            let bottom = mid - min.clamp(-1.0, 1.0) * half;
+            self.shapes.rect([x + column as f32, top, 1.0, (bottom - top).max(1.0)], self.color);
+        }
+
+        self.shapes.draw(self.window_size);
+    }
+}
+
+impl Renderer for WaveformRenderer {
+    fn set_viewport(&mut self, viewport: WindowRect, window_size: (u32, u32)) -> Result<(), Error> {
+        WaveformRenderer::set_viewport(self, viewport, window_size);
+        Ok(())
+    }
+
+    fn render(&mut self) {
+        WaveformRenderer::render(self);
+    }
+}
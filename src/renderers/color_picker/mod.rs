@@ -0,0 +1,528 @@
+use crate::renderers::shapes::{Color, ShapeRenderer};
+use crate::renderers::system_text::{SystemTextRenderer, TextBatcher, TextLine};
+use crate::renderers::{Renderer, WindowRect};
+use crate::window::{Event, MouseButton, VirtualKeyCode};
+
+use std::f32::consts::TAU;
+
+type Error = Box<dyn std::error::Error>;
+
+/// Something that happened in a color picker as a result of user input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorPickerEvent {
+    /// The color was changed by the user, as straight (not premultiplied)
+    /// RGBA in the 0 to 1 range.
+    ColorChanged([f32; 4]),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DragTarget {
+    Hue,
+    SaturationValue,
+    Alpha,
+}
+
+struct Layout {
+    center: (f32, f32),
+    outer_radius: f32,
+    inner_radius: f32,
+    square: [f32; 4],
+    alpha_bar: [f32; 4],
+    hex_field: [f32; 4],
+    swatch: [f32; 4],
+}
+
+const HEX_TEXT_HEIGHT: f32 = 14.0;
+
+/// A color picker with a hue ring around a saturation/value square, an alpha
+/// slider and an editable hex field.
+///
+/// Feed window events to [`ColorPickerRenderer::handle_event`] and draw with
+/// [`ColorPickerRenderer::render`]. Click the hex field to type a color in
+/// any form [`parse_hex`] takes; Enter applies it and Escape cancels.
+pub struct ColorPickerRenderer {
+    shapes: ShapeRenderer,
+    text_renderer: SystemTextRenderer,
+    text: TextBatcher,
+    viewport: WindowRect,
+    window_size: (u32, u32),
+    hue: f32,
+    saturation: f32,
+    value: f32,
+    alpha: f32,
+    cursor: Option<(f32, f32)>,
+    drag: Option<DragTarget>,
+    hex_edit: Option<String>,
+}
+
+impl ColorPickerRenderer {
+    pub fn new() -> Result<Self, Error> {
+        Ok(Self {
+            shapes: ShapeRenderer::new()?,
+            text_renderer: SystemTextRenderer::new()?,
+            text: TextBatcher::new()?,
+            viewport: WindowRect::default(),
+            window_size: (1, 1),
+            hue: 0.0,
+            saturation: 1.0,
+            value: 1.0,
+            alpha: 1.0,
+            cursor: None,
+            drag: None,
+            hex_edit: None,
+        })
+    }
+
+    /// Sets the area of the window the picker occupies.
+    pub fn set_viewport(&mut self, viewport: WindowRect, window_size: (u32, u32)) {
+        self.viewport = viewport;
+        self.window_size = window_size;
+    }
+
+    /// The current color as straight RGBA.
+    pub fn color(&self) -> [f32; 4] {
+        let (r, g, b) = hsv_to_rgb(self.hue, self.saturation, self.value);
+        [r, g, b, self.alpha]
+    }
+
+    pub fn set_color(&mut self, color: [f32; 4]) {
+        let (h, s, v) = rgb_to_hsv(color[0], color[1], color[2]);
+
+        // keep the previous hue for grays, where it is undefined
+        if s > 0.0 {
+            self.hue = h;
+        }
+        self.saturation = s;
+        self.value = v;
+        self.alpha = color[3].clamp(0.0, 1.0);
+    }
+
+    /// The current color as hue, saturation, value and alpha, all in the 0 to
+    /// 1 range.
+    pub fn hsva(&self) -> [f32; 4] {
+        [self.hue, self.saturation, self.value, self.alpha]
+    }
+
+    pub fn set_hsva(&mut self, hsva: [f32; 4]) {
+        self.hue = hsva[0].rem_euclid(1.0);
+        self.saturation = hsva[1].clamp(0.0, 1.0);
+        self.value = hsva[2].clamp(0.0, 1.0);
+        self.alpha = hsva[3].clamp(0.0, 1.0);
+    }
+
+    /// The current color formatted as `#RRGGBBAA`.
+    pub fn hex(&self) -> String {
+        format_hex(self.color())
+    }
+
+    pub fn is_editing_hex(&self) -> bool {
+        self.hex_edit.is_some()
+    }
+
+    pub fn handle_event(&mut self, event: &Event) -> Option<ColorPickerEvent> {
+        match *event {
+            Event::CursorMoved(x, y) => {
+                self.cursor = Some((x, y));
+                let target = self.drag?;
+                self.drag_to(target, (x, y))
+            }
+
+            Event::CursorLeft => {
+                self.cursor = None;
+                None
+            }
+
+            Event::MouseButtonPressed(MouseButton::Left) => {
+                let position = self.cursor?;
+                let layout = self.layout();
+
+                if contains(layout.hex_field, position) {
+                    if self.hex_edit.is_none() {
+                        self.hex_edit = Some(String::new());
+                    }
+                    return None;
+                }
+
+                let committed = self.finish_hex_edit(true);
+                if !self.viewport.contains(position.0, position.1) {
+                    return committed;
+                }
+
+                let target = self.hit_test(&layout, position)?;
+                self.drag = Some(target);
+                self.drag_to(target, position).or(committed)
+            }
+
+            Event::MouseButtonReleased(MouseButton::Left) => {
+                self.drag = None;
+                None
+            }
+
+            Event::ReceivedCharacter(c) => {
+                let edit = self.hex_edit.as_mut()?;
+                if c.is_ascii_hexdigit() && edit.len() < 8 {
+                    edit.push(c.to_ascii_uppercase());
+                }
+                None
+            }
+
+            Event::KeyPressed(key_event) => {
+                let edit = self.hex_edit.as_mut()?;
+                match key_event.key {
+                    VirtualKeyCode::Back => {
+                        edit.pop();
+                        None
+                    }
+                    VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => self.finish_hex_edit(true),
+                    VirtualKeyCode::Escape => self.finish_hex_edit(false),
+                    _ => None,
+                }
+            }
+
+            _ => None,
+        }
+    }
+
+    fn finish_hex_edit(&mut self, apply: bool) -> Option<ColorPickerEvent> {
+        let edit = self.hex_edit.take()?;
+        if !apply {
+            return None;
+        }
+
+        let color = parse_hex(&edit)?;
+        self.set_color(color);
+        Some(ColorPickerEvent::ColorChanged(self.color()))
+    }
+
+    fn hit_test(&self, layout: &Layout, position: (f32, f32)) -> Option<DragTarget> {
+        let (dx, dy) = (position.0 - layout.center.0, position.1 - layout.center.1);
+        let distance = (dx * dx + dy * dy).sqrt();
+
+        if distance >= layout.inner_radius && distance <= layout.outer_radius {
+            Some(DragTarget::Hue)
+        } else if contains(layout.square, position) {
+            Some(DragTarget::SaturationValue)
+        } else if contains(layout.alpha_bar, position) {
+            Some(DragTarget::Alpha)
+        } else {
+            None
+        }
+    }
+
+    fn drag_to(&mut self, target: DragTarget, position: (f32, f32)) -> Option<ColorPickerEvent> {
+        let layout = self.layout();
+        let before = self.hsva();
+
+        match target {
+            DragTarget::Hue => {
+                let (dx, dy) = (position.0 - layout.center.0, position.1 - layout.center.1);
+                self.hue = ((-dy).atan2(dx) / TAU).rem_euclid(1.0);
+            }
+
+            DragTarget::SaturationValue => {
+                let [x, y, w, h] = layout.square;
+                self.saturation = ((position.0 - x) / w).clamp(0.0, 1.0);
+                self.value = 1.0 - ((position.1 - y) / h).clamp(0.0, 1.0);
+            }
+
+            DragTarget::Alpha => {
+                let [x, _, w, _] = layout.alpha_bar;
+                self.alpha = ((position.0 - x) / w).clamp(0.0, 1.0);
+            }
+        }
+
+        if self.hsva() == before {
+            None
+        } else {
+            Some(ColorPickerEvent::ColorChanged(self.color()))
+        }
+    }
+
+    fn layout(&self) -> Layout {
+        let [x, y, w, h] = self.viewport.to_f32();
+        let spacing = 6.0;
+        let bar_height = 14.0;
+        let field_height = HEX_TEXT_HEIGHT + 8.0;
+
+        let wheel_size = f32::min(w, h - bar_height - field_height - 2.0 * spacing).max(0.0);
+        let outer_radius = wheel_size * 0.5;
+        let inner_radius = outer_radius * 0.82;
+        let center = (x + w * 0.5, y + outer_radius);
+
+        // the largest square inside the ring, with a little clearance
+        let side = inner_radius * std::f32::consts::SQRT_2 * 0.92;
+        let square = [center.0 - side * 0.5, center.1 - side * 0.5, side, side];
+
+        let alpha_bar = [x, y + wheel_size + spacing, w, bar_height];
+
+        let field_y = alpha_bar[1] + bar_height + spacing;
+        let swatch = [x + w - field_height * 2.0, field_y, field_height * 2.0, field_height];
+        let hex_field = [x, field_y, (w - swatch[2] - spacing).max(0.0), field_height];
+
+        Layout {
+            center,
+            outer_radius,
+            inner_radius,
+            square,
+            alpha_bar,
+            hex_field,
+            swatch,
+        }
+    }
+
+    pub fn render(&mut self) {
+        let layout = self.layout();
+        let color = self.color();
+        let shapes = &mut self.shapes;
+        shapes.clear();
+
+        // hue ring
+        const SEGMENTS: usize = 96;
+        let point = |a: f32, r: f32| {
+            (layout.center.0 + a.cos() * r, layout.center.1 - a.sin() * r)
+        };
+        for i in 0..SEGMENTS {
+            let (t0, t1) = (i as f32 / SEGMENTS as f32, (i + 1) as f32 / SEGMENTS as f32);
+            let (a0, a1) = (t0 * TAU, t1 * TAU);
+            let c0 = opaque(hsv_to_rgb(t0, 1.0, 1.0));
+            let c1 = opaque(hsv_to_rgb(t1, 1.0, 1.0));
+            shapes.quad(
+                [
+                    point(a0, layout.outer_radius),
+                    point(a1, layout.outer_radius),
+                    point(a1, layout.inner_radius),
+                    point(a0, layout.inner_radius),
+                ],
+                [c0, c1, c1, c0],
+            );
+        }
+
+        let hue_angle = self.hue * TAU;
+        shapes.line(
+            point(hue_angle, layout.inner_radius - 2.0),
+            point(hue_angle, layout.outer_radius + 2.0),
+            4.0,
+            [0.0, 0.0, 0.0, 1.0],
+        );
+        shapes.line(
+            point(hue_angle, layout.inner_radius - 1.0),
+            point(hue_angle, layout.outer_radius + 1.0),
+            2.0,
+            [1.0, 1.0, 1.0, 1.0],
+        );
+
+        // saturation/value square, as a grid so vertex color interpolation
+        // approximates the bilinear blend well
+        const CELLS: usize = 12;
+        let [sx, sy, side, _] = layout.square;
+        let cell = side / CELLS as f32;
+        let sv_color = |i: usize, j: usize| {
+            let s = i as f32 / CELLS as f32;
+            let v = 1.0 - j as f32 / CELLS as f32;
+            opaque(hsv_to_rgb(self.hue, s, v))
+        };
+        for j in 0..CELLS {
+            for i in 0..CELLS {
+                let rect = [sx + i as f32 * cell, sy + j as f32 * cell, cell, cell];
+                shapes.rect_gradient(
+                    rect,
+                    [sv_color(i, j), sv_color(i + 1, j), sv_color(i + 1, j + 1), sv_color(i, j + 1)],
+                );
+            }
+        }
+
+        let marker = (sx + self.saturation * side, sy + (1.0 - self.value) * side);
+        shapes.ring(marker, 4.0, 6.0, [0.0, 0.0, 0.0, 1.0]);
+        shapes.ring(marker, 3.0, 4.0, [1.0, 1.0, 1.0, 1.0]);
+
+        // alpha slider
+        let transparent = [color[0], color[1], color[2], 0.0];
+        let solid = [color[0], color[1], color[2], 1.0];
+        shapes.checkerboard(layout.alpha_bar, layout.alpha_bar[3] * 0.5);
+        shapes.rect_gradient(layout.alpha_bar, [transparent, solid, solid, transparent]);
+
+        let [ax, ay, aw, ah] = layout.alpha_bar;
+        let alpha_x = ax + self.alpha * aw;
+        shapes.rect([alpha_x - 2.0, ay - 2.0, 4.0, ah + 4.0], [0.0, 0.0, 0.0, 1.0]);
+        shapes.rect([alpha_x - 1.0, ay - 1.0, 2.0, ah + 2.0], [1.0, 1.0, 1.0, 1.0]);
+
+        // hex field and preview swatch
+        shapes.rect(layout.hex_field, [0.1, 0.1, 0.1, 1.0]);
+        let border = if self.hex_edit.is_some() { [0.3, 0.5, 0.9, 1.0] } else { [0.4, 0.4, 0.4, 1.0] };
+        shapes.rect_outline(layout.hex_field, 1.0, border);
+
+        shapes.checkerboard(layout.swatch, layout.swatch[3] * 0.5);
+        shapes.rect(layout.swatch, color);
+
+        shapes.draw(self.window_size);
+
+        let text = match &self.hex_edit {
+            Some(edit) => format!("#{}_", edit),
+            None => self.hex(),
+        };
+        self.render_text(&text, (layout.hex_field[0] + 4.0, layout.hex_field[1] + 4.0));
+    }
+
+    fn render_text(&mut self, text: &str, position: (f32, f32)) {
+        // the text renderer works in normalized device coordinates
+        let (ww, wh) = (self.window_size.0 as f32, self.window_size.1 as f32);
+        let char_height = HEX_TEXT_HEIGHT / 0.7;
+        let line = TextLine {
+            text: text.to_string(),
+            position: (position.0 / ww * 2.0 - 1.0, 1.0 - position.1 / wh * 2.0),
+            char_size: (char_height * 0.7 / ww * 2.0, char_height / wh * 2.0),
+        };
+
+        self.text.clear();
+        self.text.add(&self.text_renderer, &[line]);
+
+        let mut saved_viewport = [0i32; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, saved_viewport.as_mut_ptr());
+            gl::Viewport(0, 0, self.window_size.0 as i32, self.window_size.1 as i32);
+        }
+
+        self.text.render(&self.text_renderer);
+
+        let [x, y, w, h] = saved_viewport;
+        unsafe { gl::Viewport(x, y, w, h) };
+    }
+}
+
+fn contains(rect: [f32; 4], p: (f32, f32)) -> bool {
+    p.0 >= rect[0] && p.1 >= rect[1] && p.0 < rect[0] + rect[2] && p.1 < rect[1] + rect[3]
+}
+
+fn opaque((r, g, b): (f32, f32, f32)) -> Color {
+    [r, g, b, 1.0]
+}
+
+/// Converts hue, saturation and value, all in the 0 to 1 range, to RGB.
+pub fn hsv_to_rgb(h: f32, s: f32, v: f32) -> (f32, f32, f32) {
+    let h = h.rem_euclid(1.0) * 6.0;
+    let sector = h.floor();
+    let f = h - sector;
+    let p = v * (1.0 - s);
+    let q = v * (1.0 - s * f);
+    let t = v * (1.0 - s * (1.0 - f));
+
+    match sector as u32 {
+        0 => (v, t, p),
+        1 => (q, v, p),
+        2 => (p, v, t),
+        3 => (p, q, v),
+        4 => (t, p, v),
+        _ => (v, p, q),
+    }
+}
+
+/// Converts RGB in the 0 to 1 range to hue, saturation and value. The hue of
+/// grays is reported as 0.
+pub fn rgb_to_hsv(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+
+    let h = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        ((g - b) / delta).rem_euclid(6.0) / 6.0
+    } else if max == g {
+        ((b - r) / delta + 2.0) / 6.0
+    } else {
+        ((r - g) / delta + 4.0) / 6.0
+    };
+
+    let s = if max == 0.0 { 0.0 } else { delta / max };
+    (h, s, max)
+}
+
+/// Formats straight RGBA as `#RRGGBBAA`.
+pub fn format_hex(color: [f32; 4]) -> String {
+    let byte = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!(
+        "#{:02X}{:02X}{:02X}{:02X}",
+        byte(color[0]),
+        byte(color[1]),
+        byte(color[2]),
+        byte(color[3])
+    )
+}
+
+/// Parses `RGB`, `RGBA`, `RRGGBB` or `RRGGBBAA`, with or without a leading
+/// `#`. The short forms repeat each digit, as in CSS, so `#F80` is
+/// `#FF8800`.
+pub fn parse_hex(hex: &str) -> Option<[f32; 4]> {
+    let hex = hex.strip_prefix('#').unwrap_or(hex);
+    let digits = hex.chars().map(|c| c.to_digit(16)).collect::<Option<Vec<u32>>>()?;
+
+    let channels: Vec<u32> = match digits.len() {
+        3 | 4 => digits.iter().map(|d| d * 17).collect(),
+        6 | 8 => digits.chunks(2).map(|pair| pair[0] * 16 + pair[1]).collect(),
+        _ => return None,
+    };
+
+    let channel = |i: usize| channels.get(i).map_or(1.0, |&c| c as f32 / 255.0);
+    Some([channel(0), channel(1), channel(2), channel(3)])
+}
+
+impl Renderer for ColorPickerRenderer {
+    fn set_viewport(&mut self, viewport: WindowRect, window_size: (u32, u32)) -> Result<(), Error> {
+        ColorPickerRenderer::set_viewport(self, viewport, window_size);
+        Ok(())
+    }
+
+    fn render(&mut self) {
+        ColorPickerRenderer::render(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(color: [f32; 4]) -> [u8; 4] {
+        color.map(|c| (c * 255.0).round() as u8)
+    }
+
+    #[test]
+    fn parses_long_forms() {
+        assert_eq!(parse_hex("#FF8000").map(bytes), Some([255, 128, 0, 255]));
+        assert_eq!(parse_hex("#ff800040").map(bytes), Some([255, 128, 0, 64]));
+        assert_eq!(parse_hex("0a0B0c").map(bytes), Some([10, 11, 12, 255]));
+        assert_eq!(parse_hex("00000000"), Some([0.0; 4]));
+        assert_eq!(parse_hex("#FFFFFFFF"), Some([1.0; 4]));
+    }
+
+    #[test]
+    fn parses_short_forms() {
+        assert_eq!(parse_hex("#F80").map(bytes), Some([255, 136, 0, 255]));
+        assert_eq!(parse_hex("abc").map(bytes), Some([170, 187, 204, 255]));
+        assert_eq!(parse_hex("#f808").map(bytes), Some([255, 136, 0, 136]));
+        assert_eq!(parse_hex("#000"), parse_hex("#000000"));
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        for hex in [
+            "", "#", "#F", "#FF", "#FFFFF", "#FFFFFFF", "#FFFFFFFFF", "##FFF", "#GGGGGG", "#FF 800", " #FFF",
+            "#+1+2+3", "#-1-2-3", "0xFFFFFF", "#ÿÿÿ", "#FF80０0",
+        ] {
+            assert_eq!(parse_hex(hex), None, "{hex:?}");
+        }
+    }
+
+    #[test]
+    fn formatted_colors_parse_back() {
+        for color in [[0.0, 0.0, 0.0, 1.0], [1.0, 0.5, 0.25, 0.75], [0.2, 0.4, 0.6, 0.0]] {
+            let hex = format_hex(color);
+            assert_eq!(parse_hex(&hex).map(format_hex), Some(hex));
+        }
+    }
+
+    #[test]
+    fn formats_clamped_uppercase_bytes() {
+        assert_eq!(format_hex([1.0, 0.5, 0.0, 1.0]), "#FF8000FF");
+        assert_eq!(format_hex([2.0, -1.0, 0.0, 0.5]), "#FF000080");
+    }
+}
@@ -0,0 +1,350 @@
+use crate::renderers::shapes::ShapeRenderer;
+use crate::renderers::{Renderer, WindowRect};
+
+type Error = Box<dyn std::error::Error>;
+
+/// Extracts the isoline at `level` from a row-major grid of `size.0` by
+/// `size.1` samples with marching squares.
+///
+/// Returns line segments in grid coordinates, where sample `(i, j)` sits at
+/// `(i, j)`. Saddle cells are resolved with the average of their corners.
+/// NaN samples leave their cells empty.
+pub fn extract_contour(data: &[f32], size: (usize, usize), level: f32) -> Vec<[(f32, f32); 2]> {
+    let (w, h) = size;
+    assert_eq!(data.len(), w * h, "grid data doesn't match its size");
+
+    let mut segments = Vec::new();
+    if w < 2 || h < 2 {
+        return segments;
+    }
+
+    let at = |i: usize, j: usize| data[j * w + i];
+
+    for j in 0..h - 1 {
+        for i in 0..w - 1 {
+            // corners clockwise from top left
+            let v = [at(i, j), at(i + 1, j), at(i + 1, j + 1), at(i, j + 1)];
+            if v.iter().any(|v| v.is_nan()) {
+                continue;
+            }
+
+            let case = v
+                .iter()
+                .enumerate()
+                .fold(0, |case, (k, &v)| if v >= level { case | (1 << k) } else { case });
+            if case == 0 || case == 15 {
+                continue;
+            }
+
+            let (x, y) = (i as f32, j as f32);
+            let lerp = |a: f32, b: f32| if a == b { 0.5 } else { (level - a) / (b - a) };
+
+            // crossing points on the top, right, bottom and left edges
+            let top = (x + lerp(v[0], v[1]), y);
+            let right = (x + 1.0, y + lerp(v[1], v[2]));
+            let bottom = (x + lerp(v[3], v[2]), y + 1.0);
+            let left = (x, y + lerp(v[0], v[3]));
+
+            let center_above = (v[0] + v[1] + v[2] + v[3]) * 0.25 >= level;
+
+            match case {
+                1 | 14 => segments.push([left, top]),
+                2 | 13 => segments.push([top, right]),
+                3 | 12 => segments.push([left, right]),
+                4 | 11 => segments.push([right, bottom]),
+                6 | 9 => segments.push([top, bottom]),
+                7 | 8 => segments.push([left, bottom]),
+
+                // saddles: opposite corners above the level
+                5 if center_above => {
+                    segments.push([left, bottom]);
+                    segments.push([top, right]);
+                }
+                5 => {
+                    segments.push([left, top]);
+                    segments.push([right, bottom]);
+                }
+                10 if center_above => {
+                    segments.push([left, top]);
+                    segments.push([right, bottom]);
+                }
+                10 => {
+                    segments.push([left, bottom]);
+                    segments.push([top, right]);
+                }
+
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    segments
+}
+
+/// One isoline drawn by a [`ContourRenderer`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContourLevel {
+    pub value: f32,
+    pub color: [f32; 4],
+    pub width: f32,
+}
+
+/// Draws isolines of a 2D scalar field, stretched over its viewport with the
+/// first grid row at the top.
+pub struct ContourRenderer {
+    shapes: ShapeRenderer,
+    size: (usize, usize),
+    data: Vec<f32>,
+    levels: Vec<ContourLevel>,
+    segments: Vec<Vec<[(f32, f32); 2]>>,
+    viewport: WindowRect,
+    window_size: (u32, u32),
+}
+
+impl ContourRenderer {
+    pub fn new() -> Result<Self, Error> {
+        Ok(Self {
+            shapes: ShapeRenderer::new()?,
+            size: (0, 0),
+            data: Vec::new(),
+            levels: Vec::new(),
+            segments: Vec::new(),
+            viewport: WindowRect::default(),
+            window_size: (1, 1),
+        })
+    }
+
+    /// Replaces the field, a row-major grid of `size.0` by `size.1` samples.
+    pub fn set_field(&mut self, size: (usize, usize), data: &[f32]) -> Result<(), Error> {
+        if data.len() != size.0 * size.1 {
+            let msg = format!("Grid of {}x{} needs {} values, got {}", size.0, size.1, size.0 * size.1, data.len());
+            return Err(msg.into());
+        }
+
+        self.size = size;
+        self.data = data.to_vec();
+        self.extract();
+        Ok(())
+    }
+
+    pub fn levels(&self) -> &[ContourLevel] {
+        &self.levels
+    }
+
+    pub fn set_levels(&mut self, levels: &[ContourLevel]) {
+        self.levels = levels.to_vec();
+        self.extract();
+    }
+
+    /// Sets `count` evenly spaced levels strictly between the field's minimum
+    /// and maximum, all in one color.
+    pub fn set_even_levels(&mut self, count: usize, color: [f32; 4], width: f32) {
+        let (min, max) = self
+            .data
+            .iter()
+            .filter(|v| v.is_finite())
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &v| (min.min(v), max.max(v)));
+
+        let levels: Vec<ContourLevel> = if min < max {
+            (1..=count)
+                .map(|k| ContourLevel {
+                    value: min + (max - min) * k as f32 / (count + 1) as f32,
+                    color,
+                    width,
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        self.set_levels(&levels);
+    }
+
+    /// The extracted segments of each level, in grid coordinates.
+    pub fn segments(&self) -> &[Vec<[(f32, f32); 2]>] {
+        &self.segments
+    }
+
+    /// Sets the area of the window the field is stretched over.
+    pub fn set_viewport(&mut self, viewport: WindowRect, window_size: (u32, u32)) {
+        self.viewport = viewport;
+        self.window_size = window_size;
+    }
+
+    fn extract(&mut self) {
+        self.segments = self
+            .levels
+            .iter()
+            .map(|level| extract_contour(&self.data, self.size, level.value))
+            .collect();
+    }
+
+    pub fn render(&mut self) {
+        let (w, h) = self.size;
+        if w < 2 || h < 2 {
+            return;
+        }
+
+        let [vx, vy, vw, vh] = self.viewport.to_f32();
+        let (sx, sy) = (vw / (w - 1) as f32, vh / (h - 1) as f32);
+        let to_window = |p: (f32, f32)| (vx + p.0 * sx, vy + p.1 * sy);
+
+        self.shapes.clear();
+        for (level, segments) in self.levels.iter().zip(&self.segments) {
+            for [a, b] in segments {
+                self.shapes.line(to_window(*a), to_window(*b), level.width, level.color);
+            }
+        }
+        self.shapes.draw(self.window_size);
+    }
+}
+
+impl Renderer for ContourRenderer {
+    fn set_viewport(&mut self, viewport: WindowRect, window_size: (u32, u32)) -> Result<(), Error> {
+        ContourRenderer::set_viewport(self, viewport, window_size);
+        Ok(())
+    }
+
+    fn render(&mut self) {
+        ContourRenderer::render(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Which edge of the unit cell at the origin a crossing point lies on.
+    fn edge(p: (f32, f32)) -> char {
+        match p {
+            (_, 0.0) => 'T',
+            (1.0, _) => 'R',
+            (_, 1.0) => 'B',
+            (0.0, _) => 'L',
+            _ => panic!("{p:?} isn't on the cell's edge"),
+        }
+    }
+
+    /// The segments of a single cell as sorted pairs of edges, like "LT".
+    fn cell_edges(corners: [f32; 4], level: f32) -> Vec<String> {
+        // corners clockwise from top left, laid out row-major
+        let data = [corners[0], corners[1], corners[3], corners[2]];
+        let mut edges: Vec<String> = extract_contour(&data, (2, 2), level)
+            .into_iter()
+            .map(|[a, b]| {
+                let mut pair = [edge(a), edge(b)];
+                pair.sort_unstable();
+                pair.iter().collect()
+            })
+            .collect();
+        edges.sort_unstable();
+        edges
+    }
+
+    /// Corner values for a case, 1 above the level and 0 below.
+    fn case_corners(case: u32) -> [f32; 4] {
+        std::array::from_fn(|k| if case & (1 << k) != 0 { 1.0 } else { 0.0 })
+    }
+
+    #[test]
+    fn segment_table() {
+        let expected: [&[&str]; 16] = [
+            &[],
+            &["LT"],
+            &["RT"],
+            &["LR"],
+            &["BR"],
+            &[],
+            &["BT"],
+            &["BL"],
+            &["BL"],
+            &["BT"],
+            &[],
+            &["BR"],
+            &["LR"],
+            &["RT"],
+            &["LT"],
+            &[],
+        ];
+        for (case, expected) in expected.iter().enumerate() {
+            if case == 5 || case == 10 {
+                continue;
+            }
+            assert_eq!(cell_edges(case_corners(case as u32), 0.5), *expected, "case {case}");
+        }
+    }
+
+    #[test]
+    fn saddle_5_with_center_above_joins_the_above_corners() {
+        // top left and bottom right above, averaging 0.5 >= 0.4
+        assert_eq!(cell_edges(case_corners(5), 0.4), ["BL", "RT"]);
+    }
+
+    #[test]
+    fn saddle_5_with_center_below_separates_the_above_corners() {
+        assert_eq!(cell_edges(case_corners(5), 0.6), ["BR", "LT"]);
+    }
+
+    #[test]
+    fn saddle_10_with_center_above_joins_the_above_corners() {
+        // top right and bottom left above
+        assert_eq!(cell_edges(case_corners(10), 0.4), ["BR", "LT"]);
+    }
+
+    #[test]
+    fn saddle_10_with_center_below_separates_the_above_corners() {
+        assert_eq!(cell_edges(case_corners(10), 0.6), ["BL", "RT"]);
+    }
+
+    #[test]
+    fn saddle_center_exactly_at_level_counts_as_above() {
+        assert_eq!(cell_edges(case_corners(5), 0.5), ["BL", "RT"]);
+        assert_eq!(cell_edges(case_corners(10), 0.5), ["BR", "LT"]);
+    }
+
+    #[test]
+    fn crossings_are_interpolated_along_edges() {
+        // top left 0, top right 4, bottom row 0: only the top right is above 1
+        let segments = extract_contour(&[0.0, 4.0, 0.0, 0.0], (2, 2), 1.0);
+        assert_eq!(segments, [[(0.25, 0.0), (1.0, 0.75)]]);
+    }
+
+    #[test]
+    fn peak_makes_a_closed_loop() {
+        #[rustfmt::skip]
+        let data = [
+            0.0, 0.0, 0.0,
+            0.0, 1.0, 0.0,
+            0.0, 0.0, 0.0,
+        ];
+        let segments = extract_contour(&data, (3, 3), 0.5);
+        assert_eq!(segments.len(), 4);
+
+        // every end point is shared by exactly two segments
+        let points: Vec<(f32, f32)> = segments.iter().flatten().copied().collect();
+        for p in &points {
+            assert_eq!(points.iter().filter(|q| *q == p).count(), 2, "{p:?}");
+        }
+        for p in [(1.0, 0.5), (1.5, 1.0), (1.0, 1.5), (0.5, 1.0)] {
+            assert!(points.contains(&p), "{p:?}");
+        }
+    }
+
+    #[test]
+    fn nan_cells_are_skipped() {
+        #[rustfmt::skip]
+        let data = [
+            0.0, 1.0, f32::NAN,
+            0.0, 1.0, 1.0,
+        ];
+        let segments = extract_contour(&data, (3, 2), 0.5);
+        assert_eq!(segments, [[(0.5, 0.0), (0.5, 1.0)]]);
+    }
+
+    #[test]
+    fn grids_without_cells_are_empty() {
+        assert!(extract_contour(&[], (0, 0), 0.5).is_empty());
+        assert!(extract_contour(&[1.0, 0.0, 1.0], (3, 1), 0.5).is_empty());
+        assert!(extract_contour(&[1.0, 0.0], (1, 2), 0.5).is_empty());
+    }
+}
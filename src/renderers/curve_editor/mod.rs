@@ -0,0 +1,565 @@
+use crate::renderers::shapes::ShapeRenderer;
+use crate::renderers::{Renderer, WindowRect};
+use crate::window::{Event, KeyEvent, MouseButton, VirtualKeyCode};
+
+type Error = Box<dyn std::error::Error>;
+
+/// A control point of a [`Curve`], with Bézier handles given as offsets from
+/// the point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CurvePoint {
+    pub position: (f32, f32),
+    pub in_handle: (f32, f32),
+    pub out_handle: (f32, f32),
+}
+
+impl CurvePoint {
+    /// A point with flat handles of the given horizontal length.
+    pub fn new(position: (f32, f32), handle_length: f32) -> Self {
+        Self {
+            position,
+            in_handle: (-handle_length, 0.0),
+            out_handle: (handle_length, 0.0),
+        }
+    }
+}
+
+/// A 1D response curve made of cubic Bézier segments, mapping x to y.
+///
+/// Points are kept sorted by x, and handles are clamped so each segment is
+/// monotonic in x, which keeps [`Curve::evaluate`] well defined.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve {
+    points: Vec<CurvePoint>,
+}
+
+impl Curve {
+    pub fn new(points: Vec<CurvePoint>) -> Self {
+        let mut curve = Self { points };
+        curve.points.sort_by(|a, b| a.position.0.total_cmp(&b.position.0));
+        curve.constrain_handles();
+        curve
+    }
+
+    /// The identity curve from (0, 0) to (1, 1).
+    pub fn linear() -> Self {
+        let third = 1.0 / 3.0;
+        Self::new(vec![
+            CurvePoint {
+                position: (0.0, 0.0),
+                in_handle: (0.0, 0.0),
+                out_handle: (third, third),
+            },
+            CurvePoint {
+                position: (1.0, 1.0),
+                in_handle: (-third, -third),
+                out_handle: (0.0, 0.0),
+            },
+        ])
+    }
+
+    pub fn points(&self) -> &[CurvePoint] {
+        &self.points
+    }
+
+    /// Inserts a point on the curve at `x`, with handles following the
+    /// curve's slope there, and returns its index.
+    pub fn add_point(&mut self, x: f32) -> usize {
+        let y = self.evaluate(x);
+        let slope = (self.evaluate(x + 1e-3) - self.evaluate(x - 1e-3)) / 2e-3;
+        let index = self.points.partition_point(|p| p.position.0 <= x);
+
+        let prev_dx = index.checked_sub(1).map_or(0.0, |i| x - self.points[i].position.0);
+        let next_dx = self.points.get(index).map_or(0.0, |p| p.position.0 - x);
+        let point = CurvePoint {
+            position: (x, y),
+            in_handle: (-prev_dx / 3.0, -slope * prev_dx / 3.0),
+            out_handle: (next_dx / 3.0, slope * next_dx / 3.0),
+        };
+
+        self.points.insert(index, point);
+        self.constrain_handles();
+        index
+    }
+
+    pub fn remove_point(&mut self, index: usize) -> CurvePoint {
+        let point = self.points.remove(index);
+        self.constrain_handles();
+        point
+    }
+
+    /// Moves a point, keeping its handles, and returns its new index, which
+    /// changes if it passes another point.
+    pub fn set_point_position(&mut self, index: usize, position: (f32, f32)) -> usize {
+        let mut point = self.points.remove(index);
+        point.position = position;
+        let index = self.points.partition_point(|p| p.position.0 <= position.0);
+        self.points.insert(index, point);
+        self.constrain_handles();
+        index
+    }
+
+    pub fn set_handles(&mut self, index: usize, in_handle: (f32, f32), out_handle: (f32, f32)) {
+        self.points[index].in_handle = in_handle;
+        self.points[index].out_handle = out_handle;
+        self.constrain_handles();
+    }
+
+    /// Returns y at `x`. Outside the first and last points the curve is
+    /// extended flat.
+    pub fn evaluate(&self, x: f32) -> f32 {
+        let (first, last) = match (self.points.first(), self.points.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return 0.0,
+        };
+
+        if x <= first.position.0 {
+            return first.position.1;
+        }
+        if x >= last.position.0 {
+            return last.position.1;
+        }
+
+        let next = self.points.partition_point(|p| p.position.0 <= x);
+        let [p0, p1, p2, p3] = self.segment(next - 1);
+
+        // x(t) is monotonic on each segment, so bisection always converges
+        let (mut lo, mut hi) = (0.0f32, 1.0f32);
+        for _ in 0..24 {
+            let t = (lo + hi) * 0.5;
+            if bezier(p0.0, p1.0, p2.0, p3.0, t) < x {
+                lo = t;
+            } else {
+                hi = t;
+            }
+        }
+
+        bezier(p0.1, p1.1, p2.1, p3.1, (lo + hi) * 0.5)
+    }
+
+    /// The four Bézier control points of the segment starting at point `i`.
+    fn segment(&self, i: usize) -> [(f32, f32); 4] {
+        let (a, b) = (&self.points[i], &self.points[i + 1]);
+        [
+            a.position,
+            (a.position.0 + a.out_handle.0, a.position.1 + a.out_handle.1),
+            (b.position.0 + b.in_handle.0, b.position.1 + b.in_handle.1),
+            b.position,
+        ]
+    }
+
+    fn constrain_handles(&mut self) {
+        let n = self.points.len();
+        for i in 0..n {
+            let x = self.points[i].position.0;
+            let prev_dx = if i > 0 { x - self.points[i - 1].position.0 } else { 0.0 };
+            let next_dx = if i + 1 < n { self.points[i + 1].position.0 - x } else { 0.0 };
+
+            let point = &mut self.points[i];
+            point.in_handle.0 = point.in_handle.0.clamp(-prev_dx, 0.0);
+            point.out_handle.0 = point.out_handle.0.clamp(0.0, next_dx);
+        }
+    }
+}
+
+impl Default for Curve {
+    fn default() -> Self {
+        Self::linear()
+    }
+}
+
+fn bezier(p0: f32, p1: f32, p2: f32, p3: f32, t: f32) -> f32 {
+    let u = 1.0 - t;
+    u * u * u * p0 + 3.0 * u * u * t * p1 + 3.0 * u * t * t * p2 + t * t * t * p3
+}
+
+/// Something that happened in a curve editor as a result of user input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CurveEditorEvent {
+    /// A point or handle was added, moved or removed. Read the new curve with
+    /// [`CurveEditorRenderer::curve`].
+    CurveChanged,
+    SelectionChanged(Option<usize>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DragTarget {
+    Point(usize),
+    InHandle(usize),
+    OutHandle(usize),
+}
+
+const POINT_RADIUS: f32 = 5.0;
+const GRID_DIVISIONS: usize = 4;
+
+/// Edits a [`Curve`] inside a square-ish graph area spanning 0 to 1 on both
+/// axes.
+///
+/// Click on empty space to add a point on the curve, drag points and the
+/// selected point's handles, and press Delete to remove the selected point.
+/// At least two points are kept.
+pub struct CurveEditorRenderer {
+    shapes: ShapeRenderer,
+    curve: Curve,
+    viewport: WindowRect,
+    window_size: (u32, u32),
+    selected: Option<usize>,
+    drag: Option<DragTarget>,
+    cursor: Option<(f32, f32)>,
+    /// When set, dragged points snap to multiples of this step in curve units.
+    pub snap: Option<f32>,
+}
+
+impl CurveEditorRenderer {
+    pub fn new(curve: Curve) -> Result<Self, Error> {
+        Ok(Self {
+            shapes: ShapeRenderer::new()?,
+            curve,
+            viewport: WindowRect::default(),
+            window_size: (1, 1),
+            selected: None,
+            drag: None,
+            cursor: None,
+            snap: None,
+        })
+    }
+
+    pub fn curve(&self) -> &Curve {
+        &self.curve
+    }
+
+    pub fn set_curve(&mut self, curve: Curve) {
+        self.curve = curve;
+        self.selected = self.selected.filter(|&i| i < self.curve.points.len());
+        self.drag = None;
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    /// Sets the area of the window the editor occupies.
+    pub fn set_viewport(&mut self, viewport: WindowRect, window_size: (u32, u32)) {
+        self.viewport = viewport;
+        self.window_size = window_size;
+    }
+
+    /// The graph area, inset so points on the border stay fully visible.
+    fn graph_rect(&self) -> [f32; 4] {
+        let [x, y, w, h] = self.viewport.to_f32();
+        let margin = POINT_RADIUS * 2.0;
+        [x + margin, y + margin, (w - 2.0 * margin).max(1.0), (h - 2.0 * margin).max(1.0)]
+    }
+
+    fn to_screen(&self, p: (f32, f32)) -> (f32, f32) {
+        let [x, y, w, h] = self.graph_rect();
+        (x + p.0 * w, y + (1.0 - p.1) * h)
+    }
+
+    fn to_curve(&self, p: (f32, f32)) -> (f32, f32) {
+        let [x, y, w, h] = self.graph_rect();
+        ((p.0 - x) / w, 1.0 - (p.1 - y) / h)
+    }
+
+    fn snapped(&self, v: f32) -> f32 {
+        match self.snap {
+            Some(step) if step > 0.0 => (v / step).round() * step,
+            _ => v,
+        }
+    }
+
+    fn hit_test(&self, p: (f32, f32)) -> Option<DragTarget> {
+        let near = |q: (f32, f32)| {
+            let (dx, dy) = (p.0 - q.0, p.1 - q.1);
+            dx * dx + dy * dy <= (POINT_RADIUS * 1.5) * (POINT_RADIUS * 1.5)
+        };
+
+        if let Some(i) = self.selected {
+            let point = &self.curve.points[i];
+            let handle = |offset: (f32, f32)| {
+                self.to_screen((point.position.0 + offset.0, point.position.1 + offset.1))
+            };
+            if near(handle(point.out_handle)) {
+                return Some(DragTarget::OutHandle(i));
+            }
+            if near(handle(point.in_handle)) {
+                return Some(DragTarget::InHandle(i));
+            }
+        }
+
+        (0..self.curve.points.len())
+            .find(|&i| near(self.to_screen(self.curve.points[i].position)))
+            .map(DragTarget::Point)
+    }
+
+    pub fn handle_event(&mut self, event: &Event) -> Option<CurveEditorEvent> {
+        match *event {
+            Event::CursorMoved(x, y) => {
+                self.cursor = Some((x, y));
+                let target = self.drag?;
+                self.drag_to(target, (x, y))
+            }
+
+            Event::CursorLeft => {
+                self.cursor = None;
+                None
+            }
+
+            Event::MouseButtonPressed(MouseButton::Left) => {
+                let p = self.cursor?;
+                if !self.viewport.contains(p.0, p.1) {
+                    return None;
+                }
+
+                match self.hit_test(p) {
+                    Some(target) => {
+                        self.drag = Some(target);
+                        let index = match target {
+                            DragTarget::Point(i) | DragTarget::InHandle(i) | DragTarget::OutHandle(i) => i,
+                        };
+                        if self.selected != Some(index) {
+                            self.selected = Some(index);
+                            return Some(CurveEditorEvent::SelectionChanged(Some(index)));
+                        }
+                        None
+                    }
+
+                    None => {
+                        let x = self.snapped(self.to_curve(p).0.clamp(0.0, 1.0));
+                        let index = self.curve.add_point(x);
+                        self.selected = Some(index);
+                        self.drag = Some(DragTarget::Point(index));
+                        Some(CurveEditorEvent::CurveChanged)
+                    }
+                }
+            }
+
+            Event::MouseButtonReleased(MouseButton::Left) => {
+                self.drag = None;
+                None
+            }
+
+            Event::KeyPressed(KeyEvent { key: VirtualKeyCode::Delete, .. }) => {
+                let index = self.selected?;
+                if self.curve.points.len() <= 2 {
+                    return None;
+                }
+
+                self.curve.remove_point(index);
+                self.selected = None;
+                self.drag = None;
+                Some(CurveEditorEvent::CurveChanged)
+            }
+
+            _ => None,
+        }
+    }
+
+    fn drag_to(&mut self, target: DragTarget, p: (f32, f32)) -> Option<CurveEditorEvent> {
+        let (x, y) = self.to_curve(p);
+        let before = self.curve.clone();
+
+        match target {
+            DragTarget::Point(i) => {
+                let position = (
+                    self.snapped(x.clamp(0.0, 1.0)),
+                    self.snapped(y.clamp(0.0, 1.0)),
+                );
+                let index = self.curve.set_point_position(i, position);
+                self.selected = Some(index);
+                self.drag = Some(DragTarget::Point(index));
+            }
+
+            DragTarget::InHandle(i) | DragTarget::OutHandle(i) => {
+                let point = self.curve.points[i];
+                let offset = (x - point.position.0, y - point.position.1);
+
+                // keep the handles colinear so the curve stays smooth, but let
+                // each keep its own length
+                let other = match target {
+                    DragTarget::InHandle(_) => point.out_handle,
+                    _ => point.in_handle,
+                };
+                let other_length = (other.0 * other.0 + other.1 * other.1).sqrt();
+                let length = (offset.0 * offset.0 + offset.1 * offset.1).sqrt().max(1e-6);
+                let mirrored = (-offset.0 / length * other_length, -offset.1 / length * other_length);
+
+                let (in_handle, out_handle) = match target {
+                    DragTarget::InHandle(_) => (offset, mirrored),
+                    _ => (mirrored, offset),
+                };
+                self.curve.set_handles(i, in_handle, out_handle);
+            }
+        }
+
+        if self.curve == before {
+            None
+        } else {
+            Some(CurveEditorEvent::CurveChanged)
+        }
+    }
+
+    pub fn render(&mut self) {
+        let graph = self.graph_rect();
+        let [gx, gy, gw, gh] = graph;
+
+        let mut samples = Vec::with_capacity(gw as usize + 1);
+        let steps = (gw as usize).max(2);
+        for i in 0..=steps {
+            let x = i as f32 / steps as f32;
+            samples.push(self.to_screen((x, self.curve.evaluate(x))));
+        }
+
+        let points: Vec<_> = self.curve.points.iter().map(|p| self.to_screen(p.position)).collect();
+        let selected_handles = self.selected.map(|i| {
+            let point = &self.curve.points[i];
+            let handle = |o: (f32, f32)| self.to_screen((point.position.0 + o.0, point.position.1 + o.1));
+            (points[i], handle(point.in_handle), handle(point.out_handle))
+        });
+
+        let shapes = &mut self.shapes;
+        shapes.clear();
+        shapes.rect(self.viewport.to_f32(), [0.1, 0.1, 0.1, 1.0]);
+
+        let grid_color = [0.25, 0.25, 0.25, 1.0];
+        for i in 0..=GRID_DIVISIONS {
+            let t = i as f32 / GRID_DIVISIONS as f32;
+            shapes.line((gx + t * gw, gy), (gx + t * gw, gy + gh), 1.0, grid_color);
+            shapes.line((gx, gy + t * gh), (gx + gw, gy + t * gh), 1.0, grid_color);
+        }
+
+        shapes.polyline(&samples, 2.0, [0.9, 0.9, 0.9, 1.0]);
+
+        if let Some((point, in_handle, out_handle)) = selected_handles {
+            let handle_color = [0.3, 0.6, 1.0, 1.0];
+            shapes.line(point, in_handle, 1.0, handle_color);
+            shapes.line(point, out_handle, 1.0, handle_color);
+            shapes.ring(in_handle, 0.0, POINT_RADIUS * 0.7, handle_color);
+            shapes.ring(out_handle, 0.0, POINT_RADIUS * 0.7, handle_color);
+        }
+
+        for (i, &p) in points.iter().enumerate() {
+            let color = if self.selected == Some(i) {
+                [0.3, 0.6, 1.0, 1.0]
+            } else {
+                [1.0, 1.0, 1.0, 1.0]
+            };
+            shapes.ring(p, 0.0, POINT_RADIUS, [0.0, 0.0, 0.0, 1.0]);
+            shapes.ring(p, 0.0, POINT_RADIUS - 1.5, color);
+        }
+
+        shapes.draw(self.window_size);
+    }
+}
+
+impl Renderer for CurveEditorRenderer {
+    fn set_viewport(&mut self, viewport: WindowRect, window_size: (u32, u32)) -> Result<(), Error> {
+        CurveEditorRenderer::set_viewport(self, viewport, window_size);
+        Ok(())
+    }
+
+    fn render(&mut self) {
+        CurveEditorRenderer::render(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-4, "expected {expected}, got {actual}");
+    }
+
+    #[test]
+    fn linear_curve_is_identity() {
+        let curve = Curve::linear();
+        for x in [0.0, 0.1, 0.25, 0.5, 0.8, 1.0] {
+            assert_close(curve.evaluate(x), x);
+        }
+    }
+
+    #[test]
+    fn passes_through_control_points() {
+        let curve = Curve::new(vec![
+            CurvePoint::new((0.0, 0.0), 0.1),
+            CurvePoint::new((0.4, 0.9), 0.1),
+            CurvePoint::new((1.0, 0.3), 0.1),
+        ]);
+        assert_close(curve.evaluate(0.0), 0.0);
+        assert_close(curve.evaluate(0.4), 0.9);
+        assert_close(curve.evaluate(1.0), 0.3);
+    }
+
+    #[test]
+    fn interpolates_between_control_points() {
+        // flat handles a third of the way in make a symmetric ease in and out
+        let curve = Curve::new(vec![
+            CurvePoint::new((0.0, 0.0), 1.0 / 3.0),
+            CurvePoint::new((1.0, 1.0), 1.0 / 3.0),
+        ]);
+        assert_close(curve.evaluate(0.5), 0.5);
+        assert!(curve.evaluate(0.25) < 0.25);
+        assert!(curve.evaluate(0.75) > 0.75);
+        assert_close(curve.evaluate(0.25) + curve.evaluate(0.75), 1.0);
+
+        let mut previous = curve.evaluate(0.0);
+        for i in 1..=100 {
+            let y = curve.evaluate(i as f32 / 100.0);
+            assert!(y >= previous);
+            previous = y;
+        }
+    }
+
+    #[test]
+    fn points_are_sorted_and_handles_constrained() {
+        let curve = Curve::new(vec![
+            CurvePoint::new((1.0, 1.0), 5.0),
+            CurvePoint::new((0.0, 0.0), 5.0),
+        ]);
+        let points = curve.points();
+        assert_eq!(points[0].position, (0.0, 0.0));
+        assert_eq!(points[1].position, (1.0, 1.0));
+        assert_eq!(points[0].in_handle.0, 0.0);
+        assert_eq!(points[0].out_handle.0, 1.0);
+        assert_eq!(points[1].in_handle.0, -1.0);
+        assert_eq!(points[1].out_handle.0, 0.0);
+    }
+
+    #[test]
+    fn extends_flat_outside_end_points() {
+        let curve = Curve::new(vec![
+            CurvePoint::new((0.2, 0.3), 0.1),
+            CurvePoint::new((0.8, 0.6), 0.1),
+        ]);
+        assert_close(curve.evaluate(-1.0), 0.3);
+        assert_close(curve.evaluate(0.0), 0.3);
+        assert_close(curve.evaluate(1.0), 0.6);
+        assert_close(curve.evaluate(2.0), 0.6);
+    }
+
+    #[test]
+    fn single_point_is_constant() {
+        let curve = Curve::new(vec![CurvePoint::new((0.5, 0.7), 0.1)]);
+        for x in [-1.0, 0.0, 0.5, 1.0, 2.0] {
+            assert_close(curve.evaluate(x), 0.7);
+        }
+    }
+
+    #[test]
+    fn empty_curve_is_zero() {
+        assert_eq!(Curve::new(Vec::new()).evaluate(0.5), 0.0);
+    }
+
+    #[test]
+    fn added_point_lies_on_the_curve() {
+        let mut curve = Curve::linear();
+        let index = curve.add_point(0.3);
+        assert_eq!(index, 1);
+        assert_eq!(curve.points().len(), 3);
+        assert_close(curve.points()[1].position.1, 0.3);
+        assert_close(curve.evaluate(0.3), 0.3);
+        assert_close(curve.evaluate(0.0), 0.0);
+        assert_close(curve.evaluate(1.0), 1.0);
+    }
+}
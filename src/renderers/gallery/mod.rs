@@ -0,0 +1,381 @@
+use crate::image::ThumbnailCache;
+use crate::renderers::image_renderer::ImageRenderer;
+use crate::renderers::{Renderer, WindowRect};
+use crate::window::{Event, MouseButton, MouseScrollDelta, VirtualKeyCode};
+
+use std::path::PathBuf;
+
+type Error = Box<dyn std::error::Error>;
+
+/// Something that happened in a gallery as a result of user input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GalleryEvent {
+    SelectionChanged(Option<usize>),
+    /// An item was double-clicked, or Enter was pressed while it was selected.
+    Activated(usize),
+}
+
+/// Lays out image thumbnails in a scrollable grid with hover and selection
+/// highlights.
+///
+/// Feed window events to [`GalleryRenderer::handle_event`] and draw with
+/// [`GalleryRenderer::render`]. Only the thumbnails of visible cells are
+/// requested from the [`ThumbnailCache`].
+pub struct GalleryRenderer {
+    image_renderer: ImageRenderer,
+    paths: Vec<PathBuf>,
+    cell_size: (u32, u32),
+    spacing: u32,
+    viewport: WindowRect,
+    window_size: (u32, u32),
+    scroll: f32,
+    cursor: Option<(f32, f32)>,
+    hovered: Option<usize>,
+    selected: Option<usize>,
+    pub background_color: [f32; 4],
+    pub hover_color: [f32; 4],
+    pub selection_color: [f32; 4],
+}
+
+impl GalleryRenderer {
+    pub fn new(cell_size: (u32, u32), spacing: u32) -> Result<Self, Error> {
+        Ok(Self {
+            image_renderer: ImageRenderer::new()?,
+            paths: Vec::new(),
+            cell_size,
+            spacing,
+            viewport: WindowRect::default(),
+            window_size: (1, 1),
+            scroll: 0.0,
+            cursor: None,
+            hovered: None,
+            selected: None,
+            background_color: [0.15, 0.15, 0.15, 1.0],
+            hover_color: [0.35, 0.35, 0.35, 1.0],
+            selection_color: [0.2, 0.4, 0.8, 1.0],
+        })
+    }
+
+    /// Replaces the displayed images. Resets selection and scrolling.
+    pub fn set_paths(&mut self, paths: Vec<PathBuf>) {
+        self.paths = paths;
+        self.scroll = 0.0;
+        self.hovered = None;
+        self.selected = None;
+    }
+
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Sets the area of the window the gallery occupies. `window_size` is
+    /// needed to convert to OpenGL's bottom-left origin.
+    pub fn set_viewport(&mut self, viewport: WindowRect, window_size: (u32, u32)) {
+        self.viewport = viewport;
+        self.window_size = window_size;
+        self.clamp_scroll();
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    pub fn set_selected(&mut self, selected: Option<usize>) {
+        self.selected = selected.filter(|&i| i < self.paths.len());
+        if let Some(index) = self.selected {
+            self.scroll_to(index);
+        }
+    }
+
+    pub fn hovered(&self) -> Option<usize> {
+        self.hovered
+    }
+
+    /// Scrolls the minimum amount needed to make item `index` fully visible.
+    pub fn scroll_to(&mut self, index: usize) {
+        let columns = self.columns();
+        let row_height = (self.cell_size.1 + self.spacing) as f32;
+        let top = (index / columns) as f32 * row_height;
+        let bottom = top + row_height + self.spacing as f32;
+        let height = self.viewport.height as f32;
+
+        if top < self.scroll {
+            self.scroll = top;
+        } else if bottom > self.scroll + height {
+            self.scroll = bottom - height;
+        }
+
+        self.clamp_scroll();
+    }
+
+    pub fn handle_event(&mut self, event: &Event) -> Option<GalleryEvent> {
+        match *event {
+            Event::CursorMoved(x, y) => {
+                self.cursor = Some((x, y));
+                self.hovered = self.item_at(x, y);
+                None
+            }
+
+            Event::CursorLeft => {
+                self.cursor = None;
+                self.hovered = None;
+                None
+            }
+
+            Event::MouseWheel(delta) => {
+                let (x, y) = self.cursor?;
+                if !self.contains(x, y) {
+                    return None;
+                }
+
+                let dy = match delta {
+                    MouseScrollDelta::LineDelta(_, dy) => dy * (self.cell_size.1 + self.spacing) as f32 * 0.5,
+                    MouseScrollDelta::PixelDelta(_, dy) => dy,
+                };
+                self.scroll -= dy;
+                self.clamp_scroll();
+                self.hovered = self.item_at(x, y);
+                None
+            }
+
+            Event::MouseButtonPressed(MouseButton::Left) => {
+                let (x, y) = self.cursor?;
+                if !self.contains(x, y) {
+                    return None;
+                }
+
+                self.change_selection(self.item_at(x, y))
+            }
+
+            // the first press already selected the item
+            Event::MouseButtonDoubleClicked(MouseButton::Left, _) => {
+                let (x, y) = self.cursor?;
+                let index = self.item_at(x, y).filter(|_| self.contains(x, y))?;
+                (self.selected == Some(index)).then_some(GalleryEvent::Activated(index))
+            }
+
+            Event::KeyPressed(key_event) => self.handle_key(key_event.key),
+
+            _ => None,
+        }
+    }
+
+    fn handle_key(&mut self, key: VirtualKeyCode) -> Option<GalleryEvent> {
+        use VirtualKeyCode as Vk;
+
+        if self.paths.is_empty() {
+            return None;
+        }
+
+        let columns = self.columns() as isize;
+        let last = self.paths.len() as isize - 1;
+        let current = self.selected.map(|i| i as isize);
+
+        let next = match key {
+            Vk::Return => return self.selected.map(GalleryEvent::Activated),
+            Vk::Left => current.map_or(0, |i| i - 1),
+            Vk::Right => current.map_or(0, |i| i + 1),
+            Vk::Up => current.map_or(0, |i| i - columns),
+            Vk::Down => current.map_or(0, |i| i + columns),
+            Vk::Home => 0,
+            Vk::End => last,
+            _ => return None,
+        };
+
+        let next = next.clamp(0, last) as usize;
+        self.scroll_to(next);
+        self.change_selection(Some(next))
+    }
+
+    fn change_selection(&mut self, selected: Option<usize>) -> Option<GalleryEvent> {
+        if self.selected == selected {
+            return None;
+        }
+
+        self.selected = selected;
+        Some(GalleryEvent::SelectionChanged(selected))
+    }
+
+    pub fn render(&mut self, thumbnails: &mut ThumbnailCache) {
+        let vp = self.viewport;
+        if vp.width == 0 || vp.height == 0 {
+            return;
+        }
+
+        let mut saved_viewport = [0i32; 4];
+        let mut saved_scissor = [0i32; 4];
+        let mut saved_clear_color = [0f32; 4];
+        let scissor_was_enabled;
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, saved_viewport.as_mut_ptr());
+            gl::GetIntegerv(gl::SCISSOR_BOX, saved_scissor.as_mut_ptr());
+            gl::GetFloatv(gl::COLOR_CLEAR_VALUE, saved_clear_color.as_mut_ptr());
+            scissor_was_enabled = gl::IsEnabled(gl::SCISSOR_TEST) == gl::TRUE;
+
+            gl::Enable(gl::SCISSOR_TEST);
+        }
+
+        let gallery_rect = self.to_gl_rect(vp.x, vp.y, vp.width, vp.height);
+        fill_rect(gallery_rect, self.background_color, gallery_rect);
+
+        let (cw, ch) = self.cell_size;
+        let columns = self.columns();
+        let row_height = (ch + self.spacing) as f32;
+        let first_row = (self.scroll / row_height).floor() as usize;
+        let last_row = ((self.scroll + vp.height as f32) / row_height).ceil() as usize;
+
+        for row in first_row..=last_row {
+            for col in 0..columns {
+                let index = row * columns + col;
+                if index >= self.paths.len() {
+                    break;
+                }
+
+                let (x, y) = self.cell_origin(index);
+                let border = (self.spacing / 2).max(1) as i32;
+                let highlight = if self.selected == Some(index) {
+                    Some(self.selection_color)
+                } else if self.hovered == Some(index) {
+                    Some(self.hover_color)
+                } else {
+                    None
+                };
+
+                if let Some(color) = highlight {
+                    let rect = self.to_gl_rect(
+                        x - border,
+                        y - border,
+                        cw + 2 * border as u32,
+                        ch + 2 * border as u32,
+                    );
+                    fill_rect(rect, color, gallery_rect);
+                }
+
+                let texture = match thumbnails.texture(&self.paths[index]) {
+                    Some(texture) => texture,
+                    None => continue,
+                };
+
+                // fit the thumbnail inside the cell, preserving its aspect ratio
+                let (tw, th) = texture.size();
+                let scale = f32::min(cw as f32 / tw as f32, ch as f32 / th as f32).min(1.0);
+                let (w, h) = ((tw as f32 * scale) as u32, (th as f32 * scale) as u32);
+                let tx = x + (cw - w) as i32 / 2;
+                let ty = y + (ch - h) as i32 / 2;
+                let rect = self.to_gl_rect(tx, ty, w, h);
+
+                unsafe {
+                    gl::Viewport(rect[0], rect[1], rect[2], rect[3]);
+                    gl::Scissor(gallery_rect[0], gallery_rect[1], gallery_rect[2], gallery_rect[3]);
+                }
+                self.image_renderer.render(texture);
+            }
+        }
+
+        unsafe {
+            gl::Viewport(saved_viewport[0], saved_viewport[1], saved_viewport[2], saved_viewport[3]);
+            gl::Scissor(saved_scissor[0], saved_scissor[1], saved_scissor[2], saved_scissor[3]);
+            let [r, g, b, a] = saved_clear_color;
+            gl::ClearColor(r, g, b, a);
+            if !scissor_was_enabled {
+                gl::Disable(gl::SCISSOR_TEST);
+            }
+        }
+    }
+
+    fn columns(&self) -> usize {
+        let available = self.viewport.width.saturating_sub(self.spacing);
+        (available / (self.cell_size.0 + self.spacing)).max(1) as usize
+    }
+
+    fn content_height(&self) -> f32 {
+        let rows = self.paths.len().div_ceil(self.columns());
+        (rows as u32 * (self.cell_size.1 + self.spacing) + self.spacing) as f32
+    }
+
+    fn clamp_scroll(&mut self) {
+        let max_scroll = (self.content_height() - self.viewport.height as f32).max(0.0);
+        self.scroll = self.scroll.clamp(0.0, max_scroll);
+    }
+
+    /// Top left corner of a cell, in window pixels.
+    fn cell_origin(&self, index: usize) -> (i32, i32) {
+        let columns = self.columns();
+        let (col, row) = ((index % columns) as u32, (index / columns) as u32);
+        let x = self.spacing + col * (self.cell_size.0 + self.spacing);
+        let y = self.spacing + row * (self.cell_size.1 + self.spacing);
+        (self.viewport.x + x as i32, self.viewport.y + y as i32 - self.scroll as i32)
+    }
+
+    fn contains(&self, x: f32, y: f32) -> bool {
+        self.viewport.contains(x, y)
+    }
+
+    fn item_at(&self, x: f32, y: f32) -> Option<usize> {
+        if !self.contains(x, y) {
+            return None;
+        }
+
+        let local_x = x - self.viewport.x as f32 - self.spacing as f32;
+        let local_y = y - self.viewport.y as f32 - self.spacing as f32 + self.scroll;
+        if local_x < 0.0 || local_y < 0.0 {
+            return None;
+        }
+
+        let pitch = (
+            (self.cell_size.0 + self.spacing) as f32,
+            (self.cell_size.1 + self.spacing) as f32,
+        );
+        let (col, row) = ((local_x / pitch.0) as usize, (local_y / pitch.1) as usize);
+        let in_cell = local_x % pitch.0 < self.cell_size.0 as f32
+            && local_y % pitch.1 < self.cell_size.1 as f32;
+
+        let index = row * self.columns() + col;
+        if in_cell && col < self.columns() && index < self.paths.len() {
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    /// Converts a top-left origin rect in window pixels to a
+    /// `[x, y, width, height]` rect in bottom-left origin GL coordinates.
+    fn to_gl_rect(&self, x: i32, y: i32, width: u32, height: u32) -> [i32; 4] {
+        let gl_y = self.window_size.1 as i32 - y - height as i32;
+        [x, gl_y, width as i32, height as i32]
+    }
+}
+
+/// A [`GalleryRenderer`] with the thumbnails it draws, as a [`Renderer`].
+pub struct Gallery {
+    pub renderer: GalleryRenderer,
+    pub thumbnails: ThumbnailCache,
+}
+
+impl Renderer for Gallery {
+    fn set_viewport(&mut self, viewport: WindowRect, window_size: (u32, u32)) -> Result<(), Error> {
+        self.renderer.set_viewport(viewport, window_size);
+        Ok(())
+    }
+
+    fn render(&mut self) {
+        self.renderer.render(&mut self.thumbnails);
+    }
+}
+
+/// Fills `rect` with `color`, clipped to `clip`. Both are GL window rects.
+fn fill_rect(rect: [i32; 4], color: [f32; 4], clip: [i32; 4]) {
+    let x0 = rect[0].max(clip[0]);
+    let y0 = rect[1].max(clip[1]);
+    let x1 = (rect[0] + rect[2]).min(clip[0] + clip[2]);
+    let y1 = (rect[1] + rect[3]).min(clip[1] + clip[3]);
+    if x1 <= x0 || y1 <= y0 {
+        return;
+    }
+
+    unsafe {
+        gl::Scissor(x0, y0, x1 - x0, y1 - y0);
+        gl::ClearColor(color[0], color[1], color[2], color[3]);
+        gl::Clear(gl::COLOR_BUFFER_BIT);
+    }
+}
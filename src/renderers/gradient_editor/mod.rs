@@ -0,0 +1,452 @@
+use crate::image::{Image, PixelArray};
+use crate::renderers::color_picker::{ColorPickerEvent, ColorPickerRenderer};
+use crate::renderers::shapes::ShapeRenderer;
+use crate::renderers::{Renderer, WindowRect};
+use crate::window::{Event, KeyEvent, MouseButton, VirtualKeyCode};
+
+type Error = Box<dyn std::error::Error>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GradientStop {
+    /// Position along the gradient, from 0 to 1.
+    pub position: f32,
+    /// Straight RGBA in the 0 to 1 range.
+    pub color: [f32; 4],
+}
+
+/// A multi-stop color gradient. Stops are kept sorted by position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    stops: Vec<GradientStop>,
+}
+
+impl Gradient {
+    pub fn new(mut stops: Vec<GradientStop>) -> Self {
+        for stop in &mut stops {
+            stop.position = stop.position.clamp(0.0, 1.0);
+        }
+        stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+        Self { stops }
+    }
+
+    pub fn two_color(start: [f32; 4], end: [f32; 4]) -> Self {
+        Self::new(vec![
+            GradientStop { position: 0.0, color: start },
+            GradientStop { position: 1.0, color: end },
+        ])
+    }
+
+    pub fn stops(&self) -> &[GradientStop] {
+        &self.stops
+    }
+
+    /// Inserts a stop and returns its index.
+    pub fn add_stop(&mut self, position: f32, color: [f32; 4]) -> usize {
+        let position = position.clamp(0.0, 1.0);
+        let index = self.stops.partition_point(|s| s.position <= position);
+        self.stops.insert(index, GradientStop { position, color });
+        index
+    }
+
+    pub fn remove_stop(&mut self, index: usize) -> GradientStop {
+        self.stops.remove(index)
+    }
+
+    /// Moves a stop and returns its new index, which changes if it passes
+    /// another stop.
+    pub fn set_stop_position(&mut self, index: usize, position: f32) -> usize {
+        let stop = self.stops.remove(index);
+        self.add_stop(position, stop.color)
+    }
+
+    pub fn set_stop_color(&mut self, index: usize, color: [f32; 4]) {
+        self.stops[index].color = color;
+    }
+
+    /// Returns the color at `t`, interpolating linearly between stops.
+    /// Positions outside the outermost stops take their color.
+    pub fn sample(&self, t: f32) -> [f32; 4] {
+        let (first, last) = match (self.stops.first(), self.stops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return [0.0; 4],
+        };
+
+        if t <= first.position {
+            return first.color;
+        }
+        if t >= last.position {
+            return last.color;
+        }
+
+        let next = self.stops.partition_point(|s| s.position <= t);
+        let (a, b) = (&self.stops[next - 1], &self.stops[next]);
+        let span = b.position - a.position;
+        let f = if span > 0.0 { (t - a.position) / span } else { 0.0 };
+
+        std::array::from_fn(|i| a.color[i] + (b.color[i] - a.color[i]) * f)
+    }
+
+    /// Renders the gradient into a one pixel tall RGBA image, for use as a
+    /// lookup texture.
+    pub fn to_image(&self, width: u32) -> Image {
+        let mut data = Vec::with_capacity(width as usize * 4);
+        for x in 0..width {
+            let t = if width > 1 { x as f32 / (width - 1) as f32 } else { 0.0 };
+            for c in self.sample(t) {
+                data.push((c.clamp(0.0, 1.0) * 255.0).round() as u8);
+            }
+        }
+
+        Image::new((width, 1), PixelArray::RGBA(data))
+    }
+}
+
+impl Default for Gradient {
+    fn default() -> Self {
+        Self::two_color([0.0, 0.0, 0.0, 1.0], [1.0, 1.0, 1.0, 1.0])
+    }
+}
+
+/// Something that happened in a gradient editor as a result of user input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GradientEditorEvent {
+    /// A stop was added, moved, removed or recolored. Read the new gradient
+    /// with [`GradientEditorRenderer::gradient`].
+    GradientChanged,
+    SelectionChanged(Option<usize>),
+}
+
+const BAR_HEIGHT: f32 = 24.0;
+const HANDLE_SIZE: f32 = 12.0;
+const REMOVE_DISTANCE: f32 = 40.0;
+
+/// Edits a [`Gradient`]: a preview bar with draggable stop handles under it,
+/// and a color picker for the selected stop.
+///
+/// Click the bar to add a stop, drag a handle to move it, and drag it well
+/// below the bar or press Delete to remove it. At least one stop is kept.
+pub struct GradientEditorRenderer {
+    shapes: ShapeRenderer,
+    picker: ColorPickerRenderer,
+    gradient: Gradient,
+    viewport: WindowRect,
+    window_size: (u32, u32),
+    selected: Option<usize>,
+    dragging: bool,
+    cursor: Option<(f32, f32)>,
+}
+
+impl GradientEditorRenderer {
+    pub fn new(gradient: Gradient) -> Result<Self, Error> {
+        let mut editor = Self {
+            shapes: ShapeRenderer::new()?,
+            picker: ColorPickerRenderer::new()?,
+            gradient,
+            viewport: WindowRect::default(),
+            window_size: (1, 1),
+            selected: None,
+            dragging: false,
+            cursor: None,
+        };
+
+        let selected = if editor.gradient.stops.is_empty() { None } else { Some(0) };
+        editor.select(selected);
+        Ok(editor)
+    }
+
+    pub fn gradient(&self) -> &Gradient {
+        &self.gradient
+    }
+
+    pub fn set_gradient(&mut self, gradient: Gradient) {
+        self.gradient = gradient;
+        let selected = self.selected.filter(|&i| i < self.gradient.stops.len());
+        self.select(selected);
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    /// Sets the area of the window the editor occupies. The color picker
+    /// takes whatever space is left below the bar.
+    pub fn set_viewport(&mut self, viewport: WindowRect, window_size: (u32, u32)) {
+        self.viewport = viewport;
+        self.window_size = window_size;
+
+        let top = (BAR_HEIGHT + HANDLE_SIZE + 8.0) as u32;
+        let picker_viewport = WindowRect::new(
+            viewport.x,
+            viewport.y + top as i32,
+            viewport.width,
+            viewport.height.saturating_sub(top),
+        );
+        self.picker.set_viewport(picker_viewport, window_size);
+    }
+
+    fn select(&mut self, selected: Option<usize>) {
+        self.selected = selected;
+        if let Some(index) = selected {
+            self.picker.set_color(self.gradient.stops[index].color);
+        }
+    }
+
+    fn bar_rect(&self) -> [f32; 4] {
+        let [x, y, w, _] = self.viewport.to_f32();
+        let margin = HANDLE_SIZE * 0.5;
+        [x + margin, y, (w - 2.0 * margin).max(1.0), BAR_HEIGHT]
+    }
+
+    fn position_at(&self, x: f32) -> f32 {
+        let [bx, _, bw, _] = self.bar_rect();
+        ((x - bx) / bw).clamp(0.0, 1.0)
+    }
+
+    fn handle_at(&self, p: (f32, f32)) -> Option<usize> {
+        let [bx, by, bw, bh] = self.bar_rect();
+        let top = by + bh;
+        if p.1 < top || p.1 > top + HANDLE_SIZE {
+            return None;
+        }
+
+        // prefer the selected handle when handles overlap
+        let hit = |i: usize| {
+            let x = bx + self.gradient.stops[i].position * bw;
+            (p.0 - x).abs() <= HANDLE_SIZE * 0.5
+        };
+        if let Some(selected) = self.selected.filter(|&i| hit(i)) {
+            return Some(selected);
+        }
+
+        (0..self.gradient.stops.len()).rev().find(|&i| hit(i))
+    }
+
+    pub fn handle_event(&mut self, event: &Event) -> Option<GradientEditorEvent> {
+        if let Some(ColorPickerEvent::ColorChanged(color)) = self.picker.handle_event(event) {
+            let index = self.selected?;
+            self.gradient.set_stop_color(index, color);
+            return Some(GradientEditorEvent::GradientChanged);
+        }
+
+        match *event {
+            Event::CursorMoved(x, y) => {
+                self.cursor = Some((x, y));
+                if !self.dragging {
+                    return None;
+                }
+
+                let index = self.selected?;
+                let [_, by, _, bh] = self.bar_rect();
+                let removing = y > by + bh + HANDLE_SIZE + REMOVE_DISTANCE;
+                if removing && self.gradient.stops.len() > 1 {
+                    self.dragging = false;
+                    return self.remove_selected();
+                }
+
+                let position = self.position_at(x);
+                if position == self.gradient.stops[index].position {
+                    return None;
+                }
+
+                self.selected = Some(self.gradient.set_stop_position(index, position));
+                Some(GradientEditorEvent::GradientChanged)
+            }
+
+            Event::CursorLeft => {
+                self.cursor = None;
+                None
+            }
+
+            Event::MouseButtonPressed(MouseButton::Left) => {
+                let p = self.cursor?;
+                if let Some(index) = self.handle_at(p) {
+                    self.dragging = true;
+                    if self.selected != Some(index) {
+                        self.select(Some(index));
+                        return Some(GradientEditorEvent::SelectionChanged(Some(index)));
+                    }
+                    return None;
+                }
+
+                let [bx, by, bw, bh] = self.bar_rect();
+                if p.0 >= bx && p.0 <= bx + bw && p.1 >= by && p.1 < by + bh {
+                    let position = self.position_at(p.0);
+                    let index = self.gradient.add_stop(position, self.gradient.sample(position));
+                    self.select(Some(index));
+                    self.dragging = true;
+                    return Some(GradientEditorEvent::GradientChanged);
+                }
+
+                None
+            }
+
+            Event::MouseButtonReleased(MouseButton::Left) => {
+                self.dragging = false;
+                None
+            }
+
+            Event::KeyPressed(KeyEvent { key: VirtualKeyCode::Delete, .. }) if !self.picker.is_editing_hex() => {
+                if self.gradient.stops.len() > 1 {
+                    self.remove_selected()
+                } else {
+                    None
+                }
+            }
+
+            _ => None,
+        }
+    }
+
+    fn remove_selected(&mut self) -> Option<GradientEditorEvent> {
+        let index = self.selected?;
+        self.gradient.remove_stop(index);
+        let selected = index.min(self.gradient.stops.len() - 1);
+        self.select(Some(selected));
+        Some(GradientEditorEvent::GradientChanged)
+    }
+
+    pub fn render(&mut self) {
+        let bar = self.bar_rect();
+        let [bx, by, bw, bh] = bar;
+        let shapes = &mut self.shapes;
+        shapes.clear();
+
+        shapes.checkerboard(bar, bh * 0.5);
+
+        // one segment per stop interval is exact for linear interpolation
+        let stops = &self.gradient.stops;
+        if let (Some(first), Some(last)) = (stops.first(), stops.last()) {
+            let mut edges = vec![(0.0, first.color)];
+            edges.extend(stops.iter().map(|s| (s.position, s.color)));
+            edges.push((1.0, last.color));
+
+            for pair in edges.windows(2) {
+                let (p0, c0) = pair[0];
+                let (p1, c1) = pair[1];
+                let rect = [bx + p0 * bw, by, (p1 - p0) * bw, bh];
+                shapes.rect_gradient(rect, [c0, c1, c1, c0]);
+            }
+        }
+        shapes.rect_outline(bar, 1.0, [0.4, 0.4, 0.4, 1.0]);
+
+        let top = by + bh;
+        for (i, stop) in stops.iter().enumerate() {
+            let x = bx + stop.position * bw;
+            let half = HANDLE_SIZE * 0.5;
+            let outline = if self.selected == Some(i) {
+                [1.0, 1.0, 1.0, 1.0]
+            } else {
+                [0.3, 0.3, 0.3, 1.0]
+            };
+
+            shapes.triangle(
+                [(x, top), (x + half + 2.0, top + HANDLE_SIZE + 2.0), (x - half - 2.0, top + HANDLE_SIZE + 2.0)],
+                [outline; 3],
+            );
+            let [r, g, b, _] = stop.color;
+            shapes.triangle(
+                [(x, top + 3.0), (x + half - 1.0, top + HANDLE_SIZE), (x - half + 1.0, top + HANDLE_SIZE)],
+                [[r, g, b, 1.0]; 3],
+            );
+        }
+
+        shapes.draw(self.window_size);
+
+        if self.selected.is_some() {
+            self.picker.render();
+        }
+    }
+}
+
+impl Renderer for GradientEditorRenderer {
+    fn set_viewport(&mut self, viewport: WindowRect, window_size: (u32, u32)) -> Result<(), Error> {
+        GradientEditorRenderer::set_viewport(self, viewport, window_size);
+        Ok(())
+    }
+
+    fn render(&mut self) {
+        GradientEditorRenderer::render(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+    const GREEN: [f32; 4] = [0.0, 1.0, 0.0, 1.0];
+    const BLUE: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+
+    fn stop(position: f32, color: [f32; 4]) -> GradientStop {
+        GradientStop { position, color }
+    }
+
+    fn assert_close(actual: [f32; 4], expected: [f32; 4]) {
+        let close = actual.iter().zip(expected).all(|(a, e)| (a - e).abs() < 1e-5);
+        assert!(close, "expected {expected:?}, got {actual:?}");
+    }
+
+    #[test]
+    fn interpolates_between_stops() {
+        let gradient = Gradient::new(vec![stop(0.0, RED), stop(0.5, GREEN), stop(1.0, BLUE)]);
+        assert_close(gradient.sample(0.0), RED);
+        assert_close(gradient.sample(0.25), [0.5, 0.5, 0.0, 1.0]);
+        assert_close(gradient.sample(0.5), GREEN);
+        assert_close(gradient.sample(0.875), [0.0, 0.25, 0.75, 1.0]);
+        assert_close(gradient.sample(1.0), BLUE);
+    }
+
+    #[test]
+    fn clamps_outside_outer_stops() {
+        let gradient = Gradient::new(vec![stop(0.25, RED), stop(0.75, BLUE)]);
+        assert_close(gradient.sample(-1.0), RED);
+        assert_close(gradient.sample(0.1), RED);
+        assert_close(gradient.sample(0.9), BLUE);
+        assert_close(gradient.sample(2.0), BLUE);
+    }
+
+    #[test]
+    fn clamps_and_sorts_stop_positions() {
+        let gradient = Gradient::new(vec![stop(1.5, BLUE), stop(-0.5, RED)]);
+        let positions: Vec<f32> = gradient.stops().iter().map(|s| s.position).collect();
+        assert_eq!(positions, [0.0, 1.0]);
+        assert_close(gradient.stops()[0].color, RED);
+    }
+
+    #[test]
+    fn single_stop_is_constant() {
+        let gradient = Gradient::new(vec![stop(0.4, GREEN)]);
+        for t in [-1.0, 0.0, 0.4, 1.0, 2.0] {
+            assert_close(gradient.sample(t), GREEN);
+        }
+    }
+
+    #[test]
+    fn empty_gradient_is_transparent() {
+        assert_eq!(Gradient::new(Vec::new()).sample(0.5), [0.0; 4]);
+    }
+
+    #[test]
+    fn stops_at_same_position_make_a_hard_edge() {
+        let gradient = Gradient::new(vec![stop(0.0, RED), stop(0.5, RED), stop(0.5, BLUE), stop(1.0, BLUE)]);
+        assert_close(gradient.sample(0.4999), RED);
+        assert_close(gradient.sample(0.5), BLUE);
+        assert_close(gradient.sample(0.5001), BLUE);
+    }
+
+    #[test]
+    fn added_stop_goes_after_stops_at_same_position() {
+        let mut gradient = Gradient::two_color(RED, BLUE);
+        assert_eq!(gradient.add_stop(1.0, GREEN), 2);
+        assert_close(gradient.sample(1.0), GREEN);
+        assert_eq!(gradient.add_stop(0.0, GREEN), 1);
+        assert_close(gradient.sample(0.0), RED);
+    }
+
+    #[test]
+    fn lookup_image_spans_the_gradient() {
+        let image = Gradient::two_color(RED, BLUE).to_image(3);
+        assert_eq!(image.size(), (3, 1));
+        assert_eq!(image.as_ref().pixel_array().bytes(), [255, 0, 0, 255, 128, 0, 128, 255, 0, 0, 255, 255]);
+    }
+}
@@ -1,5 +1,7 @@
-use crate::gl_utils::{compile_shader, link_shader_program, create_buffer_f32};
-use crate::image::ImageRef;
+use crate::gl_utils::{compile_shader, link_shader_program, create_buffer_f32, BlendMode, MemoryCategory, TrackedMemory};
+use crate::gl_utils::SavedBlendState;
+use crate::image::{Image, ImageRef, PixelArrayRef, Rgba8};
+use crate::renderers::{Renderer, WindowRect};
 
 type Error = Box<dyn std::error::Error>;
 
@@ -7,6 +9,8 @@ type Error = Box<dyn std::error::Error>;
 pub struct ImageTexture {
     texture_id: u32,
     size: (u32, u32),
+    premultiplied: bool,
+    _memory: TrackedMemory,
 }
 
 impl ImageTexture {
@@ -22,15 +26,47 @@ impl ImageTexture {
             gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
         }
 
+        let mut memory = TrackedMemory::new(MemoryCategory::Image);
+        memory.set(image.texture_bytes());
+
         Self {
             texture_id,
             size: image.size(),
+            premultiplied: false,
+            _memory: memory,
         }
     }
 
+    /// Uploads a premultiplied copy of a straight alpha image, to be drawn
+    /// with [`BlendMode::PremultipliedAlpha`](crate::gl_utils::BlendMode::PremultipliedAlpha).
+    pub fn new_premultiplied(image: ImageRef) -> Self {
+        let mut pixel_array = image.pixel_array().to_owned();
+        pixel_array.premultiply();
+        let image = Image::new(image.size(), pixel_array);
+
+        let mut texture = Self::new(image.as_ref());
+        texture.premultiplied = true;
+        texture
+    }
+
     pub fn size(&self) -> (u32, u32) {
         self.size
     }
+
+    /// Whether the texture holds premultiplied colors. Drawing it with the
+    /// matching blend mode is up to the caller.
+    pub fn is_premultiplied(&self) -> bool {
+        self.premultiplied
+    }
+
+    /// The blend mode that composites the texture correctly.
+    pub fn blend_mode(&self) -> BlendMode {
+        if self.premultiplied { BlendMode::PremultipliedAlpha } else { BlendMode::Alpha }
+    }
+
+    pub(crate) fn id(&self) -> u32 {
+        self.texture_id
+    }
 }
 
 impl Drop for ImageTexture {
@@ -41,8 +77,99 @@ impl Drop for ImageTexture {
     }
 }
 
+/// An indexed image kept as indices on the GPU, looked up in a palette
+/// texture when drawn with [`ImageRenderer::render_indexed`], so changing
+/// the palette recolors it without uploading the image again.
+pub struct IndexedTexture {
+    index_texture: u32,
+    palette_texture: u32,
+    size: (u32, u32),
+    _memory: TrackedMemory,
+}
+
+impl IndexedTexture {
+    pub fn new(image: ImageRef) -> Result<Self, Error> {
+        let (palette, indices) = match image.pixel_array() {
+            PixelArrayRef::Indexed { palette, indices } => (palette, indices),
+            _ => return Err("IndexedTexture needs an indexed image".into()),
+        };
+
+        let size = image.size();
+        let mut textures = [0; 2];
+        unsafe {
+            gl::GenTextures(2, textures.as_mut_ptr());
+
+            // indices can't be interpolated
+            gl::BindTexture(gl::TEXTURE_2D, textures[0]);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::R8 as i32,
+                size.0 as i32,
+                size.1 as i32,
+                0,
+                gl::RED,
+                gl::UNSIGNED_BYTE,
+                indices.as_ptr() as _,
+            );
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+
+            gl::BindTexture(gl::TEXTURE_2D, textures[1]);
+            gl::TexImage2D(gl::TEXTURE_2D, 0, gl::RGBA8 as i32, 256, 1, 0, gl::RGBA, gl::UNSIGNED_BYTE, std::ptr::null());
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+        }
+
+        let mut memory = TrackedMemory::new(MemoryCategory::Image);
+        memory.set(indices.len() as u64 + 256 * 4);
+
+        let texture = Self {
+            index_texture: textures[0],
+            palette_texture: textures[1],
+            size,
+            _memory: memory,
+        };
+        texture.set_palette(palette);
+        Ok(texture)
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    /// Replaces the palette. Entries past the end of `palette`, up to 256,
+    /// become transparent black.
+    pub fn set_palette(&self, palette: &[Rgba8]) {
+        let mut data = [[0u8; 4]; 256];
+        for (entry, color) in data.iter_mut().zip(palette) {
+            *entry = *color;
+        }
+
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.palette_texture);
+            gl::TexSubImage2D(gl::TEXTURE_2D, 0, 0, 0, 256, 1, gl::RGBA, gl::UNSIGNED_BYTE, data.as_ptr() as _);
+        }
+    }
+}
+
+impl Drop for IndexedTexture {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(2, [self.index_texture, self.palette_texture].as_ptr());
+        }
+    }
+}
+
+/// Draws textures as they are, without touching the blend state; see
+/// [`ImageTexture::blend_mode`].
 pub struct ImageRenderer {
     program: u32,
+    indexed_program: u32,
     vao: u32,
     vbo: u32,
 }
@@ -60,6 +187,20 @@ impl ImageRenderer {
 
         let program = link_shader_program(shaders)?;
 
+        let fcode = include_str!("shaders/indexed_fragment_shader.glsl");
+        let indexed_fshader = compile_shader(fcode, gl::FRAGMENT_SHADER)?;
+        let indexed_program = link_shader_program(&[vshader, indexed_fshader])?;
+
+        unsafe {
+            gl::DeleteShader(vshader);
+            gl::DeleteShader(fshader);
+            gl::DeleteShader(indexed_fshader);
+
+            gl::UseProgram(indexed_program);
+            gl::Uniform1i(gl::GetUniformLocation(indexed_program, c"tex".as_ptr()), 0);
+            gl::Uniform1i(gl::GetUniformLocation(indexed_program, c"palette".as_ptr()), 1);
+        }
+
         let vertices: &[f32] = &[
             // positions
             -1.0, 1.0,
@@ -85,7 +226,7 @@ impl ImageRenderer {
 
             let stride = 0;
 
-            let offset = 0 as *const _;
+            let offset = std::ptr::null();
             gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, offset);
             gl::EnableVertexAttribArray(0);
 
@@ -96,11 +237,59 @@ impl ImageRenderer {
 
         Ok(Self {
             program,
+            indexed_program,
             vao,
             vbo,
         })
     }
 
+    /// Replaces the fragment stage of the image program, e.g. for inverting
+    /// or false-coloring an image. The shader gets `in vec2 v_tex_coords` and
+    /// `uniform sampler2D tex` like the standard one, and writes its color to
+    /// any `out vec4`. Compile or link errors are returned, leaving the
+    /// current program in place. Indexed rendering isn't affected.
+    pub fn set_custom_fragment_shader(&mut self, source: &str) -> Result<(), Error> {
+        let program = Self::build_program(source)?;
+        unsafe {
+            gl::DeleteProgram(self.program);
+        }
+        self.program = program;
+        Ok(())
+    }
+
+    /// Goes back to drawing textures as they are.
+    pub fn reset_fragment_shader(&mut self) -> Result<(), Error> {
+        self.set_custom_fragment_shader(include_str!("shaders/fragment_shader.glsl"))
+    }
+
+    /// The image program, for setting a custom fragment shader's uniforms.
+    pub fn program(&self) -> u32 {
+        self.program
+    }
+
+    fn build_program(fragment_source: &str) -> Result<u32, Error> {
+        let vshader = compile_shader(include_str!("shaders/vertex_shader.glsl"), gl::VERTEX_SHADER)?;
+        let fshader = match compile_shader(fragment_source, gl::FRAGMENT_SHADER) {
+            Ok(fshader) => fshader,
+            Err(e) => {
+                unsafe { gl::DeleteShader(vshader) };
+                return Err(e);
+            }
+        };
+
+        let program = link_shader_program(&[vshader, fshader]);
+        unsafe {
+            gl::DeleteShader(vshader);
+            gl::DeleteShader(fshader);
+        }
+        program
+    }
+
+    /// Renders an arbitrary texture through the image program.
+    ///
+    /// # Safety
+    ///
+    /// `texture_id` must name a live 2D texture in the current GL context.
     pub unsafe fn render_raw_texture(&self, texture_id: u32) {
         gl::UseProgram(self.program);
         gl::BindTexture(gl::TEXTURE_2D, texture_id);
@@ -114,6 +303,18 @@ impl ImageRenderer {
         }
     }
 
+    pub fn render_indexed(&self, texture: &IndexedTexture) {
+        unsafe {
+            gl::UseProgram(self.indexed_program);
+            gl::ActiveTexture(gl::TEXTURE1);
+            gl::BindTexture(gl::TEXTURE_2D, texture.palette_texture);
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, texture.index_texture);
+            gl::BindVertexArray(self.vao);
+            gl::DrawArrays(gl::TRIANGLE_FAN, 0, 4);
+        }
+    }
+
     pub fn set_render_quad(&mut self, vertices: &[f32]) {
         if vertices.len() != 8 {
             panic!("Invalid number of vertices");
@@ -121,7 +322,7 @@ impl ImageRenderer {
 
         unsafe {
             gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
-            gl::BufferSubData(gl::ARRAY_BUFFER, 0, (vertices.len() * std::mem::size_of::<f32>()) as isize, vertices.as_ptr() as _);
+            gl::BufferSubData(gl::ARRAY_BUFFER, 0, std::mem::size_of_val(vertices) as isize, vertices.as_ptr() as _);
         }
     }
 
@@ -142,8 +343,59 @@ impl Drop for ImageRenderer {
     fn drop(&mut self) {
         unsafe {
             gl::DeleteProgram(self.program);
+            gl::DeleteProgram(self.indexed_program);
             gl::DeleteVertexArrays(1, &self.vao);
             gl::DeleteBuffers(1, &self.vbo);
         }
     }
-}
\ No newline at end of file
+}
+
+/// An [`ImageRenderer`] with the texture it draws, as a [`Renderer`]. The
+/// texture is stretched over the viewport and drawn with its
+/// [`ImageTexture::blend_mode`].
+pub struct ImageView {
+    pub renderer: ImageRenderer,
+    pub texture: ImageTexture,
+    viewport: WindowRect,
+    window_size: (u32, u32),
+}
+
+impl ImageView {
+    pub fn new(renderer: ImageRenderer, texture: ImageTexture) -> Self {
+        Self {
+            renderer,
+            texture,
+            viewport: WindowRect::default(),
+            window_size: (1, 1),
+        }
+    }
+}
+
+impl Renderer for ImageView {
+    fn set_viewport(&mut self, viewport: WindowRect, window_size: (u32, u32)) -> Result<(), Error> {
+        self.viewport = viewport;
+        self.window_size = window_size;
+        Ok(())
+    }
+
+    fn render(&mut self) {
+        let rect = self.viewport;
+        let gl_y = self.window_size.1 as i32 - rect.y - rect.height as i32;
+
+        let mut saved_viewport = [0i32; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, saved_viewport.as_mut_ptr());
+            gl::Viewport(rect.x, gl_y, rect.width as i32, rect.height as i32);
+        }
+        let saved_blend = SavedBlendState::save();
+        self.texture.blend_mode().apply();
+
+        self.renderer.render(&self.texture);
+
+        saved_blend.restore();
+        unsafe {
+            let [x, y, w, h] = saved_viewport;
+            gl::Viewport(x, y, w, h);
+        }
+    }
+}
@@ -0,0 +1,12 @@
+#version 330 core
+
+in vec2 v_tex_coords;
+out vec4 f_color;
+
+uniform sampler2D tex;
+uniform sampler2D palette;
+
+void main() {
+    int index = int(texture(tex, v_tex_coords).r * 255.0 + 0.5);
+    f_color = texelFetch(palette, ivec2(index, 0), 0);
+}
@@ -0,0 +1,164 @@
+use crate::renderers::image_renderer::{ImageRenderer, ImageTexture};
+
+type Error = Box<dyn std::error::Error>;
+
+/// Clips drawing to an arbitrary shape using the stencil buffer, e.g. for
+/// rounded or circular panes.
+///
+/// The mask is whatever a closure draws, or the opaque part of an image;
+/// only the pixels it covers count, not its colors. The framebuffer needs
+/// stencil bits, which the window has by default but [`Framebuffer`](crate::gl_utils::Framebuffer)
+/// targets don't.
+///
+/// Masks nest: content drawn inside a masked region may use another mask,
+/// which is clipped to the outer one. Nesting is detected from the stencil
+/// test being on, so don't leave it on for other purposes around a mask.
+/// The outermost mask clears the stencil buffer, within the scissor box if
+/// scissoring is on. Scissoring keeps applying as usual, and all stencil
+/// state is restored afterwards.
+pub struct MaskRenderer {
+    image_renderer: ImageRenderer,
+    threshold_location: i32,
+    /// Image mask pixels with alpha below this are outside the mask.
+    pub alpha_threshold: f32,
+}
+
+#[derive(Default)]
+struct StencilState {
+    enabled: bool,
+    func: i32,
+    reference: i32,
+    value_mask: i32,
+    write_mask: i32,
+    fail: i32,
+    depth_fail: i32,
+    depth_pass: i32,
+    color_mask: [u8; 4],
+    depth_mask: u8,
+}
+
+impl StencilState {
+    fn save() -> Self {
+        let mut state = Self::default();
+        unsafe {
+            state.enabled = gl::IsEnabled(gl::STENCIL_TEST) == gl::TRUE;
+            gl::GetIntegerv(gl::STENCIL_FUNC, &mut state.func);
+            gl::GetIntegerv(gl::STENCIL_REF, &mut state.reference);
+            gl::GetIntegerv(gl::STENCIL_VALUE_MASK, &mut state.value_mask);
+            gl::GetIntegerv(gl::STENCIL_WRITEMASK, &mut state.write_mask);
+            gl::GetIntegerv(gl::STENCIL_FAIL, &mut state.fail);
+            gl::GetIntegerv(gl::STENCIL_PASS_DEPTH_FAIL, &mut state.depth_fail);
+            gl::GetIntegerv(gl::STENCIL_PASS_DEPTH_PASS, &mut state.depth_pass);
+            gl::GetBooleanv(gl::COLOR_WRITEMASK, state.color_mask.as_mut_ptr());
+            gl::GetBooleanv(gl::DEPTH_WRITEMASK, &mut state.depth_mask);
+        }
+        state
+    }
+
+    fn restore(&self) {
+        unsafe {
+            if !self.enabled {
+                gl::Disable(gl::STENCIL_TEST);
+            }
+            gl::StencilFunc(self.func as u32, self.reference, self.value_mask as u32);
+            gl::StencilMask(self.write_mask as u32);
+            gl::StencilOp(self.fail as u32, self.depth_fail as u32, self.depth_pass as u32);
+            let [r, g, b, a] = self.color_mask;
+            gl::ColorMask(r, g, b, a);
+            gl::DepthMask(self.depth_mask);
+        }
+    }
+}
+
+impl MaskRenderer {
+    pub fn new() -> Result<Self, Error> {
+        let mut image_renderer = ImageRenderer::new()?;
+        image_renderer.set_custom_fragment_shader(include_str!("shaders/alpha_mask_fragment_shader.glsl"))?;
+
+        let threshold_location = unsafe {
+            gl::GetUniformLocation(image_renderer.program(), c"threshold".as_ptr())
+        };
+
+        Ok(Self {
+            image_renderer,
+            threshold_location,
+            alpha_threshold: 0.5,
+        })
+    }
+
+    /// Runs `content` clipped to what `mask` draws. `mask` runs with color
+    /// and depth writes off, and runs a second time afterwards to undo its
+    /// stencil marks when nested, so it must draw the same thing both times.
+    pub fn render<M: Fn(), F: FnOnce()>(&self, mask: M, content: F) {
+        let saved = StencilState::save();
+
+        // inside another mask, build on its level instead of clearing it
+        let level = if saved.enabled { saved.reference } else { 0 };
+
+        unsafe {
+            if !saved.enabled {
+                gl::StencilMask(0xFF);
+                gl::ClearStencil(0);
+                gl::Clear(gl::STENCIL_BUFFER_BIT);
+            }
+
+            gl::Enable(gl::STENCIL_TEST);
+            gl::StencilMask(0xFF);
+            gl::ColorMask(gl::FALSE, gl::FALSE, gl::FALSE, gl::FALSE);
+            gl::DepthMask(gl::FALSE);
+
+            // a pixel only passes while still at `level`, so overlapping mask
+            // geometry marks it once
+            gl::StencilFunc(gl::EQUAL, level, 0xFF);
+            gl::StencilOp(gl::KEEP, gl::KEEP, gl::INCR);
+        }
+        mask();
+
+        unsafe {
+            let [r, g, b, a] = saved.color_mask;
+            gl::ColorMask(r, g, b, a);
+            gl::DepthMask(saved.depth_mask);
+            gl::StencilFunc(gl::EQUAL, level + 1, 0xFF);
+            gl::StencilOp(gl::KEEP, gl::KEEP, gl::KEEP);
+        }
+        content();
+
+        if saved.enabled {
+            unsafe {
+                gl::ColorMask(gl::FALSE, gl::FALSE, gl::FALSE, gl::FALSE);
+                gl::DepthMask(gl::FALSE);
+                gl::StencilMask(0xFF);
+                gl::StencilFunc(gl::EQUAL, level + 1, 0xFF);
+                gl::StencilOp(gl::KEEP, gl::KEEP, gl::DECR);
+            }
+            mask();
+        }
+
+        saved.restore();
+    }
+
+    /// Runs `content` clipped to the opaque part of `mask`, drawn over the
+    /// current viewport like [`ImageRenderer::render`].
+    pub fn render_with_image_mask<F: FnOnce()>(&self, mask: &ImageTexture, content: F) {
+        unsafe {
+            gl::UseProgram(self.image_renderer.program());
+            gl::Uniform1f(self.threshold_location, self.alpha_threshold);
+        }
+
+        let mut viewport = [0i32; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        }
+
+        // content may move the viewport, and the mask is drawn again after it
+        let draw_mask = || unsafe {
+            let mut current = [0i32; 4];
+            gl::GetIntegerv(gl::VIEWPORT, current.as_mut_ptr());
+            gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+            self.image_renderer.render(mask);
+            gl::Viewport(current[0], current[1], current[2], current[3]);
+        };
+
+        self.render(draw_mask, content);
+    }
+}
@@ -0,0 +1,15 @@
+#version 330 core
+
+in vec2 v_tex_coords;
+out vec4 f_color;
+
+uniform sampler2D tex;
+uniform float threshold;
+
+void main() {
+    vec4 color = texture(tex, v_tex_coords);
+    if (color.a < threshold) {
+        discard;
+    }
+    f_color = color;
+}
@@ -1,2 +1,85 @@
+pub mod audio;
+pub mod color_picker;
+pub mod contour;
+pub mod curve_editor;
+pub mod gallery;
+pub mod gradient_editor;
 pub mod image_renderer;
-pub mod system_text;
\ No newline at end of file
+pub mod mask;
+pub mod opacity;
+pub mod pixel_perfect;
+pub mod post_process;
+pub mod rounded_pane;
+pub mod shader_quad;
+pub mod shadow;
+pub mod system_text;
+pub mod tiled_image;
+pub mod transform_gizmo;
+pub mod volume_slice;
+
+mod shapes;
+
+type Error = Box<dyn std::error::Error>;
+
+/// A renderer that fills its part of the window, like the editors and plots
+/// in this module. Lets a renderer be the root of a window with
+/// [`Window::run_with_renderer`](crate::window::Window::run_with_renderer).
+///
+/// Renderers that draw something they're handed each frame implement it
+/// through a wrapper holding what they draw, such as
+/// [`Gallery`](gallery::Gallery) or [`ImageView`](image_renderer::ImageView).
+/// Renderers that draw around other content implement it through a wrapper
+/// holding the child renderer, such as [`Shadow`](shadow::Shadow) or
+/// [`PixelPerfect`](pixel_perfect::PixelPerfect).
+///
+/// [`MaskRenderer`](mask::MaskRenderer) doesn't implement it, since its mask
+/// is drawing code rather than an area; [`RoundedPane`](rounded_pane::RoundedPane)
+/// covers clipping to a rectangle. Neither do
+/// [`TransformGizmoRenderer`](transform_gizmo::TransformGizmoRenderer), which
+/// is placed around its target and driven by events, nor
+/// [`SystemTextRenderer`](system_text::SystemTextRenderer), which draws text
+/// at the positions in its lines.
+pub trait Renderer {
+    /// Sets the area drawn into, in window pixels. `window_size` is needed
+    /// to convert to OpenGL's bottom-left origin. Fails if resources sized
+    /// to the viewport can't be created.
+    fn set_viewport(&mut self, viewport: WindowRect, window_size: (u32, u32)) -> Result<(), Error>;
+    fn render(&mut self);
+}
+
+impl<R: Renderer + ?Sized> Renderer for Box<R> {
+    fn set_viewport(&mut self, viewport: WindowRect, window_size: (u32, u32)) -> Result<(), Error> {
+        (**self).set_viewport(viewport, window_size)
+    }
+
+    fn render(&mut self) {
+        (**self).render();
+    }
+}
+
+/// A rectangle in window pixels, with the origin at the top left like cursor
+/// positions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WindowRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl WindowRect {
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self { x, y, width, height }
+    }
+
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x as f32
+            && y >= self.y as f32
+            && x < (self.x + self.width as i32) as f32
+            && y < (self.y + self.height as i32) as f32
+    }
+
+    pub(crate) fn to_f32(self) -> [f32; 4] {
+        [self.x as f32, self.y as f32, self.width as f32, self.height as f32]
+    }
+}
@@ -0,0 +1,156 @@
+use crate::gl_utils::{BlendMode, Framebuffer, SavedBlendState};
+use crate::renderers::image_renderer::ImageRenderer;
+use crate::renderers::{Renderer, WindowRect};
+
+type Error = Box<dyn std::error::Error>;
+
+/// Fades a whole group of drawing in or out at once, e.g. a pane with
+/// everything in it.
+///
+/// Drawing each part with reduced alpha would let overlapping parts show
+/// through each other. Instead, the content closure passed to
+/// [`OpacityRenderer::render`] draws into an offscreen window-sized
+/// [`Framebuffer`], exactly as it would to the window. The result is then
+/// composited with [`OpacityRenderer::opacity`].
+///
+/// Only the part inside the rectangle from [`OpacityRenderer::set_viewport`]
+/// is composited. Content that binds framebuffer 0 itself bypasses the
+/// fade. The offscreen target has no depth or stencil buffer, so masks
+/// don't work inside it.
+pub struct OpacityRenderer {
+    image_renderer: ImageRenderer,
+    source_rect_location: i32,
+    opacity_location: i32,
+    target: Framebuffer,
+    viewport: WindowRect,
+    window_size: (u32, u32),
+    /// From 0, fully transparent, to 1, drawn as if directly.
+    pub opacity: f32,
+}
+
+impl OpacityRenderer {
+    pub fn new() -> Result<Self, Error> {
+        let mut image_renderer = ImageRenderer::new()?;
+        image_renderer.set_custom_fragment_shader(include_str!("shaders/opacity_fragment_shader.glsl"))?;
+
+        let program = image_renderer.program();
+        let (source_rect_location, opacity_location) = unsafe {
+            (
+                gl::GetUniformLocation(program, c"source_rect".as_ptr()),
+                gl::GetUniformLocation(program, c"opacity".as_ptr()),
+            )
+        };
+
+        Ok(Self {
+            image_renderer,
+            source_rect_location,
+            opacity_location,
+            target: Framebuffer::new((1, 1))?,
+            viewport: WindowRect::default(),
+            window_size: (1, 1),
+            opacity: 1.0,
+        })
+    }
+
+    /// Sets the area that is faded, in window pixels. `window_size` is the
+    /// size of the offscreen target, which is reallocated when it changes.
+    pub fn set_viewport(&mut self, viewport: WindowRect, window_size: (u32, u32)) -> Result<(), Error> {
+        if window_size != self.window_size {
+            self.target.resize(window_size)?;
+            self.window_size = window_size;
+        }
+        self.viewport = viewport;
+        Ok(())
+    }
+
+    pub fn viewport(&self) -> WindowRect {
+        self.viewport
+    }
+
+    /// Runs `content` with the GL viewport set to the rectangle, then
+    /// composites what it drew. At full opacity `content` draws straight to
+    /// the current framebuffer, and at zero it doesn't run at all. The
+    /// previous framebuffer, viewport and blend state are restored
+    /// afterwards.
+    pub fn render<F: FnOnce()>(&self, content: F) {
+        let opacity = self.opacity.clamp(0.0, 1.0);
+        if opacity <= 0.0 {
+            return;
+        }
+
+        let rect = self.viewport;
+        let (ww, wh) = self.window_size;
+        let gl_y = wh as i32 - rect.y - rect.height as i32;
+
+        let mut saved_framebuffer = 0;
+        let mut saved_viewport = [0i32; 4];
+        let mut saved_clear_color = [0f32; 4];
+        unsafe {
+            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut saved_framebuffer);
+            gl::GetIntegerv(gl::VIEWPORT, saved_viewport.as_mut_ptr());
+            gl::GetFloatv(gl::COLOR_CLEAR_VALUE, saved_clear_color.as_mut_ptr());
+        }
+
+        if opacity >= 1.0 {
+            unsafe { gl::Viewport(rect.x, gl_y, rect.width as i32, rect.height as i32) };
+            content();
+            let [x, y, w, h] = saved_viewport;
+            unsafe { gl::Viewport(x, y, w, h) };
+            return;
+        }
+
+        self.target.bind();
+        unsafe {
+            gl::ClearColor(0.0, 0.0, 0.0, 0.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+            let [r, g, b, a] = saved_clear_color;
+            gl::ClearColor(r, g, b, a);
+            gl::Viewport(rect.x, gl_y, rect.width as i32, rect.height as i32);
+        }
+
+        content();
+
+        let source_rect = [
+            rect.x as f32 / ww as f32,
+            gl_y as f32 / wh as f32,
+            rect.width as f32 / ww as f32,
+            rect.height as f32 / wh as f32,
+        ];
+
+        let saved_blend = SavedBlendState::save();
+        BlendMode::PremultipliedAlpha.apply();
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, saved_framebuffer as u32);
+            gl::Viewport(rect.x, gl_y, rect.width as i32, rect.height as i32);
+
+            gl::UseProgram(self.image_renderer.program());
+            gl::Uniform4fv(self.source_rect_location, 1, source_rect.as_ptr());
+            gl::Uniform1f(self.opacity_location, opacity);
+            gl::ActiveTexture(gl::TEXTURE0);
+            self.image_renderer.render_raw_texture(self.target.color_texture());
+
+            let [x, y, w, h] = saved_viewport;
+            gl::Viewport(x, y, w, h);
+        }
+        saved_blend.restore();
+    }
+}
+
+/// An [`OpacityRenderer`] fading `child`, as a [`Renderer`]. Both get the
+/// same viewport.
+pub struct Opacity<R> {
+    pub renderer: OpacityRenderer,
+    pub child: R,
+}
+
+impl<R: Renderer> Renderer for Opacity<R> {
+    fn set_viewport(&mut self, viewport: WindowRect, window_size: (u32, u32)) -> Result<(), Error> {
+        self.renderer.set_viewport(viewport, window_size)?;
+        self.child.set_viewport(viewport, window_size)
+    }
+
+    fn render(&mut self) {
+        let child = &mut self.child;
+        self.renderer.render(|| child.render());
+    }
+}
@@ -0,0 +1,16 @@
+#version 330 core
+
+in vec2 v_tex_coords;
+out vec4 f_color;
+
+uniform sampler2D tex;
+uniform vec4 source_rect;
+uniform float opacity;
+
+void main() {
+    // the framebuffer's rows run bottom to top, unlike an image's
+    vec2 coords = source_rect.xy + vec2(v_tex_coords.x, 1.0 - v_tex_coords.y) * source_rect.zw;
+
+    // what was drawn into the transparent target is premultiplied
+    f_color = texture(tex, coords) * opacity;
+}
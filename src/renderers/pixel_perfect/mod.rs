@@ -0,0 +1,154 @@
+use crate::gl_utils::Framebuffer;
+use crate::renderers::image_renderer::ImageRenderer;
+use crate::renderers::{Renderer, WindowRect};
+
+type Error = Box<dyn std::error::Error>;
+
+/// Renders into a fixed low-resolution target and shows it scaled up by the
+/// largest integer factor that fits the window, centered with letterboxing.
+///
+/// Sampling uses `NEAREST` filtering, so every low-resolution pixel becomes a
+/// crisp square block on screen.
+pub struct PixelPerfectRenderer {
+    framebuffer: Framebuffer,
+    image_renderer: ImageRenderer,
+    window_size: (u32, u32),
+    pub letterbox_color: [f32; 4],
+}
+
+impl PixelPerfectRenderer {
+    pub fn new(resolution: (u32, u32)) -> Result<Self, Error> {
+        let framebuffer = Framebuffer::new(resolution)?;
+        framebuffer.set_filter(gl::NEAREST);
+
+        let mut image_renderer = ImageRenderer::new()?;
+
+        // framebuffer textures are stored bottom row first, unlike images
+        image_renderer.set_render_quad(&[
+            -1.0, -1.0,
+            1.0, -1.0,
+            1.0, 1.0,
+            -1.0, 1.0,
+        ]);
+
+        Ok(Self {
+            framebuffer,
+            image_renderer,
+            window_size: resolution,
+            letterbox_color: [0.0, 0.0, 0.0, 1.0],
+        })
+    }
+
+    pub fn resolution(&self) -> (u32, u32) {
+        self.framebuffer.size()
+    }
+
+    /// Changes the low-resolution target size.
+    pub fn set_resolution(&mut self, resolution: (u32, u32)) -> Result<(), Error> {
+        self.framebuffer.resize(resolution)
+    }
+
+    /// Sets the window size the output is fitted to. Call this on resize.
+    pub fn set_window_size(&mut self, window_size: (u32, u32)) {
+        self.window_size = window_size;
+    }
+
+    /// The integer factor the target is currently scaled up by. Zero if the
+    /// window is smaller than the target.
+    pub fn scale_factor(&self) -> u32 {
+        let (rw, rh) = self.framebuffer.size();
+        u32::min(self.window_size.0 / rw, self.window_size.1 / rh)
+    }
+
+    /// The area of the window the scaled target covers, as
+    /// `[x, y, width, height]` in GL window coordinates.
+    pub fn output_rect(&self) -> [i32; 4] {
+        let (rw, rh) = self.framebuffer.size();
+        let (ww, wh) = self.window_size;
+
+        // if the window is too small, shrink to fit rather than show nothing
+        let (w, h) = match self.scale_factor() {
+            0 => {
+                let scale = f32::min(ww as f32 / rw as f32, wh as f32 / rh as f32);
+                ((rw as f32 * scale) as u32, (rh as f32 * scale) as u32)
+            }
+            scale => (rw * scale, rh * scale),
+        };
+
+        let x = (ww - w) / 2;
+        let y = (wh - h) / 2;
+        [x as i32, y as i32, w as i32, h as i32]
+    }
+
+    /// Converts a cursor position in window pixels (top-left origin) to a
+    /// position in target pixels (also top-left origin). Returns `None` if the
+    /// cursor is over the letterbox.
+    pub fn window_to_target(&self, position: (f32, f32)) -> Option<(f32, f32)> {
+        let [x, y, w, h] = self.output_rect();
+        let top = self.window_size.1 as i32 - y - h;
+        let local_x = position.0 - x as f32;
+        let local_y = position.1 - top as f32;
+        if local_x < 0.0 || local_y < 0.0 || local_x >= w as f32 || local_y >= h as f32 {
+            return None;
+        }
+
+        let (rw, rh) = self.framebuffer.size();
+        Some((local_x * rw as f32 / w as f32, local_y * rh as f32 / h as f32))
+    }
+
+    /// Runs `draw` with the low-resolution target bound and its viewport set,
+    /// then presents the result to the currently bound framebuffer.
+    pub fn render<F: FnOnce()>(&self, draw: F) {
+        let mut previous_framebuffer = 0;
+        let mut previous_viewport = [0i32; 4];
+        let mut previous_clear_color = [0f32; 4];
+        unsafe {
+            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut previous_framebuffer);
+            gl::GetIntegerv(gl::VIEWPORT, previous_viewport.as_mut_ptr());
+            gl::GetFloatv(gl::COLOR_CLEAR_VALUE, previous_clear_color.as_mut_ptr());
+        }
+
+        self.framebuffer.bind();
+        draw();
+
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, previous_framebuffer as u32);
+
+            let [r, g, b, a] = self.letterbox_color;
+            gl::Viewport(0, 0, self.window_size.0 as i32, self.window_size.1 as i32);
+            gl::ClearColor(r, g, b, a);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+
+            let [x, y, w, h] = self.output_rect();
+            gl::Viewport(x, y, w, h);
+            self.image_renderer.render_raw_texture(self.framebuffer.color_texture());
+
+            let [x, y, w, h] = previous_viewport;
+            gl::Viewport(x, y, w, h);
+            let [r, g, b, a] = previous_clear_color;
+            gl::ClearColor(r, g, b, a);
+        }
+    }
+}
+
+/// A [`PixelPerfectRenderer`] drawing `child` at its resolution, as a
+/// [`Renderer`]. The output is fitted to the whole window whatever the
+/// viewport, and `child` gets the whole low-resolution target as its
+/// viewport, so call `set_viewport` again after changing the resolution.
+pub struct PixelPerfect<R> {
+    pub renderer: PixelPerfectRenderer,
+    pub child: R,
+}
+
+impl<R: Renderer> Renderer for PixelPerfect<R> {
+    fn set_viewport(&mut self, _viewport: WindowRect, window_size: (u32, u32)) -> Result<(), Error> {
+        self.renderer.set_window_size(window_size);
+        let (width, height) = self.renderer.resolution();
+        self.child.set_viewport(WindowRect::new(0, 0, width, height), (width, height))
+    }
+
+    fn render(&mut self) {
+        let child = &mut self.child;
+        self.renderer.render(|| child.render());
+    }
+}
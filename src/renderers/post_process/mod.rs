@@ -0,0 +1,374 @@
+use crate::gl_utils::{compile_shader, create_buffer_f32, link_shader_program, Framebuffer};
+
+type Error = Box<dyn std::error::Error>;
+
+/// Ready-made post-process effects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostProcessPreset {
+    /// Copies the input unchanged.
+    Passthrough,
+    /// Screen curvature, rolling scanlines and a vignette.
+    Crt,
+    /// Darkens the border of every source pixel, like an LCD grid. Best used
+    /// after magnifying low-resolution content.
+    PixelGrid,
+    /// Splits the color channels apart towards the edges of the screen.
+    ChromaticAberration,
+    /// Animated per-pixel noise.
+    FilmGrain,
+    /// Gamma, brightness, contrast and color-blindness simulation. Configure
+    /// it with [`ColorAdjustment::apply_to`].
+    ColorAdjust,
+}
+
+impl PostProcessPreset {
+    /// The fragment shader source of the preset. These double as examples of
+    /// the interface custom passes are written against.
+    pub fn fragment_source(&self) -> &'static str {
+        match self {
+            PostProcessPreset::Passthrough => include_str!("shaders/passthrough.glsl"),
+            PostProcessPreset::Crt => include_str!("shaders/crt.glsl"),
+            PostProcessPreset::PixelGrid => include_str!("shaders/pixel_grid.glsl"),
+            PostProcessPreset::ChromaticAberration => include_str!("shaders/chromatic_aberration.glsl"),
+            PostProcessPreset::FilmGrain => include_str!("shaders/film_grain.glsl"),
+            PostProcessPreset::ColorAdjust => include_str!("shaders/color_adjust.glsl"),
+        }
+    }
+}
+
+/// Simulated color vision deficiencies, for previewing how content looks to
+/// color-blind users.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorBlindness {
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+    Achromatopsia,
+}
+
+impl ColorBlindness {
+    /// Row-major linear RGB simulation matrix (Machado et al. 2009 at full
+    /// severity, plain luminance for achromatopsia).
+    fn matrix(&self) -> [f32; 9] {
+        match self {
+            ColorBlindness::Protanopia => [
+                0.152286, 1.052583, -0.204868,
+                0.114503, 0.786281, 0.099216,
+                -0.003882, -0.048116, 1.051998,
+            ],
+            ColorBlindness::Deuteranopia => [
+                0.367322, 0.860646, -0.227968,
+                0.280085, 0.672501, 0.047413,
+                -0.011820, 0.042940, 0.968881,
+            ],
+            ColorBlindness::Tritanopia => [
+                1.255528, -0.076749, -0.178779,
+                -0.078411, 0.930809, 0.147602,
+                0.004733, 0.691367, 0.301900,
+            ],
+            ColorBlindness::Achromatopsia => [
+                0.2126, 0.7152, 0.0722,
+                0.2126, 0.7152, 0.0722,
+                0.2126, 0.7152, 0.0722,
+            ],
+        }
+    }
+}
+
+/// Settings for the [`PostProcessPreset::ColorAdjust`] pass.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorAdjustment {
+    /// Output gamma; values above 1 brighten midtones.
+    pub gamma: f32,
+    /// Added to every channel, after contrast.
+    pub brightness: f32,
+    /// Scales the distance of every channel from middle gray.
+    pub contrast: f32,
+    pub color_blindness: Option<ColorBlindness>,
+}
+
+impl Default for ColorAdjustment {
+    fn default() -> Self {
+        Self {
+            gamma: 1.0,
+            brightness: 0.0,
+            contrast: 1.0,
+            color_blindness: None,
+        }
+    }
+}
+
+impl ColorAdjustment {
+    /// Uploads the settings to a pass created from
+    /// [`PostProcessPreset::ColorAdjust`].
+    pub fn apply_to(&self, pass: &PostProcessPass) {
+        const IDENTITY: [f32; 9] = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+        let matrix = self.color_blindness.map_or(IDENTITY, |c| c.matrix());
+        unsafe {
+            gl::UseProgram(pass.program());
+            gl::Uniform1f(pass.uniform_location("u_gamma"), self.gamma);
+            gl::Uniform1f(pass.uniform_location("u_brightness"), self.brightness);
+            gl::Uniform1f(pass.uniform_location("u_contrast"), self.contrast);
+            gl::UniformMatrix3fv(pass.uniform_location("u_color_matrix"), 1, gl::TRUE, matrix.as_ptr());
+        }
+    }
+}
+
+/// One full-screen pass of a [`PostProcessChain`].
+///
+/// The fragment shader receives `in vec2 v_tex_coords` and may declare any of
+/// these uniforms, which are set automatically:
+///
+/// - `sampler2D u_texture`: the output of the previous pass
+/// - `vec2 u_resolution`: the size of the pass output in pixels
+/// - `float u_time`: seconds since the chain was created
+pub struct PostProcessPass {
+    program: u32,
+    texture_location: i32,
+    resolution_location: i32,
+    time_location: i32,
+    pub enabled: bool,
+}
+
+impl PostProcessPass {
+    pub fn new(fragment_source: &str) -> Result<Self, Error> {
+        let vshader = compile_shader(include_str!("shaders/vertex_shader.glsl"), gl::VERTEX_SHADER)?;
+        let fshader = match compile_shader(fragment_source, gl::FRAGMENT_SHADER) {
+            Ok(fshader) => fshader,
+            Err(e) => {
+                unsafe { gl::DeleteShader(vshader) };
+                return Err(e);
+            }
+        };
+
+        let program = link_shader_program(&[vshader, fshader]);
+
+        unsafe {
+            gl::DeleteShader(vshader);
+            gl::DeleteShader(fshader);
+        }
+
+        let program = program?;
+
+        let location = |name: &str| {
+            let name = std::ffi::CString::new(name).unwrap();
+            unsafe { gl::GetUniformLocation(program, name.as_ptr()) }
+        };
+
+        Ok(Self {
+            program,
+            texture_location: location("u_texture"),
+            resolution_location: location("u_resolution"),
+            time_location: location("u_time"),
+            enabled: true,
+        })
+    }
+
+    pub fn from_preset(preset: PostProcessPreset) -> Result<Self, Error> {
+        Self::new(preset.fragment_source())
+    }
+
+    /// The program object, for setting custom uniforms.
+    pub fn program(&self) -> u32 {
+        self.program
+    }
+
+    /// Looks up a uniform in the pass program. Returns -1 if there is no
+    /// active uniform with that name.
+    pub fn uniform_location(&self, name: &str) -> i32 {
+        let name = std::ffi::CString::new(name).unwrap();
+        unsafe { gl::GetUniformLocation(self.program, name.as_ptr()) }
+    }
+
+    fn apply(&self, input_texture: u32, resolution: (f32, f32), time: f32) {
+        unsafe {
+            gl::UseProgram(self.program);
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, input_texture);
+            gl::Uniform1i(self.texture_location, 0);
+            gl::Uniform2f(self.resolution_location, resolution.0, resolution.1);
+            gl::Uniform1f(self.time_location, time);
+            gl::DrawArrays(gl::TRIANGLE_FAN, 0, 4);
+        }
+    }
+}
+
+impl Drop for PostProcessPass {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteProgram(self.program);
+        }
+    }
+}
+
+/// Renders content offscreen and runs it through a list of full-screen
+/// passes before it reaches the screen.
+pub struct PostProcessChain {
+    passes: Vec<PostProcessPass>,
+    passthrough: PostProcessPass,
+    targets: [Framebuffer; 2],
+    vao: u32,
+    vbo: u32,
+    start_time: std::time::Instant,
+}
+
+impl PostProcessChain {
+    /// Creates an empty chain rendering at `size`, which should usually be
+    /// the window size.
+    pub fn new(size: (u32, u32)) -> Result<Self, Error> {
+        let vertices: &[f32] = &[
+            -1.0, -1.0,
+            1.0, -1.0,
+            1.0, 1.0,
+            -1.0, 1.0,
+        ];
+
+        let vbo = create_buffer_f32(vertices, gl::STATIC_DRAW)?;
+
+        let mut vao = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, 0, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+        }
+
+        Ok(Self {
+            passes: Vec::new(),
+            passthrough: PostProcessPass::from_preset(PostProcessPreset::Passthrough)?,
+            targets: [Framebuffer::new(size)?, Framebuffer::new(size)?],
+            vao,
+            vbo,
+            start_time: std::time::Instant::now(),
+        })
+    }
+
+    /// Resizes the offscreen targets. Call this on window resize.
+    pub fn set_size(&mut self, size: (u32, u32)) -> Result<(), Error> {
+        for target in &mut self.targets {
+            target.resize(size)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        self.targets[0].size()
+    }
+
+    /// Appends a pass. Passes run in the order they were added.
+    pub fn push(&mut self, pass: PostProcessPass) {
+        self.passes.push(pass);
+    }
+
+    pub fn passes(&self) -> &[PostProcessPass] {
+        &self.passes
+    }
+
+    /// Gives access to the passes, for reordering, removing or toggling them.
+    pub fn passes_mut(&mut self) -> &mut Vec<PostProcessPass> {
+        &mut self.passes
+    }
+
+    /// Runs `draw` with the first offscreen target bound, then applies every
+    /// enabled pass. The last pass writes to whichever framebuffer was bound
+    /// when `render` was called, using the viewport that was set then.
+    pub fn render<F: FnOnce()>(&self, draw: F) {
+        let frame = self.begin();
+        draw();
+        self.end(frame);
+    }
+
+    /// Like [`PostProcessChain::render`], split in two for when the drawing
+    /// can't be wrapped in a closure. Binds the first offscreen target and
+    /// records where the output should go; pass the result to
+    /// [`PostProcessChain::end`] once drawing is done.
+    pub fn begin(&self) -> PostProcessFrame {
+        let mut output_framebuffer = 0;
+        let mut output_viewport = [0i32; 4];
+        unsafe {
+            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut output_framebuffer);
+            gl::GetIntegerv(gl::VIEWPORT, output_viewport.as_mut_ptr());
+        }
+
+        self.targets[0].bind();
+
+        PostProcessFrame {
+            output_framebuffer: output_framebuffer as u32,
+            output_viewport,
+        }
+    }
+
+    /// Applies every enabled pass to what was drawn since `begin`.
+    pub fn end(&self, frame: PostProcessFrame) {
+        let enabled: Vec<&PostProcessPass> = self.passes.iter().filter(|p| p.enabled).collect();
+        let passes = if enabled.is_empty() { vec![&self.passthrough] } else { enabled };
+
+        let time = self.start_time.elapsed().as_secs_f32();
+        let mut blend_was_enabled = 0;
+        unsafe {
+            gl::GetBooleanv(gl::BLEND, &mut blend_was_enabled);
+            gl::Disable(gl::BLEND);
+            gl::BindVertexArray(self.vao);
+        }
+
+        for (i, pass) in passes.iter().enumerate() {
+            let input = &self.targets[i % 2];
+            let resolution = if i + 1 == passes.len() {
+                let [x, y, w, h] = frame.output_viewport;
+                unsafe {
+                    gl::BindFramebuffer(gl::FRAMEBUFFER, frame.output_framebuffer);
+                    gl::Viewport(x, y, w, h);
+                }
+                (w as f32, h as f32)
+            } else {
+                let output = &self.targets[(i + 1) % 2];
+                output.bind();
+                (output.size().0 as f32, output.size().1 as f32)
+            };
+
+            pass.apply(input.color_texture(), resolution, time);
+        }
+
+        if blend_was_enabled == gl::TRUE {
+            unsafe { gl::Enable(gl::BLEND) };
+        }
+    }
+
+    /// Drops a chain whose context was lost, like
+    /// `Framebuffer::forget_lost`, so the memory tracking of its targets is
+    /// released but no GL names are deleted.
+    pub(crate) fn forget_lost(self) {
+        let chain = std::mem::ManuallyDrop::new(self);
+        // each field is read out once, and `chain` itself is never dropped
+        let (passes, passthrough, targets) = unsafe {
+            (std::ptr::read(&chain.passes), std::ptr::read(&chain.passthrough), std::ptr::read(&chain.targets))
+        };
+
+        for pass in passes {
+            std::mem::forget(pass);
+        }
+        std::mem::forget(passthrough);
+        for target in targets {
+            target.forget_lost();
+        }
+    }
+}
+
+/// Where a [`PostProcessChain`] frame started by `begin` should end up.
+#[must_use]
+#[derive(Debug)]
+pub struct PostProcessFrame {
+    output_framebuffer: u32,
+    output_viewport: [i32; 4],
+}
+
+impl Drop for PostProcessChain {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteBuffers(1, &self.vbo);
+        }
+    }
+}
@@ -0,0 +1,19 @@
+#version 330 core
+
+in vec2 v_tex_coords;
+out vec4 f_color;
+
+uniform sampler2D u_texture;
+uniform vec2 u_resolution;
+
+void main() {
+    // split the channels apart, more strongly towards the edges
+    vec2 direction = v_tex_coords - 0.5;
+    vec2 offset = direction * length(direction) * 12.0 / u_resolution;
+
+    float r = texture(u_texture, v_tex_coords + offset).r;
+    vec4 g = texture(u_texture, v_tex_coords);
+    float b = texture(u_texture, v_tex_coords - offset).b;
+
+    f_color = vec4(r, g.g, b, g.a);
+}
@@ -0,0 +1,24 @@
+#version 330 core
+
+in vec2 v_tex_coords;
+out vec4 f_color;
+
+uniform sampler2D u_texture;
+uniform float u_gamma;
+uniform float u_brightness;
+uniform float u_contrast;
+uniform mat3 u_color_matrix;
+
+void main() {
+    vec4 color = texture(u_texture, v_tex_coords);
+
+    // the simulation matrices are defined for linear RGB
+    vec3 linear = pow(color.rgb, vec3(2.2));
+    linear = clamp(u_color_matrix * linear, 0.0, 1.0);
+    vec3 rgb = pow(linear, vec3(1.0 / 2.2));
+
+    rgb = (rgb - 0.5) * u_contrast + 0.5 + u_brightness;
+    rgb = pow(clamp(rgb, 0.0, 1.0), vec3(1.0 / u_gamma));
+
+    f_color = vec4(rgb, color.a);
+}
@@ -0,0 +1,34 @@
+#version 330 core
+
+in vec2 v_tex_coords;
+out vec4 f_color;
+
+uniform sampler2D u_texture;
+uniform vec2 u_resolution;
+uniform float u_time;
+
+void main() {
+    // barrel distortion
+    vec2 uv = v_tex_coords * 2.0 - 1.0;
+    vec2 offset = uv.yx / 5.0;
+    uv = uv + uv * offset * offset;
+    uv = uv * 0.5 + 0.5;
+
+    if (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0) {
+        f_color = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+
+    vec3 color = texture(u_texture, uv).rgb;
+
+    // scanlines, slowly rolling
+    float scanline = 0.8 + 0.2 * sin((uv.y * u_resolution.y + u_time * 10.0) * 3.14159);
+    color *= scanline;
+
+    // vignette
+    vec2 centered = uv * (1.0 - uv.yx);
+    float vignette = pow(centered.x * centered.y * 15.0, 0.25);
+    color *= vignette;
+
+    f_color = vec4(color, 1.0);
+}
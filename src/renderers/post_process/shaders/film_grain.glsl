@@ -0,0 +1,21 @@
+#version 330 core
+
+in vec2 v_tex_coords;
+out vec4 f_color;
+
+uniform sampler2D u_texture;
+uniform vec2 u_resolution;
+uniform float u_time;
+
+float hash(vec2 p) {
+    return fract(sin(dot(p, vec2(12.9898, 78.233))) * 43758.5453);
+}
+
+void main() {
+    vec4 color = texture(u_texture, v_tex_coords);
+
+    vec2 pixel = floor(v_tex_coords * u_resolution);
+    float noise = hash(pixel + fract(u_time) * 100.0) - 0.5;
+
+    f_color = vec4(color.rgb + noise * 0.08, color.a);
+}
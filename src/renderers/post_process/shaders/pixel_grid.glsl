@@ -0,0 +1,20 @@
+#version 330 core
+
+in vec2 v_tex_coords;
+out vec4 f_color;
+
+uniform sampler2D u_texture;
+uniform vec2 u_resolution;
+
+void main() {
+    vec4 color = texture(u_texture, v_tex_coords);
+
+    // darken the border of every output pixel cell, scaled so the grid stays
+    // visible regardless of how far the image is magnified
+    vec2 cell = fract(v_tex_coords * vec2(textureSize(u_texture, 0)));
+    vec2 texels_per_pixel = vec2(textureSize(u_texture, 0)) / u_resolution;
+    vec2 edge = step(texels_per_pixel, cell);
+    float grid = edge.x * edge.y;
+
+    f_color = vec4(color.rgb * mix(0.6, 1.0, grid), color.a);
+}
@@ -0,0 +1,243 @@
+use crate::gl_utils::{BlendMode, Program, SavedBlendState};
+use crate::renderers::mask::MaskRenderer;
+use crate::renderers::{Renderer, WindowRect};
+
+type Error = Box<dyn std::error::Error>;
+
+/// Edge softness, in pixels, that antialiases without visibly blurring.
+const CRISP: f32 = 0.5;
+
+/// An outline drawn along the inside of a pane's edge, over its content.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaneBorder {
+    pub width: f32,
+    pub color: [f32; 4],
+}
+
+/// A blurred copy of a pane's shape drawn underneath it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaneShadow {
+    /// Offset in pixels, positive down and to the right.
+    pub offset: (f32, f32),
+    /// How far the shadow fades out past the pane's edge, in pixels.
+    pub blur: f32,
+    pub color: [f32; 4],
+}
+
+/// One draw of the pane shader.
+struct Shape {
+    /// The area drawn over, in window pixels.
+    quad: [f32; 4],
+    rect: [f32; 4],
+    radius: f32,
+    /// How many pixels either side of the edge it fades over.
+    softness: f32,
+    border_width: f32,
+    fill_color: [f32; 4],
+    border_color: [f32; 4],
+    /// Discards fragments outside `rect` instead of drawing colors.
+    mask: bool,
+}
+
+impl Shape {
+    fn new(rect: [f32; 4], radius: f32) -> Self {
+        Self {
+            quad: rect,
+            rect,
+            radius,
+            softness: CRISP,
+            border_width: 0.0,
+            fill_color: [0.0; 4],
+            border_color: [0.0; 4],
+            mask: false,
+        }
+    }
+}
+
+/// Draws a rounded rectangle with an optional border and drop shadow, and
+/// clips whatever is drawn inside it to the rounded shape.
+///
+/// Set where the pane goes with [`RoundedPaneRenderer::set_viewport`], then
+/// draw the content in the closure passed to [`RoundedPaneRenderer::render`].
+/// Clipping uses a [`MaskRenderer`], so panes can nest and the same stencil
+/// requirements apply. The clip itself isn't antialiased; a border covers
+/// the jagged edge.
+pub struct RoundedPaneRenderer {
+    program: Program,
+    vao: u32,
+    mask_renderer: MaskRenderer,
+    viewport: WindowRect,
+    window_size: (u32, u32),
+    /// Limited to half the pane's shorter side.
+    pub corner_radius: f32,
+    pub background_color: [f32; 4],
+    pub border: Option<PaneBorder>,
+    pub shadow: Option<PaneShadow>,
+}
+
+impl RoundedPaneRenderer {
+    pub fn new() -> Result<Self, Error> {
+        let program = Program::new(
+            include_str!("shaders/vertex_shader.glsl"),
+            include_str!("shaders/fragment_shader.glsl"),
+        )?;
+
+        // the quad's corners come from gl_VertexID, but core profiles still
+        // need a vertex array bound to draw
+        let mut vao = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+        }
+
+        Ok(Self {
+            program,
+            vao,
+            mask_renderer: MaskRenderer::new()?,
+            viewport: WindowRect::default(),
+            window_size: (1, 1),
+            corner_radius: 8.0,
+            background_color: [0.15, 0.15, 0.15, 1.0],
+            border: None,
+            shadow: None,
+        })
+    }
+
+    /// Sets the pane's rectangle in the window, not counting the shadow.
+    /// `window_size` is needed to convert to OpenGL's bottom-left origin.
+    pub fn set_viewport(&mut self, viewport: WindowRect, window_size: (u32, u32)) {
+        self.viewport = viewport;
+        self.window_size = window_size;
+    }
+
+    pub fn viewport(&self) -> WindowRect {
+        self.viewport
+    }
+
+    /// Draws the shadow and background, runs `content` clipped to the
+    /// inside of the border, then draws the border on top. `content` runs
+    /// with the GL viewport set to the pane's rectangle; the previous
+    /// viewport and blend state are restored afterwards.
+    pub fn render<F: FnOnce()>(&self, content: F) {
+        let rect = self.viewport.to_f32();
+        let radius = self.corner_radius.clamp(0.0, rect[2].min(rect[3]) * 0.5);
+        let border_width = self.border.map_or(0.0, |border| border.width.clamp(0.0, radius.max(1.0)));
+
+        let mut saved_viewport = [0i32; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, saved_viewport.as_mut_ptr());
+        }
+        let saved_blend = SavedBlendState::save();
+        BlendMode::Alpha.apply();
+
+        if let Some(shadow) = self.shadow {
+            let blur = shadow.blur.max(CRISP);
+            let [x, y, w, h] = rect;
+            let shadow_rect = [x + shadow.offset.0, y + shadow.offset.1, w, h];
+            self.draw(&Shape {
+                quad: [shadow_rect[0] - blur, shadow_rect[1] - blur, w + 2.0 * blur, h + 2.0 * blur],
+                softness: blur,
+                fill_color: shadow.color,
+                border_color: shadow.color,
+                ..Shape::new(shadow_rect, radius)
+            });
+        }
+
+        self.draw(&Shape {
+            fill_color: self.background_color,
+            border_color: self.background_color,
+            ..Shape::new(rect, radius)
+        });
+
+        let [x, y, w, h] = rect;
+        let inner = [x + border_width, y + border_width, w - 2.0 * border_width, h - 2.0 * border_width];
+        let inner_radius = (radius - border_width).max(0.0);
+
+        let mask = Shape {
+            mask: true,
+            ..Shape::new(inner, inner_radius)
+        };
+        let draw_mask = || self.draw(&mask);
+
+        let pane_height = self.viewport.height as i32;
+        let gl_y = self.window_size.1 as i32 - self.viewport.y - pane_height;
+        self.mask_renderer.render(draw_mask, || {
+            unsafe {
+                gl::Viewport(self.viewport.x, gl_y, self.viewport.width as i32, pane_height);
+            }
+            content();
+        });
+
+        // content may have left a different blend mode
+        BlendMode::Alpha.apply();
+        if let Some(border) = self.border {
+            self.draw(&Shape {
+                border_width,
+                border_color: border.color,
+                ..Shape::new(rect, radius)
+            });
+        }
+
+        saved_blend.restore();
+        unsafe {
+            let [x, y, w, h] = saved_viewport;
+            gl::Viewport(x, y, w, h);
+        }
+    }
+
+    /// Draws over the whole window, restoring the viewport afterwards since
+    /// content may have moved it before the mask is drawn a second time.
+    fn draw(&self, shape: &Shape) {
+        let (ww, wh) = self.window_size;
+        let mut saved_viewport = [0i32; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, saved_viewport.as_mut_ptr());
+            gl::Viewport(0, 0, ww as i32, wh as i32);
+        }
+
+        let program = &self.program;
+        program.set_uniform_by_name("u_quad", shape.quad).unwrap();
+        program.set_uniform_by_name("u_window_size", (ww as f32, wh as f32)).unwrap();
+        program.set_uniform_by_name("u_rect", shape.rect).unwrap();
+        program.set_uniform_by_name("u_radius", shape.radius).unwrap();
+        program.set_uniform_by_name("u_softness", shape.softness).unwrap();
+        program.set_uniform_by_name("u_border_width", shape.border_width).unwrap();
+        program.set_uniform_by_name("u_fill_color", shape.fill_color).unwrap();
+        program.set_uniform_by_name("u_border_color", shape.border_color).unwrap();
+        program.set_uniform_by_name("u_mask", shape.mask).unwrap();
+
+        unsafe {
+            gl::BindVertexArray(self.vao);
+            gl::DrawArrays(gl::TRIANGLE_FAN, 0, 4);
+
+            let [x, y, w, h] = saved_viewport;
+            gl::Viewport(x, y, w, h);
+        }
+    }
+}
+
+/// A [`RoundedPaneRenderer`] with `child` drawn inside the pane, as a
+/// [`Renderer`]. Both get the same viewport.
+pub struct RoundedPane<R> {
+    pub renderer: RoundedPaneRenderer,
+    pub child: R,
+}
+
+impl<R: Renderer> Renderer for RoundedPane<R> {
+    fn set_viewport(&mut self, viewport: WindowRect, window_size: (u32, u32)) -> Result<(), Error> {
+        self.renderer.set_viewport(viewport, window_size);
+        self.child.set_viewport(viewport, window_size)
+    }
+
+    fn render(&mut self) {
+        let child = &mut self.child;
+        self.renderer.render(|| child.render());
+    }
+}
+
+impl Drop for RoundedPaneRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
@@ -0,0 +1,36 @@
+#version 330 core
+
+in vec2 v_position;
+out vec4 f_color;
+
+uniform vec4 u_rect;
+uniform float u_radius;
+uniform float u_softness;
+uniform float u_border_width;
+uniform vec4 u_fill_color;
+uniform vec4 u_border_color;
+uniform bool u_mask;
+
+// signed distance to the edge of a rounded rect, negative inside
+float rounded_rect_distance(vec2 p, vec4 rect, float radius) {
+    vec2 half_size = rect.zw * 0.5;
+    vec2 q = abs(p - rect.xy - half_size) - half_size + radius;
+    return length(max(q, 0.0)) + min(max(q.x, q.y), 0.0) - radius;
+}
+
+void main() {
+    float d = rounded_rect_distance(v_position, u_rect, u_radius);
+
+    if (u_mask) {
+        if (d > 0.0) {
+            discard;
+        }
+        f_color = vec4(0.0);
+        return;
+    }
+
+    float coverage = 1.0 - smoothstep(-u_softness, u_softness, d);
+    float fill = 1.0 - smoothstep(-u_softness, u_softness, d + u_border_width);
+    vec4 color = mix(u_border_color, u_fill_color, fill);
+    f_color = vec4(color.rgb, color.a * coverage);
+}
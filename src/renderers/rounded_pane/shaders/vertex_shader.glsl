@@ -0,0 +1,15 @@
+#version 330 core
+
+uniform vec4 u_quad;
+uniform vec2 u_window_size;
+
+out vec2 v_position;
+
+void main() {
+    // a fan over u_quad, in window pixels with the origin at the top left
+    vec2 corner = vec2(gl_VertexID == 1 || gl_VertexID == 2, gl_VertexID >= 2);
+    v_position = u_quad.xy + corner * u_quad.zw;
+
+    vec2 ndc = v_position / u_window_size * 2.0 - 1.0;
+    gl_Position = vec4(ndc.x, -ndc.y, 0.0, 1.0);
+}
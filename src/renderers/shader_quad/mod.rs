@@ -0,0 +1,288 @@
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+use crate::gl_utils::{create_buffer_f32, Program, UniformValue};
+use crate::renderers::image_renderer::ImageTexture;
+use crate::renderers::{Renderer, WindowRect};
+use crate::window::{Event, MouseButton};
+
+type Error = Box<dyn std::error::Error>;
+
+const HEADER: &str = include_str!("shaders/header.glsl");
+const VERTEX_SHADER: &str = include_str!("shaders/vertex_shader.glsl");
+
+/// The number of `iChannel` inputs.
+pub const NUM_CHANNELS: usize = 4;
+
+struct SourceFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+/// Renders a ShaderToy-style fragment shader across its viewport.
+///
+/// The source defines `void mainImage(out vec4 fragColor, in vec2 fragCoord)`
+/// and must not start with a `#version` line. These uniforms are declared for
+/// it and set automatically:
+///
+/// - `vec3 iResolution`: viewport size in pixels, and 1
+/// - `float iTime`, `float iTimeDelta`: seconds since creation and since the
+///   previous frame
+/// - `int iFrame`: frames rendered since creation
+/// - `vec4 iMouse`: `xy` is the cursor position while the left button is
+///   held, `zw` where it was pressed, with `z` negative once released
+/// - `sampler2D iChannel0` to `iChannel3` and `vec3 iChannelResolution[4]`:
+///   the textures passed to [`ShaderQuadRenderer::render`]
+///
+/// All positions are in pixels from the bottom-left of the viewport.
+pub struct ShaderQuadRenderer {
+    program: Program,
+    vao: u32,
+    vbo: u32,
+    file: Option<SourceFile>,
+    viewport: WindowRect,
+    window_size: (u32, u32),
+    start_time: Instant,
+    last_frame: Option<Instant>,
+    frame: i32,
+    cursor: Option<(f32, f32)>,
+    mouse: [f32; 4],
+    mouse_down: bool,
+}
+
+impl ShaderQuadRenderer {
+    pub fn new(source: &str) -> Result<Self, Error> {
+        let program = Self::compile(source)?;
+
+        let vertices: &[f32] = &[
+            -1.0, -1.0,
+            1.0, -1.0,
+            1.0, 1.0,
+            -1.0, 1.0,
+        ];
+
+        let vbo = create_buffer_f32(vertices, gl::STATIC_DRAW)?;
+
+        let mut vao = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, 0, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+        }
+
+        Ok(Self {
+            program,
+            vao,
+            vbo,
+            file: None,
+            viewport: WindowRect::default(),
+            window_size: (1, 1),
+            start_time: Instant::now(),
+            last_frame: None,
+            frame: 0,
+            cursor: None,
+            mouse: [0.0; 4],
+            mouse_down: false,
+        })
+    }
+
+    /// Loads the shader from a file, which
+    /// [`ShaderQuadRenderer::reload_if_changed`] then watches.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        let source = std::fs::read_to_string(&path)?;
+        let mut renderer = Self::new(&source)?;
+        renderer.file = Some(SourceFile {
+            modified: modified_time(&path),
+            path,
+        });
+        Ok(renderer)
+    }
+
+    fn compile(source: &str) -> Result<Program, Error> {
+        let fragment_source = format!("{}{}", HEADER, source);
+        Program::new(VERTEX_SHADER, &fragment_source)
+    }
+
+    /// Replaces the shader. On a compile error the previous shader stays in
+    /// use.
+    pub fn set_source(&mut self, source: &str) -> Result<(), Error> {
+        self.program = Self::compile(source)?;
+        Ok(())
+    }
+
+    /// Recompiles the shader if its file changed since it was last loaded,
+    /// returning whether it did. Call this once per frame for hot reload.
+    ///
+    /// A broken edit is reported once and the previous shader stays in use
+    /// until the file changes again.
+    pub fn reload_if_changed(&mut self) -> Result<bool, Error> {
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => return Ok(false),
+        };
+
+        let modified = modified_time(&file.path);
+        if modified == file.modified {
+            return Ok(false);
+        }
+        file.modified = modified;
+
+        let source = std::fs::read_to_string(&file.path)?;
+        self.set_source(&source)?;
+        Ok(true)
+    }
+
+    /// The program, for setting extra uniforms declared by the shader.
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    /// Sets the area of the window the shader covers.
+    pub fn set_viewport(&mut self, viewport: WindowRect, window_size: (u32, u32)) {
+        self.viewport = viewport;
+        self.window_size = window_size;
+    }
+
+    /// Restarts `iTime` and `iFrame` from zero.
+    pub fn reset_time(&mut self) {
+        self.start_time = Instant::now();
+        self.last_frame = None;
+        self.frame = 0;
+    }
+
+    /// Tracks the cursor and left button for `iMouse`.
+    pub fn handle_event(&mut self, event: &Event) {
+        match *event {
+            Event::CursorMoved(x, y) => {
+                self.cursor = Some((x, y));
+                if self.mouse_down {
+                    let (x, y) = self.to_viewport((x, y));
+                    self.mouse[0] = x;
+                    self.mouse[1] = y;
+                }
+            }
+
+            Event::CursorLeft => {
+                self.cursor = None;
+            }
+
+            Event::MouseButtonPressed(MouseButton::Left) => {
+                if let Some(p) = self.cursor.filter(|p| self.viewport.contains(p.0, p.1)) {
+                    let (x, y) = self.to_viewport(p);
+                    self.mouse = [x, y, x, y];
+                    self.mouse_down = true;
+                }
+            }
+
+            Event::MouseButtonReleased(MouseButton::Left) if self.mouse_down => {
+                self.mouse_down = false;
+                self.mouse[2] = -self.mouse[2].abs();
+            }
+
+            _ => (),
+        }
+    }
+
+    /// Converts a window position (top-left origin) to viewport pixels with
+    /// a bottom-left origin.
+    fn to_viewport(&self, p: (f32, f32)) -> (f32, f32) {
+        let [x, y, _, h] = self.viewport.to_f32();
+        (p.0 - x, y + h - p.1)
+    }
+
+    /// Draws the shader. `channels` are bound to `iChannel0` onwards; at most
+    /// [`NUM_CHANNELS`] are used.
+    pub fn render(&mut self, channels: &[&ImageTexture]) {
+        let now = Instant::now();
+        let delta = self.last_frame.map_or(0.0, |last| (now - last).as_secs_f32());
+        self.last_frame = Some(now);
+
+        let [x, y, w, h] = self.viewport.to_f32();
+        let gl_y = self.window_size.1 as f32 - y - h;
+
+        let program = &self.program;
+
+        // uniforms the shader doesn't use are optimized out, so skip them
+        // rather than report errors
+        let set = |name: &str, value: UniformValue| {
+            if program.uniform(name).is_some() {
+                program.set_uniform_by_name(name, value).unwrap();
+            }
+        };
+
+        let mut previous_viewport = [0i32; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, previous_viewport.as_mut_ptr());
+            gl::Viewport(x as i32, gl_y as i32, w as i32, h as i32);
+        }
+
+        program.use_program();
+        set("iResolution", UniformValue::Vec3([w, h, 1.0]));
+        set("iTime", UniformValue::Float((now - self.start_time).as_secs_f32()));
+        set("iTimeDelta", UniformValue::Float(delta));
+        set("iFrame", UniformValue::Int(self.frame));
+        set("iMouse", UniformValue::Vec4(self.mouse));
+        set("glume_viewport_origin", UniformValue::Vec2([x, gl_y]));
+
+        let mut resolutions = [0.0f32; NUM_CHANNELS * 3];
+        for (i, texture) in channels.iter().take(NUM_CHANNELS).enumerate() {
+            let (tw, th) = texture.size();
+            resolutions[i * 3..i * 3 + 3].copy_from_slice(&[tw as f32, th as f32, 1.0]);
+            set(&format!("iChannel{}", i), UniformValue::Int(i as i32));
+            unsafe {
+                gl::ActiveTexture(gl::TEXTURE0 + i as u32);
+                gl::BindTexture(gl::TEXTURE_2D, texture.id());
+            }
+        }
+
+        unsafe {
+            if let Some(info) = program.uniform("iChannelResolution") {
+                gl::Uniform3fv(info.location, NUM_CHANNELS as i32, resolutions.as_ptr());
+            }
+
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindVertexArray(self.vao);
+            gl::DrawArrays(gl::TRIANGLE_FAN, 0, 4);
+
+            let [x, y, w, h] = previous_viewport;
+            gl::Viewport(x, y, w, h);
+        }
+
+        self.frame += 1;
+    }
+}
+
+impl Drop for ShaderQuadRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteBuffers(1, &self.vbo);
+        }
+    }
+}
+
+/// A [`ShaderQuadRenderer`] with its input textures, as a [`Renderer`].
+pub struct ShaderQuad {
+    pub renderer: ShaderQuadRenderer,
+    /// Bound to `iChannel0` onwards, as [`ShaderQuadRenderer::render`] does.
+    pub channels: Vec<ImageTexture>,
+}
+
+impl Renderer for ShaderQuad {
+    fn set_viewport(&mut self, viewport: WindowRect, window_size: (u32, u32)) -> Result<(), Error> {
+        self.renderer.set_viewport(viewport, window_size);
+        Ok(())
+    }
+
+    fn render(&mut self) {
+        let channels: Vec<&ImageTexture> = self.channels.iter().collect();
+        self.renderer.render(&channels);
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
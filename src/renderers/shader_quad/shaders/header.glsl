@@ -0,0 +1,24 @@
+#version 330 core
+
+uniform vec3 iResolution;
+uniform float iTime;
+uniform float iTimeDelta;
+uniform int iFrame;
+uniform vec4 iMouse;
+uniform sampler2D iChannel0;
+uniform sampler2D iChannel1;
+uniform sampler2D iChannel2;
+uniform sampler2D iChannel3;
+uniform vec3 iChannelResolution[4];
+
+uniform vec2 glume_viewport_origin;
+
+out vec4 glume_frag_color;
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord);
+
+void main() {
+    mainImage(glume_frag_color, gl_FragCoord.xy - glume_viewport_origin);
+}
+
+#line 1
@@ -0,0 +1,152 @@
+use crate::gl_utils::{BlendMode, Program, SavedBlendState};
+use crate::renderers::{Renderer, WindowRect};
+
+type Error = Box<dyn std::error::Error>;
+
+/// Draws a soft drop shadow behind a rectangle, then whatever goes on top of
+/// it, for floating panels, popups and the like.
+///
+/// Set the rectangle with [`ShadowRenderer::set_viewport`] and draw the
+/// content in the closure passed to [`ShadowRenderer::render`]. The shadow
+/// is a gaussian blur of the rectangle, rounded to match content with
+/// rounded corners, and is computed per pixel rather than from a texture,
+/// so it stays smooth at any size.
+pub struct ShadowRenderer {
+    program: Program,
+    vao: u32,
+    viewport: WindowRect,
+    window_size: (u32, u32),
+    /// Offset in pixels, positive down and to the right.
+    pub offset: (f32, f32),
+    /// How far the shadow fades out past the rectangle's edge, in pixels.
+    pub blur: f32,
+    /// Limited to half the rectangle's shorter side.
+    pub corner_radius: f32,
+    pub color: [f32; 4],
+}
+
+impl ShadowRenderer {
+    pub fn new() -> Result<Self, Error> {
+        let program = Program::new(
+            include_str!("shaders/vertex_shader.glsl"),
+            include_str!("shaders/fragment_shader.glsl"),
+        )?;
+
+        // the quad's corners come from gl_VertexID
+        let mut vao = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+        }
+
+        let mut renderer = Self {
+            program,
+            vao,
+            viewport: WindowRect::default(),
+            window_size: (1, 1),
+            offset: (0.0, 0.0),
+            blur: 0.0,
+            corner_radius: 0.0,
+            color: [0.0, 0.0, 0.0, 0.0],
+        };
+        renderer.set_elevation(4.0);
+        Ok(renderer)
+    }
+
+    /// Sets the rectangle casting the shadow, in window pixels.
+    /// `window_size` is needed to convert to OpenGL's bottom-left origin.
+    pub fn set_viewport(&mut self, viewport: WindowRect, window_size: (u32, u32)) {
+        self.viewport = viewport;
+        self.window_size = window_size;
+    }
+
+    pub fn viewport(&self) -> WindowRect {
+        self.viewport
+    }
+
+    /// Sets the offset, blur and color for something floating `elevation`
+    /// pixels above what's behind it, lit from above: higher means a larger,
+    /// softer and fainter shadow. Keeps the color's RGB and the corner
+    /// radius.
+    pub fn set_elevation(&mut self, elevation: f32) {
+        let elevation = elevation.max(0.0);
+        self.offset = (0.0, elevation * 0.5);
+        self.blur = elevation * 1.5;
+        self.color[3] = if elevation > 0.0 { (0.5 - elevation * 0.01).max(0.2) } else { 0.0 };
+    }
+
+    /// Draws the shadow, then runs `content` with the GL viewport set to the
+    /// rectangle. The previous viewport and blend state are restored
+    /// afterwards.
+    pub fn render<F: FnOnce()>(&self, content: F) {
+        let (ww, wh) = self.window_size;
+        let rect = self.viewport;
+
+        let mut saved_viewport = [0i32; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, saved_viewport.as_mut_ptr());
+        }
+
+        if self.color[3] > 0.0 {
+            let [x, y, w, h] = rect.to_f32();
+            let (x, y) = (x + self.offset.0, y + self.offset.1);
+            let radius = self.corner_radius.clamp(0.0, w.min(h) * 0.5);
+            // the blur fades out over three standard deviations
+            let sigma = (self.blur / 3.0).max(0.1);
+            let margin = sigma * 3.0;
+
+            let saved_blend = SavedBlendState::save();
+            BlendMode::Alpha.apply();
+            let program = &self.program;
+            let quad = [x - margin, y - margin, w + 2.0 * margin, h + 2.0 * margin];
+            program.set_uniform_by_name("u_quad", quad).unwrap();
+            program.set_uniform_by_name("u_window_size", (ww as f32, wh as f32)).unwrap();
+            program.set_uniform_by_name("u_rect", [x, y, w, h]).unwrap();
+            program.set_uniform_by_name("u_radius", radius).unwrap();
+            program.set_uniform_by_name("u_sigma", sigma).unwrap();
+            program.set_uniform_by_name("u_color", self.color).unwrap();
+
+            unsafe {
+                gl::Viewport(0, 0, ww as i32, wh as i32);
+                gl::BindVertexArray(self.vao);
+                gl::DrawArrays(gl::TRIANGLE_FAN, 0, 4);
+            }
+            saved_blend.restore();
+        }
+
+        unsafe {
+            let gl_y = wh as i32 - rect.y - rect.height as i32;
+            gl::Viewport(rect.x, gl_y, rect.width as i32, rect.height as i32);
+        }
+        content();
+
+        let [x, y, w, h] = saved_viewport;
+        unsafe { gl::Viewport(x, y, w, h) };
+    }
+}
+
+/// A [`ShadowRenderer`] casting a shadow behind `child`, as a [`Renderer`].
+/// Both get the same viewport.
+pub struct Shadow<R> {
+    pub renderer: ShadowRenderer,
+    pub child: R,
+}
+
+impl<R: Renderer> Renderer for Shadow<R> {
+    fn set_viewport(&mut self, viewport: WindowRect, window_size: (u32, u32)) -> Result<(), Error> {
+        self.renderer.set_viewport(viewport, window_size);
+        self.child.set_viewport(viewport, window_size)
+    }
+
+    fn render(&mut self) {
+        let child = &mut self.child;
+        self.renderer.render(|| child.render());
+    }
+}
+
+impl Drop for ShadowRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
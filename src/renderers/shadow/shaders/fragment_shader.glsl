@@ -0,0 +1,60 @@
+#version 330 core
+
+in vec2 v_position;
+out vec4 f_color;
+
+uniform vec4 u_rect;
+uniform float u_radius;
+uniform float u_sigma;
+uniform vec4 u_color;
+
+// A rounded rect convolved with a gaussian, integrated exactly along x and
+// sampled along y. See Evan Wallace's "Fast Rounded Rectangle Shadows".
+
+const float PI = 3.141592653589793;
+
+float gaussian(float x, float sigma) {
+    return exp(-(x * x) / (2.0 * sigma * sigma)) / (sqrt(2.0 * PI) * sigma);
+}
+
+// a polynomial approximation, good to about 5e-4
+vec2 approx_erf(vec2 x) {
+    vec2 s = sign(x);
+    vec2 a = abs(x);
+    x = 1.0 + (0.278393 + (0.230389 + 0.078108 * (a * a)) * a) * a;
+    x *= x;
+    return s - s / (x * x);
+}
+
+// the shadow's coverage of one row, `y` from the rect's center
+float row_coverage(float x, float y, float sigma, float radius, vec2 half_size) {
+    float delta = min(half_size.y - radius - abs(y), 0.0);
+    float curved = half_size.x - radius + sqrt(max(0.0, radius * radius - delta * delta));
+    vec2 integral = 0.5 + 0.5 * approx_erf((x + vec2(-curved, curved)) * (sqrt(0.5) / sigma));
+    return integral.y - integral.x;
+}
+
+float shadow(vec2 p, vec4 rect, float sigma, float radius) {
+    vec2 half_size = rect.zw * 0.5;
+    p -= rect.xy + half_size;
+
+    // only the rows within three sigma contribute noticeably
+    float low = p.y - half_size.y;
+    float high = p.y + half_size.y;
+    float start = clamp(-3.0 * sigma, low, high);
+    float end = clamp(3.0 * sigma, low, high);
+
+    float row_height = (end - start) / 4.0;
+    float y = start + row_height * 0.5;
+    float value = 0.0;
+    for (int i = 0; i < 4; i++) {
+        value += row_coverage(p.x, p.y - y, sigma, radius, half_size) * gaussian(y, sigma) * row_height;
+        y += row_height;
+    }
+    return value;
+}
+
+void main() {
+    float coverage = shadow(v_position, u_rect, u_sigma, u_radius);
+    f_color = vec4(u_color.rgb, u_color.a * coverage);
+}
@@ -0,0 +1,204 @@
+use crate::gl_utils::{compile_shader, link_shader_program};
+
+type Error = Box<dyn std::error::Error>;
+
+pub(crate) type Color = [f32; 4];
+
+const FLOATS_PER_VERTEX: usize = 6;
+
+/// Batches flat and vertex-colored triangles given in window pixels, for
+/// drawing widget chrome. Geometry accumulates until `draw` and is cleared
+/// with `clear`.
+pub(crate) struct ShapeRenderer {
+    program: u32,
+    window_size_location: i32,
+    vao: u32,
+    vbo: u32,
+    capacity: usize,
+    vertices: Vec<f32>,
+}
+
+impl ShapeRenderer {
+    pub fn new() -> Result<Self, Error> {
+        let vshader = compile_shader(include_str!("shaders/vertex_shader.glsl"), gl::VERTEX_SHADER)?;
+        let fshader = compile_shader(include_str!("shaders/fragment_shader.glsl"), gl::FRAGMENT_SHADER)?;
+        let program = link_shader_program(&[vshader, fshader])?;
+
+        unsafe {
+            gl::DeleteShader(vshader);
+            gl::DeleteShader(fshader);
+        }
+
+        let window_size_location = unsafe {
+            gl::GetUniformLocation(program, c"u_window_size".as_ptr())
+        };
+
+        let mut vao = 0;
+        let mut vbo = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+
+            gl::GenBuffers(1, &mut vbo);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+
+            let stride = (FLOATS_PER_VERTEX * std::mem::size_of::<f32>()) as i32;
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+
+            let offset = (2 * std::mem::size_of::<f32>()) as *const _;
+            gl::VertexAttribPointer(1, 4, gl::FLOAT, gl::FALSE, stride, offset);
+            gl::EnableVertexAttribArray(1);
+        }
+
+        Ok(Self {
+            program,
+            window_size_location,
+            vao,
+            vbo,
+            capacity: 0,
+            vertices: Vec::new(),
+        })
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    fn vertex(&mut self, p: (f32, f32), c: Color) {
+        self.vertices.extend_from_slice(&[p.0, p.1, c[0], c[1], c[2], c[3]]);
+    }
+
+    pub fn triangle(&mut self, p: [(f32, f32); 3], c: [Color; 3]) {
+        for i in 0..3 {
+            self.vertex(p[i], c[i]);
+        }
+    }
+
+    /// A quad with corners in clockwise order starting at the top left.
+    pub fn quad(&mut self, p: [(f32, f32); 4], c: [Color; 4]) {
+        self.triangle([p[0], p[1], p[2]], [c[0], c[1], c[2]]);
+        self.triangle([p[0], p[2], p[3]], [c[0], c[2], c[3]]);
+    }
+
+    pub fn rect(&mut self, rect: [f32; 4], color: Color) {
+        self.rect_gradient(rect, [color; 4]);
+    }
+
+    /// A rect with one color per corner, clockwise from the top left.
+    pub fn rect_gradient(&mut self, rect: [f32; 4], colors: [Color; 4]) {
+        let [x, y, w, h] = rect;
+        self.quad([(x, y), (x + w, y), (x + w, y + h), (x, y + h)], colors);
+    }
+
+    /// A rect outline drawn inside `rect`.
+    pub fn rect_outline(&mut self, rect: [f32; 4], width: f32, color: Color) {
+        let [x, y, w, h] = rect;
+        self.rect([x, y, w, width], color);
+        self.rect([x, y + h - width, w, width], color);
+        self.rect([x, y + width, width, h - 2.0 * width], color);
+        self.rect([x + w - width, y + width, width, h - 2.0 * width], color);
+    }
+
+    pub fn line(&mut self, a: (f32, f32), b: (f32, f32), width: f32, color: Color) {
+        let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+        let len = (dx * dx + dy * dy).sqrt();
+        if len == 0.0 {
+            return;
+        }
+
+        let (nx, ny) = (-dy / len * width * 0.5, dx / len * width * 0.5);
+        self.quad(
+            [(a.0 + nx, a.1 + ny), (b.0 + nx, b.1 + ny), (b.0 - nx, b.1 - ny), (a.0 - nx, a.1 - ny)],
+            [color; 4],
+        );
+    }
+
+    /// A connected line through `points`.
+    pub fn polyline(&mut self, points: &[(f32, f32)], width: f32, color: Color) {
+        for pair in points.windows(2) {
+            self.line(pair[0], pair[1], width, color);
+        }
+    }
+
+    /// A ring between two radii. An inner radius of zero gives a disc.
+    pub fn ring(&mut self, center: (f32, f32), inner: f32, outer: f32, color: Color) {
+        const SEGMENTS: usize = 32;
+        for i in 0..SEGMENTS {
+            let a0 = i as f32 / SEGMENTS as f32 * std::f32::consts::TAU;
+            let a1 = (i + 1) as f32 / SEGMENTS as f32 * std::f32::consts::TAU;
+            let point = |a: f32, r: f32| (center.0 + a.cos() * r, center.1 - a.sin() * r);
+            self.quad(
+                [point(a0, outer), point(a1, outer), point(a1, inner), point(a0, inner)],
+                [color; 4],
+            );
+        }
+    }
+
+    /// A checkerboard, for showing transparency behind a color.
+    pub fn checkerboard(&mut self, rect: [f32; 4], cell: f32) {
+        let [x, y, w, h] = rect;
+        self.rect(rect, [0.8, 0.8, 0.8, 1.0]);
+
+        let (columns, rows) = ((w / cell).ceil() as usize, (h / cell).ceil() as usize);
+        for row in 0..rows {
+            for col in (row % 2..columns).step_by(2) {
+                let cx = x + col as f32 * cell;
+                let cy = y + row as f32 * cell;
+                let cw = cell.min(x + w - cx);
+                let ch = cell.min(y + h - cy);
+                self.rect([cx, cy, cw, ch], [0.55, 0.55, 0.55, 1.0]);
+            }
+        }
+    }
+
+    /// Draws everything added since the last `clear`, with alpha blending,
+    /// over the whole window. The GL viewport and blend state are restored
+    /// afterwards.
+    pub fn draw(&mut self, window_size: (u32, u32)) {
+        if self.vertices.is_empty() {
+            return;
+        }
+
+        let size = std::mem::size_of_val(self.vertices.as_slice()) as isize;
+        let mut saved_viewport = [0i32; 4];
+        let mut blend_was_enabled = 0;
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            if self.vertices.len() > self.capacity {
+                self.capacity = self.vertices.len().next_power_of_two();
+                let capacity_size = (self.capacity * std::mem::size_of::<f32>()) as isize;
+                gl::BufferData(gl::ARRAY_BUFFER, capacity_size, std::ptr::null(), gl::DYNAMIC_DRAW);
+            }
+            gl::BufferSubData(gl::ARRAY_BUFFER, 0, size, self.vertices.as_ptr() as *const _);
+
+            gl::GetIntegerv(gl::VIEWPORT, saved_viewport.as_mut_ptr());
+            gl::GetBooleanv(gl::BLEND, &mut blend_was_enabled);
+
+            gl::Viewport(0, 0, window_size.0 as i32, window_size.1 as i32);
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+
+            gl::UseProgram(self.program);
+            gl::Uniform2f(self.window_size_location, window_size.0 as f32, window_size.1 as f32);
+            gl::BindVertexArray(self.vao);
+            gl::DrawArrays(gl::TRIANGLES, 0, (self.vertices.len() / FLOATS_PER_VERTEX) as i32);
+
+            if blend_was_enabled != gl::TRUE {
+                gl::Disable(gl::BLEND);
+            }
+            let [x, y, w, h] = saved_viewport;
+            gl::Viewport(x, y, w, h);
+        }
+    }
+}
+
+impl Drop for ShapeRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteProgram(self.program);
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteBuffers(1, &self.vbo);
+        }
+    }
+}
@@ -0,0 +1,15 @@
+#version 330 core
+
+layout(location = 0) in vec2 position;
+layout(location = 1) in vec4 color;
+
+uniform vec2 u_window_size;
+
+out vec4 v_color;
+
+void main() {
+    // positions are in window pixels with the origin at the top left
+    vec2 ndc = position / u_window_size * 2.0 - 1.0;
+    gl_Position = vec4(ndc.x, -ndc.y, 0.0, 1.0);
+    v_color = color;
+}
@@ -1,4 +1,6 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::rc::{Rc, Weak};
 
 include!(concat!(env!("OUT_DIR"), "/system_text_font.rs"));
 
@@ -6,7 +8,7 @@ type Error = Box<dyn std::error::Error>;
 
 pub struct SystemTextRenderer {
     program: u32,
-    character_vertices: HashMap<char, Vec<f32>>,
+    label_cache: RefCell<LabelCache>,
 }
 
 #[derive(Debug, Clone)]
@@ -16,56 +18,68 @@ pub struct TextLine {
     pub char_size: (f32, f32),
 }
 
+/// Line vertices for one string at one character size, relative to the
+/// string's origin. Shared between everything displaying the same string.
+type LabelVertices = Rc<[f32]>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct LabelKey {
+    text: String,
+    char_size: (u32, u32),
+}
+
+impl LabelKey {
+    fn new(line: &TextLine) -> Self {
+        Self {
+            text: line.text.clone(),
+            char_size: (line.char_size.0.to_bits(), line.char_size.1.to_bits()),
+        }
+    }
+}
+
+/// Caches generated vertices by string and character size. Entries only hold
+/// weak references, so a label's vertices live as long as some `SystemText`
+/// or `TextBatcher` is still using them.
+#[derive(Default)]
+struct LabelCache {
+    labels: HashMap<LabelKey, Weak<[f32]>>,
+    prune_at: usize,
+}
+
 pub struct SystemText {
     vao: u32,
     buffer: u32,
-    num_indices: usize,
+    num_vertices: usize,
+    _labels: Vec<LabelVertices>,
 }
 
 impl SystemText {
     pub fn new(renderer: &SystemTextRenderer, text: &[TextLine]) -> Result<Self, Error> {
-        let char_scale = (0.8, 0.7);
-        unsafe {
-            let mut vertices = Vec::new();
-            for t in text {
-                let char_scale = (char_scale.0 * t.char_size.0, char_scale.1 * t.char_size.1);
-                let mut char_start = t.position;
-                for c in t.text.chars() {
-                    if let Some(v) = renderer.character_vertices.get(&c) {
-                        for i in 0..v.len() / 2 {
-                            let index = i * 2;
-                            let vx = char_start.0 + v[index] * char_scale.0;
-                            let vy = char_start.1 - v[index + 1] * char_scale.1;
-                            vertices.push(vx);
-                            vertices.push(vy);
-                        }
-                    }
-                    char_start.0 += t.char_size.0;
-
-                    if c == '\n' {
-                        char_start.0 = t.position.0;
-                        char_start.1 -= t.char_size.1;
-                    }
-                }
-            }
+        let labels: Vec<_> = text.iter().map(|t| renderer.label_vertices(t)).collect();
 
-            let num_indices = vertices.len();
+        let mut vertices = Vec::new();
+        let mut num_vertices = 0;
+        for (label, t) in labels.iter().zip(text) {
+            num_vertices += append_translated(&mut vertices, label, t.position);
+        }
 
-            let (vao, buffer) = create_vertex_array()?;
+        let (vao, buffer) = create_vertex_array()?;
 
+        unsafe {
             gl::NamedBufferData(
                 buffer,
-                (vertices.len() * std::mem::size_of::<f32>()) as isize,
+                std::mem::size_of_val(vertices.as_slice()) as isize,
                 vertices.as_ptr() as *const _,
                 gl::STATIC_DRAW,
             );
-
-            Ok(Self {
-                vao,
-                buffer,
-                num_indices,
-            })
         }
+
+        Ok(Self {
+            vao,
+            buffer,
+            num_vertices,
+            _labels: labels,
+        })
     }
 }
 
@@ -81,19 +95,87 @@ impl Drop for SystemText {
 impl SystemTextRenderer {
     pub fn new() -> Result<Self, Error> {
         let program = create_program()?;
-        let character_vertices = create_character_vertices();
 
         Ok(Self {
             program,
-            character_vertices,
+            label_cache: RefCell::new(LabelCache::default()),
         })
     }
 
+    /// Returns the cached vertices for `line`, generating them if no live
+    /// entry for the same string and character size exists.
+    fn label_vertices(&self, line: &TextLine) -> LabelVertices {
+        let key = LabelKey::new(line);
+        let mut cache = self.label_cache.borrow_mut();
+        if let Some(label) = cache.labels.get(&key).and_then(Weak::upgrade) {
+            return label;
+        }
+
+        let label: LabelVertices = self.generate_vertices(&line.text, line.char_size).into();
+        cache.labels.insert(key, Rc::downgrade(&label));
+
+        // drop dead entries once the map has doubled since the last prune
+        if cache.labels.len() >= cache.prune_at {
+            cache.labels.retain(|_, label| label.strong_count() > 0);
+            cache.prune_at = (cache.labels.len() * 2).max(64);
+        }
+
+        label
+    }
+
+    /// Generates line vertices for `text` with its origin at (0, 0).
+    fn generate_vertices(&self, text: &str, char_size: (f32, f32)) -> Vec<f32> {
+        let char_scale = (0.8 * char_size.0, 0.7 * char_size.1);
+        let mut vertices = Vec::new();
+        let mut char_start = (0.0, 0.0);
+        for c in text.chars() {
+            if let Some(v) = glyph(c) {
+                for i in 0..v.len() / 2 {
+                    let index = i * 2;
+                    let vx = char_start.0 + v[index] * char_scale.0;
+                    let vy = char_start.1 - v[index + 1] * char_scale.1;
+                    vertices.push(vx);
+                    vertices.push(vy);
+                }
+            }
+            char_start.0 += char_size.0;
+
+            if c == '\n' {
+                char_start.0 = 0.0;
+                char_start.1 -= char_size.1;
+            }
+        }
+
+        vertices
+    }
+
+    /// Returns true if `c` can be drawn with its own glyph, or a close
+    /// unaccented substitute, rather than the replacement box.
+    pub fn has_glyph(&self, c: char) -> bool {
+        c.is_whitespace()
+            || c.is_control()
+            || font_glyph(c).is_some()
+            || latin1_base_letter(c).and_then(font_glyph).is_some()
+    }
+
+    /// Returns the distinct characters in `text` that would be drawn as the
+    /// replacement box, in order of first appearance.
+    pub fn missing_glyphs(&self, text: &str) -> Vec<char> {
+        let mut missing = Vec::new();
+        for c in text.chars() {
+            if !self.has_glyph(c) && !missing.contains(&c) {
+                missing.push(c);
+            }
+        }
+
+        missing
+    }
+
     pub fn render(&self, text: &SystemText) {
         unsafe {
             gl::UseProgram(self.program);
             gl::BindVertexArray(text.vao);
-            gl::DrawArrays(gl::LINES, 0, text.num_indices as i32);
+            gl::DrawArrays(gl::LINES, 0, text.num_vertices as i32);
         }
     }
 }
@@ -106,25 +188,184 @@ impl Drop for SystemTextRenderer {
     }
 }
 
-fn create_character_vertices() -> HashMap<char, Vec<f32>> {
-    let mut vertices = HashMap::new();
+/// Returns the line vertices used to draw `c`, as pairs of points from
+/// (0, 0) at the top left of the glyph to (1, 1), falling back to the
+/// unaccented letter for accented Latin-1 letters and to a replacement box
+/// for anything else the font doesn't cover. Whitespace has no vertices.
+pub(crate) fn glyph(c: char) -> Option<&'static [f32]> {
+    if let Some(v) = font_glyph(c) {
+        return Some(v);
+    }
+
+    if c.is_whitespace() || c.is_control() {
+        return None;
+    }
+
+    latin1_base_letter(c).and_then(font_glyph).or(Some(REPLACEMENT_GLYPH))
+}
+
+/// Looks `c` up in the font, using the uppercase glyph for lowercase
+/// letters the font doesn't have.
+fn font_glyph(c: char) -> Option<&'static [f32]> {
+    let find = |c: char| {
+        CHARACTER_VERTICES
+            .binary_search_by_key(&c, |&(key, _)| key)
+            .ok()
+            .map(|i| CHARACTER_VERTICES[i].1)
+    };
+
+    find(c).or_else(|| c.is_ascii_lowercase().then(|| find(c.to_ascii_uppercase())).flatten())
+}
+
+/// An outlined box, drawn for characters the font doesn't cover.
+const REPLACEMENT_GLYPH: &[f32] = &[
+    0.1, 0.0, 0.9, 0.0,
+    0.9, 0.0, 0.9, 1.0,
+    0.9, 1.0, 0.1, 1.0,
+    0.1, 1.0, 0.1, 0.0,
+];
+
+/// Maps accented Latin-1 letters to the letter without the accent.
+fn latin1_base_letter(c: char) -> Option<char> {
+    let base = match c {
+        'À'..='Å' => 'A',
+        'Ç' => 'C',
+        'È'..='Ë' => 'E',
+        'Ì'..='Ï' => 'I',
+        'Ð' => 'D',
+        'Ñ' => 'N',
+        'Ò'..='Ö' | 'Ø' => 'O',
+        'Ù'..='Ü' => 'U',
+        'Ý' => 'Y',
+        'à'..='å' => 'a',
+        'ç' => 'c',
+        'è'..='ë' => 'e',
+        'ì'..='ï' => 'i',
+        'ð' => 'd',
+        'ñ' => 'n',
+        'ò'..='ö' | 'ø' => 'o',
+        'ù'..='ü' => 'u',
+        'ý' | 'ÿ' => 'y',
+        _ => return None,
+    };
+
+    Some(base)
+}
+
+/// Appends `label` moved to `position`, and returns how many vertices that
+/// is. Each vertex takes two floats.
+fn append_translated(vertices: &mut Vec<f32>, label: &[f32], position: (f32, f32)) -> usize {
+    vertices.reserve(label.len());
+    for v in label.chunks_exact(2) {
+        vertices.push(v[0] + position.0);
+        vertices.push(v[1] + position.1);
+    }
+    label.len() / 2
+}
+
+/// Collects text from many sources into a single buffer so it can all be
+/// drawn with one draw call, instead of one `SystemText` per label.
+///
+/// Add text with [`TextBatcher::add`] every frame (or whenever it changes),
+/// then call [`TextBatcher::render`]. If the batch holds the same strings at
+/// the same positions as last frame, nothing is regenerated or reuploaded.
+pub struct TextBatcher {
+    vao: u32,
+    buffer: u32,
+    labels: Vec<(LabelVertices, (f32, f32))>,
+    uploaded: Vec<(LabelVertices, (f32, f32))>,
+    vertices: Vec<f32>,
+    capacity: usize,
+    /// Vertices in the buffer, which can be smaller than its capacity.
+    num_uploaded: usize,
+}
+
+impl TextBatcher {
+    pub fn new() -> Result<Self, Error> {
+        let (vao, buffer) = create_vertex_array()?;
+
+        Ok(Self {
+            vao,
+            buffer,
+            labels: Vec::new(),
+            uploaded: Vec::new(),
+            vertices: Vec::new(),
+            capacity: 0,
+            num_uploaded: 0,
+        })
+    }
+
+    pub fn add(&mut self, renderer: &SystemTextRenderer, text: &[TextLine]) {
+        for t in text {
+            self.labels.push((renderer.label_vertices(t), t.position));
+        }
+    }
 
-    for (c, v) in CHARACTER_VERTICES {
-        vertices.insert(c, v.to_vec());
+    /// Removes all text from the batch. The previously rendered text stays
+    /// cached until the next render, so re-adding it is cheap.
+    pub fn clear(&mut self) {
+        self.labels.clear();
     }
 
-    // Use uppercase vertices for lowercase characters
-    for i in 0..26 {
-        let lower = (i + 97) as u8 as char;
-        let upper = (i + 65) as u8 as char;
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    pub fn render(&mut self, renderer: &SystemTextRenderer) {
+        let unchanged = self.labels.len() == self.uploaded.len()
+            && self.labels.iter().zip(&self.uploaded).all(|(a, b)| {
+                Rc::ptr_eq(&a.0, &b.0) && a.1 == b.1
+            });
+
+        if !unchanged {
+            self.upload();
+        }
 
-        if !vertices.contains_key(&lower) {
-            let v = vertices.get(&upper).unwrap().to_vec();
-            vertices.insert(lower, v);
+        if self.num_uploaded == 0 {
+            return;
+        }
+
+        unsafe {
+            gl::UseProgram(renderer.program);
+            gl::BindVertexArray(self.vao);
+            gl::DrawArrays(gl::LINES, 0, self.num_uploaded as i32);
         }
     }
 
-    vertices
+    fn upload(&mut self) {
+        self.vertices.clear();
+        let mut num_vertices = 0;
+        for (label, position) in &self.labels {
+            num_vertices += append_translated(&mut self.vertices, label, *position);
+        }
+
+        let size = std::mem::size_of_val(self.vertices.as_slice()) as isize;
+        unsafe {
+            if self.vertices.len() > self.capacity {
+                // grow geometrically so a slowly growing batch doesn't
+                // reallocate every frame
+                self.capacity = self.vertices.len().next_power_of_two();
+                let capacity_size = (self.capacity * std::mem::size_of::<f32>()) as isize;
+                gl::NamedBufferData(self.buffer, capacity_size, std::ptr::null(), gl::DYNAMIC_DRAW);
+            }
+
+            if size > 0 {
+                gl::NamedBufferSubData(self.buffer, 0, size, self.vertices.as_ptr() as *const _);
+            }
+        }
+
+        self.num_uploaded = num_vertices;
+        self.uploaded = self.labels.clone();
+    }
+}
+
+impl Drop for TextBatcher {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.buffer);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
 }
 
 fn create_vertex_array() -> Result<(u32, u32), Error> {
@@ -172,3 +413,40 @@ fn create_program() -> Result<u32, Error> {
 
     Ok(program)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vertex_count_is_half_the_floats() {
+        // two lines, so four vertices
+        let label = [0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0, 1.0];
+        let mut vertices = Vec::new();
+        assert_eq!(append_translated(&mut vertices, &label, (10.0, 20.0)), 4);
+        assert_eq!(vertices, [10.0, 20.0, 11.0, 20.0, 11.0, 20.0, 11.0, 21.0]);
+
+        assert_eq!(append_translated(&mut vertices, &label[..4], (0.0, 0.0)), 2);
+        assert_eq!(vertices.len(), 12);
+    }
+
+    #[test]
+    fn empty_label_adds_no_vertices() {
+        let mut vertices = vec![1.0, 2.0];
+        assert_eq!(append_translated(&mut vertices, &[], (5.0, 5.0)), 0);
+        assert_eq!(vertices, [1.0, 2.0]);
+    }
+
+    #[test]
+    fn glyphs_are_whole_lines() {
+        // LINES needs an even vertex count, or the last line joins the next
+        // glyph's first point
+        for c in ['A', 'g', '0', '?', 'é', '\u{fffd}'] {
+            let glyph = glyph(c).unwrap();
+            let mut vertices = Vec::new();
+            let count = append_translated(&mut vertices, glyph, (0.0, 0.0));
+            assert_eq!(count * 2, glyph.len(), "{c:?}");
+            assert_eq!(count % 2, 0, "{c:?}");
+        }
+    }
+}
@@ -0,0 +1,347 @@
+use crate::image::{Image, PixelArray, PixelArrayRef};
+use crate::renderers::image_renderer::{ImageRenderer, ImageTexture};
+use crate::renderers::{Renderer, WindowRect};
+use crate::window::{Event, MouseButton, MouseScrollDelta};
+
+use std::collections::HashMap;
+
+type Error = Box<dyn std::error::Error>;
+
+/// Identifies one tile: pyramid level, then column and row.
+type TileKey = (usize, u32, u32);
+
+struct Tile {
+    texture: ImageTexture,
+    last_drawn: u64,
+}
+
+/// An image of any size, cut into tiles that fit in a texture.
+///
+/// Keeps a pyramid of half-size levels down to one that fits in a single
+/// tile, so zoomed-out views draw few tiles and don't alias. Tiles are only
+/// uploaded when a [`TiledImageRenderer`] first draws them, and the least
+/// recently drawn are freed past [`TiledImage::set_max_resident_tiles`].
+pub struct TiledImage {
+    levels: Vec<Image>,
+    tile_size: u32,
+    tiles: HashMap<TileKey, Tile>,
+    max_resident_tiles: usize,
+}
+
+impl TiledImage {
+    /// Uses tiles of up to 1024 pixels, or less if the driver's texture size
+    /// limit is lower. Must be called with the GL context current.
+    pub fn new(image: Image) -> Self {
+        let mut max_texture_size = 0;
+        unsafe {
+            gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut max_texture_size);
+        }
+        Self::with_tile_size(image, (max_texture_size as u32).clamp(64, 1024))
+    }
+
+    pub fn with_tile_size(image: Image, tile_size: u32) -> Self {
+        let tile_size = tile_size.max(1);
+
+        let mut levels = vec![image];
+        loop {
+            let (w, h) = levels.last().unwrap().size();
+            if w <= tile_size && h <= tile_size {
+                break;
+            }
+            let half = levels.last().unwrap().resized((w.div_ceil(2), h.div_ceil(2)));
+            levels.push(half);
+        }
+
+        Self {
+            levels,
+            tile_size,
+            tiles: HashMap::new(),
+            max_resident_tiles: 64,
+        }
+    }
+
+    /// The size of the full-resolution image.
+    pub fn size(&self) -> (u32, u32) {
+        self.levels[0].size()
+    }
+
+    pub fn tile_size(&self) -> u32 {
+        self.tile_size
+    }
+
+    /// Levels in the pyramid, including the full-resolution one.
+    pub fn num_levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn resident_tiles(&self) -> usize {
+        self.tiles.len()
+    }
+
+    /// How many tile textures stay uploaded at most. A frame that needs
+    /// more still draws them all.
+    pub fn set_max_resident_tiles(&mut self, max: usize) {
+        self.max_resident_tiles = max;
+    }
+
+    /// Frees every tile texture. They're uploaded again as they're drawn.
+    pub fn release_tiles(&mut self) {
+        self.tiles.clear();
+    }
+
+    /// The pixel rect of a tile within its level.
+    fn tile_rect(&self, (level, column, row): TileKey) -> (u32, u32, u32, u32) {
+        let (w, h) = self.levels[level].size();
+        let (x, y) = (column * self.tile_size, row * self.tile_size);
+        (x, y, self.tile_size.min(w - x), self.tile_size.min(h - y))
+    }
+
+    fn tile_texture(&mut self, key: TileKey, frame: u64) -> &ImageTexture {
+        if !self.tiles.contains_key(&key) {
+            let (x, y, w, h) = self.tile_rect(key);
+            let tile = crop(&self.levels[key.0], (x, y), (w, h));
+            unsafe {
+                // edge tiles of RGB images can have rows that aren't 4-byte
+                // aligned
+                gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            }
+            let texture = ImageTexture::new(tile.as_ref());
+            unsafe {
+                gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            }
+            self.tiles.insert(key, Tile { texture, last_drawn: frame });
+        }
+
+        let tile = self.tiles.get_mut(&key).unwrap();
+        tile.last_drawn = frame;
+        &tile.texture
+    }
+
+    /// Frees the least recently drawn tiles over the limit, sparing those
+    /// drawn in `frame`.
+    fn evict(&mut self, frame: u64) {
+        while self.tiles.len() > self.max_resident_tiles {
+            let oldest = self
+                .tiles
+                .iter()
+                .filter(|(_, tile)| tile.last_drawn != frame)
+                .min_by_key(|(_, tile)| tile.last_drawn)
+                .map(|(key, _)| *key);
+
+            match oldest {
+                Some(key) => self.tiles.remove(&key),
+                None => break,
+            };
+        }
+    }
+}
+
+fn crop(image: &Image, origin: (u32, u32), size: (u32, u32)) -> Image {
+    let pixels = image.pixel_array();
+    let channels = pixels.bytes_per_pixel();
+    let stride = image.size().0 as usize * channels;
+    let bytes = pixels.bytes();
+
+    let mut data = Vec::with_capacity(size.0 as usize * size.1 as usize * channels);
+    for y in origin.1..origin.1 + size.1 {
+        let start = y as usize * stride + origin.0 as usize * channels;
+        data.extend_from_slice(&bytes[start..start + size.0 as usize * channels]);
+    }
+
+    let pixel_array = match pixels {
+        PixelArrayRef::RGB(_) => PixelArray::RGB(data),
+        PixelArrayRef::RGBA(_) => PixelArray::RGBA(data),
+        PixelArrayRef::Indexed { palette, .. } => PixelArray::Indexed { palette: palette.to_vec(), indices: data },
+    };
+    Image::new(size, pixel_array)
+}
+
+/// A pan and zoom view of a [`TiledImage`].
+///
+/// The mouse wheel zooms around the cursor and dragging with the left button
+/// pans.
+pub struct TiledImageRenderer {
+    image_renderer: ImageRenderer,
+    /// The full-resolution image pixel at the center of the viewport.
+    center: (f64, f64),
+    /// Window pixels per full-resolution image pixel.
+    zoom: f64,
+    viewport: WindowRect,
+    window_size: (u32, u32),
+    cursor: Option<(f32, f32)>,
+    dragging: bool,
+    frame: u64,
+}
+
+impl TiledImageRenderer {
+    pub fn new() -> Result<Self, Error> {
+        Ok(Self {
+            image_renderer: ImageRenderer::new()?,
+            center: (0.0, 0.0),
+            zoom: 1.0,
+            viewport: WindowRect::default(),
+            window_size: (1, 1),
+            cursor: None,
+            dragging: false,
+            frame: 0,
+        })
+    }
+
+    /// Sets the area of the window the view occupies.
+    pub fn set_viewport(&mut self, viewport: WindowRect, window_size: (u32, u32)) {
+        self.viewport = viewport;
+        self.window_size = window_size;
+    }
+
+    /// The view as `(center, zoom)`: the image pixel at the middle of the
+    /// viewport, and window pixels per image pixel.
+    pub fn view(&self) -> ((f64, f64), f64) {
+        (self.center, self.zoom)
+    }
+
+    pub fn set_view(&mut self, center: (f64, f64), zoom: f64) {
+        self.center = center;
+        self.zoom = zoom.clamp(1e-6, 1e4);
+    }
+
+    /// Centers an image of `size` and zooms to show all of it.
+    pub fn zoom_to_fit(&mut self, size: (u32, u32)) {
+        let (w, h) = (size.0.max(1) as f64, size.1.max(1) as f64);
+        let zoom = f64::min(self.viewport.width as f64 / w, self.viewport.height as f64 / h);
+        self.set_view((w * 0.5, h * 0.5), zoom);
+    }
+
+    /// Converts a window position to full-resolution image pixels.
+    pub fn image_position(&self, x: f32, y: f32) -> (f64, f64) {
+        let [vx, vy, vw, vh] = self.viewport.to_f32();
+        let dx = (x - vx - vw * 0.5) as f64 / self.zoom;
+        let dy = (y - vy - vh * 0.5) as f64 / self.zoom;
+        (self.center.0 + dx, self.center.1 + dy)
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        match *event {
+            Event::CursorMoved(x, y) => {
+                let previous = self.cursor.replace((x, y));
+                if let (true, Some(previous)) = (self.dragging, previous) {
+                    self.center.0 -= (x - previous.0) as f64 / self.zoom;
+                    self.center.1 -= (y - previous.1) as f64 / self.zoom;
+                }
+            }
+
+            Event::CursorLeft => {
+                self.cursor = None;
+            }
+
+            Event::MouseButtonPressed(MouseButton::Left) => {
+                self.dragging = self.cursor.is_some_and(|p| self.viewport.contains(p.0, p.1));
+            }
+
+            Event::MouseButtonReleased(MouseButton::Left) => {
+                self.dragging = false;
+            }
+
+            Event::MouseWheel(delta) => {
+                let p = match self.cursor {
+                    Some(p) if self.viewport.contains(p.0, p.1) => p,
+                    _ => return,
+                };
+
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y,
+                    MouseScrollDelta::PixelDelta(_, y) => y / 20.0,
+                };
+
+                // keep the image pixel under the cursor in place
+                let anchor = self.image_position(p.0, p.1);
+                self.set_view(self.center, self.zoom * 1.25f64.powf(lines as f64));
+                let moved = self.image_position(p.0, p.1);
+                self.center.0 += anchor.0 - moved.0;
+                self.center.1 += anchor.1 - moved.1;
+            }
+
+            _ => (),
+        }
+    }
+
+    pub fn render(&mut self, image: &mut TiledImage) {
+        let vp = self.viewport;
+        if vp.width == 0 || vp.height == 0 {
+            return;
+        }
+        self.frame += 1;
+
+        // the coarsest level that still has a texel per window pixel
+        let level = ((1.0 / self.zoom).log2().floor().max(0.0) as usize).min(image.num_levels() - 1);
+        let (full_w, full_h) = image.size();
+        let (level_w, level_h) = image.levels[level].size();
+        let (sx, sy) = (full_w as f64 / level_w as f64, full_h as f64 / level_h as f64);
+
+        // the visible part of the image, in level pixels
+        let [_, _, vw, vh] = vp.to_f32();
+        let (half_w, half_h) = (vw as f64 * 0.5 / self.zoom, vh as f64 * 0.5 / self.zoom);
+        let left = ((self.center.0 - half_w) / sx).max(0.0);
+        let top = ((self.center.1 - half_h) / sy).max(0.0);
+        let right = ((self.center.0 + half_w) / sx).min(level_w as f64);
+        let bottom = ((self.center.1 + half_h) / sy).min(level_h as f64);
+        if left >= right || top >= bottom {
+            return;
+        }
+
+        let tile_size = image.tile_size() as f64;
+        let columns = (left / tile_size) as u32..(right / tile_size).ceil() as u32;
+        let rows = (top / tile_size) as u32..(bottom / tile_size).ceil() as u32;
+
+        let mut saved_viewport = [0i32; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, saved_viewport.as_mut_ptr());
+            let gl_y = self.window_size.1 as i32 - vp.y - vp.height as i32;
+            gl::Viewport(vp.x, gl_y, vp.width as i32, vp.height as i32);
+        }
+
+        // level pixels to normalized device coordinates within the viewport
+        let to_ndc = |x: f64, y: f64| {
+            let nx = (x * sx - self.center.0) * self.zoom / (vw as f64 * 0.5);
+            let ny = (self.center.1 - y * sy) * self.zoom / (vh as f64 * 0.5);
+            (nx as f32, ny as f32)
+        };
+
+        for row in rows {
+            for column in columns.clone() {
+                let key = (level, column, row);
+                let (x, y, w, h) = image.tile_rect(key);
+                let (x0, y0) = to_ndc(x as f64, y as f64);
+                let (x1, y1) = to_ndc((x + w) as f64, (y + h) as f64);
+
+                let texture = image.tile_texture(key, self.frame);
+                self.image_renderer.set_render_quad(&[x0, y0, x1, y0, x1, y1, x0, y1]);
+                self.image_renderer.render(texture);
+            }
+        }
+
+        self.image_renderer.reset_render_quad();
+        image.evict(self.frame);
+
+        unsafe {
+            let [x, y, w, h] = saved_viewport;
+            gl::Viewport(x, y, w, h);
+        }
+    }
+}
+
+/// A [`TiledImageRenderer`] with the image it draws, as a [`Renderer`].
+pub struct TiledImageView {
+    pub renderer: TiledImageRenderer,
+    pub image: TiledImage,
+}
+
+impl Renderer for TiledImageView {
+    fn set_viewport(&mut self, viewport: WindowRect, window_size: (u32, u32)) -> Result<(), Error> {
+        self.renderer.set_viewport(viewport, window_size);
+        Ok(())
+    }
+
+    fn render(&mut self) {
+        self.renderer.render(&mut self.image);
+    }
+}
@@ -0,0 +1,262 @@
+use crate::renderers::shapes::ShapeRenderer;
+use crate::window::{Event, MouseButton};
+
+type Error = Box<dyn std::error::Error>;
+
+/// A transform change produced by dragging a [`TransformGizmoRenderer`]
+/// handle. Deltas are relative to the previous event, so applying every event
+/// in order keeps the app's object in sync with the gizmo.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransformGizmoEvent {
+    /// Move by this many window pixels.
+    Translated(f32, f32),
+    /// Rotate about the target center by this many radians, clockwise on
+    /// screen.
+    Rotated(f32),
+    /// Scale about the target center by these factors along the target's own
+    /// (rotated) axes.
+    Scaled(f32, f32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Handle {
+    Move,
+    Rotate,
+    /// Scale along the local axes whose signs are nonzero.
+    Scale(f32, f32),
+}
+
+const HANDLE_SIZE: f32 = 8.0;
+const ROTATE_HANDLE_DISTANCE: f32 = 24.0;
+const GIZMO_COLOR: [f32; 4] = [0.3, 0.6, 1.0, 1.0];
+const ACTIVE_COLOR: [f32; 4] = [1.0, 0.8, 0.2, 1.0];
+
+/// Draws move, rotate and scale handles around a target rectangle and turns
+/// handle drags into [`TransformGizmoEvent`]s.
+///
+/// Everything is in window pixels with a top-left origin. Drag inside the
+/// target to move it, drag a corner or edge handle to scale it, and drag the
+/// handle above the top edge to rotate it. The gizmo tracks its own target
+/// while dragging; call [`TransformGizmoRenderer::set_target`] whenever the
+/// object changes for other reasons.
+pub struct TransformGizmoRenderer {
+    shapes: ShapeRenderer,
+    center: (f32, f32),
+    size: (f32, f32),
+    rotation: f32,
+    window_size: (u32, u32),
+    cursor: Option<(f32, f32)>,
+    drag: Option<Handle>,
+    /// Aligns rotations to this step, in radians, when set.
+    pub rotation_snap: Option<f32>,
+    rotation_unsnapped: f32,
+}
+
+impl TransformGizmoRenderer {
+    pub fn new() -> Result<Self, Error> {
+        Ok(Self {
+            shapes: ShapeRenderer::new()?,
+            center: (0.0, 0.0),
+            size: (0.0, 0.0),
+            rotation: 0.0,
+            window_size: (1, 1),
+            cursor: None,
+            drag: None,
+            rotation_snap: None,
+            rotation_unsnapped: 0.0,
+        })
+    }
+
+    /// Sets the rectangle the gizmo surrounds as `[x, y, width, height]`
+    /// before rotation, and its clockwise rotation about its center.
+    pub fn set_target(&mut self, rect: [f32; 4], rotation: f32) {
+        let [x, y, w, h] = rect;
+        self.center = (x + w * 0.5, y + h * 0.5);
+        self.size = (w, h);
+        self.rotation = rotation;
+        self.rotation_unsnapped = rotation;
+    }
+
+    pub fn target(&self) -> ([f32; 4], f32) {
+        let (cx, cy) = self.center;
+        let (w, h) = self.size;
+        ([cx - w * 0.5, cy - h * 0.5, w, h], self.rotation)
+    }
+
+    pub fn set_window_size(&mut self, window_size: (u32, u32)) {
+        self.window_size = window_size;
+    }
+
+    /// Whether a handle is being dragged, so the app can ignore the same
+    /// mouse input for its own purposes.
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    fn to_local(&self, p: (f32, f32)) -> (f32, f32) {
+        let (s, c) = self.rotation.sin_cos();
+        let (dx, dy) = (p.0 - self.center.0, p.1 - self.center.1);
+        (dx * c + dy * s, -dx * s + dy * c)
+    }
+
+    fn to_window(&self, p: (f32, f32)) -> (f32, f32) {
+        let (s, c) = self.rotation.sin_cos();
+        (self.center.0 + p.0 * c - p.1 * s, self.center.1 + p.0 * s + p.1 * c)
+    }
+
+    fn scale_handles(&self) -> impl Iterator<Item = (f32, f32)> {
+        [
+            (-1.0, -1.0), (0.0, -1.0), (1.0, -1.0),
+            (1.0, 0.0), (1.0, 1.0), (0.0, 1.0),
+            (-1.0, 1.0), (-1.0, 0.0),
+        ]
+        .into_iter()
+    }
+
+    fn rotate_handle_local(&self) -> (f32, f32) {
+        (0.0, -self.size.1 * 0.5 - ROTATE_HANDLE_DISTANCE)
+    }
+
+    fn hit_test(&self, p: (f32, f32)) -> Option<Handle> {
+        let local = self.to_local(p);
+        let near = |q: (f32, f32)| (local.0 - q.0).abs() <= HANDLE_SIZE && (local.1 - q.1).abs() <= HANDLE_SIZE;
+        let (hw, hh) = (self.size.0 * 0.5, self.size.1 * 0.5);
+
+        if near(self.rotate_handle_local()) {
+            return Some(Handle::Rotate);
+        }
+        if let Some((sx, sy)) = self.scale_handles().find(|&(sx, sy)| near((sx * hw, sy * hh))) {
+            return Some(Handle::Scale(sx, sy));
+        }
+        if local.0.abs() <= hw && local.1.abs() <= hh {
+            return Some(Handle::Move);
+        }
+
+        None
+    }
+
+    pub fn handle_event(&mut self, event: &Event) -> Option<TransformGizmoEvent> {
+        match *event {
+            Event::CursorMoved(x, y) => {
+                let previous = self.cursor.replace((x, y));
+                let handle = self.drag?;
+                self.drag_to(handle, previous?, (x, y))
+            }
+
+            Event::CursorLeft => {
+                self.cursor = None;
+                None
+            }
+
+            Event::MouseButtonPressed(MouseButton::Left) => {
+                self.drag = self.hit_test(self.cursor?);
+                self.rotation_unsnapped = self.rotation;
+                None
+            }
+
+            Event::MouseButtonReleased(MouseButton::Left) => {
+                self.drag = None;
+                None
+            }
+
+            _ => None,
+        }
+    }
+
+    fn drag_to(&mut self, handle: Handle, from: (f32, f32), to: (f32, f32)) -> Option<TransformGizmoEvent> {
+        match handle {
+            Handle::Move => {
+                let delta = (to.0 - from.0, to.1 - from.1);
+                self.center = (self.center.0 + delta.0, self.center.1 + delta.1);
+                Some(TransformGizmoEvent::Translated(delta.0, delta.1))
+            }
+
+            Handle::Rotate => {
+                let angle = |p: (f32, f32)| (p.1 - self.center.1).atan2(p.0 - self.center.0);
+                let mut delta = angle(to) - angle(from);
+                if delta > std::f32::consts::PI {
+                    delta -= std::f32::consts::TAU;
+                } else if delta < -std::f32::consts::PI {
+                    delta += std::f32::consts::TAU;
+                }
+
+                self.rotation_unsnapped += delta;
+                let rotation = match self.rotation_snap {
+                    Some(step) if step > 0.0 => (self.rotation_unsnapped / step).round() * step,
+                    _ => self.rotation_unsnapped,
+                };
+
+                let delta = rotation - self.rotation;
+                if delta == 0.0 {
+                    return None;
+                }
+                self.rotation = rotation;
+                Some(TransformGizmoEvent::Rotated(delta))
+            }
+
+            Handle::Scale(sx, sy) => {
+                let (from, to) = (self.to_local(from), self.to_local(to));
+                let factor = |axis: f32, from: f32, to: f32, size: f32| {
+                    // measure against the handle position rather than the
+                    // cursor, so grabbing a handle off-center doesn't jump
+                    let half = size * 0.5;
+                    if axis == 0.0 || half <= f32::EPSILON {
+                        return 1.0;
+                    }
+                    let new_half = (half + (to - from) * axis).max(1.0);
+                    new_half / half
+                };
+
+                let scale = (
+                    factor(sx, from.0, to.0, self.size.0),
+                    factor(sy, from.1, to.1, self.size.1),
+                );
+                if scale == (1.0, 1.0) {
+                    return None;
+                }
+
+                self.size = (self.size.0 * scale.0, self.size.1 * scale.1);
+                Some(TransformGizmoEvent::Scaled(scale.0, scale.1))
+            }
+        }
+    }
+
+    pub fn render(&mut self) {
+        let (hw, hh) = (self.size.0 * 0.5, self.size.1 * 0.5);
+        let corners = [(-hw, -hh), (hw, -hh), (hw, hh), (-hw, hh)].map(|p| self.to_window(p));
+        let top_center = self.to_window((0.0, -hh));
+        let rotate_handle = self.to_window(self.rotate_handle_local());
+        let scale_handles: Vec<_> = self
+            .scale_handles()
+            .map(|(sx, sy)| (Handle::Scale(sx, sy), self.to_window((sx * hw, sy * hh))))
+            .collect();
+
+        let color = |handle: Handle| if self.drag == Some(handle) { ACTIVE_COLOR } else { GIZMO_COLOR };
+        let outline_color = color(Handle::Move);
+        let rotate_color = color(Handle::Rotate);
+        let (s, c) = self.rotation.sin_cos();
+        let square = |p: (f32, f32), half: f32| {
+            [(-half, -half), (half, -half), (half, half), (-half, half)]
+                .map(|(x, y)| (p.0 + x * c - y * s, p.1 + x * s + y * c))
+        };
+
+        let shapes = &mut self.shapes;
+        shapes.clear();
+
+        for i in 0..4 {
+            shapes.line(corners[i], corners[(i + 1) % 4], 1.5, outline_color);
+        }
+
+        shapes.line(top_center, rotate_handle, 1.0, rotate_color);
+        shapes.ring(rotate_handle, 0.0, HANDLE_SIZE * 0.75, [0.0, 0.0, 0.0, 1.0]);
+        shapes.ring(rotate_handle, 0.0, HANDLE_SIZE * 0.75 - 1.5, rotate_color);
+
+        for (handle, p) in scale_handles {
+            let half = HANDLE_SIZE * 0.5;
+            shapes.quad(square(p, half + 1.0), [[0.0, 0.0, 0.0, 1.0]; 4]);
+            shapes.quad(square(p, half - 0.5), [color(handle); 4]);
+        }
+
+        shapes.draw(self.window_size);
+    }
+}
@@ -0,0 +1,337 @@
+use crate::gl_utils::{create_buffer_f32, create_texture_3d_r32f, Program};
+use crate::renderers::{Renderer, WindowRect};
+use crate::window::{Event, KeyEvent, MouseButton, MouseScrollDelta, VirtualKeyCode};
+
+type Error = Box<dyn std::error::Error>;
+
+/// The axis a [`VolumeSliceRenderer`] cuts across.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SliceAxis {
+    /// Shows the y-z plane, y to the right and z down.
+    X,
+    /// Shows the x-z plane, x to the right and z down.
+    Y,
+    /// Shows the x-y plane, x to the right and y down.
+    Z,
+}
+
+impl SliceAxis {
+    fn index(&self) -> usize {
+        match self {
+            SliceAxis::X => 0,
+            SliceAxis::Y => 1,
+            SliceAxis::Z => 2,
+        }
+    }
+}
+
+/// Something that happened in a volume slice view as a result of user input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VolumeSliceEvent {
+    SliceChanged(usize),
+    WindowLevelChanged { window: f32, level: f32 },
+}
+
+/// Displays axis-aligned slices of a scalar volume in grayscale, with a
+/// window/level control mapping values to brightness.
+///
+/// The mouse wheel or Page Up/Down steps through slices, and dragging with
+/// the right button adjusts the window horizontally and the level
+/// vertically. Slices are shown aspect-correct, centered in the viewport.
+pub struct VolumeSliceRenderer {
+    program: Program,
+    vao: u32,
+    vbo: u32,
+    texture: u32,
+    size: (u32, u32, u32),
+    value_range: (f32, f32),
+    axis: SliceAxis,
+    slice: usize,
+    window: f32,
+    level: f32,
+    viewport: WindowRect,
+    window_size: (u32, u32),
+    cursor: Option<(f32, f32)>,
+    adjusting: bool,
+}
+
+impl VolumeSliceRenderer {
+    pub fn new() -> Result<Self, Error> {
+        let program = Program::new(
+            include_str!("shaders/vertex_shader.glsl"),
+            include_str!("shaders/fragment_shader.glsl"),
+        )?;
+
+        let vertices: &[f32] = &[
+            -1.0, -1.0,
+            1.0, -1.0,
+            1.0, 1.0,
+            -1.0, 1.0,
+        ];
+
+        let vbo = create_buffer_f32(vertices, gl::STATIC_DRAW)?;
+
+        let mut vao = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, 0, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+        }
+
+        Ok(Self {
+            program,
+            vao,
+            vbo,
+            texture: 0,
+            size: (0, 0, 0),
+            value_range: (0.0, 1.0),
+            axis: SliceAxis::Z,
+            slice: 0,
+            window: 1.0,
+            level: 0.5,
+            viewport: WindowRect::default(),
+            window_size: (1, 1),
+            cursor: None,
+            adjusting: false,
+        })
+    }
+
+    /// Uploads a volume, ordered x fastest, then y, then z. Resets the window
+    /// and level to cover the full value range, and moves to the middle
+    /// slice.
+    pub fn set_volume(&mut self, size: (u32, u32, u32), data: &[f32]) -> Result<(), Error> {
+        let texture = create_texture_3d_r32f(size, data)?;
+        unsafe {
+            gl::DeleteTextures(1, &self.texture);
+        }
+
+        self.texture = texture;
+        self.size = size;
+
+        let (min, max) = data
+            .iter()
+            .filter(|v| v.is_finite())
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &v| (min.min(v), max.max(v)));
+        self.value_range = if min <= max { (min, max) } else { (0.0, 1.0) };
+        self.reset_window_level();
+
+        self.slice = self.num_slices() / 2;
+        Ok(())
+    }
+
+    pub fn volume_size(&self) -> (u32, u32, u32) {
+        self.size
+    }
+
+    /// The smallest and largest finite values in the volume.
+    pub fn value_range(&self) -> (f32, f32) {
+        self.value_range
+    }
+
+    pub fn axis(&self) -> SliceAxis {
+        self.axis
+    }
+
+    /// Changes the slicing axis, keeping the same relative depth.
+    pub fn set_axis(&mut self, axis: SliceAxis) {
+        let depth = (self.slice as f32 + 0.5) / self.num_slices().max(1) as f32;
+        self.axis = axis;
+        self.slice = ((depth * self.num_slices() as f32) as usize).min(self.num_slices().saturating_sub(1));
+    }
+
+    pub fn num_slices(&self) -> usize {
+        let (x, y, z) = self.size;
+        [x, y, z][self.axis.index()] as usize
+    }
+
+    pub fn slice(&self) -> usize {
+        self.slice
+    }
+
+    pub fn set_slice(&mut self, slice: usize) {
+        self.slice = slice.min(self.num_slices().saturating_sub(1));
+    }
+
+    pub fn window_level(&self) -> (f32, f32) {
+        (self.window, self.level)
+    }
+
+    /// Maps values from `level - window / 2` (black) to `level + window / 2`
+    /// (white).
+    pub fn set_window_level(&mut self, window: f32, level: f32) {
+        self.window = window.max(f32::EPSILON);
+        self.level = level;
+    }
+
+    pub fn reset_window_level(&mut self) {
+        let (min, max) = self.value_range;
+        self.set_window_level(max - min, (min + max) * 0.5);
+    }
+
+    /// Sets the area of the window the view occupies.
+    pub fn set_viewport(&mut self, viewport: WindowRect, window_size: (u32, u32)) {
+        self.viewport = viewport;
+        self.window_size = window_size;
+    }
+
+    /// The width and height of the current slice, in voxels.
+    fn slice_size(&self) -> (u32, u32) {
+        let (x, y, z) = self.size;
+        match self.axis {
+            SliceAxis::X => (y, z),
+            SliceAxis::Y => (x, z),
+            SliceAxis::Z => (x, y),
+        }
+    }
+
+    /// The aspect-fitted rect the slice is drawn in, in window pixels.
+    fn slice_rect(&self) -> [f32; 4] {
+        let [x, y, w, h] = self.viewport.to_f32();
+        let (sw, sh) = self.slice_size();
+        if sw == 0 || sh == 0 {
+            return [x, y, w, h];
+        }
+
+        let scale = f32::min(w / sw as f32, h / sh as f32);
+        let (fw, fh) = (sw as f32 * scale, sh as f32 * scale);
+        [x + (w - fw) * 0.5, y + (h - fh) * 0.5, fw, fh]
+    }
+
+    /// Converts a window position to the voxel under it on the current
+    /// slice, if any.
+    pub fn voxel_at(&self, position: (f32, f32)) -> Option<(u32, u32, u32)> {
+        let [x, y, w, h] = self.slice_rect();
+        let (u, v) = ((position.0 - x) / w, (position.1 - y) / h);
+        if !(0.0..1.0).contains(&u) || !(0.0..1.0).contains(&v) {
+            return None;
+        }
+
+        let (sw, sh) = self.slice_size();
+        let (i, j) = ((u * sw as f32) as u32, (v * sh as f32) as u32);
+        let k = self.slice as u32;
+        Some(match self.axis {
+            SliceAxis::X => (k, i, j),
+            SliceAxis::Y => (i, k, j),
+            SliceAxis::Z => (i, j, k),
+        })
+    }
+
+    fn step_slice(&mut self, delta: i64) -> Option<VolumeSliceEvent> {
+        let last = self.num_slices().saturating_sub(1) as i64;
+        let slice = (self.slice as i64 + delta).clamp(0, last) as usize;
+        if slice == self.slice {
+            return None;
+        }
+
+        self.slice = slice;
+        Some(VolumeSliceEvent::SliceChanged(slice))
+    }
+
+    pub fn handle_event(&mut self, event: &Event) -> Option<VolumeSliceEvent> {
+        match *event {
+            Event::CursorMoved(x, y) => {
+                let previous = self.cursor.replace((x, y));
+                if !self.adjusting {
+                    return None;
+                }
+
+                // one viewport width spans the full value range
+                let (dx, dy) = (x - previous?.0, y - previous?.1);
+                let (min, max) = self.value_range;
+                let per_pixel = (max - min).max(f32::EPSILON) / self.viewport.width.max(1) as f32;
+                self.set_window_level(self.window + dx * per_pixel, self.level - dy * per_pixel);
+                Some(VolumeSliceEvent::WindowLevelChanged { window: self.window, level: self.level })
+            }
+
+            Event::CursorLeft => {
+                self.cursor = None;
+                None
+            }
+
+            Event::MouseButtonPressed(MouseButton::Right) => {
+                let p = self.cursor?;
+                self.adjusting = self.viewport.contains(p.0, p.1);
+                None
+            }
+
+            Event::MouseButtonReleased(MouseButton::Right) => {
+                self.adjusting = false;
+                None
+            }
+
+            Event::MouseWheel(delta) => {
+                let p = self.cursor?;
+                if !self.viewport.contains(p.0, p.1) {
+                    return None;
+                }
+
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y,
+                    MouseScrollDelta::PixelDelta(_, y) => y / 20.0,
+                };
+                self.step_slice(-lines.round() as i64)
+            }
+
+            Event::KeyPressed(KeyEvent { key: VirtualKeyCode::PageUp, .. }) => self.step_slice(-1),
+            Event::KeyPressed(KeyEvent { key: VirtualKeyCode::PageDown, .. }) => self.step_slice(1),
+
+            _ => None,
+        }
+    }
+
+    pub fn render(&self) {
+        if self.texture == 0 {
+            return;
+        }
+
+        let [x, y, w, h] = self.slice_rect();
+        let gl_y = self.window_size.1 as f32 - y - h;
+        let slice = (self.slice as f32 + 0.5) / self.num_slices().max(1) as f32;
+
+        let mut previous_viewport = [0i32; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, previous_viewport.as_mut_ptr());
+            gl::Viewport(x as i32, gl_y as i32, w as i32, h as i32);
+        }
+
+        let program = &self.program;
+        program.set_uniform_by_name("u_volume", 0).unwrap();
+        program.set_uniform_by_name("u_axis", self.axis.index() as i32).unwrap();
+        program.set_uniform_by_name("u_slice", slice).unwrap();
+        program.set_uniform_by_name("u_window", self.window).unwrap();
+        program.set_uniform_by_name("u_level", self.level).unwrap();
+
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_3D, self.texture);
+            gl::BindVertexArray(self.vao);
+            gl::DrawArrays(gl::TRIANGLE_FAN, 0, 4);
+
+            let [x, y, w, h] = previous_viewport;
+            gl::Viewport(x, y, w, h);
+        }
+    }
+}
+
+impl Drop for VolumeSliceRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteTextures(1, &self.texture);
+        }
+    }
+}
+
+impl Renderer for VolumeSliceRenderer {
+    fn set_viewport(&mut self, viewport: WindowRect, window_size: (u32, u32)) -> Result<(), Error> {
+        VolumeSliceRenderer::set_viewport(self, viewport, window_size);
+        Ok(())
+    }
+
+    fn render(&mut self) {
+        VolumeSliceRenderer::render(self);
+    }
+}
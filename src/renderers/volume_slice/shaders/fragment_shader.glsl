@@ -0,0 +1,27 @@
+#version 330 core
+
+in vec2 v_tex_coords;
+out vec4 f_color;
+
+uniform sampler3D u_volume;
+// 0, 1, 2 for slices across x, y, z
+uniform int u_axis;
+// slice position in texture coordinates along the axis
+uniform float u_slice;
+uniform float u_window;
+uniform float u_level;
+
+void main() {
+    vec3 p;
+    if (u_axis == 0) {
+        p = vec3(u_slice, v_tex_coords.x, v_tex_coords.y);
+    } else if (u_axis == 1) {
+        p = vec3(v_tex_coords.x, u_slice, v_tex_coords.y);
+    } else {
+        p = vec3(v_tex_coords, u_slice);
+    }
+
+    float value = texture(u_volume, p).r;
+    float gray = clamp((value - (u_level - 0.5 * u_window)) / max(u_window, 1e-20), 0.0, 1.0);
+    f_color = vec4(vec3(gray), 1.0);
+}
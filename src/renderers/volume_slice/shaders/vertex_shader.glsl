@@ -0,0 +1,11 @@
+#version 330 core
+
+layout(location = 0) in vec2 position;
+
+out vec2 v_tex_coords;
+
+void main() {
+    gl_Position = vec4(position, 0.0, 1.0);
+    // top-left origin, so slices read like images
+    v_tex_coords = vec2(position.x * 0.5 + 0.5, 0.5 - position.y * 0.5);
+}
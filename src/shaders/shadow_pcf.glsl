@@ -0,0 +1,19 @@
+float shadow_pcf(sampler2DShadow shadow_map, vec4 light_space_position, int radius) {
+    vec3 coords = light_space_position.xyz / light_space_position.w * 0.5 + 0.5;
+
+    // beyond the far plane, nothing is in front
+    if (coords.z > 1.0) {
+        return 1.0;
+    }
+
+    vec2 texel = 1.0 / vec2(textureSize(shadow_map, 0));
+    float lit = 0.0;
+    for (int y = -radius; y <= radius; y++) {
+        for (int x = -radius; x <= radius; x++) {
+            lit += texture(shadow_map, vec3(coords.xy + vec2(x, y) * texel, coords.z));
+        }
+    }
+
+    float side = float(2 * radius + 1);
+    return lit / (side * side);
+}
@@ -1,32 +1,117 @@
 use super::{VirtualKeyCode, MouseButton};
+use crate::input::{ActionId, ActionState};
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Event {
     EventLoopStarted,
     CloseRequested,
     Suspended,
     Resumed,
+    /// The GL context and everything in it is gone, e.g. after `Suspended`
+    /// on Android. Nothing may be drawn until `ContextRestored`.
+    ContextLost,
+    /// A new context is current after `ContextLost`, and the callbacks from
+    /// [`WindowController::add_reinit_callback`](super::WindowController::add_reinit_callback)
+    /// have run. GL objects not rebuilt by a callback must be recreated here.
+    ContextRestored,
+    /// The handler panicked with this message while handling an earlier
+    /// event. Only sent with `WindowConfiguration::catch_panics`, so state
+    /// can be saved before the panic is handled as an error.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    HandlerPanicked(String),
+    #[cfg_attr(feature = "serde", serde(skip))]
     Tick(TickEvent),
+    /// A timer from [`WindowController::add_timer`](super::WindowController::add_timer)
+    /// came due, with how many of its periods passed since it last fired.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Timer(TimerId, u32),
+    /// Sent once per frame before `RedrawRequested` in
+    /// [`RedrawMode::Continuous`](super::RedrawMode::Continuous).
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Frame(FrameEvent),
+    /// A fixed simulation step; see
+    /// [`WindowController::set_fixed_timestep`](super::WindowController::set_fixed_timestep).
+    Update(std::time::Duration),
+    /// Replaces `RedrawRequested` while a fixed timestep is set. `alpha` is
+    /// how far the present lies between the last two updates, from 0 to 1,
+    /// for interpolating what is drawn.
+    Render { alpha: f32 },
+    /// Sent once a second after a frame is presented, when enabled with
+    /// [`WindowController::set_frame_stats_events`](super::WindowController::set_frame_stats_events).
+    #[cfg_attr(feature = "serde", serde(skip))]
+    FrameStats(super::FrameStats),
+    /// A screenshot or frame of a burst was saved to this file; see
+    /// [`WindowController::set_screenshot_config`](super::WindowController::set_screenshot_config).
+    #[cfg(feature = "png")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    ScreenshotSaved(std::path::PathBuf),
+    /// A screenshot couldn't be saved, with why. The rest of a burst is
+    /// cancelled.
+    #[cfg(feature = "png")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    ScreenshotFailed(String),
     Moved(i32, i32),
+    /// The new inner size in physical pixels.
     Resized(u32, u32),
+    /// The window's DPI scale changed, e.g. after moving to another monitor.
+    /// Sent before the `Resized` that follows.
+    ScaleFactorChanged(f64),
     Focused(bool),
+    /// The window was minimized, or is otherwise completely hidden where
+    /// the platform reports that. Not reported on Wayland.
+    Minimized,
+    /// The window can be seen again after `Minimized`.
+    Restored,
     RedrawRequested,
     ModifiersChanged(ModifierState),
-    KeyPressed(VirtualKeyCode),
-    KeyReleased(VirtualKeyCode),
+    KeyPressed(KeyEvent),
+    KeyReleased(KeyEvent),
     MouseButtonPressed(MouseButton),
     MouseButtonReleased(MouseButton),
+    /// Sent after the `MouseButtonPressed` of a second press close enough
+    /// in time and place to the first, with the cursor position in physical
+    /// pixels; see [`WindowController::set_double_click_time`](super::WindowController::set_double_click_time).
+    /// Later presses in the same run count up in
+    /// [`WindowController::click_count`](super::WindowController::click_count).
+    MouseButtonDoubleClicked(MouseButton, (f32, f32)),
     CursorEntered,
     CursorLeft,
+    /// The cursor position in physical pixels from the top left. Divide by
+    /// `WindowController::scale_factor` for logical units.
     CursorMoved(f32, f32),
+    /// Unaccelerated, unclamped mouse movement, keeps coming while the
+    /// cursor is grabbed. Only sent when enabled in the configuration.
+    RawMouseMotion(f64, f64),
     MouseWheel(MouseScrollDelta),
-    DroppedFile(std::path::PathBuf),
-    HoveredFile(std::path::PathBuf),
+    /// A file was dropped on the window. `position` is the cursor position
+    /// in physical pixels as last reported, which on platforms that don't
+    /// send cursor movement during a drag is where it entered the window.
+    DroppedFile { path: std::path::PathBuf, position: (f32, f32) },
+    /// A file is being dragged over the window; `position` is as for
+    /// `DroppedFile`.
+    HoveredFile { path: std::path::PathBuf, position: (f32, f32) },
     HoveredFileCancelled,
     ReceivedCharacter(char),
+    /// A bound action started or stopped; see [`crate::input`].
+    Action(ActionId, ActionState),
+    #[cfg(feature = "gamepad")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    GamepadConnected(super::GamepadId),
+    #[cfg(feature = "gamepad")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    GamepadDisconnected(super::GamepadId),
+    #[cfg(feature = "gamepad")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    GamepadButton { id: super::GamepadId, button: super::GamepadButton, pressed: bool },
+    /// `value` ranges from -1 to 1.
+    #[cfg(feature = "gamepad")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    GamepadAxis { id: super::GamepadId, axis: super::GamepadAxis, value: f32 },
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ModifierState {
     pub shift: bool,
     pub ctrl: bool,
@@ -34,13 +119,48 @@ pub struct ModifierState {
     pub super_: bool,
 }
 
+impl ModifierState {
+    pub const NONE: ModifierState = ModifierState {
+        shift: false,
+        ctrl: false,
+        alt: false,
+        super_: false,
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeyEvent {
+    pub key: VirtualKeyCode,
+    /// The platform's code for the physical key, independent of layout.
+    pub scancode: u32,
+    /// True for presses generated by holding the key down. Always false for
+    /// releases.
+    pub is_repeat: bool,
+    /// The modifiers held as of this event. For a modifier key this already
+    /// counts the key itself: pressing Shift reports `shift`, releasing it
+    /// doesn't, whichever order the platform sends `ModifiersChanged` in.
+    pub modifiers: ModifierState,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct TickEvent {
     pub ticks_passed: u32,
     pub time: std::time::Instant,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimerId(pub(crate) u64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameEvent {
+    /// Time since the previous frame, zero for the first one.
+    pub dt: std::time::Duration,
+    pub time: std::time::Instant,
+}
+
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MouseScrollDelta {
     LineDelta(f32, f32),
     PixelDelta(f32, f32),
@@ -51,7 +171,7 @@ impl From<glutin::event::MouseScrollDelta> for MouseScrollDelta {
         use glutin::event::MouseScrollDelta as GlutinDelta;
         use MouseScrollDelta::{LineDelta, PixelDelta};
         match delta {
-            GlutinDelta::LineDelta(x, y) => LineDelta(x as f32, y as f32),
+            GlutinDelta::LineDelta(x, y) => LineDelta(x, y),
             GlutinDelta::PixelDelta(delta) => PixelDelta(delta.x as f32, delta.y as f32),
         }
     }
@@ -0,0 +1,87 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How many recent frames the averages are taken over.
+const AVERAGE_FRAMES: usize = 120;
+
+/// Timing of recently presented frames; see
+/// [`WindowController::frame_stats`](super::WindowController::frame_stats).
+/// Frame times are measured between buffer swaps, so in
+/// [`RedrawMode::OnDemand`](super::RedrawMode::OnDemand) they include the
+/// time spent waiting for a redraw.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FrameStats {
+    /// Frames presented in the last second.
+    pub fps: f32,
+    pub last_frame: Duration,
+    /// The mean over the last 120 frames.
+    pub average_frame: Duration,
+    /// The longest of the last 120 frames.
+    pub worst_frame: Duration,
+    /// Frames presented since the window was created.
+    pub frame_count: u64,
+}
+
+pub(crate) struct FrameStatsTracker {
+    presents: VecDeque<Instant>,
+    frame_count: u64,
+    next_report: Option<Instant>,
+    pub events: bool,
+}
+
+impl FrameStatsTracker {
+    pub fn new() -> Self {
+        Self {
+            presents: VecDeque::new(),
+            frame_count: 0,
+            next_report: None,
+            events: false,
+        }
+    }
+
+    /// Records a presented frame. Returns whether the once a second
+    /// `Event::FrameStats` is due.
+    pub fn record(&mut self, now: Instant) -> bool {
+        self.presents.push_back(now);
+        self.frame_count += 1;
+
+        // keep the last second for fps and enough frames for the averages
+        while self.presents.len() > AVERAGE_FRAMES + 1
+            && self.presents.front().is_some_and(|&t| now - t > Duration::from_secs(1))
+        {
+            self.presents.pop_front();
+        }
+
+        let next_report = *self.next_report.get_or_insert(now + Duration::from_secs(1));
+        if !self.events || now < next_report {
+            return false;
+        }
+
+        self.next_report = Some(now + Duration::from_secs(1));
+        true
+    }
+
+    pub fn stats(&self, now: Instant) -> FrameStats {
+        let fps = self.presents.iter().rev().take_while(|&&t| now - t <= Duration::from_secs(1)).count();
+
+        let intervals: Vec<Duration> = self.presents
+            .iter()
+            .zip(self.presents.iter().skip(1))
+            .map(|(a, b)| *b - *a)
+            .rev()
+            .take(AVERAGE_FRAMES)
+            .collect();
+
+        FrameStats {
+            fps: fps as f32,
+            last_frame: intervals.first().copied().unwrap_or_default(),
+            average_frame: if intervals.is_empty() {
+                Duration::ZERO
+            } else {
+                intervals.iter().sum::<Duration>() / intervals.len() as u32
+            },
+            worst_frame: intervals.iter().max().copied().unwrap_or_default(),
+            frame_count: self.frame_count,
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use glutin::event_loop::{EventLoop, EventLoopBuilder};
+use glutin::ContextBuilder;
+
+use crate::gl_utils::Framebuffer;
+use crate::image::{Image, PixelFormat};
+
+type Error = Box<dyn std::error::Error>;
+
+/// An OpenGL context without a visible window and without running an event
+/// loop, for tests and batch rendering.
+///
+/// The context has no default framebuffer of its own, so it comes with an
+/// offscreen [`Framebuffer`] of `size`, bound on creation. Draw as usual and
+/// read the result with [`HeadlessContext::read_pixels_to_image`].
+///
+/// On Linux this still needs an X11 or Wayland display to connect to, such
+/// as Xvfb on CI machines. Unlike a [`Window`](super::Window) it can be
+/// created off the main thread, e.g. in tests.
+pub struct HeadlessContext {
+    // dropped before the context, while it's still current
+    framebuffer: Framebuffer,
+    _context: glutin::Context<glutin::PossiblyCurrent>,
+    _event_loop: EventLoop<()>,
+    gl_version: (u8, u8),
+}
+
+impl HeadlessContext {
+    pub fn new(gl_version: (u8, u8), size: (u32, u32)) -> Result<Self, Error> {
+        let event_loop = build_event_loop()?;
+
+        let context = ContextBuilder::new()
+            .with_gl_profile(glutin::GlProfile::Core)
+            .with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGl, gl_version))
+            .build_headless(&event_loop, glutin::dpi::PhysicalSize::new(size.0, size.1))?;
+        let context = unsafe { context.make_current().map_err(|(_, e)| e)? };
+
+        gl::load_with(|s| context.get_proc_address(s) as *const _);
+
+        let (mut major, mut minor) = (0, 0);
+        unsafe {
+            gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+            gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+        }
+
+        let framebuffer = Framebuffer::new(size)?;
+        framebuffer.bind();
+
+        Ok(Self {
+            framebuffer,
+            _context: context,
+            _event_loop: event_loop,
+            gl_version: (major as u8, minor as u8),
+        })
+    }
+
+    /// The OpenGL version of the context that was created.
+    pub fn gl_version(&self) -> (u8, u8) {
+        self.gl_version
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        self.framebuffer.size()
+    }
+
+    /// Reallocates the framebuffer at a new size and binds it. The contents
+    /// are lost.
+    pub fn resize(&mut self, size: (u32, u32)) -> Result<(), Error> {
+        self.framebuffer.resize(size)?;
+        self.framebuffer.bind();
+        Ok(())
+    }
+
+    pub fn framebuffer(&self) -> &Framebuffer {
+        &self.framebuffer
+    }
+
+    /// Binds the context's framebuffer again after drawing elsewhere.
+    pub fn bind(&self) {
+        self.framebuffer.bind();
+    }
+
+    /// A new glow context over this context; see
+    /// [`WindowController::glow_context`](super::WindowController::glow_context).
+    #[cfg(feature = "glow")]
+    pub fn create_glow_context(&self) -> glow::Context {
+        unsafe { glow::Context::from_loader_function(|s| self._context.get_proc_address(s) as *const _) }
+    }
+
+    /// Reads the framebuffer into an RGBA image, top row first.
+    pub fn read_pixels_to_image(&self) -> Image {
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.framebuffer.id());
+        }
+        Image::read_from_framebuffer((0, 0), self.size(), PixelFormat::RGBA)
+    }
+}
+
+/// Creates an event loop for the context to hang off, allowing any thread
+/// where the platform does.
+fn build_event_loop() -> Result<EventLoop<()>, Error> {
+    let mut builder = EventLoopBuilder::new();
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+    ))]
+    glutin::platform::unix::EventLoopBuilderExtUnix::with_any_thread(&mut builder, true);
+    #[cfg(target_os = "windows")]
+    glutin::platform::windows::EventLoopBuilderExtWindows::with_any_thread(&mut builder, true);
+
+    // winit panics rather than failing when there's no display to connect to
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| builder.build()))
+        .map_err(|_| "Couldn't create an event loop for the headless context; is there a display?".into())
+}
@@ -1,32 +1,346 @@
 mod event;
+mod frame_stats;
+mod headless;
+#[cfg(feature = "png")]
+mod screenshot;
+mod shared_loader;
+#[cfg(feature = "event-recording")]
+mod recording;
+mod resize;
+mod test_window;
 pub use event::*;
+pub use frame_stats::FrameStats;
+pub use headless::HeadlessContext;
+pub use resize::ResizeDirection;
+#[cfg(feature = "png")]
+pub use screenshot::ScreenshotConfig;
+pub use shared_loader::{LoadError, SharedLoader, Upload};
+pub use test_window::TestWindow;
+#[cfg(feature = "event-recording")]
+pub use recording::{EventRecorder, EventReplay, RecordedEvent};
 
 use glutin::event_loop::{ControlFlow, EventLoop};
 use glutin::window::WindowBuilder;
 use glutin::ContextBuilder;
+use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle};
 
 type Error = Box<dyn std::error::Error>;
 type WindowedContext = glutin::WindowedContext<glutin::PossiblyCurrent>;
 
 pub use glutin::event::VirtualKeyCode;
 pub use glutin::event::MouseButton;
+pub use glutin::window::{CursorGrabMode, CursorIcon};
+#[cfg(feature = "gamepad")]
+pub use gilrs::{Axis as GamepadAxis, Button as GamepadButton, GamepadId};
+pub use crate::renderers::post_process::{ColorAdjustment, ColorBlindness};
 
+use crate::image::{Image, PixelFormat};
+use crate::input::{ActionMap, ActionState, ActionTracker, Input};
+use crate::renderers::post_process::PostProcessChain;
+use crate::renderers::{Renderer, WindowRect};
+use frame_stats::FrameStatsTracker;
+use resize::ResizeDrag;
+#[cfg(feature = "png")]
+use screenshot::PendingCapture;
+
+/// How to create a window. Start from `WindowConfiguration::default()` and
+/// change what's needed with the builder methods, or set the fields
+/// directly.
 #[derive(Debug, Clone)]
 pub struct WindowConfiguration {
     pub title: String,
     pub size: (u32, u32),
     pub gl_version: (u8, u8),
+    /// Core profile versions to try, in order, if `gl_version` isn't
+    /// available. See `Window::gl_version` for the one obtained.
+    pub gl_fallback_versions: Vec<(u8, u8)>,
+    /// Start fullscreen instead of in a window of `size`.
+    pub fullscreen: Option<Fullscreen>,
+    pub redraw_mode: RedrawMode,
+    /// Send `Event::RawMouseMotion`, e.g. for mouse-look cameras.
+    pub raw_mouse_motion: bool,
+    /// Lets the desktop show through wherever the window is drawn with alpha
+    /// below 1, e.g. after clearing to `(0, 0, 0, 0)`. Needs a compositor,
+    /// and can't be changed after the window is created.
+    pub transparent: bool,
+    /// Whether the window has a title bar and border.
+    pub decorations: bool,
+    /// Keeps the window above all others.
+    pub always_on_top: bool,
+    pub framebuffer: FramebufferConfig,
+    /// Waits for the display's vertical blank when presenting, capping the
+    /// frame rate at its refresh rate.
+    pub vsync: bool,
+    /// Asks for a debug context and installs
+    /// [`standard_debug_callback`](crate::gl_utils::standard_debug_callback),
+    /// which logs driver messages through the `log` crate. Debug contexts
+    /// can be slower, so leave this off in release builds.
+    pub debug_context: bool,
+    /// Catches panics in the event handler instead of letting them unwind
+    /// out of `Window::run`. A caught panic is sent to the handler as
+    /// `Event::HandlerPanicked`, then treated like an error from it.
+    pub catch_panics: bool,
+}
+
+/// What the window's default framebuffer is asked to have. The driver may
+/// give more; see `WindowController::framebuffer_config` for what it gave.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FramebufferConfig {
+    /// Samples per pixel for multisample antialiasing, 0 for none. Must be a
+    /// power of two.
+    pub samples: u16,
+    pub depth_bits: u8,
+    pub stencil_bits: u8,
+    /// An sRGB-capable color buffer. Writes are only converted from linear
+    /// while `GL_FRAMEBUFFER_SRGB` is enabled.
+    pub srgb: bool,
+}
+
+impl Default for FramebufferConfig {
+    fn default() -> Self {
+        Self {
+            samples: 0,
+            depth_bits: 24,
+            stencil_bits: 8,
+            srgb: true,
+        }
+    }
+}
+
+/// When the window is redrawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RedrawMode {
+    /// Only when the system asks or `WindowController::request_redraw` is
+    /// called. The loop sleeps between events and ticks.
+    #[default]
+    OnDemand,
+    /// Every frame, as fast as the swap interval allows, with an
+    /// `Event::Frame` before each redraw. Ticks keep firing as usual.
+    Continuous,
+}
+
+/// How much the loop slows down while the window is in the background, so an
+/// idle app doesn't keep using CPU and GPU time. The default changes
+/// nothing; see `WindowController::set_background_behavior`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BackgroundBehavior {
+    /// Ticks at most this often while the window is unfocused.
+    pub unfocused_tick: Option<std::time::Duration>,
+    /// Stops `Event::Frame` and `Event::Update` while the window is
+    /// unfocused. Redraws the system asks for still happen.
+    pub pause_unfocused: bool,
+    /// Ticks at most this often while the window is minimized. Falls back
+    /// to `unfocused_tick` if `None`.
+    pub minimized_tick: Option<std::time::Duration>,
+    /// Stops `Event::Frame`, `Event::Update` and all redraws while the
+    /// window is minimized.
+    pub pause_minimized: bool,
+}
+
+impl BackgroundBehavior {
+    /// Ticks at most once a second in the background, pauses frames and
+    /// updates while unfocused and stops drawing while minimized.
+    pub fn power_saving() -> Self {
+        Self {
+            unfocused_tick: Some(std::time::Duration::from_secs(1)),
+            pause_unfocused: true,
+            minimized_tick: None,
+            pause_minimized: true,
+        }
+    }
+}
+
+/// How a fullscreen window covers its monitor.
+///
+/// `monitor` indexes the system's monitor list; `None` means the monitor the
+/// window is currently on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fullscreen {
+    /// A borderless window the size of the monitor, without a video mode
+    /// change. Switching in and out is fast.
+    Borderless { monitor: Option<usize> },
+    /// Takes over the monitor at its highest resolution and refresh rate.
+    Exclusive { monitor: Option<usize> },
+}
+
+/// A display attached to the system; see `WindowController::monitors`.
+/// Sizes and positions are in physical pixels, with positions on the
+/// desktop shared by all monitors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonitorInfo {
+    pub name: Option<String>,
+    pub size: (u32, u32),
+    /// The top-left corner, comparable with `WindowController::position`.
+    pub position: (i32, i32),
+    pub scale_factor: f64,
+    /// In hertz, if the platform reports it.
+    pub refresh_rate: Option<f64>,
+    pub is_primary: bool,
+    /// Whether the window is on this monitor.
+    pub is_current: bool,
+}
+
+impl Default for WindowConfiguration {
+    /// An 800x600 window asking for OpenGL 4.5, falling back as far as 3.3,
+    /// with vsync on.
+    fn default() -> Self {
+        Self {
+            title: "glume".to_string(),
+            size: (800, 600),
+            gl_version: (4, 5),
+            gl_fallback_versions: vec![(4, 3), (4, 1), (3, 3)],
+            fullscreen: None,
+            redraw_mode: RedrawMode::OnDemand,
+            raw_mouse_motion: false,
+            transparent: false,
+            decorations: true,
+            always_on_top: false,
+            framebuffer: FramebufferConfig::default(),
+            vsync: true,
+            debug_context: false,
+            catch_panics: false,
+        }
+    }
 }
 
 impl WindowConfiguration {
-    pub fn build_window(&self) -> Window {
+    pub fn build_window(&self) -> Result<Window, WindowCreationError> {
         Window::new(self.clone())
     }
+
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = title.to_string();
+        self
+    }
+
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.size = (width, height);
+        self
+    }
+
+    /// Sets the version to ask for first. The fallbacks are kept, minus any
+    /// that aren't older than `major.minor`.
+    pub fn gl_version(mut self, major: u8, minor: u8) -> Self {
+        self.gl_version = (major, minor);
+        self.gl_fallback_versions.retain(|&version| version < (major, minor));
+        self
+    }
+
+    pub fn gl_fallback_versions(mut self, versions: &[(u8, u8)]) -> Self {
+        self.gl_fallback_versions = versions.to_vec();
+        self
+    }
+
+    pub fn fullscreen(mut self, fullscreen: Option<Fullscreen>) -> Self {
+        self.fullscreen = fullscreen;
+        self
+    }
+
+    pub fn redraw_mode(mut self, redraw_mode: RedrawMode) -> Self {
+        self.redraw_mode = redraw_mode;
+        self
+    }
+
+    pub fn raw_mouse_motion(mut self, raw_mouse_motion: bool) -> Self {
+        self.raw_mouse_motion = raw_mouse_motion;
+        self
+    }
+
+    pub fn transparent(mut self, transparent: bool) -> Self {
+        self.transparent = transparent;
+        self
+    }
+
+    pub fn decorations(mut self, decorations: bool) -> Self {
+        self.decorations = decorations;
+        self
+    }
+
+    pub fn always_on_top(mut self, always_on_top: bool) -> Self {
+        self.always_on_top = always_on_top;
+        self
+    }
+
+    pub fn framebuffer(mut self, framebuffer: FramebufferConfig) -> Self {
+        self.framebuffer = framebuffer;
+        self
+    }
+
+    /// Sets the multisampling of the framebuffer; see
+    /// `FramebufferConfig::samples`.
+    pub fn samples(mut self, samples: u16) -> Self {
+        self.framebuffer.samples = samples;
+        self
+    }
+
+    pub fn vsync(mut self, vsync: bool) -> Self {
+        self.vsync = vsync;
+        self
+    }
+
+    pub fn debug_context(mut self, debug_context: bool) -> Self {
+        self.debug_context = debug_context;
+        self
+    }
+
+    pub fn catch_panics(mut self, catch_panics: bool) -> Self {
+        self.catch_panics = catch_panics;
+        self
+    }
+}
+
+/// Why a window couldn't be created.
+#[derive(Debug)]
+pub enum WindowCreationError {
+    /// No context could be created for any of the requested GL versions.
+    /// Holds each version tried with the reason it failed.
+    Context(Vec<((u8, u8), glutin::CreationError)>),
+    MakeCurrent(glutin::ContextError),
+    Fullscreen(Error),
+}
+
+impl std::fmt::Display for WindowCreationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WindowCreationError::Context(attempts) => {
+                write!(f, "Couldn't create an OpenGL context")?;
+                for ((major, minor), e) in attempts {
+                    write!(f, "; {}.{}: {}", major, minor, e)?;
+                }
+                Ok(())
+            }
+            WindowCreationError::MakeCurrent(e) => write!(f, "Couldn't make the OpenGL context current: {}", e),
+            WindowCreationError::Fullscreen(e) => write!(f, "Couldn't go fullscreen: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for WindowCreationError {}
+
+/// What to do about an error, as decided by the `on_error` callback of
+/// [`Window::run_with_error_handler`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorAction {
+    /// Drop the event that failed and carry on.
+    Continue,
+    /// Send the event that failed to the handler again, straight away.
+    /// After `MAX_RETRIES` retries of the same event, further errors end
+    /// the loop as with `Exit`, so an error that keeps happening can't hang
+    /// it.
+    Retry,
+    /// End the event loop, as `WindowController::close` would.
+    Exit,
+}
+
+impl ErrorAction {
+    /// How many times `Retry` resends one event before giving up.
+    pub const MAX_RETRIES: u32 = 3;
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct ProcessEventStatus {
     pub exit: bool,
+    pub poll: bool,
     pub wait_until: Option<std::time::Instant>,
 }
 
@@ -38,13 +352,15 @@ pub struct WindowController<'a> {
 impl<'a> WindowController<'a> {
     fn new(windata: &'a mut WinData) -> Self {
         Self {
-            status: ProcessEventStatus { exit: false, wait_until: None },
+            status: ProcessEventStatus { exit: false, poll: false, wait_until: None },
             windata,
         }
     }
 
     pub fn set_title(&self, title: &str) {
-        self.windata.windowed_context.window().set_title(title);
+        if let Some(window) = self.windata.window() {
+            window.set_title(title);
+        }
     }
 
     pub fn close(&mut self) {
@@ -52,103 +368,1401 @@ impl<'a> WindowController<'a> {
     }
 
     pub fn request_redraw(&self) {
-        self.windata.windowed_context.window().request_redraw();
+        match &self.windata.surface {
+            Surface::Window(windowed_context) => windowed_context.window().request_redraw(),
+            Surface::Headless(_) => self.windata.redraw_requested.set(true),
+        }
+    }
+
+    pub fn set_cursor_visible(&self, visible: bool) {
+        if let Some(window) = self.windata.window() {
+            window.set_cursor_visible(visible);
+        }
+    }
+
+    /// Confines or locks the cursor to the window. `Locked` falls back to
+    /// `Confined` on platforms that only support confining (X11, Windows);
+    /// either way, hide the cursor separately if needed.
+    pub fn set_cursor_grab(&self, mode: CursorGrabMode) -> Result<(), Error> {
+        let window = match self.windata.window() {
+            Some(window) => window,
+            None => return Ok(()),
+        };
+        match window.set_cursor_grab(mode) {
+            Err(glutin::error::ExternalError::NotSupported(_)) if mode == CursorGrabMode::Locked => {
+                window.set_cursor_grab(CursorGrabMode::Confined)?;
+            }
+            result => result?,
+        }
+
+        Ok(())
+    }
+
+    /// Sets the cursor shown over the window. Over the border set with
+    /// `set_resize_hit_test`, resize cursors show instead.
+    pub fn set_cursor_icon(&self, icon: CursorIcon) {
+        self.windata.cursor_icon.set(icon);
+        if let Some(window) = self.windata.window() {
+            if !self.windata.over_resize_border {
+                window.set_cursor_icon(icon);
+            }
+        }
+    }
+
+    /// Lets the user move the window by dragging, e.g. by a custom title bar
+    /// in an undecorated window. Call it on `Event::MouseButtonPressed` for
+    /// the left button; the system moves the window until it's released.
+    pub fn begin_drag_window(&self) -> Result<(), Error> {
+        if let Some(window) = self.windata.window() {
+            window.drag_window()?;
+        }
+        Ok(())
+    }
+
+    /// Resizes the window from `direction` as the cursor moves, until the
+    /// left button is released, e.g. from a resize grip. Call it on
+    /// `Event::MouseButtonPressed` for the left button.
+    ///
+    /// Resizing is done by glume rather than the system, so dragging the
+    /// left or top edge may lag behind the cursor. Fails on Wayland, where
+    /// windows have no position.
+    pub fn begin_resize_window(&mut self, direction: ResizeDirection) -> Result<(), Error> {
+        if let Some(window) = self.windata.window() {
+            let drag = ResizeDrag::new(window, direction, self.windata.cursor_position)?;
+            self.windata.resize_drag = Some(drag);
+        }
+        Ok(())
+    }
+
+    /// Lets the user resize an undecorated window from within `border`
+    /// physical pixels of its edges, or stops with `None`. The cursor
+    /// changes over the border, and presses there start
+    /// `begin_resize_window` instead of being sent, along with their
+    /// releases.
+    pub fn set_resize_hit_test(&mut self, border: Option<f32>) {
+        self.windata.resize_border = border;
+        if border.is_none() && self.windata.over_resize_border {
+            self.windata.over_resize_border = false;
+            if let Some(window) = self.windata.window() {
+                window.set_cursor_icon(self.windata.cursor_icon.get());
+            }
+        }
+    }
+
+    pub fn resize_hit_test(&self) -> Option<f32> {
+        self.windata.resize_border
+    }
+
+    /// Turns `Event::RawMouseMotion` on or off.
+    pub fn set_raw_mouse_motion(&mut self, enabled: bool) {
+        self.windata.raw_mouse_motion = enabled;
+    }
+
+    /// Moves the cursor to a position in window pixels, with a top-left
+    /// origin like `Event::CursorMoved`.
+    pub fn set_cursor_position(&self, x: f32, y: f32) -> Result<(), Error> {
+        let position = glutin::dpi::PhysicalPosition::new(x as f64, y as f64);
+        if let Some(window) = self.windata.window() {
+            window.set_cursor_position(position)?;
+        }
+        Ok(())
+    }
+
+    /// The drawable area in physical pixels, as reported by `Event::Resized`.
+    pub fn inner_size(&self) -> (u32, u32) {
+        self.windata.inner_size()
+    }
+
+    /// The size of the whole window including decorations, in physical
+    /// pixels.
+    pub fn outer_size(&self) -> (u32, u32) {
+        match self.windata.window() {
+            Some(window) => window.outer_size().into(),
+            None => self.windata.inner_size(),
+        }
+    }
+
+    /// Requests a new drawable area in logical pixels, like
+    /// `WindowConfiguration::size`. The change arrives as `Event::Resized`.
+    pub fn set_inner_size(&self, width: u32, height: u32) {
+        let size = glutin::dpi::LogicalSize::new(width, height);
+        if let Some(window) = self.windata.window() {
+            window.set_inner_size(size);
+        }
+    }
+
+    /// Keeps the user from shrinking the window below a logical size, or
+    /// lifts the limit with `None`.
+    pub fn set_min_inner_size(&self, size: Option<(u32, u32)>) {
+        let size = size.map(|(w, h)| glutin::dpi::LogicalSize::new(w, h));
+        if let Some(window) = self.windata.window() {
+            window.set_min_inner_size(size);
+        }
+    }
+
+    /// Keeps the user from growing the window past a logical size, or lifts
+    /// the limit with `None`.
+    pub fn set_max_inner_size(&self, size: Option<(u32, u32)>) {
+        let size = size.map(|(w, h)| glutin::dpi::LogicalSize::new(w, h));
+        if let Some(window) = self.windata.window() {
+            window.set_max_inner_size(size);
+        }
+    }
+
+    pub fn set_resizable(&self, resizable: bool) {
+        if let Some(window) = self.windata.window() {
+            window.set_resizable(resizable);
+        }
+    }
+
+    pub fn is_resizable(&self) -> bool {
+        self.windata.window().is_some_and(|window| window.is_resizable())
+    }
+
+    /// Shows or hides the title bar and border.
+    pub fn set_decorations(&self, decorations: bool) {
+        if let Some(window) = self.windata.window() {
+            window.set_decorations(decorations);
+        }
+    }
+
+    pub fn set_always_on_top(&self, always_on_top: bool) {
+        if let Some(window) = self.windata.window() {
+            window.set_always_on_top(always_on_top);
+        }
+    }
+
+    /// The top-left corner of the window on the desktop, in physical pixels
+    /// like `Event::Moved`. Fails on platforms without window positions,
+    /// such as Wayland.
+    pub fn position(&self) -> Result<(i32, i32), Error> {
+        let window = self.windata.window().ok_or("No window to position")?;
+        Ok(window.outer_position()?.into())
+    }
+
+    /// Moves the top-left corner of the window, in physical pixels. Ignored
+    /// on Wayland.
+    pub fn set_position(&self, x: i32, y: i32) {
+        let position = glutin::dpi::PhysicalPosition::new(x, y);
+        if let Some(window) = self.windata.window() {
+            window.set_outer_position(position);
+        }
+    }
+
+    /// The system's monitors, in the order `Fullscreen` and
+    /// `move_to_monitor` index them. Empty in a [`TestWindow`].
+    pub fn monitors(&self) -> Vec<MonitorInfo> {
+        let Some(window) = self.windata.window() else {
+            return Vec::new();
+        };
+
+        let primary = window.primary_monitor();
+        let current = window.current_monitor();
+        window
+            .available_monitors()
+            .map(|monitor| MonitorInfo {
+                name: monitor.name(),
+                size: monitor.size().into(),
+                position: monitor.position().into(),
+                scale_factor: monitor.scale_factor(),
+                refresh_rate: monitor.refresh_rate_millihertz().map(|mhz| mhz as f64 / 1000.0),
+                is_primary: primary.as_ref() == Some(&monitor),
+                is_current: current.as_ref() == Some(&monitor),
+            })
+            .collect()
+    }
+
+    /// Moves the window to the monitor at `index` in `monitors`. A
+    /// fullscreen window goes fullscreen there the same way; otherwise the
+    /// window is centered on it. Moving windowed is ignored on Wayland.
+    pub fn move_to_monitor(&mut self, index: usize) -> Result<(), Error> {
+        let window = self.windata.window().ok_or("No window to move")?;
+        let monitor = window
+            .available_monitors()
+            .nth(index)
+            .ok_or_else(|| format!("No monitor with index {}", index))?;
+
+        match self.windata.fullscreen {
+            Some(Fullscreen::Borderless { .. }) => {
+                self.set_fullscreen(Some(Fullscreen::Borderless { monitor: Some(index) }))
+            }
+            Some(Fullscreen::Exclusive { .. }) => {
+                self.set_fullscreen(Some(Fullscreen::Exclusive { monitor: Some(index) }))
+            }
+            None => {
+                let (x, y): (i32, i32) = monitor.position().into();
+                let (width, height): (u32, u32) = monitor.size().into();
+                let (outer_width, outer_height): (u32, u32) = window.outer_size().into();
+                let x = x + (width as i32 - outer_width as i32).max(0) / 2;
+                let y = y + (height as i32 - outer_height as i32).max(0) / 2;
+                self.set_position(x, y);
+                Ok(())
+            }
+        }
+    }
+
+    /// The configuration the window was created with. The window may have
+    /// changed since, e.g. its size.
+    pub fn configuration(&self) -> &WindowConfiguration {
+        &self.windata.configuration
+    }
+
+    /// Writes a [`crate::diagnostics::report`] to `path` whenever an error
+    /// reaches the loop's error handling, before the error is handled, or
+    /// stops with `None`. A `.json` extension writes JSON. Each report
+    /// replaces the last.
+    pub fn set_report_on_error(&mut self, path: Option<std::path::PathBuf>) {
+        self.windata.report_on_error = path;
+    }
+
+    pub fn report_on_error(&self) -> Option<&std::path::Path> {
+        self.windata.report_on_error.as_deref()
+    }
+
+    /// The format of the default framebuffer the driver actually provided.
+    pub fn framebuffer_config(&self) -> FramebufferConfig {
+        let windowed_context = match &self.windata.surface {
+            Surface::Window(windowed_context) => windowed_context,
+            // the headless framebuffer is a single RGBA8 texture
+            Surface::Headless(_) => {
+                return FramebufferConfig { samples: 0, depth_bits: 0, stencil_bits: 0, srgb: false };
+            }
+        };
+        let format = windowed_context.get_pixel_format();
+        FramebufferConfig {
+            samples: format.multisampling.unwrap_or(0),
+            depth_bits: format.depth_bits,
+            stencil_bits: format.stencil_bits,
+            srgb: format.srgb,
+        }
+    }
+
+    /// Physical pixels per logical pixel on the window's current monitor.
+    pub fn scale_factor(&self) -> f64 {
+        self.windata.scale_factor()
+    }
+
+    /// The drawable area in logical pixels, the units of
+    /// `WindowConfiguration::size`. `Event::Resized` reports physical pixels.
+    pub fn logical_size(&self) -> (f64, f64) {
+        let (width, height) = self.windata.inner_size();
+        let scale = self.windata.scale_factor();
+        (width as f64 / scale, height as f64 / scale)
+    }
+
+    /// Converts a physical position, like that of `Event::CursorMoved`, to
+    /// logical pixels.
+    pub fn to_logical(&self, x: f32, y: f32) -> (f32, f32) {
+        let scale = self.scale_factor() as f32;
+        (x / scale, y / scale)
+    }
+
+    /// Timing of the frames presented recently.
+    pub fn frame_stats(&self) -> FrameStats {
+        self.windata.frame_stats.stats(self.windata.now())
+    }
+
+    /// Sends `Event::FrameStats` once a second while frames are being
+    /// presented. Off by default.
+    pub fn set_frame_stats_events(&mut self, enabled: bool) {
+        self.windata.frame_stats.events = enabled;
+    }
+
+    /// Starts a timer that sends `Event::Timer` every `duration`, alongside
+    /// the tick and any other timers. Durations under a millisecond are
+    /// rounded up to one.
+    pub fn add_timer(&mut self, name: &str, duration: std::time::Duration) -> TimerId {
+        let duration = duration.max(std::time::Duration::from_millis(1));
+        let id = TimerId(self.windata.next_timer_id);
+        self.windata.next_timer_id += 1;
+        self.windata.timers.push(Timer {
+            id,
+            name: name.to_string(),
+            duration,
+            next: self.windata.now() + duration,
+        });
+        id
+    }
+
+    /// Stops a timer. Returns false if it wasn't running.
+    pub fn cancel_timer(&mut self, id: TimerId) -> bool {
+        let len = self.windata.timers.len();
+        self.windata.timers.retain(|timer| timer.id != id);
+        self.windata.timers.len() != len
+    }
+
+    /// The name a running timer was added with.
+    pub fn timer_name(&self, id: TimerId) -> Option<&str> {
+        self.windata.timers.iter().find(|timer| timer.id == id).map(|timer| timer.name.as_str())
+    }
+
+    /// The longest gap between presses that still counts them as one double
+    /// (or triple...) click. 500ms by default.
+    pub fn set_double_click_time(&mut self, time: std::time::Duration) {
+        self.windata.double_click_time = time;
+    }
+
+    pub fn double_click_time(&self) -> std::time::Duration {
+        self.windata.double_click_time
+    }
+
+    /// How many presses of the last pressed button made up the current
+    /// click: 1 for a single click, 2 for a double click, and so on.
+    pub fn click_count(&self) -> u32 {
+        self.windata.last_click.map_or(0, |click| click.count)
+    }
+
+    /// Holds `Event::Resized` back until the size has stopped changing for
+    /// `delay`, then sends just the last one followed by a redraw, so
+    /// dragging a window edge doesn't relayout on every step. `None`, the
+    /// default, sends every resize immediately. The GL surface itself
+    /// always resizes immediately.
+    pub fn set_resize_debounce(&mut self, delay: Option<std::time::Duration>) {
+        self.windata.resize_debounce = delay;
+    }
+
+    /// Registers a callback that rebuilds GL objects after the context is
+    /// lost, such as a renderer's shaders and buffers. Callbacks run in the
+    /// order they were added, just before `Event::ContextRestored`; an error
+    /// from one is returned from the loop like a handler error.
+    pub fn add_reinit_callback<C>(&mut self, callback: C) -> ReinitId
+    where
+        C: 'static + FnMut() -> Result<(), Error>
+    {
+        let id = ReinitId(self.windata.next_reinit_id);
+        self.windata.next_reinit_id += 1;
+        self.windata.reinit_callbacks.push((id, Box::new(callback)));
+        id
+    }
+
+    /// Unregisters a reinit callback. Returns false if it wasn't registered.
+    pub fn remove_reinit_callback(&mut self, id: ReinitId) -> bool {
+        let len = self.windata.reinit_callbacks.len();
+        self.windata.reinit_callbacks.retain(|(callback_id, _)| *callback_id != id);
+        self.windata.reinit_callbacks.len() != len
+    }
+
+    /// Whether the GL context is usable, i.e. not between
+    /// `Event::ContextLost` and `Event::ContextRestored`.
+    pub fn has_context(&self) -> bool {
+        !self.windata.context_lost
+    }
+
+    /// Feeds recorded events back through the handler at their recorded
+    /// times, starting now. Real keyboard and mouse input is ignored until
+    /// the replay ends, so it runs the same way every time.
+    #[cfg(feature = "event-recording")]
+    pub fn start_replay(&mut self, mut replay: EventReplay) {
+        replay.start(self.windata.now());
+        self.windata.replay = Some(replay);
+    }
+
+    #[cfg(feature = "event-recording")]
+    pub fn stop_replay(&mut self) {
+        self.windata.replay = None;
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        replaying(self.windata)
     }
 
     pub fn set_tick_duration(&mut self, duration: std::time::Duration) {
         self.windata.tick_duration = duration;
-        self.windata.next_tick = std::time::Instant::now() + duration;
+        self.windata.next_tick = self.windata.now() + self.windata.effective_tick_duration();
+    }
+
+    /// Slows the loop down while the window is unfocused or minimized.
+    pub fn set_background_behavior(&mut self, behavior: BackgroundBehavior) {
+        self.windata.update_background(|windata| windata.background = behavior);
+    }
+
+    pub fn background_behavior(&self) -> BackgroundBehavior {
+        self.windata.background
+    }
+
+    /// Whether the window has keyboard focus, as of the last
+    /// `Event::Focused`.
+    pub fn is_focused(&self) -> bool {
+        self.windata.focused
+    }
+
+    /// Whether the window is between `Event::Minimized` and
+    /// `Event::Restored`.
+    pub fn is_minimized(&self) -> bool {
+        self.windata.minimized()
+    }
+
+    pub fn set_redraw_mode(&mut self, redraw_mode: RedrawMode) {
+        if redraw_mode != self.windata.redraw_mode {
+            self.windata.redraw_mode = redraw_mode;
+            self.windata.last_frame = None;
+        }
+    }
+
+    pub fn redraw_mode(&self) -> RedrawMode {
+        self.windata.redraw_mode
+    }
+
+    /// Turns on a fixed-timestep game loop, or turns it off with `None`.
+    ///
+    /// While on, the loop runs continuously: `Event::Update` fires once per
+    /// `step` of elapsed time, catching up after slow frames, and redraws
+    /// come as `Event::Render` instead of `Event::RedrawRequested`.
+    pub fn set_fixed_timestep(&mut self, step: Option<std::time::Duration>) {
+        self.windata.fixed_timestep = step.map(|step| FixedTimestep {
+            step,
+            accumulator: std::time::Duration::ZERO,
+            last_update: None,
+        });
+    }
+
+    pub fn fixed_timestep(&self) -> Option<std::time::Duration> {
+        self.windata.fixed_timestep.as_ref().map(|f| f.step)
+    }
+
+    /// Switches the window to fullscreen, or back to windowed with `None`.
+    pub fn set_fullscreen(&mut self, fullscreen: Option<Fullscreen>) -> Result<(), Error> {
+        if let Some(window) = self.windata.window() {
+            set_window_fullscreen(window, fullscreen)?;
+        }
+        self.windata.fullscreen = fullscreen;
+        Ok(())
+    }
+
+    /// Switches between windowed and borderless fullscreen on the current
+    /// monitor.
+    pub fn toggle_fullscreen(&mut self) -> Result<(), Error> {
+        match self.windata.fullscreen {
+            Some(_) => self.set_fullscreen(None),
+            None => self.set_fullscreen(Some(Fullscreen::Borderless { monitor: None })),
+        }
+    }
+
+    pub fn fullscreen(&self) -> Option<Fullscreen> {
+        self.windata.fullscreen
+    }
+
+    /// Installs bindings for `Event::Action`, or removes them with `None`.
+    /// Actions held at the time are dropped without a release event.
+    pub fn set_action_map(&mut self, action_map: Option<ActionMap>) {
+        self.windata.action_map = action_map;
+        self.windata.action_tracker.clear();
+    }
+
+    pub fn action_map(&self) -> Option<&ActionMap> {
+        self.windata.action_map.as_ref()
+    }
+
+    /// Whether any binding of `action` is currently held.
+    pub fn is_action_active(&self, action: &str) -> bool {
+        self.windata.action_tracker.is_active(action)
     }
 
     pub fn get_modifiers(&self) -> ModifierState {
         self.windata.modifiers
     }
+
+    /// The text on the system clipboard, or `None` if it holds no text or
+    /// the clipboard isn't available.
+    #[cfg(feature = "clipboard")]
+    pub fn clipboard_get(&mut self) -> Option<String> {
+        self.windata.clipboard.as_mut()?.get_text().ok()
+    }
+
+    #[cfg(feature = "clipboard")]
+    pub fn clipboard_set(&mut self, text: &str) -> Result<(), Error> {
+        let clipboard = self.windata.clipboard.as_mut().ok_or("Clipboard unavailable")?;
+        clipboard.set_text(text)?;
+        Ok(())
+    }
+
+    /// Applies a color adjustment to everything drawn during
+    /// `Event::RedrawRequested`, or turns it off with `None`.
+    ///
+    /// While enabled, the handler draws into an offscreen framebuffer which
+    /// is then adjusted onto the window, so anything that explicitly binds
+    /// framebuffer 0 bypasses the adjustment.
+    pub fn set_color_adjustment(&mut self, adjustment: Option<ColorAdjustment>) -> Result<(), Error> {
+        let adjustment = match adjustment {
+            Some(adjustment) => adjustment,
+            None => {
+                self.windata.color_adjust = None;
+                return Ok(());
+            }
+        };
+
+        match &mut self.windata.color_adjust {
+            Some(color_adjust) => color_adjust.adjustment = adjustment,
+            None => {
+                let size = self.windata.inner_size();
+                self.windata.color_adjust = Some(WindowColorAdjust::new(adjustment, size)?);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn color_adjustment(&self) -> Option<ColorAdjustment> {
+        self.windata.color_adjust.as_ref().map(|c| c.adjustment)
+    }
+
+    /// Reads back what has been drawn so far into the framebuffer being
+    /// drawn to, at the window's size. Call it in `Event::RedrawRequested`
+    /// after drawing; once the frame is presented its contents are undefined.
+    ///
+    /// With a color adjustment on, this is the frame before adjustment.
+    pub fn capture_frame(&self) -> Image {
+        let mut framebuffer = 0;
+        unsafe {
+            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut framebuffer);
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, framebuffer as u32);
+            if framebuffer == 0 {
+                gl::ReadBuffer(gl::BACK);
+            }
+        }
+        Image::read_from_framebuffer((0, 0), self.inner_size(), PixelFormat::RGB)
+    }
+
+    /// A glow context over the window's OpenGL context, created the first
+    /// time it's asked for. Code written against glow, such as
+    /// [`crate::glow_utils`], can then draw alongside glume's renderers.
+    #[cfg(feature = "glow")]
+    pub fn glow_context(&self) -> &glow::Context {
+        self.windata.glow.get_or_init(|| match &self.windata.surface {
+            Surface::Window(windowed_context) => unsafe {
+                glow::Context::from_loader_function(|s| windowed_context.get_proc_address(s) as *const _)
+            },
+            Surface::Headless(context) => context.create_glow_context(),
+        })
+    }
+
+    /// The underlying winit window, or `None` in a [`TestWindow`]. Changing
+    /// things glume also tracks, like fullscreen, can leave the controller
+    /// reporting stale values.
+    #[cfg(feature = "raw-access")]
+    pub fn winit_window(&self) -> Option<&glutin::window::Window> {
+        self.windata.window()
+    }
+
+    /// Runs `f` with the underlying glutin context, or returns `None` in a
+    /// [`TestWindow`].
+    #[cfg(feature = "raw-access")]
+    pub fn with_windowed_context<R, F>(&self, f: F) -> Option<R>
+    where
+        F: FnOnce(&glutin::WindowedContext<glutin::PossiblyCurrent>) -> R
+    {
+        match &self.windata.surface {
+            Surface::Window(windowed_context) => Some(f(windowed_context)),
+            Surface::Headless(_) => None,
+        }
+    }
+
+    /// Captures the frame as with [`WindowController::capture_frame`] and
+    /// saves it as a PNG file.
+    #[cfg(feature = "png")]
+    pub fn capture_frame_to_file<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), Error> {
+        self.capture_frame().save_png(path)
+    }
+
+    /// Turns on the built-in screenshot key, or turns it off with `None`.
+    /// Each screenshot is saved once the next frame is drawn, then
+    /// `Event::ScreenshotSaved` or `Event::ScreenshotFailed` is sent.
+    #[cfg(feature = "png")]
+    pub fn set_screenshot_config(&mut self, config: Option<ScreenshotConfig>) {
+        self.windata.screenshot_config = config;
+    }
+
+    #[cfg(feature = "png")]
+    pub fn screenshot_config(&self) -> Option<&ScreenshotConfig> {
+        self.windata.screenshot_config.as_ref()
+    }
+
+    /// Saves the next frame as if the screenshot key was pressed, using the
+    /// default [`ScreenshotConfig`] if none is set.
+    #[cfg(feature = "png")]
+    pub fn take_screenshot(&mut self) {
+        self.capture_burst(1);
+    }
+
+    /// Saves each of the next `frames` frames, e.g. to step through an
+    /// animation glitch. Redraws are requested until it's done. Frames are
+    /// encoded as they're captured, so they take longer than usual.
+    #[cfg(feature = "png")]
+    pub fn capture_burst(&mut self, frames: u32) {
+        if frames > 0 {
+            self.windata.pending_capture = Some(PendingCapture::new(frames));
+            self.request_redraw();
+        }
+    }
+}
+
+/// For handing the window to other libraries, e.g. native dialogs. Panics in
+/// a [`TestWindow`], which has no window.
+unsafe impl HasRawWindowHandle for WindowController<'_> {
+    fn raw_window_handle(&self) -> RawWindowHandle {
+        self.windata.window().expect("A TestWindow has no window handle").raw_window_handle()
+    }
+}
+
+/// Panics in a [`TestWindow`], which has no display connection of its own.
+unsafe impl HasRawDisplayHandle for WindowController<'_> {
+    fn raw_display_handle(&self) -> RawDisplayHandle {
+        self.windata.window().expect("A TestWindow has no display handle").raw_display_handle()
+    }
+}
+
+struct WindowColorAdjust {
+    adjustment: ColorAdjustment,
+    chain: PostProcessChain,
+}
+
+impl WindowColorAdjust {
+    fn new(adjustment: ColorAdjustment, size: (u32, u32)) -> Result<Self, Error> {
+        use crate::renderers::post_process::{PostProcessPass, PostProcessPreset};
+
+        let mut chain = PostProcessChain::new(size)?;
+        chain.push(PostProcessPass::from_preset(PostProcessPreset::ColorAdjust)?);
+        Ok(Self { adjustment, chain })
+    }
+}
+
+/// Caps how many updates run in one frame, so a long stall (or updates that
+/// are slower than real time) doesn't snowball.
+const MAX_UPDATES_PER_FRAME: u32 = 8;
+
+struct FixedTimestep {
+    step: std::time::Duration,
+    accumulator: std::time::Duration,
+    last_update: Option<std::time::Instant>,
+}
+
+impl FixedTimestep {
+    fn alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.step.as_secs_f32()
+    }
+}
+
+/// How far apart, in logical pixels, the presses of a double click can be.
+const DOUBLE_CLICK_DISTANCE: f64 = 4.0;
+
+#[derive(Clone, Copy)]
+struct Click {
+    button: MouseButton,
+    position: (f32, f32),
+    time: std::time::Instant,
+    count: u32,
+}
+
+/// Identifies a callback from [`WindowController::add_reinit_callback`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReinitId(u64);
+
+type ReinitCallback = Box<dyn FnMut() -> Result<(), Error>>;
+
+/// Whether the platform destroys the GL context while the app is suspended.
+const CONTEXT_LOST_ON_SUSPEND: bool = cfg!(target_os = "android");
+
+struct Timer {
+    id: TimerId,
+    name: String,
+    duration: std::time::Duration,
+    next: std::time::Instant,
+}
+
+/// What a `WinData` draws to: a real window, or the headless context of a
+/// `TestWindow`.
+enum Surface {
+    Window(WindowedContext),
+    Headless(Box<HeadlessContext>),
 }
 
 struct WinData {
-    windowed_context: WindowedContext,
+    surface: Surface,
+    /// The mock time of a `TestWindow`, or `None` to use the real time.
+    clock: Option<std::time::Instant>,
+    /// Redraws requested on a headless surface, which has no window to ask.
+    redraw_requested: std::cell::Cell<bool>,
+    cursor_icon: std::cell::Cell<CursorIcon>,
+    resize_border: Option<f32>,
+    over_resize_border: bool,
+    resize_drag: Option<ResizeDrag>,
+    #[cfg(feature = "glow")]
+    glow: std::cell::OnceCell<glow::Context>,
     tick_duration: std::time::Duration,
     next_tick: std::time::Instant,
+    timers: Vec<Timer>,
+    next_timer_id: u64,
+    reinit_callbacks: Vec<(ReinitId, ReinitCallback)>,
+    next_reinit_id: u64,
+    context_lost: bool,
+    focused: bool,
+    /// Reported hidden by the platform.
+    occluded: bool,
+    /// Resized to nothing, which is how Windows reports minimizing.
+    zero_sized: bool,
+    background: BackgroundBehavior,
     modifiers: ModifierState,
+    keys_down: std::collections::HashSet<VirtualKeyCode>,
+    cursor_position: (f32, f32),
+    double_click_time: std::time::Duration,
+    last_click: Option<Click>,
+    resize_debounce: Option<std::time::Duration>,
+    /// A debounced size and when to send it.
+    pending_resize: Option<((u32, u32), std::time::Instant)>,
+    #[cfg(feature = "event-recording")]
+    replay: Option<EventReplay>,
+    color_adjust: Option<WindowColorAdjust>,
+    fullscreen: Option<Fullscreen>,
+    redraw_mode: RedrawMode,
+    last_frame: Option<std::time::Instant>,
+    fixed_timestep: Option<FixedTimestep>,
+    raw_mouse_motion: bool,
+    action_map: Option<ActionMap>,
+    action_tracker: ActionTracker,
+    frame_stats: FrameStatsTracker,
+    configuration: WindowConfiguration,
+    report_on_error: Option<std::path::PathBuf>,
+    #[cfg(feature = "png")]
+    screenshot_config: Option<ScreenshotConfig>,
+    #[cfg(feature = "png")]
+    pending_capture: Option<PendingCapture>,
+    #[cfg(feature = "gamepad")]
+    gilrs: Option<gilrs::Gilrs>,
+    /// Kept for the window's lifetime: on X11, copied text is only
+    /// available while the clipboard that set it is alive.
+    #[cfg(feature = "clipboard")]
+    clipboard: Option<arboard::Clipboard>,
+}
+
+impl WinData {
+    fn new(surface: Surface, window_settings: &WindowConfiguration, clock: Option<std::time::Instant>) -> Self {
+        let tick_duration = std::time::Duration::from_secs(1);
+        let now = clock.unwrap_or_else(std::time::Instant::now);
+
+        let modifiers = ModifierState {
+            shift: false,
+            ctrl: false,
+            alt: false,
+            super_: false,
+        };
+
+        Self {
+            surface,
+            clock,
+            redraw_requested: std::cell::Cell::new(false),
+            cursor_icon: std::cell::Cell::new(CursorIcon::Default),
+            resize_border: None,
+            over_resize_border: false,
+            resize_drag: None,
+            #[cfg(feature = "glow")]
+            glow: std::cell::OnceCell::new(),
+            tick_duration,
+            next_tick: now + tick_duration,
+            timers: Vec::new(),
+            next_timer_id: 0,
+            reinit_callbacks: Vec::new(),
+            next_reinit_id: 0,
+            context_lost: false,
+            focused: true,
+            occluded: false,
+            zero_sized: false,
+            background: BackgroundBehavior::default(),
+            modifiers,
+            keys_down: std::collections::HashSet::new(),
+            cursor_position: (0.0, 0.0),
+            double_click_time: std::time::Duration::from_millis(500),
+            last_click: None,
+            resize_debounce: None,
+            pending_resize: None,
+            #[cfg(feature = "event-recording")]
+            replay: None,
+            color_adjust: None,
+            fullscreen: window_settings.fullscreen,
+            redraw_mode: window_settings.redraw_mode,
+            last_frame: None,
+            fixed_timestep: None,
+            raw_mouse_motion: window_settings.raw_mouse_motion,
+            action_map: None,
+            action_tracker: ActionTracker::default(),
+            frame_stats: FrameStatsTracker::new(),
+            configuration: window_settings.clone(),
+            report_on_error: None,
+            #[cfg(feature = "png")]
+            screenshot_config: None,
+            #[cfg(feature = "png")]
+            pending_capture: None,
+            // a test window shouldn't pick up real controllers
+            #[cfg(feature = "gamepad")]
+            gilrs: match (&clock, gilrs::Gilrs::new()) {
+                (Some(_), _) => None,
+                (None, Ok(gilrs)) => Some(gilrs),
+                (None, Err(e)) => {
+                    log::warn!("Gamepad support unavailable: {}", e);
+                    None
+                }
+            },
+            #[cfg(feature = "clipboard")]
+            clipboard: match arboard::Clipboard::new() {
+                Ok(clipboard) => Some(clipboard),
+                Err(e) => {
+                    log::warn!("Clipboard unavailable: {}", e);
+                    None
+                }
+            },
+        }
+    }
+
+    fn now(&self) -> std::time::Instant {
+        self.clock.unwrap_or_else(std::time::Instant::now)
+    }
+
+    fn window(&self) -> Option<&glutin::window::Window> {
+        match &self.surface {
+            Surface::Window(windowed_context) => Some(windowed_context.window()),
+            Surface::Headless(_) => None,
+        }
+    }
+
+    fn inner_size(&self) -> (u32, u32) {
+        match &self.surface {
+            Surface::Window(windowed_context) => windowed_context.window().inner_size().into(),
+            Surface::Headless(context) => context.size(),
+        }
+    }
+
+    fn scale_factor(&self) -> f64 {
+        self.window().map_or(1.0, |window| window.scale_factor())
+    }
+
+    fn minimized(&self) -> bool {
+        self.occluded || self.zero_sized
+    }
+
+    /// The tick duration, slowed down if the window is in the background.
+    fn effective_tick_duration(&self) -> std::time::Duration {
+        let mut throttle = None;
+        if !self.focused {
+            throttle = self.background.unfocused_tick;
+        }
+        if self.minimized() {
+            throttle = self.background.minimized_tick.or(throttle);
+        }
+        throttle.map_or(self.tick_duration, |tick| tick.max(self.tick_duration))
+    }
+
+    /// Whether `Event::Frame` and `Event::Update` are paused for the
+    /// background.
+    fn updates_paused(&self) -> bool {
+        (!self.focused && self.background.pause_unfocused)
+            || (self.minimized() && self.background.pause_minimized)
+    }
+
+    fn redraws_paused(&self) -> bool {
+        self.minimized() && self.background.pause_minimized
+    }
+
+    /// Applies a change to the focus, minimized state or background
+    /// behavior. The tick restarts if its rate changed, and frame and
+    /// update timing skip over time spent paused.
+    fn update_background<G: FnOnce(&mut WinData)>(&mut self, change: G) {
+        let tick_before = self.effective_tick_duration();
+        let paused_before = self.updates_paused();
+        change(self);
+
+        let tick = self.effective_tick_duration();
+        if tick != tick_before {
+            self.next_tick = self.now() + tick;
+        }
+
+        if paused_before && !self.updates_paused() {
+            self.last_frame = None;
+            if let Some(fixed) = &mut self.fixed_timestep {
+                fixed.last_update = None;
+                fixed.accumulator = std::time::Duration::ZERO;
+            }
+        }
+    }
+
+    /// Resizes what is drawn to, along with the color adjustment's targets.
+    fn resize_surface(&mut self, size: (u32, u32)) -> Result<(), Error> {
+        match &mut self.surface {
+            Surface::Window(windowed_context) => {
+                windowed_context.resize(glutin::dpi::PhysicalSize::new(size.0, size.1));
+            }
+            Surface::Headless(context) => context.resize(size)?,
+        }
+
+        if let Some(color_adjust) = &mut self.color_adjust {
+            color_adjust.chain.set_size(size)?;
+        }
+
+        Ok(())
+    }
+
+    /// Binds the framebuffer frames are presented from.
+    fn bind_surface_framebuffer(&self) {
+        match &self.surface {
+            Surface::Window(_) => unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, 0) },
+            Surface::Headless(context) => context.bind(),
+        }
+    }
+
+    /// The earliest time a tick, timer, debounced resize or replayed event
+    /// is due.
+    fn next_deadline(&self) -> std::time::Instant {
+        let next_timer = self.timers.iter().map(|timer| timer.next).min();
+        let pending_resize = self.pending_resize.map(|(_, deadline)| deadline);
+        #[cfg(feature = "event-recording")]
+        let replay = self.replay.as_ref().and_then(|replay| replay.next_deadline());
+        #[cfg(not(feature = "event-recording"))]
+        let replay = None;
+
+        std::iter::once(self.next_tick)
+            .chain(next_timer)
+            .chain(pending_resize)
+            .chain(replay)
+            .min()
+            .unwrap_or(self.next_tick)
+    }
+}
+
+/// gilrs has to be polled, so while it's running the loop wakes at least this
+/// often to deliver gamepad events promptly.
+#[cfg(feature = "gamepad")]
+const GAMEPAD_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(8);
+
+#[cfg(feature = "gamepad")]
+fn poll_gamepads<F, E>(wc: &mut WindowController, event_handler: &mut F) -> Result<(), E>
+where
+    F: FnMut(&mut WindowController, Event) -> Result<(), E>,
+    E: From<Error>,
+{
+    use gilrs::EventType;
+
+    let gilrs = match &mut wc.windata.gilrs {
+        Some(gilrs) => gilrs,
+        None => return Ok(()),
+    };
+
+    let mut events = Vec::new();
+    while let Some(gilrs::Event { id, event, .. }) = gilrs.next_event() {
+        let event = match event {
+            EventType::Connected => Event::GamepadConnected(id),
+            EventType::Disconnected => Event::GamepadDisconnected(id),
+            EventType::ButtonPressed(button, _) => Event::GamepadButton { id, button, pressed: true },
+            EventType::ButtonReleased(button, _) => Event::GamepadButton { id, button, pressed: false },
+            EventType::AxisChanged(axis, value, _) => Event::GamepadAxis { id, axis, value },
+            _ => continue,
+        };
+        events.push(event);
+    }
+
+    for event in events {
+        event_handler(wc, event)?;
+    }
+
+    Ok(())
+}
+
+fn set_window_fullscreen(window: &glutin::window::Window, fullscreen: Option<Fullscreen>) -> Result<(), Error> {
+    use glutin::window::Fullscreen as WinitFullscreen;
+
+    let monitor = |index: Option<usize>| -> Result<_, Error> {
+        match index {
+            Some(index) => window
+                .available_monitors()
+                .nth(index)
+                .map(Some)
+                .ok_or_else(|| format!("No monitor with index {}", index).into()),
+            None => Ok(None),
+        }
+    };
+
+    let fullscreen = match fullscreen {
+        None => None,
+
+        Some(Fullscreen::Borderless { monitor: index }) => {
+            Some(WinitFullscreen::Borderless(monitor(index)?))
+        }
+
+        Some(Fullscreen::Exclusive { monitor: index }) => {
+            let monitor = monitor(index)?
+                .or_else(|| window.current_monitor())
+                .or_else(|| window.primary_monitor())
+                .ok_or("No monitor available for exclusive fullscreen")?;
+
+            let video_mode = monitor
+                .video_modes()
+                .max_by_key(|m| (m.size().width * m.size().height, m.refresh_rate_millihertz(), m.bit_depth()))
+                .ok_or("Monitor has no video modes")?;
+
+            Some(WinitFullscreen::Exclusive(video_mode))
+        }
+    };
+
+    window.set_fullscreen(fullscreen);
+    Ok(())
 }
 
 pub struct Window {
-    event_loop: EventLoop<()>,
+    // dropped before the event loop, with the context still current, once
+    // run_return has returned
     windata: WinData,
+    event_loop: EventLoop<()>,
+    gl_version: (u8, u8),
+    catch_panics: bool,
 }
 
 impl Window {
-    fn new(window_settings: WindowConfiguration) -> Self {
+    fn new(window_settings: WindowConfiguration) -> Result<Self, WindowCreationError> {
         let el = EventLoop::new();
         let wb = WindowBuilder::new();
-        let wb = wb.with_title(window_settings.title);
+        let wb = wb.with_title(&window_settings.title);
 
         let inner_size = glutin::dpi::LogicalSize::new(window_settings.size.0, window_settings.size.1);
-        let wb = wb.with_inner_size(inner_size);
+        let wb = wb.with_inner_size(inner_size)
+            .with_transparent(window_settings.transparent)
+            .with_decorations(window_settings.decorations)
+            .with_always_on_top(window_settings.always_on_top);
 
-        let windowed_context = ContextBuilder::new();
-        let windowed_context = windowed_context.with_gl_profile(glutin::GlProfile::Core);
-        let windowed_context = windowed_context.with_gl(glutin::GlRequest::Specific(
-            glutin::Api::OpenGl,
-            window_settings.gl_version,
-        ));
+        let versions = std::iter::once(window_settings.gl_version)
+            .chain(window_settings.gl_fallback_versions.iter().copied());
 
-        let windowed_context = windowed_context.build_windowed(wb, &el).unwrap();
-        let windowed_context = unsafe { windowed_context.make_current().unwrap() };
+        let framebuffer = window_settings.framebuffer;
+        let mut failures = Vec::new();
+        let mut windowed_context = None;
+        for version in versions {
+            let context_builder = ContextBuilder::new()
+                .with_gl_profile(glutin::GlProfile::Core)
+                .with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGl, version))
+                .with_multisampling(framebuffer.samples)
+                .with_depth_buffer(framebuffer.depth_bits)
+                .with_stencil_buffer(framebuffer.stencil_bits)
+                .with_srgb(framebuffer.srgb)
+                .with_vsync(window_settings.vsync)
+                .with_gl_debug_flag(window_settings.debug_context);
+
+            match context_builder.build_windowed(wb.clone(), &el) {
+                Ok(context) => {
+                    windowed_context = Some(context);
+                    break;
+                }
+                Err(e) => failures.push((version, e)),
+            }
+        }
+
+        let windowed_context = windowed_context.ok_or(WindowCreationError::Context(failures))?;
+        let windowed_context = unsafe {
+            windowed_context
+                .make_current()
+                .map_err(|(_, e)| WindowCreationError::MakeCurrent(e))?
+        };
+
+        if window_settings.fullscreen.is_some() {
+            set_window_fullscreen(windowed_context.window(), window_settings.fullscreen)
+                .map_err(WindowCreationError::Fullscreen)?;
+        }
 
         gl::load_with(|s| windowed_context.get_proc_address(s) as *const _);
 
-        let tick_duration = std::time::Duration::from_secs(1);
+        let (mut major, mut minor) = (0, 0);
+        unsafe {
+            gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+            gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+        }
 
-        let modifiers = ModifierState {
-            shift: false,
-            ctrl: false,
-            alt: false,
-            super_: false,
-        };
+        if window_settings.debug_context {
+            crate::gl_utils::enable_debug_output();
+        }
 
-        let windata = WinData {
-            windowed_context,
-            tick_duration,
-            next_tick: std::time::Instant::now() + tick_duration,
-            modifiers,
-        };
+        let windata = WinData::new(Surface::Window(windowed_context), &window_settings, None);
 
-        Self {
+        Ok(Self {
             event_loop: el,
             windata,
-        }
+            gl_version: (major as u8, minor as u8),
+            catch_panics: window_settings.catch_panics,
+        })
+    }
+
+    /// The underlying winit window, e.g. for setup glume doesn't wrap before
+    /// the loop starts.
+    #[cfg(feature = "raw-access")]
+    pub fn winit_window(&self) -> &glutin::window::Window {
+        self.windata.window().expect("Window always has a window")
+    }
+
+    /// Starts a [`SharedLoader`] thread with a context that shares objects
+    /// with the window's, for uploads off the main thread. Keep it in the
+    /// handler's state; it stops when dropped.
+    pub fn create_shared_loader(&self) -> Result<SharedLoader, Error> {
+        let windowed_context = match &self.windata.surface {
+            Surface::Window(windowed_context) => windowed_context,
+            Surface::Headless(_) => unreachable!("a Window always has a window"),
+        };
+        SharedLoader::new(&self.event_loop, windowed_context, self.gl_version)
     }
 
-    
-    pub fn run<F>(mut self, event_handler: F) -> !
+    /// The OpenGL version of the context that was created, which may be
+    /// newer than the one asked for, or one of the fallbacks.
+    pub fn gl_version(&self) -> (u8, u8) {
+        self.gl_version
+    }
+
+    /// Runs the event loop, sending every event to `event_handler`. An error
+    /// from the handler is printed and ends the loop; see
+    /// [`Window::run_with_error_handler`] to recover instead.
+    ///
+    /// Never returns: the process exits when the loop ends. Use
+    /// [`Window::run_return`] to carry on afterwards.
+    pub fn run<F>(self, event_handler: F) -> !
     where
         F: 'static + FnMut(&mut WindowController, Event) -> Result<(), Error>
+    {
+        self.run_with_error_handler(event_handler, |_, e: Error| {
+            eprintln!("Error: {}", e);
+            ErrorAction::Exit
+        })
+    }
+
+    /// Like [`Window::run`], but errors go to `on_error`, which decides
+    /// whether the loop carries on.
+    ///
+    /// The handler can return its own error type, as long as glume's errors
+    /// convert into it. Those are passed to `on_error` too, e.g. a failed
+    /// buffer swap, though retrying one just continues.
+    pub fn run_with_error_handler<F, E, H>(mut self, event_handler: F, on_error: H) -> !
+    where
+        F: 'static + FnMut(&mut WindowController, Event) -> Result<(), E>,
+        E: 'static + From<Error>,
+        H: 'static + FnMut(&mut WindowController, E) -> ErrorAction,
     {
         let mut event_handler = event_handler;
+        let mut on_error = on_error;
+        let catch_panics = self.catch_panics;
+
+        self.event_loop.run(move |event, _, control_flow| {
+            let handlers = (&mut event_handler, &mut on_error);
+            run_loop_event(&mut self.windata, event, control_flow, catch_panics, handlers);
+        });
+    }
+
+    /// Like [`Window::run`], with `root` drawn over the whole window. Its
+    /// viewport follows the window's size, and it renders on every redraw
+    /// after the handler has handled the redraw event, so the handler can
+    /// clear first. The handler gets `root` along with each event, e.g. to
+    /// pass it input.
+    ///
+    /// Works with a `Box<dyn Renderer>` as well as a concrete renderer.
+    pub fn run_with_renderer<R, F>(self, root: R, event_handler: F) -> !
+    where
+        R: 'static + Renderer,
+        F: 'static + FnMut(&mut WindowController, &mut R, Event) -> Result<(), Error>
+    {
+        let mut root = root;
+        let mut event_handler = event_handler;
+        self.run(move |wc, event| {
+            match event {
+                Event::EventLoopStarted => {
+                    let size = wc.inner_size();
+                    root.set_viewport(WindowRect::new(0, 0, size.0, size.1), size)?;
+                    event_handler(wc, &mut root, event)
+                }
+
+                Event::Resized(width, height) => {
+                    root.set_viewport(WindowRect::new(0, 0, width, height), (width, height))?;
+                    event_handler(wc, &mut root, event)
+                }
+
+                Event::RedrawRequested | Event::Render { .. } => {
+                    event_handler(wc, &mut root, event)?;
+                    root.render();
+                    Ok(())
+                }
+
+                _ => event_handler(wc, &mut root, event),
+            }
+        })
+    }
+
+    /// Like [`Window::run`], but returns once the loop ends, so the caller
+    /// can clean up, open another window, and so on. The window closes when
+    /// this returns.
+    ///
+    /// The first error from the handler ends the loop and is returned. The
+    /// handler doesn't have to be `'static`, so it can borrow local state.
+    ///
+    /// Not available on iOS, where the loop can't return. On Windows and
+    /// macOS, code after the loop can't run while a window is being resized.
+    #[cfg(any(
+        target_os = "windows",
+        target_os = "macos",
+        target_os = "android",
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+    ))]
+    pub fn run_return<F>(mut self, event_handler: F) -> Result<(), Error>
+    where
+        F: FnMut(&mut WindowController, Event) -> Result<(), Error>
+    {
+        use glutin::platform::run_return::EventLoopExtRunReturn;
+
+        let mut event_handler = event_handler;
+        let mut failure = None;
+        let mut on_error = |_: &mut WindowController, e: Error| {
+            failure.get_or_insert(e);
+            ErrorAction::Exit
+        };
+
+        let catch_panics = self.catch_panics;
+        let windata = &mut self.windata;
+        let exit_code = self.event_loop.run_return(|event, _, control_flow| {
+            let handlers = (&mut event_handler, &mut on_error);
+            run_loop_event(windata, event, control_flow, catch_panics, handlers);
+        });
+
+        match failure {
+            Some(e) => Err(e),
+            None if exit_code != 0 => Err(format!("The event loop ended with code {}", exit_code).into()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Handles one event from winit for `Window::run` and `Window::run_return`,
+/// passing the handler's errors to `on_error`.
+fn run_loop_event<F, E, H>(
+    windata: &mut WinData,
+    event: glutin::event::Event<()>,
+    control_flow: &mut ControlFlow,
+    catch_panics: bool,
+    (event_handler, on_error): (&mut F, &mut H),
+)
+where
+    F: FnMut(&mut WindowController, Event) -> Result<(), E>,
+    E: From<Error>,
+    H: FnMut(&mut WindowController, E) -> ErrorAction,
+{
+    let mut guarded_handler = |wc: &mut WindowController, event: Event| -> Result<(), E> {
+        let mut retries = 0;
+        loop {
+            let result = if catch_panics {
+                call_catching_panics(event_handler, wc, event.clone())
+            } else {
+                event_handler(wc, event.clone())
+            };
+
+            let e = match result {
+                Ok(()) => return Ok(()),
+                Err(e) => e,
+            };
+
+            write_error_report(wc);
+            match on_error(wc, e) {
+                ErrorAction::Continue => return Ok(()),
+                ErrorAction::Retry if retries < ErrorAction::MAX_RETRIES => retries += 1,
+                ErrorAction::Retry | ErrorAction::Exit => {
+                    wc.close();
+                    return Ok(());
+                }
+            }
+        }
+    };
+
+    match process_event(windata, event, &mut guarded_handler) {
+        Ok(status) => {
+            if status.exit {
+                *control_flow = ControlFlow::Exit;
+            } else if status.poll {
+                *control_flow = ControlFlow::Poll;
+            } else if let Some(wait_until) = status.wait_until {
+                *control_flow = ControlFlow::WaitUntil(wait_until);
+            }
+        },
+        Err(e) => {
+            let mut wc = WindowController::new(windata);
+            write_error_report(&wc);
+            let action = on_error(&mut wc, e);
+            if action == ErrorAction::Exit || wc.status.exit {
+                *control_flow = ControlFlow::Exit;
+            }
+        }
+    }
+}
+
+/// Writes the report asked for with `WindowController::set_report_on_error`.
+/// A failure is only printed, so it can't hide the error being reported.
+fn write_error_report(wc: &WindowController) {
+    if let Some(path) = &wc.windata.report_on_error {
+        if let Err(e) = crate::diagnostics::write_report(wc, path) {
+            eprintln!("Error writing diagnostics report to {}: {}", path.display(), e);
+        }
+    }
+}
+
+unsafe impl HasRawWindowHandle for Window {
+    fn raw_window_handle(&self) -> RawWindowHandle {
+        self.windata.window().expect("Window always has a window").raw_window_handle()
+    }
+}
+
+unsafe impl HasRawDisplayHandle for Window {
+    fn raw_display_handle(&self) -> RawDisplayHandle {
+        self.event_loop.raw_display_handle()
+    }
+}
+
+/// A panic caught in the event handler, passed on as an error.
+#[derive(Debug)]
+pub struct HandlerPanic {
+    pub message: String,
+}
 
-        self.event_loop.run(move |event, _, control_flow| {
-            match process_event(&mut self.windata, event, &mut event_handler) {
-                Ok(status) => {
-                    if status.exit {
-                        *control_flow = ControlFlow::Exit;
-                    } else if let Some(wait_until) = status.wait_until {
-                        *control_flow = ControlFlow::WaitUntil(wait_until);
-                    }
-                },
-                Err(e) => {
-                    eprintln!("Error: {}", e);
-                    *control_flow = ControlFlow::Exit;
-                }
-            }
-        });
+impl std::fmt::Display for HandlerPanic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Event handler panicked: {}", self.message)
     }
 }
 
-fn process_event<F>(windata: &mut WinData, event: glutin::event::Event<()>, event_handler: &mut F)
-    -> Result<ProcessEventStatus, Error>
+impl std::error::Error for HandlerPanic {}
+
+/// Calls the handler, turning a panic into `Event::HandlerPanicked` and then
+/// a `HandlerPanic` error.
+fn call_catching_panics<F, E>(event_handler: &mut F, wc: &mut WindowController, event: Event) -> Result<(), E>
+where
+    F: FnMut(&mut WindowController, Event) -> Result<(), E>,
+    E: From<Error>,
+{
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let payload = match catch_unwind(AssertUnwindSafe(|| event_handler(wc, event))) {
+        Ok(result) => return result,
+        Err(payload) => payload,
+    };
+
+    let message = match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&'static str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "unknown panic".to_string(),
+        },
+    };
+
+    // the panic is the error that matters, whatever this returns
+    let notice = Event::HandlerPanicked(message.clone());
+    let _ = catch_unwind(AssertUnwindSafe(|| event_handler(wc, notice)));
+
+    Err(E::from(Box::new(HandlerPanic { message })))
+}
+
+fn process_event<F, E>(windata: &mut WinData, event: glutin::event::Event<()>, event_handler: &mut F)
+    -> Result<ProcessEventStatus, E>
 where
-    F: FnMut(&mut WindowController, Event) -> Result<(), Error>
+    F: FnMut(&mut WindowController, Event) -> Result<(), E>,
+    E: From<Error>,
 {
     let mut wc = WindowController::new(windata);
 
@@ -156,48 +1770,40 @@ where
     use glutin::event::WindowEvent as WinEv;
     use glutin::event::ElementState;
 
-    wc.status.wait_until = Some(wc.windata.next_tick);
-
     match event {
         Ev::LoopDestroyed => (),
 
         Ev::NewEvents(cause) => {
             use glutin::event::StartCause;
             match cause {
-                StartCause::Init => {
-                    let now = std::time::Instant::now();
-                    wc.windata.next_tick = now + wc.windata.tick_duration;
-                    wc.status.wait_until = Some(wc.windata.next_tick);
-                    event_handler(&mut wc, Event::EventLoopStarted)?;
-                },
-                StartCause::ResumeTimeReached { .. } => {
-                    let now = std::time::Instant::now();
-                    let mut ticks_passed = 0;
-                    while now >= wc.windata.next_tick {
-                        wc.windata.next_tick += wc.windata.tick_duration;
-                        ticks_passed += 1;
-                    }
-
-                    if ticks_passed > 0 {
-                        let last_tick = wc.windata.next_tick - wc.windata.tick_duration;
-                        let tick_event = TickEvent {
-                            ticks_passed,
-                            time: last_tick,
-                        };
-                        event_handler(&mut wc, Event::Tick(tick_event))?;
-                    }
-
-                    wc.status.wait_until = Some(wc.windata.next_tick);
-                },
+                StartCause::Init => start_loop(&mut wc, event_handler)?,
+                StartCause::ResumeTimeReached { .. } | StartCause::Poll => fire_ticks(&mut wc, event_handler)?,
                 _ => (),
             }
+
+            fire_due(&mut wc, event_handler)?;
         }
 
+        Ev::WindowEvent { event, .. } if replaying(wc.windata) && is_user_input(&event) => (),
+
         Ev::WindowEvent { event, .. } => match event {
             WinEv::Resized(physical_size) => {
-                wc.windata.windowed_context.resize(physical_size);
-                let (w, h) = physical_size.into();
-                event_handler(&mut wc, Event::Resized(w, h))?;
+                let size: (u32, u32) = physical_size.into();
+                send_minimized(&mut wc, |windata| windata.zero_sized = size == (0, 0), event_handler)?;
+                wc.windata.resize_surface(size)?;
+                send_resized(&mut wc, size, event_handler)?;
+            }
+
+            WinEv::Occluded(occluded) => {
+                send_minimized(&mut wc, |windata| windata.occluded = occluded, event_handler)?;
+            }
+
+            WinEv::ScaleFactorChanged { scale_factor, new_inner_size } => {
+                let physical_size = *new_inner_size;
+                event_handler(&mut wc, Event::ScaleFactorChanged(scale_factor))?;
+
+                wc.windata.resize_surface(physical_size.into())?;
+                send_resized(&mut wc, physical_size.into(), event_handler)?;
             }
 
             WinEv::CloseRequested => {
@@ -207,20 +1813,30 @@ where
 
             WinEv::KeyboardInput { input, .. } => {
                 if let Some(vk) = input.virtual_keycode {
-                    match input.state {
-                        ElementState::Pressed => event_handler(&mut wc, Event::KeyPressed(vk))?,
-                        ElementState::Released => event_handler(&mut wc, Event::KeyReleased(vk))?,
-                    }
+                    // winit doesn't flag autorepeat, so a press for a key
+                    // that's already down is one
+                    let is_repeat = match input.state {
+                        ElementState::Pressed => wc.windata.keys_down.contains(&vk),
+                        ElementState::Released => false,
+                    };
+
+                    // platforms differ on whether ModifiersChanged comes
+                    // before or after the modifier key itself
+                    let modifiers = modifiers_after_key(wc.windata, vk, input.state);
+                    wc.windata.modifiers = modifiers;
+
+                    let key_event = KeyEvent {
+                        key: vk,
+                        scancode: input.scancode,
+                        is_repeat,
+                        modifiers,
+                    };
+                    send_key(&mut wc, key_event, input.state, event_handler)?;
                 }
             },
 
             WinEv::MouseInput { state, button, .. } => {
-                match state {
-                    ElementState::Pressed =>
-                        event_handler(&mut wc, Event::MouseButtonPressed(button))?,
-                    ElementState::Released =>
-                        event_handler(&mut wc, Event::MouseButtonReleased(button))?,
-                };
+                send_mouse_button(&mut wc, button, state, event_handler)?;
             },
 
             WinEv::CursorEntered { .. } => {
@@ -233,6 +1849,8 @@ where
 
             WinEv::CursorMoved { position, .. } => {
                 let (x, y) = (position.x as f32, position.y as f32);
+                wc.windata.cursor_position = (x, y);
+                resize_on_cursor_moved(&mut wc);
                 event_handler(&mut wc, Event::CursorMoved(x, y))?;
             },
 
@@ -255,7 +1873,7 @@ where
             },
 
             WinEv::Focused(focused) => {
-                event_handler(&mut wc, Event::Focused(focused))?;
+                send_focused(&mut wc, focused, event_handler)?;
             },
 
             WinEv::Moved(position) => {
@@ -264,11 +1882,13 @@ where
             },
 
             WinEv::DroppedFile(path) => {
-                event_handler(&mut wc, Event::DroppedFile(path))?;
+                let position = wc.windata.cursor_position;
+                event_handler(&mut wc, Event::DroppedFile { path, position })?;
             },
 
             WinEv::HoveredFile(path) => {
-                event_handler(&mut wc, Event::HoveredFile(path))?;
+                let position = wc.windata.cursor_position;
+                event_handler(&mut wc, Event::HoveredFile { path, position })?;
             },
 
             WinEv::HoveredFileCancelled => {
@@ -282,21 +1902,737 @@ where
             _ => ()
         },
 
+        Ev::DeviceEvent { event: glutin::event::DeviceEvent::MouseMotion { delta }, .. }
+            if wc.windata.raw_mouse_motion && !replaying(wc.windata) =>
+        {
+            event_handler(&mut wc, Event::RawMouseMotion(delta.0, delta.1))?;
+        },
+
+        Ev::MainEventsCleared => {
+            main_events_cleared(&mut wc, event_handler)?;
+        },
+
+        Ev::RedrawRequested(_) if wc.windata.context_lost || wc.windata.redraws_paused() => (),
+
         Ev::RedrawRequested(_) => {
-            event_handler(&mut wc, Event::RedrawRequested)?;
-            wc.windata.windowed_context.swap_buffers()?;
+            redraw(&mut wc, event_handler)?;
         },
 
         Ev::Suspended => {
             event_handler(&mut wc, Event::Suspended)?;
+            if CONTEXT_LOST_ON_SUSPEND {
+                lose_context(&mut wc, event_handler)?;
+            }
         },
 
         Ev::Resumed => {
             event_handler(&mut wc, Event::Resumed)?;
+            if wc.windata.context_lost {
+                restore_context(&mut wc, event_handler)?;
+            }
         },
 
         _ => ()
     }
 
+    wc.status.poll = (wc.windata.redraw_mode == RedrawMode::Continuous || wc.windata.fixed_timestep.is_some())
+        && !wc.windata.updates_paused();
+    wc.status.wait_until = Some(wc.windata.next_deadline());
+
+    #[cfg(feature = "gamepad")]
+    if wc.windata.gilrs.is_some() {
+        let poll_deadline = wc.windata.now() + GAMEPAD_POLL_INTERVAL;
+        wc.status.wait_until = wc.status.wait_until.map(|t| t.min(poll_deadline));
+    }
     Ok(wc.status)
 }
+
+/// Restarts the tick from now and sends `Event::EventLoopStarted`.
+fn start_loop<F, E>(wc: &mut WindowController, event_handler: &mut F) -> Result<(), E>
+where
+    F: FnMut(&mut WindowController, Event) -> Result<(), E>,
+    E: From<Error>,
+{
+    wc.windata.next_tick = wc.windata.now() + wc.windata.effective_tick_duration();
+    event_handler(wc, Event::EventLoopStarted)
+}
+
+/// Sends `Event::Tick` if the tick has come due, with how many passed.
+fn fire_ticks<F, E>(wc: &mut WindowController, event_handler: &mut F) -> Result<(), E>
+where
+    F: FnMut(&mut WindowController, Event) -> Result<(), E>,
+    E: From<Error>,
+{
+    let now = wc.windata.now();
+    let tick_duration = wc.windata.effective_tick_duration();
+    let mut ticks_passed = 0;
+    while now >= wc.windata.next_tick {
+        wc.windata.next_tick += tick_duration;
+        ticks_passed += 1;
+    }
+
+    if ticks_passed > 0 {
+        let last_tick = wc.windata.next_tick - tick_duration;
+        let tick_event = TickEvent {
+            ticks_passed,
+            time: last_tick,
+        };
+        event_handler(wc, Event::Tick(tick_event))?;
+    }
+
+    Ok(())
+}
+
+/// Sends whatever else has come due at the start of a loop iteration.
+fn fire_due<F, E>(wc: &mut WindowController, event_handler: &mut F) -> Result<(), E>
+where
+    F: FnMut(&mut WindowController, Event) -> Result<(), E>,
+    E: From<Error>,
+{
+    fire_timers(wc, event_handler)?;
+    flush_pending_resize(wc, event_handler)?;
+    #[cfg(feature = "event-recording")]
+    play_replay(wc, event_handler)?;
+
+    #[cfg(feature = "gamepad")]
+    poll_gamepads(wc, event_handler)?;
+
+    Ok(())
+}
+
+/// Sends `Event::Frame` and `Event::Update` and requests the redraw that
+/// follows them, once all of an iteration's input has been handled.
+fn main_events_cleared<F, E>(wc: &mut WindowController, event_handler: &mut F) -> Result<(), E>
+where
+    F: FnMut(&mut WindowController, Event) -> Result<(), E>,
+    E: From<Error>,
+{
+    if wc.windata.updates_paused() {
+        return Ok(());
+    }
+
+    let now = wc.windata.now();
+
+    if wc.windata.redraw_mode == RedrawMode::Continuous {
+        let dt = wc.windata.last_frame.map_or(std::time::Duration::ZERO, |last| now - last);
+        wc.windata.last_frame = Some(now);
+
+        event_handler(wc, Event::Frame(FrameEvent { dt, time: now }))?;
+        wc.request_redraw();
+    }
+
+    if let Some(fixed) = &mut wc.windata.fixed_timestep {
+        let elapsed = fixed.last_update.map_or(std::time::Duration::ZERO, |last| now - last);
+        fixed.last_update = Some(now);
+        fixed.accumulator += elapsed;
+
+        let step = fixed.step;
+        let mut updates = 0;
+        while fixed.accumulator >= step && updates < MAX_UPDATES_PER_FRAME {
+            fixed.accumulator -= step;
+            updates += 1;
+        }
+
+        // drop any remaining backlog rather than try to catch up forever
+        if fixed.accumulator >= step {
+            fixed.accumulator = std::time::Duration::ZERO;
+        }
+
+        for _ in 0..updates {
+            event_handler(wc, Event::Update(step))?;
+            if wc.windata.fixed_timestep.is_none() {
+                break;
+            }
+        }
+
+        wc.request_redraw();
+    }
+
+    Ok(())
+}
+
+/// Sends the redraw event, applies any color adjustment and presents the
+/// frame.
+fn redraw<F, E>(wc: &mut WindowController, event_handler: &mut F) -> Result<(), E>
+where
+    F: FnMut(&mut WindowController, Event) -> Result<(), E>,
+    E: From<Error>,
+{
+    wc.windata.redraw_requested.set(false);
+
+    let frame = wc.windata.color_adjust.as_ref().map(|color_adjust| {
+        let (width, height) = wc.windata.inner_size();
+        let mut viewport = [0i32; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+            gl::Viewport(0, 0, width as i32, height as i32);
+        }
+
+        let frame = color_adjust.chain.begin();
+
+        // the handler keeps whatever viewport it had set up
+        let [x, y, w, h] = viewport;
+        unsafe { gl::Viewport(x, y, w, h) };
+        (frame, viewport)
+    });
+
+    let redraw_event = match &wc.windata.fixed_timestep {
+        Some(fixed) => Event::Render { alpha: fixed.alpha() },
+        None => Event::RedrawRequested,
+    };
+    event_handler(wc, redraw_event)?;
+
+    if let Some((frame, viewport)) = frame {
+        match &wc.windata.color_adjust {
+            Some(color_adjust) => {
+                color_adjust.adjustment.apply_to(&color_adjust.chain.passes()[0]);
+                color_adjust.chain.end(frame);
+            }
+
+            // turned off by the handler mid-frame
+            None => wc.windata.bind_surface_framebuffer(),
+        }
+
+        let [x, y, w, h] = viewport;
+        unsafe { gl::Viewport(x, y, w, h) };
+    }
+
+    #[cfg(feature = "png")]
+    save_pending_capture(wc, event_handler)?;
+
+    if let Surface::Window(windowed_context) = &wc.windata.surface {
+        windowed_context.swap_buffers().map_err(Error::from)?;
+    }
+
+    let now = wc.windata.now();
+    if wc.windata.frame_stats.record(now) {
+        let stats = wc.windata.frame_stats.stats(now);
+        event_handler(wc, Event::FrameStats(stats))?;
+    }
+
+    Ok(())
+}
+
+/// Saves the finished frame if a screenshot is pending, before it's
+/// presented.
+#[cfg(feature = "png")]
+fn save_pending_capture<F, E>(wc: &mut WindowController, event_handler: &mut F) -> Result<(), E>
+where
+    F: FnMut(&mut WindowController, Event) -> Result<(), E>,
+    E: From<Error>,
+{
+    let Some(pending) = &mut wc.windata.pending_capture else {
+        return Ok(());
+    };
+
+    let config = wc.windata.screenshot_config.clone().unwrap_or_default();
+    let (path, last) = pending.next_path(&config);
+    if last {
+        wc.windata.pending_capture = None;
+    } else {
+        wc.request_redraw();
+    }
+
+    let saved = std::fs::create_dir_all(&config.directory)
+        .map_err(Error::from)
+        .and_then(|()| wc.capture_frame_to_file(&path));
+
+    match saved {
+        Ok(()) => event_handler(wc, Event::ScreenshotSaved(path)),
+        Err(error) => {
+            // the rest of a burst would fail the same way
+            wc.windata.pending_capture = None;
+            event_handler(wc, Event::ScreenshotFailed(format!("{}: {error}", path.display())))
+        }
+    }
+}
+
+/// The modifiers held once a key event takes effect, so a modifier key's own
+/// event includes it on press and not on release.
+fn modifiers_after_key(windata: &WinData, key: VirtualKeyCode, state: glutin::event::ElementState) -> ModifierState {
+    use VirtualKeyCode as Vk;
+
+    let pressed = state == glutin::event::ElementState::Pressed;
+    // on release, the modifier stays held if its other key is down
+    let held = |other: Vk| pressed || windata.keys_down.contains(&other);
+
+    let mut modifiers = windata.modifiers;
+    match key {
+        Vk::LShift => modifiers.shift = held(Vk::RShift),
+        Vk::RShift => modifiers.shift = held(Vk::LShift),
+        Vk::LControl => modifiers.ctrl = held(Vk::RControl),
+        Vk::RControl => modifiers.ctrl = held(Vk::LControl),
+        Vk::LAlt => modifiers.alt = held(Vk::RAlt),
+        Vk::RAlt => modifiers.alt = held(Vk::LAlt),
+        Vk::LWin => modifiers.super_ = held(Vk::RWin),
+        Vk::RWin => modifiers.super_ = held(Vk::LWin),
+        _ => (),
+    }
+    modifiers
+}
+
+/// Sends a key press or release and any actions it triggers.
+fn send_key<F, E>(
+    wc: &mut WindowController,
+    key_event: KeyEvent,
+    state: glutin::event::ElementState,
+    event_handler: &mut F,
+) -> Result<(), E>
+where
+    F: FnMut(&mut WindowController, Event) -> Result<(), E>,
+    E: From<Error>,
+{
+    use glutin::event::ElementState;
+
+    match state {
+        ElementState::Pressed => {
+            wc.windata.keys_down.insert(key_event.key);
+            event_handler(wc, Event::KeyPressed(key_event))?;
+        }
+        ElementState::Released => {
+            wc.windata.keys_down.remove(&key_event.key);
+            event_handler(wc, Event::KeyReleased(key_event))?;
+
+            // Windows only reports PrintScreen's release
+            #[cfg(feature = "png")]
+            if let Some(config) = &wc.windata.screenshot_config {
+                if config.keys.contains(&key_event.key) {
+                    let frames = if key_event.modifiers.shift { config.burst_frames } else { 1 };
+                    wc.capture_burst(frames);
+                }
+            }
+        }
+    }
+
+    if !key_event.is_repeat {
+        process_actions(wc, Input::Key(key_event.key), state, event_handler)?;
+    }
+
+    Ok(())
+}
+
+/// Sends a mouse button press or release, a double click if it makes one,
+/// and any actions it triggers, unless it's used for resizing.
+fn send_mouse_button<F, E>(
+    wc: &mut WindowController,
+    button: MouseButton,
+    state: glutin::event::ElementState,
+    event_handler: &mut F,
+) -> Result<(), E>
+where
+    F: FnMut(&mut WindowController, Event) -> Result<(), E>,
+    E: From<Error>,
+{
+    use glutin::event::ElementState;
+
+    if resize_on_button(wc, button, state) {
+        return Ok(());
+    }
+
+    match state {
+        ElementState::Pressed => {
+            let count = count_click(wc.windata, button);
+            event_handler(wc, Event::MouseButtonPressed(button))?;
+            if count == 2 {
+                let position = wc.windata.cursor_position;
+                event_handler(wc, Event::MouseButtonDoubleClicked(button, position))?;
+            }
+        }
+        ElementState::Released =>
+            event_handler(wc, Event::MouseButtonReleased(button))?,
+    };
+    process_actions(wc, Input::Mouse(button), state, event_handler)
+}
+
+/// Starts or ends a resize from the hit test border or
+/// `begin_resize_window`. Returns whether the button was used for it, and so
+/// shouldn't be sent.
+fn resize_on_button(wc: &mut WindowController, button: MouseButton, state: glutin::event::ElementState) -> bool {
+    use glutin::event::ElementState;
+
+    if button != MouseButton::Left || wc.windata.window().is_none() {
+        return false;
+    }
+
+    match state {
+        ElementState::Released => wc.windata.resize_drag.take().is_some(),
+        ElementState::Pressed => {
+            let Some(border) = wc.windata.resize_border else {
+                return false;
+            };
+            let size = wc.windata.inner_size();
+            match ResizeDirection::hit_test(wc.windata.cursor_position, size, border) {
+                // where resizing isn't possible, the press goes to the app
+                Some(direction) => wc.begin_resize_window(direction).is_ok(),
+                None => false,
+            }
+        }
+    }
+}
+
+/// Follows the cursor with a resize in progress, or shows a resize cursor
+/// over the hit test border.
+fn resize_on_cursor_moved(wc: &mut WindowController) {
+    let windata = &mut *wc.windata;
+    let Some(window) = windata.window() else {
+        return;
+    };
+
+    if let Some(drag) = &windata.resize_drag {
+        drag.update(window, windata.cursor_position);
+        return;
+    }
+
+    let direction = windata.resize_border.and_then(|border| {
+        ResizeDirection::hit_test(windata.cursor_position, windata.inner_size(), border)
+    });
+    match direction {
+        Some(direction) => window.set_cursor_icon(direction.cursor_icon()),
+        None if windata.over_resize_border => window.set_cursor_icon(windata.cursor_icon.get()),
+        None => (),
+    }
+    windata.over_resize_border = direction.is_some();
+}
+
+fn lose_context<F, E>(wc: &mut WindowController, event_handler: &mut F) -> Result<(), E>
+where
+    F: FnMut(&mut WindowController, Event) -> Result<(), E>,
+    E: From<Error>,
+{
+    wc.windata.context_lost = true;
+    event_handler(wc, Event::ContextLost)
+}
+
+/// Runs the reinit callbacks, then sends `Event::ContextRestored`.
+fn restore_context<F, E>(wc: &mut WindowController, event_handler: &mut F) -> Result<(), E>
+where
+    F: FnMut(&mut WindowController, Event) -> Result<(), E>,
+    E: From<Error>,
+{
+    wc.windata.context_lost = false;
+
+    // the old chain's objects went with the context, so deleting them now
+    // could delete new objects that reused their names
+    if let Some(color_adjust) = wc.windata.color_adjust.take() {
+        let adjustment = color_adjust.adjustment;
+        color_adjust.chain.forget_lost();
+        wc.windata.color_adjust = Some(WindowColorAdjust::new(adjustment, wc.windata.inner_size())?);
+    }
+
+    for (_, callback) in &mut wc.windata.reinit_callbacks {
+        callback()?;
+    }
+    event_handler(wc, Event::ContextRestored)
+}
+
+fn send_focused<F, E>(wc: &mut WindowController, focused: bool, event_handler: &mut F) -> Result<(), E>
+where
+    F: FnMut(&mut WindowController, Event) -> Result<(), E>,
+    E: From<Error>,
+{
+    wc.windata.update_background(|windata| windata.focused = focused);
+    event_handler(wc, Event::Focused(focused))?;
+
+    // release events for held inputs won't arrive once focus is gone
+    if !focused {
+        wc.windata.keys_down.clear();
+        for action in wc.windata.action_tracker.clear() {
+            event_handler(wc, Event::Action(action, ActionState::Released))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Applies a change to what hides the window, sending `Event::Minimized` or
+/// `Event::Restored` if that changes whether it's minimized.
+fn send_minimized<F, E, G>(wc: &mut WindowController, change: G, event_handler: &mut F) -> Result<(), E>
+where
+    F: FnMut(&mut WindowController, Event) -> Result<(), E>,
+    E: From<Error>,
+    G: FnOnce(&mut WinData),
+{
+    let was_minimized = wc.windata.minimized();
+    wc.windata.update_background(change);
+
+    match (was_minimized, wc.windata.minimized()) {
+        (false, true) => event_handler(wc, Event::Minimized),
+        (true, false) => {
+            // what was drawn may not have survived, and redraws may have
+            // been paused
+            wc.request_redraw();
+            event_handler(wc, Event::Restored)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Sends `Event::Timer` for every timer that has come due, with how many of
+/// its periods passed since it last fired.
+fn fire_timers<F, E>(wc: &mut WindowController, event_handler: &mut F) -> Result<(), E>
+where
+    F: FnMut(&mut WindowController, Event) -> Result<(), E>,
+    E: From<Error>,
+{
+    let now = wc.windata.now();
+    let mut fired = Vec::new();
+    for timer in &mut wc.windata.timers {
+        if now < timer.next {
+            continue;
+        }
+
+        let ticks_passed = 1 + ((now - timer.next).as_nanos() / timer.duration.as_nanos()) as u32;
+        timer.next += timer.duration * ticks_passed;
+        fired.push((timer.id, ticks_passed));
+    }
+
+    for (id, ticks_passed) in fired {
+        // an earlier handler may have cancelled it
+        if wc.windata.timers.iter().any(|timer| timer.id == id) {
+            event_handler(wc, Event::Timer(id, ticks_passed))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "event-recording")]
+fn replaying(windata: &WinData) -> bool {
+    windata.replay.is_some()
+}
+
+#[cfg(not(feature = "event-recording"))]
+fn replaying(_windata: &WinData) -> bool {
+    false
+}
+
+/// Input that a replay stands in for.
+fn is_user_input(event: &glutin::event::WindowEvent) -> bool {
+    use glutin::event::WindowEvent as WinEv;
+    matches!(
+        event,
+        WinEv::KeyboardInput { .. }
+            | WinEv::ModifiersChanged(_)
+            | WinEv::ReceivedCharacter(_)
+            | WinEv::MouseInput { .. }
+            | WinEv::MouseWheel { .. }
+            | WinEv::CursorMoved { .. }
+            | WinEv::CursorEntered { .. }
+            | WinEv::CursorLeft { .. }
+            | WinEv::DroppedFile(_)
+            | WinEv::HoveredFile(_)
+            | WinEv::HoveredFileCancelled
+    )
+}
+
+/// Sends the replayed events that have come due, and ends a finished replay.
+#[cfg(feature = "event-recording")]
+fn play_replay<F, E>(wc: &mut WindowController, event_handler: &mut F) -> Result<(), E>
+where
+    F: FnMut(&mut WindowController, Event) -> Result<(), E>,
+    E: From<Error>,
+{
+    let now = wc.windata.now();
+    let replay = match &mut wc.windata.replay {
+        Some(replay) => replay,
+        None => return Ok(()),
+    };
+
+    let due = replay.take_due(now);
+    if replay.is_finished() {
+        wc.windata.replay = None;
+    }
+
+    for event in due {
+        event_handler(wc, event)?;
+    }
+
+    Ok(())
+}
+
+/// Sends `Event::Resized`, or holds it back while resizes are debounced.
+fn send_resized<F, E>(wc: &mut WindowController, size: (u32, u32), event_handler: &mut F) -> Result<(), E>
+where
+    F: FnMut(&mut WindowController, Event) -> Result<(), E>,
+    E: From<Error>,
+{
+    match wc.windata.resize_debounce {
+        Some(delay) => {
+            wc.windata.pending_resize = Some((size, wc.windata.now() + delay));
+            Ok(())
+        }
+        None => event_handler(wc, Event::Resized(size.0, size.1)),
+    }
+}
+
+/// Sends a debounced resize once the size has settled.
+fn flush_pending_resize<F, E>(wc: &mut WindowController, event_handler: &mut F) -> Result<(), E>
+where
+    F: FnMut(&mut WindowController, Event) -> Result<(), E>,
+    E: From<Error>,
+{
+    match wc.windata.pending_resize {
+        Some((size, deadline)) if wc.windata.now() >= deadline => {
+            wc.windata.pending_resize = None;
+            event_handler(wc, Event::Resized(size.0, size.1))?;
+            wc.request_redraw();
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Records a press and returns how many presses in a row make up its click.
+fn count_click(windata: &mut WinData, button: MouseButton) -> u32 {
+    let click = Click::next(
+        windata.last_click,
+        button,
+        windata.cursor_position,
+        windata.now(),
+        windata.double_click_time,
+        DOUBLE_CLICK_DISTANCE * windata.scale_factor(),
+    );
+    windata.last_click = Some(click);
+    click.count
+}
+
+impl Click {
+    /// The click made by pressing `button` at `position`, continuing the
+    /// count of `last` if it was the same button, no more than `max_time`
+    /// earlier and no more than `max_distance` pixels away.
+    fn next(
+        last: Option<Click>,
+        button: MouseButton,
+        position: (f32, f32),
+        time: std::time::Instant,
+        max_time: std::time::Duration,
+        max_distance: f64,
+    ) -> Click {
+        let distance = |last: &Click| {
+            ((position.0 - last.position.0) as f64).hypot((position.1 - last.position.1) as f64)
+        };
+        let count = match last {
+            Some(last) if last.button == button
+                && time - last.time <= max_time
+                && distance(&last) <= max_distance =>
+            {
+                last.count + 1
+            }
+            _ => 1,
+        };
+
+        Click { button, position, time, count }
+    }
+}
+
+fn process_actions<F, E>(
+    wc: &mut WindowController,
+    input: Input,
+    state: glutin::event::ElementState,
+    event_handler: &mut F,
+) -> Result<(), E>
+where
+    F: FnMut(&mut WindowController, Event) -> Result<(), E>,
+    E: From<Error>,
+{
+    let windata = &mut *wc.windata;
+    let action_map = match &windata.action_map {
+        Some(action_map) => action_map,
+        None => return Ok(()),
+    };
+
+    let state = match state {
+        glutin::event::ElementState::Pressed => ActionState::Pressed,
+        glutin::event::ElementState::Released => ActionState::Released,
+    };
+
+    let changes = windata.action_tracker.process(action_map, input, state, windata.modifiers);
+    for (action, state) in changes {
+        event_handler(wc, Event::Action(action, state))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    const MAX_TIME: Duration = Duration::from_millis(500);
+    const MAX_DISTANCE: f64 = 4.0;
+
+    fn press(last: Option<Click>, button: MouseButton, position: (f32, f32), time: Instant) -> Click {
+        Click::next(last, button, position, time, MAX_TIME, MAX_DISTANCE)
+    }
+
+    #[test]
+    fn quick_presses_count_up() {
+        let start = Instant::now();
+        let mut click = press(None, MouseButton::Left, (10.0, 10.0), start);
+        assert_eq!(click.count, 1);
+        for (i, ms) in [200, 400, 600].into_iter().enumerate() {
+            click = press(Some(click), MouseButton::Left, (10.0, 10.0), start + Duration::from_millis(ms));
+            assert_eq!(click.count, i as u32 + 2);
+        }
+    }
+
+    #[test]
+    fn count_resets_after_double_click_time() {
+        let start = Instant::now();
+        let first = press(None, MouseButton::Left, (10.0, 10.0), start);
+
+        let in_time = press(Some(first), MouseButton::Left, (10.0, 10.0), start + MAX_TIME);
+        assert_eq!(in_time.count, 2);
+
+        let late = MAX_TIME + Duration::from_millis(1);
+        let too_late = press(Some(first), MouseButton::Left, (10.0, 10.0), start + late);
+        assert_eq!(too_late.count, 1);
+    }
+
+    #[test]
+    fn time_is_measured_from_the_previous_press() {
+        let start = Instant::now();
+        let mut click = press(None, MouseButton::Left, (0.0, 0.0), start);
+        click = press(Some(click), MouseButton::Left, (0.0, 0.0), start + Duration::from_millis(400));
+        click = press(Some(click), MouseButton::Left, (0.0, 0.0), start + Duration::from_millis(800));
+        assert_eq!(click.count, 3);
+    }
+
+    #[test]
+    fn count_resets_after_double_click_distance() {
+        let start = Instant::now();
+        let time = start + Duration::from_millis(100);
+        let first = press(None, MouseButton::Left, (10.0, 10.0), start);
+
+        assert_eq!(press(Some(first), MouseButton::Left, (12.0, 12.0), time).count, 2);
+        // exactly at the limit
+        assert_eq!(press(Some(first), MouseButton::Left, (14.0, 10.0), time).count, 2);
+        assert_eq!(press(Some(first), MouseButton::Left, (14.5, 10.0), time).count, 1);
+        assert_eq!(press(Some(first), MouseButton::Left, (13.0, 13.0), time).count, 1);
+    }
+
+    #[test]
+    fn count_resets_for_another_button() {
+        let start = Instant::now();
+        let time = start + Duration::from_millis(100);
+        let first = press(None, MouseButton::Left, (10.0, 10.0), start);
+
+        let right = press(Some(first), MouseButton::Right, (10.0, 10.0), time);
+        assert_eq!(right.count, 1);
+        let left = press(Some(right), MouseButton::Left, (10.0, 10.0), time + Duration::from_millis(100));
+        assert_eq!(left.count, 1);
+    }
+
+    #[test]
+    fn reset_press_starts_a_new_count() {
+        let start = Instant::now();
+        let first = press(None, MouseButton::Left, (0.0, 0.0), start);
+        let second = press(Some(first), MouseButton::Left, (0.0, 0.0), start + Duration::from_millis(100));
+        let moved = press(Some(second), MouseButton::Left, (50.0, 0.0), start + Duration::from_millis(200));
+        assert_eq!(moved.count, 1);
+
+        let next = press(Some(moved), MouseButton::Left, (50.0, 0.0), start + Duration::from_millis(300));
+        assert_eq!(next.count, 2);
+    }
+}
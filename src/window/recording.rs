@@ -0,0 +1,169 @@
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use super::Event;
+
+type Error = Box<dyn std::error::Error>;
+
+/// An event and when it arrived, relative to the start of the recording.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RecordedEvent {
+    pub time: Duration,
+    pub event: Event,
+}
+
+/// Records the events an app receives so they can be replayed later with
+/// [`WindowController::start_replay`](super::WindowController::start_replay),
+/// e.g. for regression tests of interactive behavior.
+///
+/// Pass every event the handler gets to [`EventRecorder::record`]. Only
+/// input and window changes are kept; ticks, timers, frames and redraws
+/// are produced by the event loop again during replay.
+pub struct EventRecorder {
+    start: Instant,
+    events: Vec<RecordedEvent>,
+}
+
+impl EventRecorder {
+    /// Starts recording, with time counted from now.
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, event: &Event) {
+        if is_external(event) {
+            self.events.push(RecordedEvent {
+                time: self.start.elapsed(),
+                event: event.clone(),
+            });
+        }
+    }
+
+    pub fn events(&self) -> &[RecordedEvent] {
+        &self.events
+    }
+
+    /// Writes the recording as JSON, one event per line.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        for event in &self.events {
+            serde_json::to_writer(&mut file, event)?;
+            file.write_all(b"\n")?;
+        }
+        file.flush()?;
+        Ok(())
+    }
+}
+
+impl Default for EventRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether an event comes from outside the app rather than from the event
+/// loop's own clock.
+fn is_external(event: &Event) -> bool {
+    !matches!(
+        event,
+        Event::EventLoopStarted
+            | Event::Tick(_)
+            | Event::Timer(..)
+            | Event::Frame(_)
+            | Event::Update(_)
+            | Event::Render { .. }
+            | Event::FrameStats(_)
+            | Event::RedrawRequested
+            | Event::ContextLost
+            | Event::ContextRestored
+            | Event::HandlerPanicked(_)
+    ) && !is_gamepad(event)
+        && !is_screenshot(event)
+}
+
+#[cfg(feature = "gamepad")]
+fn is_gamepad(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::GamepadConnected(_)
+            | Event::GamepadDisconnected(_)
+            | Event::GamepadButton { .. }
+            | Event::GamepadAxis { .. }
+    )
+}
+
+#[cfg(not(feature = "gamepad"))]
+fn is_gamepad(_event: &Event) -> bool {
+    false
+}
+
+#[cfg(feature = "png")]
+fn is_screenshot(event: &Event) -> bool {
+    matches!(event, Event::ScreenshotSaved(_) | Event::ScreenshotFailed(_))
+}
+
+#[cfg(not(feature = "png"))]
+fn is_screenshot(_event: &Event) -> bool {
+    false
+}
+
+/// Recorded events waiting to be fed back through the event handler, each
+/// at the same time after the replay starts as it was after the recording
+/// started.
+pub struct EventReplay {
+    events: Vec<RecordedEvent>,
+    next: usize,
+    start: Option<Instant>,
+}
+
+impl EventReplay {
+    pub fn new(events: Vec<RecordedEvent>) -> Self {
+        Self {
+            events,
+            next: 0,
+            start: None,
+        }
+    }
+
+    /// Reads a recording written by [`EventRecorder::save`].
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = std::io::BufReader::new(std::fs::File::open(path)?);
+        let mut events = Vec::new();
+        for line in file.lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                events.push(serde_json::from_str(&line)?);
+            }
+        }
+        Ok(Self::new(events))
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.events.len()
+    }
+
+    pub(crate) fn start(&mut self, now: Instant) {
+        self.start = Some(now);
+    }
+
+    pub(crate) fn next_deadline(&self) -> Option<Instant> {
+        Some(self.start? + self.events.get(self.next)?.time)
+    }
+
+    /// Takes the events whose time has come, in order.
+    pub(crate) fn take_due(&mut self, now: Instant) -> Vec<Event> {
+        let mut due = Vec::new();
+        while let Some(deadline) = self.next_deadline() {
+            if deadline > now {
+                break;
+            }
+            due.push(self.events[self.next].event.clone());
+            self.next += 1;
+        }
+        due
+    }
+}
@@ -0,0 +1,135 @@
+use super::CursorIcon;
+
+/// An edge or corner a window is resized from; see
+/// [`WindowController::begin_resize_window`](super::WindowController::begin_resize_window).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResizeDirection {
+    North,
+    South,
+    East,
+    West,
+    NorthEast,
+    NorthWest,
+    SouthEast,
+    SouthWest,
+}
+
+impl ResizeDirection {
+    /// The edge or corner of a window of `size` that `position` is within
+    /// `border` pixels of, if any. Corners count `border` along both edges.
+    pub fn hit_test(position: (f32, f32), size: (u32, u32), border: f32) -> Option<Self> {
+        let (x, y) = position;
+        let (width, height) = (size.0 as f32, size.1 as f32);
+        if x < 0.0 || y < 0.0 || x >= width || y >= height {
+            return None;
+        }
+
+        let west = x < border;
+        let east = x >= width - border;
+        let north = y < border;
+        let south = y >= height - border;
+
+        match (north, south, west, east) {
+            (true, _, true, _) => Some(Self::NorthWest),
+            (true, _, _, true) => Some(Self::NorthEast),
+            (_, true, true, _) => Some(Self::SouthWest),
+            (_, true, _, true) => Some(Self::SouthEast),
+            (true, ..) => Some(Self::North),
+            (_, true, ..) => Some(Self::South),
+            (_, _, true, _) => Some(Self::West),
+            (_, _, _, true) => Some(Self::East),
+            _ => None,
+        }
+    }
+
+    /// The cursor to show over this edge or corner.
+    pub fn cursor_icon(self) -> CursorIcon {
+        match self {
+            Self::North => CursorIcon::NResize,
+            Self::South => CursorIcon::SResize,
+            Self::East => CursorIcon::EResize,
+            Self::West => CursorIcon::WResize,
+            Self::NorthEast => CursorIcon::NeResize,
+            Self::NorthWest => CursorIcon::NwResize,
+            Self::SouthEast => CursorIcon::SeResize,
+            Self::SouthWest => CursorIcon::SwResize,
+        }
+    }
+
+    /// Which way each axis moves: -1 for the left or top edge, 1 for the
+    /// right or bottom edge, 0 for neither.
+    fn axes(self) -> (i32, i32) {
+        match self {
+            Self::North => (0, -1),
+            Self::South => (0, 1),
+            Self::East => (1, 0),
+            Self::West => (-1, 0),
+            Self::NorthEast => (1, -1),
+            Self::NorthWest => (-1, -1),
+            Self::SouthEast => (1, 1),
+            Self::SouthWest => (-1, 1),
+        }
+    }
+}
+
+/// A resize in progress, done by moving and sizing the window as the cursor
+/// moves, since winit 0.27 can't hand resizing to the system.
+pub(crate) struct ResizeDrag {
+    direction: ResizeDirection,
+    /// Where the cursor started on the desktop.
+    start_cursor: (f64, f64),
+    start_position: (i32, i32),
+    start_size: (u32, u32),
+}
+
+impl ResizeDrag {
+    /// Starts resizing `window`, with the cursor at `cursor` in window
+    /// pixels. Fails where windows have no position, as on Wayland.
+    pub fn new(
+        window: &glutin::window::Window,
+        direction: ResizeDirection,
+        cursor: (f32, f32),
+    ) -> Result<Self, glutin::error::NotSupportedError> {
+        let start_position: (i32, i32) = window.outer_position()?.into();
+        let start_cursor = desktop_position(start_position, cursor);
+        Ok(Self {
+            direction,
+            start_cursor,
+            start_position,
+            start_size: window.inner_size().into(),
+        })
+    }
+
+    /// Resizes `window` for the cursor having moved to `cursor`, in window
+    /// pixels.
+    pub fn update(&self, window: &glutin::window::Window, cursor: (f32, f32)) {
+        let Ok(position) = window.outer_position() else {
+            return;
+        };
+        let (cursor_x, cursor_y) = desktop_position(position.into(), cursor);
+        let dx = (cursor_x - self.start_cursor.0).round() as i32;
+        let dy = (cursor_y - self.start_cursor.1).round() as i32;
+
+        let (x_axis, y_axis) = self.direction.axes();
+        let width = (self.start_size.0 as i32 + x_axis * dx).max(1);
+        let height = (self.start_size.1 as i32 + y_axis * dy).max(1);
+
+        // dragging the left or top edge moves the opposite one by as much
+        let (mut x, mut y) = self.start_position;
+        if x_axis < 0 {
+            x += self.start_size.0 as i32 - width;
+        }
+        if y_axis < 0 {
+            y += self.start_size.1 as i32 - height;
+        }
+
+        window.set_inner_size(glutin::dpi::PhysicalSize::new(width as u32, height as u32));
+        if (x, y) != position.into() {
+            window.set_outer_position(glutin::dpi::PhysicalPosition::new(x, y));
+        }
+    }
+}
+
+fn desktop_position(window_position: (i32, i32), cursor: (f32, f32)) -> (f64, f64) {
+    (window_position.0 as f64 + cursor.0 as f64, window_position.1 as f64 + cursor.1 as f64)
+}
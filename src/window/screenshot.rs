@@ -0,0 +1,94 @@
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::VirtualKeyCode;
+
+/// The built-in screenshot key; see
+/// [`WindowController::set_screenshot_config`](super::WindowController::set_screenshot_config).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScreenshotConfig {
+    /// Any of these takes a screenshot, or a burst with Shift held. The key
+    /// events still reach the handler.
+    pub keys: Vec<VirtualKeyCode>,
+    /// Where screenshots are saved, created if it doesn't exist.
+    pub directory: PathBuf,
+    /// Starts each file name, followed by the UTC time.
+    pub prefix: String,
+    /// How many consecutive frames a burst captures.
+    pub burst_frames: u32,
+}
+
+impl Default for ScreenshotConfig {
+    fn default() -> Self {
+        Self {
+            keys: vec![VirtualKeyCode::Snapshot, VirtualKeyCode::F12],
+            directory: PathBuf::from("screenshots"),
+            prefix: String::from("screenshot"),
+            burst_frames: 10,
+        }
+    }
+}
+
+/// Frames still to be captured for a screenshot or burst.
+pub(crate) struct PendingCapture {
+    /// Shared by every frame of a burst, so its files sort together.
+    stamp: String,
+    burst: bool,
+    next_index: u32,
+    remaining: u32,
+}
+
+impl PendingCapture {
+    pub fn new(frames: u32) -> Self {
+        Self {
+            stamp: timestamp(SystemTime::now()),
+            burst: frames > 1,
+            next_index: 0,
+            remaining: frames,
+        }
+    }
+
+    /// The path for the next frame, and whether it's the last one.
+    pub fn next_path(&mut self, config: &ScreenshotConfig) -> (PathBuf, bool) {
+        let name = if self.burst {
+            format!("{}_{}_{:03}.png", config.prefix, self.stamp, self.next_index)
+        } else {
+            format!("{}_{}.png", config.prefix, self.stamp)
+        };
+        self.next_index += 1;
+        self.remaining = self.remaining.saturating_sub(1);
+        (config.directory.join(name), self.remaining == 0)
+    }
+}
+
+/// `YYYY-MM-DD_HH-MM-SS.mmm` in UTC, which sorts in time order and is a
+/// valid file name everywhere.
+fn timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let time_of_day = secs % 86400;
+
+    format!(
+        "{year:04}-{month:02}-{day:02}_{:02}-{:02}-{:02}.{:03}",
+        time_of_day / 3600,
+        time_of_day / 60 % 60,
+        time_of_day % 60,
+        since_epoch.subsec_millis(),
+    )
+}
+
+/// The Gregorian date of a day counted from 1970-01-01, after Howard
+/// Hinnant's `civil_from_days`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
@@ -0,0 +1,169 @@
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+
+use glutin::event_loop::EventLoop;
+use glutin::ContextBuilder;
+
+type Error = Box<dyn std::error::Error>;
+/// Errors from a job have to cross back from the loader thread.
+pub type LoadError = Box<dyn std::error::Error + Send + Sync>;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// A thread with its own OpenGL context, sharing objects with the window's,
+/// for uploading large textures and buffers without stalling the render
+/// loop. Create it with [`Window::create_shared_loader`](super::Window::create_shared_loader).
+///
+/// Jobs run in the order they are sent. Objects created by a job can be
+/// used on the main thread once its [`Upload`] is ready, which waits on a
+/// fence for the GPU to finish as well as for the job to return.
+/// Framebuffers and vertex arrays aren't shared between contexts, so create
+/// those on the main thread.
+///
+/// Dropping the loader finishes the queued jobs and stops the thread.
+pub struct SharedLoader {
+    sender: Option<mpsc::Sender<Job>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SharedLoader {
+    pub(crate) fn new(
+        event_loop: &EventLoop<()>,
+        windowed_context: &glutin::WindowedContext<glutin::PossiblyCurrent>,
+        gl_version: (u8, u8),
+    ) -> Result<Self, Error> {
+        let context = ContextBuilder::new()
+            .with_gl_profile(glutin::GlProfile::Core)
+            .with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGl, gl_version))
+            .with_shared_lists(windowed_context)
+            .build_headless(event_loop, glutin::dpi::PhysicalSize::new(1, 1))?;
+
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let (ready_sender, ready_receiver) = mpsc::channel();
+
+        let thread = std::thread::Builder::new()
+            .name("glume shared loader".to_string())
+            .spawn(move || {
+                let _context = match unsafe { context.make_current() } {
+                    Ok(context) => {
+                        let _ = ready_sender.send(Ok(()));
+                        context
+                    }
+                    Err((_, e)) => {
+                        let _ = ready_sender.send(Err(e.to_string()));
+                        return;
+                    }
+                };
+
+                for job in receiver {
+                    job();
+                }
+            })?;
+
+        ready_receiver.recv()??;
+
+        Ok(Self {
+            sender: Some(sender),
+            thread: Some(thread),
+        })
+    }
+
+    /// Runs `job` on the loader thread with its context current.
+    pub fn upload<T, F>(&self, job: F) -> Upload<T>
+    where
+        T: 'static + Send,
+        F: 'static + Send + FnOnce() -> Result<T, LoadError>,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let job = Box::new(move || {
+            let result = job();
+            let fence = unsafe {
+                let fence = gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0);
+                // without a flush the fence may never reach the GPU
+                gl::Flush();
+                Fence(fence)
+            };
+            let _ = sender.send((result, fence));
+        });
+
+        if let Some(sender) = &self.sender {
+            // the thread only stops once the loader is dropped
+            let _ = sender.send(job);
+        }
+
+        Upload { receiver, done: None }
+    }
+}
+
+impl Drop for SharedLoader {
+    fn drop(&mut self) {
+        self.sender = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+struct Fence(gl::types::GLsync);
+
+// sync objects are shared between the loader's context and the window's
+unsafe impl Send for Fence {}
+
+impl Drop for Fence {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteSync(self.0) };
+    }
+}
+
+/// The result of a job sent to a [`SharedLoader`]. Check it from the
+/// thread of the window's context, e.g. once per frame.
+pub struct Upload<T> {
+    receiver: mpsc::Receiver<(Result<T, LoadError>, Fence)>,
+    done: Option<(Result<T, LoadError>, Fence)>,
+}
+
+impl<T> Upload<T> {
+    /// Whether the job has returned and the GPU has finished its commands.
+    pub fn is_ready(&mut self) -> bool {
+        self.poll(0)
+    }
+
+    /// The job's result once it is ready; see [`Upload::is_ready`].
+    pub fn try_take(&mut self) -> Option<Result<T, Error>> {
+        if !self.poll(0) {
+            return None;
+        }
+        self.take()
+    }
+
+    /// Blocks until the job is ready and returns its result.
+    pub fn wait(mut self) -> Result<T, Error> {
+        if self.done.is_none() {
+            self.done = Some(self.receiver.recv().map_err(|_| "The shared loader stopped")?);
+        }
+        while !self.poll(u64::MAX) {}
+        self.take().unwrap_or_else(|| Err("The shared loader stopped".into()))
+    }
+
+    fn poll(&mut self, timeout_ns: u64) -> bool {
+        if self.done.is_none() {
+            match self.receiver.try_recv() {
+                Ok(done) => self.done = Some(done),
+                Err(_) => return false,
+            }
+        }
+
+        let fence = match &self.done {
+            Some((_, fence)) => fence,
+            None => return false,
+        };
+
+        // a failed wait won't succeed later, so don't hold the result back
+        let status = unsafe { gl::ClientWaitSync(fence.0, gl::SYNC_FLUSH_COMMANDS_BIT, timeout_ns) };
+        status != gl::TIMEOUT_EXPIRED
+    }
+
+    fn take(&mut self) -> Option<Result<T, Error>> {
+        self.done.take().map(|(result, _)| result.map_err(|e| e as Error))
+    }
+}
@@ -0,0 +1,162 @@
+use std::time::{Duration, Instant};
+
+use glutin::event::ElementState;
+
+use super::*;
+
+/// Runs an event handler like [`Window::run`] would, but against a
+/// [`HeadlessContext`] and a mock clock that only moves when told to, so
+/// application logic can be tested deterministically.
+///
+/// Time starts at the moment the `TestWindow` is created. Ticks, timers,
+/// frames and fixed updates all follow the mock clock; events are only
+/// delivered through [`TestWindow::send_event`].
+///
+/// Controller methods that act on the window itself, like `set_title` or
+/// `set_cursor_grab`, do nothing. The scale factor is always 1.
+pub struct TestWindow<F> {
+    windata: WinData,
+    event_handler: F,
+    closed: bool,
+}
+
+impl<F, E> TestWindow<F>
+where
+    F: FnMut(&mut WindowController, Event) -> Result<(), E>,
+    E: From<Error>,
+{
+    /// Creates the headless context from `config.gl_version` and
+    /// `config.size`, then sends `Event::EventLoopStarted`.
+    pub fn new(config: &WindowConfiguration, event_handler: F) -> Result<Self, E> {
+        let context = HeadlessContext::new(config.gl_version, config.size)?;
+        if config.debug_context {
+            crate::gl_utils::enable_debug_output();
+        }
+        let windata = WinData::new(Surface::Headless(Box::new(context)), config, Some(Instant::now()));
+
+        let mut test_window = Self {
+            windata,
+            event_handler,
+            closed: false,
+        };
+
+        test_window.with_controller(start_loop)?;
+        Ok(test_window)
+    }
+
+    /// The current time on the mock clock.
+    pub fn now(&self) -> Instant {
+        self.windata.now()
+    }
+
+    /// Moves the mock clock forward by `duration`, running the loop at each
+    /// tick, timer or other deadline along the way and again at the end.
+    ///
+    /// Each run sends what has come due, then `Event::Frame` and
+    /// `Event::Update` as the redraw mode and fixed timestep call for, then
+    /// renders a frame if one was requested.
+    pub fn advance(&mut self, duration: Duration) -> Result<(), E> {
+        let end = self.now() + duration;
+        loop {
+            let deadline = self.windata.next_deadline();
+            let last = deadline >= end;
+            self.windata.clock = Some(if last { end } else { deadline.max(self.now()) });
+
+            self.with_controller(|wc, event_handler| {
+                fire_ticks(wc, event_handler)?;
+                fire_due(wc, event_handler)?;
+                main_events_cleared(wc, event_handler)
+            })?;
+
+            let paused = self.windata.context_lost || self.windata.redraws_paused();
+            if self.windata.redraw_requested.get() && !paused {
+                self.render_frame()?;
+            }
+
+            if last || self.closed {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Sends an event to the handler as if it came from the window, keeping
+    /// the state the controller reports in step: `Resized` resizes the
+    /// context, `KeyPressed` and mouse button events fire actions and
+    /// double clicks, `CursorMoved` moves the cursor, and so on.
+    ///
+    /// `ContextLost` and `ContextRestored` simulate losing the context; the
+    /// headless context itself stays alive, but reinit callbacks run as
+    /// they would for real.
+    pub fn send_event(&mut self, event: Event) -> Result<(), E> {
+        self.with_controller(|wc, event_handler| match event {
+            Event::Resized(width, height) => {
+                wc.windata.resize_surface((width, height))?;
+                send_resized(wc, (width, height), event_handler)
+            }
+            Event::CloseRequested => {
+                wc.status.exit = true;
+                event_handler(wc, Event::CloseRequested)
+            }
+            Event::KeyPressed(key_event) => send_key(wc, key_event, ElementState::Pressed, event_handler),
+            Event::KeyReleased(key_event) => send_key(wc, key_event, ElementState::Released, event_handler),
+            Event::MouseButtonPressed(button) => send_mouse_button(wc, button, ElementState::Pressed, event_handler),
+            Event::MouseButtonReleased(button) => send_mouse_button(wc, button, ElementState::Released, event_handler),
+            Event::Focused(focused) => send_focused(wc, focused, event_handler),
+            Event::Minimized => send_minimized(wc, |windata| windata.occluded = true, event_handler),
+            Event::Restored => {
+                let restore = |windata: &mut WinData| {
+                    windata.occluded = false;
+                    windata.zero_sized = false;
+                };
+                send_minimized(wc, restore, event_handler)
+            }
+            Event::ContextLost => lose_context(wc, event_handler),
+            Event::ContextRestored => restore_context(wc, event_handler),
+            Event::CursorMoved(x, y) => {
+                wc.windata.cursor_position = (x, y);
+                event_handler(wc, event)
+            }
+            Event::ModifiersChanged(modifiers) => {
+                wc.windata.modifiers = modifiers;
+                event_handler(wc, event)
+            }
+            event => event_handler(wc, event),
+        })
+    }
+
+    /// Sends `Event::RedrawRequested` (or `Event::Render`) and finishes the
+    /// frame, whether or not a redraw was requested.
+    pub fn render_frame(&mut self) -> Result<(), E> {
+        self.with_controller(redraw)
+    }
+
+    /// A controller for setting things up outside the handler, e.g. timers
+    /// or an action map.
+    pub fn controller(&mut self) -> WindowController<'_> {
+        WindowController::new(&mut self.windata)
+    }
+
+    /// Whether the handler closed the window or `Event::CloseRequested` was
+    /// sent.
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Reads what the last frame drew into an RGBA image, top row first.
+    pub fn read_pixels_to_image(&self) -> Image {
+        match &self.windata.surface {
+            Surface::Headless(context) => context.read_pixels_to_image(),
+            Surface::Window(_) => unreachable!("a TestWindow is always headless"),
+        }
+    }
+
+    fn with_controller<G>(&mut self, f: G) -> Result<(), E>
+    where
+        G: FnOnce(&mut WindowController, &mut F) -> Result<(), E>
+    {
+        let mut wc = WindowController::new(&mut self.windata);
+        let result = f(&mut wc, &mut self.event_handler);
+        self.closed |= wc.status.exit;
+        result
+    }
+}